@@ -21,15 +21,28 @@
 use gtk::prelude::*;
 use adw::subclass::prelude::*;
 use gtk::{gio, glib};
+use std::cell::{Cell, OnceCell, RefCell};
 
 use crate::config::VERSION;
-use crate::TodoWindow;
+use crate::links::Link;
+use crate::{cli, dbus_api, quick_add, search_provider};
+use crate::{TodoWindow, APP_ID};
 
 mod imp {
     use super::*;
 
     #[derive(Debug, Default)]
-    pub struct TodoApplication {}
+    pub struct TodoApplication {
+        pub settings: OnceCell<gio::Settings>,
+        // The objects exported on the session bus
+        pub dbus_registrations: RefCell<Vec<gio::RegistrationId>>,
+        // Given on the command line of the first instance, opened once it is activated
+        pub startup_files: RefCell<Vec<gio::File>>,
+        // Started with `--quick-add`, only the quick-add window is shown once activated
+        pub startup_quick_add: Cell<bool>,
+        // Started with `--today`, the window opens on the tasks due today
+        pub startup_today: Cell<bool>,
+    }
 
     #[glib::object_subclass]
     impl ObjectSubclass for TodoApplication {
@@ -43,17 +56,105 @@ mod imp {
             self.parent_constructed();
             let obj = self.obj();
             obj.setup_gactions();
+            cli::add_options(&*obj);
             obj.set_accels_for_action("app.quit", &["<primary>q"]);
         }
     }
 
     impl ApplicationImpl for TodoApplication {
+        // Commands like `todo add` are run here, before the application starts up
+        fn handle_local_options(&self, options: &glib::VariantDict) -> glib::ExitCode {
+            if let Some(exit_code) = cli::handle(options) {
+                return exit_code;
+            }
+
+            if cli::wants_quick_add(options) {
+                let application = self.obj();
+                if let Err(err) = application.register(gio::Cancellable::NONE) {
+                    eprintln!("Could not start: {err}");
+                    return glib::ExitCode::FAILURE;
+                }
+                if application.is_remote() {
+                    application.activate_action("quick-add", None);
+                    return glib::ExitCode::SUCCESS;
+                }
+                self.startup_quick_add.set(true);
+            }
+
+            if cli::wants_today(options) {
+                let application = self.obj();
+                if let Err(err) = application.register(gio::Cancellable::NONE) {
+                    eprintln!("Could not start: {err}");
+                    return glib::ExitCode::FAILURE;
+                }
+                if application.is_remote() {
+                    application.activate_action("show-today", None);
+                    return glib::ExitCode::SUCCESS;
+                }
+                self.startup_today.set(true);
+            }
+
+            // The files are taken out of the command line with the options, so they are
+            // handed to the running instance here
+            let files = cli::files(options);
+            if !files.is_empty() {
+                let application = self.obj();
+                if let Err(err) = application.register(gio::Cancellable::NONE) {
+                    eprintln!("Could not start: {err}");
+                    return glib::ExitCode::FAILURE;
+                }
+                if application.is_remote() {
+                    application.open(&files, "");
+                    return glib::ExitCode::SUCCESS;
+                }
+                self.startup_files.replace(files);
+            }
+
+            self.parent_handle_local_options(options)
+        }
+
+        fn dbus_register(&self, connection: &gio::DBusConnection, object_path: &str) -> Result<(), glib::Error> {
+            self.parent_dbus_register(connection, object_path)?;
+            let registrations = vec![
+                search_provider::register(&self.obj(), connection)?,
+                dbus_api::register(&self.obj(), connection)?,
+            ];
+            self.dbus_registrations.replace(registrations);
+            Ok(())
+        }
+
+        fn dbus_unregister(&self, connection: &gio::DBusConnection, object_path: &str) {
+            for registration_id in self.dbus_registrations.take() {
+                if let Err(err) = connection.unregister_object(registration_id) {
+                    glib::g_warning!("todo", "Could not remove a D-Bus object: {}", err);
+                }
+            }
+            self.parent_dbus_unregister(connection, object_path);
+        }
+
+        fn startup(&self) {
+            self.parent_startup();
+            // The style manager is only available once libadwaita is initialized
+            self.obj().setup_appearance();
+        }
+
         // We connect to the activate callback to create a window when the application
         // has been launched. Additionally, this callback notifies us when the user
         // tries to launch a "second instance" of the application. When they try
         // to do that, we'll just present any existing window.
         fn activate(&self) {
             let application = self.obj();
+            if self.startup_quick_add.take() {
+                quick_add::show(&application.main_window());
+                return;
+            }
+
+            let startup_files = self.startup_files.take();
+            if !startup_files.is_empty() {
+                application.open(&startup_files, "");
+                return;
+            }
+
             // Get the current window or create one if necessary
             let window = if let Some(window) = application.active_window() {
                 window
@@ -64,6 +165,22 @@ mod imp {
 
             // Ask the window manager/compositor to present the window
             window.present();
+
+            if self.startup_today.take() {
+                if let Some(window) = window.downcast_ref::<TodoWindow>() {
+                    window.show_today();
+                }
+            }
+        }
+
+        fn open(&self, files: &[gio::File], _hint: &str) {
+            let application = self.obj();
+            for file in files {
+                match Link::parse(&file.uri()) {
+                    Some(link) => application.open_link(&link),
+                    None => application.import_file(file.clone()),
+                }
+            }
         }
     }
 
@@ -85,14 +202,114 @@ impl TodoApplication {
             .build()
     }
 
+    // The window holds the tasks, it is created without being shown when there is none yet
+    pub fn main_window(&self) -> TodoWindow {
+        self.windows()
+            .into_iter()
+            .find_map(|window| window.downcast::<TodoWindow>().ok())
+            .unwrap_or_else(|| TodoWindow::new(self))
+    }
+
+    // Shows the collection or task a todo:// link points to
+    pub fn open_link(&self, link: &Link) {
+        let window = self.main_window();
+        match link {
+            Link::Collection(uuid) => window.show_collection(uuid),
+            Link::Task(uuid) => window.show_task(uuid),
+        }
+        window.present();
+    }
+
+    fn import_file(&self, file: gio::File) {
+        let window = self.main_window();
+        window.present();
+        glib::MainContext::default().spawn_local(async move {
+            window.import_file(file).await;
+        });
+    }
+
     fn setup_gactions(&self) {
         let quit_action = gio::ActionEntry::builder("quit")
-            .activate(move |app: &Self, _, _| app.quit())
+            .activate(move |app: &Self, _, _| {
+                // Closing the windows saves the data, the application exits after the last one
+                for window in app.windows() {
+                    match window.downcast::<TodoWindow>() {
+                        Ok(window) => window.quit(),
+                        Err(window) => window.close(),
+                    }
+                }
+            })
             .build();
         let about_action = gio::ActionEntry::builder("about")
             .activate(move |app: &Self, _, _| app.show_about())
             .build();
-        self.add_action_entries([quit_action, about_action]);
+        let show_task_action = gio::ActionEntry::builder("show-task")
+            .parameter_type(Some(glib::VariantTy::STRING))
+            .activate(move |app: &Self, _, parameter| {
+                let Some(uuid) = parameter.and_then(|parameter| parameter.get::<String>()) else {
+                    return;
+                };
+
+                app.activate();
+                if let Some(window) = app.active_window().and_downcast::<TodoWindow>() {
+                    window.show_task(&uuid);
+                }
+            })
+            .build();
+        // Snoozing from a reminder leaves the window as it is
+        let snooze_task_action = gio::ActionEntry::builder("snooze-task")
+            .parameter_type(Some(glib::VariantTy::STRING))
+            .activate(move |app: &Self, _, parameter| {
+                let Some(uuid) = parameter.and_then(|parameter| parameter.get::<String>()) else {
+                    return;
+                };
+                app.main_window().snooze_task(&uuid);
+            })
+            .build();
+        let show_today_action = gio::ActionEntry::builder("show-today")
+            .activate(move |app: &Self, _, _| {
+                app.activate();
+                if let Some(window) = app.active_window().and_downcast::<TodoWindow>() {
+                    window.show_today();
+                }
+            })
+            .build();
+        let quick_add_action = gio::ActionEntry::builder("quick-add")
+            .activate(move |app: &Self, _, _| quick_add::show(&app.main_window()))
+            .build();
+        self.add_action_entries([
+            quit_action,
+            about_action,
+            show_task_action,
+            snooze_task_action,
+            show_today_action,
+            quick_add_action,
+        ]);
+    }
+
+    fn setup_appearance(&self) {
+        let settings = gio::Settings::new(APP_ID);
+        self.add_action(&settings.create_action("color-scheme"));
+
+        Self::apply_color_scheme(&settings);
+        settings.connect_changed(Some("color-scheme"), |settings, _| {
+            Self::apply_color_scheme(settings);
+        });
+
+        self.imp()
+            .settings
+            .set(settings)
+            .expect("settings should only be set in setup_appearance");
+    }
+
+    fn apply_color_scheme(settings: &gio::Settings) {
+        let color_scheme: String = settings.get("color-scheme");
+        let color_scheme = match color_scheme.as_str() {
+            "Light" => adw::ColorScheme::ForceLight,
+            "Dark" => adw::ColorScheme::ForceDark,
+            _ => adw::ColorScheme::Default,
+        };
+        adw::StyleManager::default().set_color_scheme(color_scheme);
     }
 
     fn show_about(&self) {
@@ -0,0 +1,80 @@
+use std::path::{Path, PathBuf};
+
+use gtk::{gio, glib};
+
+use crate::APP_ID;
+
+// The attachments of a task are copies in a directory named after its uuid, the task
+// only keeps their file names. They are deleted along with the task, not when removed
+// from it, so removing one can be undone.
+pub fn directory(task_uuid: &str) -> PathBuf {
+    let mut path = glib::user_data_dir();
+    path.push(APP_ID);
+    path.push("attachments");
+    path.push(task_uuid);
+    path
+}
+
+pub fn path(task_uuid: &str, name: &str) -> PathBuf {
+    directory(task_uuid).join(name)
+}
+
+// Copies `source` to the attachments of the task, returning the name it is kept under
+pub fn attach(task_uuid: &str, source: &Path) -> std::io::Result<String> {
+    let directory = directory(task_uuid);
+    std::fs::create_dir_all(&directory)?;
+
+    let stem = source
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_else(|| "Attachment".to_string());
+    let extension = source
+        .extension()
+        .map(|extension| format!(".{}", extension.to_string_lossy()))
+        .unwrap_or_default();
+
+    // Files of the same name are numbered like "receipt (2).pdf"
+    let mut name = format!("{stem}{extension}");
+    let mut number = 2;
+    while directory.join(&name).exists() {
+        name = format!("{stem} ({number}){extension}");
+        number += 1;
+    }
+
+    std::fs::copy(source, directory.join(&name))?;
+    Ok(name)
+}
+
+// For copies of a task, which have a uuid of their own
+pub fn copy_all(from_uuid: &str, to_uuid: &str) -> std::io::Result<()> {
+    let from = directory(from_uuid);
+    if !from.exists() {
+        return Ok(());
+    }
+
+    let to = directory(to_uuid);
+    std::fs::create_dir_all(&to)?;
+    for entry in std::fs::read_dir(&from)? {
+        let entry = entry?;
+        std::fs::copy(entry.path(), to.join(entry.file_name()))?;
+    }
+    Ok(())
+}
+
+pub fn remove_all(task_uuid: &str) {
+    match std::fs::remove_dir_all(directory(task_uuid)) {
+        Ok(()) => {}
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+        Err(err) => glib::g_warning!("todo", "Could not delete the attachments of {}: {}", task_uuid, err),
+    }
+}
+
+pub fn is_image(path: &Path) -> bool {
+    let (content_type, _) = gio::content_type_guess(Some(path), &[]);
+    content_type.starts_with("image/")
+}
+
+pub fn icon(path: &Path) -> gio::Icon {
+    let (content_type, _) = gio::content_type_guess(Some(path), &[]);
+    gio::content_type_get_icon(&content_type)
+}
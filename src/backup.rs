@@ -0,0 +1,90 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::path::Path;
+
+use anyhow::{bail, Context};
+use gio::Settings;
+use gtk::{gio, glib, prelude::*};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::migration::{self, StoreData};
+
+// Bump when the layout of the backup itself changes, the data inside has its own version
+const BACKUP_VERSION: u32 = 1;
+
+// Everything needed to set the application up again on another machine
+#[derive(Serialize, Deserialize)]
+struct BackupFile {
+    backup_version: u32,
+    // In the format of the data file, so older backups are migrated like it
+    data: Value,
+    // Settings keys with their values in GVariant text format
+    #[serde(default)]
+    settings: BTreeMap<String, String>,
+}
+
+pub struct Backup {
+    pub data: StoreData,
+    pub settings: BTreeMap<String, String>,
+}
+
+impl Backup {
+    pub fn new(data: StoreData, settings: &Settings) -> Self {
+        let settings = settings
+            .settings_schema()
+            .map(|schema| schema.list_keys())
+            .unwrap_or_default()
+            .into_iter()
+            .map(|key| (key.to_string(), settings.value(&key).print(true).to_string()))
+            .collect();
+
+        Self { data, settings }
+    }
+
+    pub fn write(&self, path: &Path) -> anyhow::Result<()> {
+        let backup_file = BackupFile {
+            backup_version: BACKUP_VERSION,
+            data: serde_json::to_value(&self.data).context("Could not serialize data")?,
+            settings: self.settings.clone(),
+        };
+
+        let file = File::create(path).with_context(|| format!("Could not create {}", path.display()))?;
+        serde_json::to_writer(file, &backup_file).with_context(|| format!("Could not write {}", path.display()))
+    }
+
+    pub fn read(path: &Path) -> anyhow::Result<Self> {
+        let file = File::open(path).with_context(|| format!("Could not open {}", path.display()))?;
+        let backup_file: BackupFile = serde_json::from_reader(file).context("This is not a backup")?;
+        if backup_file.backup_version > BACKUP_VERSION {
+            bail!("The backup was made by a newer version of the application");
+        }
+
+        Ok(Self {
+            data: migration::migrate(backup_file.data)?,
+            settings: backup_file.settings,
+        })
+    }
+
+    // Keys that no longer exist or hold a value of another type are skipped
+    pub fn restore_settings(&self, settings: &Settings) {
+        let Some(schema) = settings.settings_schema() else {
+            return;
+        };
+
+        for (key, text) in &self.settings {
+            if !schema.has_key(key) {
+                continue;
+            }
+            let value_type = schema.key(key).value_type();
+            match glib::Variant::parse(Some(&*value_type), text) {
+                Ok(value) => {
+                    if let Err(err) = settings.set_value(key, &value) {
+                        glib::g_warning!("todo", "Could not restore setting {}: {}", key, err);
+                    }
+                }
+                Err(err) => glib::g_warning!("todo", "Could not restore setting {}: {}", key, err),
+            }
+        }
+    }
+}
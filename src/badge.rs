@@ -0,0 +1,24 @@
+use gtk::{gio, glib, prelude::*};
+
+use crate::APP_ID;
+
+// Docks like Dash to Dock and Plank show the count on the icon of the application
+const INTERFACE: &str = "com.canonical.Unity.LauncherEntry";
+const OBJECT_PATH: &str = "/org/gnome/ToDo/LauncherEntry";
+
+// Shows `count` on the icon of the application, no count hides it
+pub fn update(application: &impl IsA<gio::Application>, count: usize) {
+    let Some(connection) = application.dbus_connection() else {
+        return;
+    };
+
+    let properties = glib::VariantDict::new(None);
+    properties.insert_value("count", &(count as i64).to_variant());
+    properties.insert_value("count-visible", &(count > 0).to_variant());
+    let app_uri = format!("application://{APP_ID}.desktop");
+    let parameters = glib::Variant::tuple_from_iter([app_uri.to_variant(), properties.end()]);
+
+    if let Err(err) = connection.emit_signal(None, OBJECT_PATH, INTERFACE, "Update", Some(&parameters)) {
+        glib::g_warning!("todo", "Could not update the badge: {}", err);
+    }
+}
@@ -0,0 +1,31 @@
+use gtk::glib;
+
+// Weeks in the month grid, enough for any month starting late in the week
+pub const WEEKS: i32 = 6;
+pub const WEEKDAYS: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+// Tasks listed in a day before the rest is summed up
+pub const TASKS_PER_DAY: usize = 3;
+
+// The first day of the month `date` falls into
+pub fn month_start(date: &glib::DateTime) -> Option<glib::DateTime> {
+    glib::DateTime::from_local(date.year(), date.month(), 1, 0, 0, 0.0).ok()
+}
+
+// The days of the grid for the month starting at `month_start`, from the Monday on or before it
+pub fn grid_days(month_start: &glib::DateTime) -> Vec<glib::DateTime> {
+    let Ok(first_day) = month_start.add_days(1 - month_start.day_of_week()) else {
+        return Vec::new();
+    };
+
+    (0..WEEKS * 7)
+        .filter_map(|day| first_day.add_days(day).ok())
+        .collect()
+}
+
+// "March 2024"
+pub fn month_title(month_start: &glib::DateTime) -> String {
+    month_start
+        .format("%B %Y")
+        .map(|title| title.to_string())
+        .unwrap_or_default()
+}
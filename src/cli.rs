@@ -0,0 +1,261 @@
+use gtk::{gio, glib, prelude::*};
+use serde_json::json;
+
+use crate::dbus_api;
+use crate::parser;
+use crate::APP_ID;
+
+const USAGE: &str = "Usage:
+  todo add TEXT [--collection NAME] [--due DATE]
+  todo list [COLLECTION] [--json]
+  todo done ID [--json]
+  todo export [COLLECTION] --format FORMAT
+  todo --quick-add
+  todo --today";
+
+// The options of the commands, the command itself and its arguments are left over
+pub fn add_options(application: &impl IsA<gio::Application>) {
+    application.add_main_option(
+        "collection",
+        glib::Char::from(b'c'),
+        glib::OptionFlags::NONE,
+        glib::OptionArg::String,
+        "Collection to add the task to, by title",
+        Some("NAME"),
+    );
+    application.add_main_option(
+        "due",
+        glib::Char::from(b'd'),
+        glib::OptionFlags::NONE,
+        glib::OptionArg::String,
+        "Due date of the task, like \"tomorrow\" or \"2024-05-01\"",
+        Some("DATE"),
+    );
+    application.add_main_option(
+        "format",
+        glib::Char::from(b'f'),
+        glib::OptionFlags::NONE,
+        glib::OptionArg::String,
+        "Format to export to: todo.txt, csv, markdown, org or json",
+        Some("FORMAT"),
+    );
+    application.add_main_option(
+        "json",
+        glib::Char::from(0),
+        glib::OptionFlags::NONE,
+        glib::OptionArg::None,
+        "Print JSON instead of text, for scripts",
+        None,
+    );
+    application.add_main_option(
+        "quick-add",
+        glib::Char::from(0),
+        glib::OptionFlags::NONE,
+        glib::OptionArg::None,
+        "Show a small window to add a task, for a keyboard shortcut of the desktop",
+        None,
+    );
+    application.add_main_option(
+        "today",
+        glib::Char::from(0),
+        glib::OptionFlags::NONE,
+        glib::OptionArg::None,
+        "Show the tasks due today",
+        None,
+    );
+    // Everything that is not an option, G_OPTION_REMAINING
+    application.add_main_option(
+        "",
+        glib::Char::from(0),
+        glib::OptionFlags::NONE,
+        glib::OptionArg::StringArray,
+        "",
+        Some("add|list|done|export|LINK"),
+    );
+}
+
+// Runs the command given on the command line in the running instance, starting it
+// without a window if needed. `None` if there is no command, to start as usual.
+pub fn handle(options: &glib::VariantDict) -> Option<glib::ExitCode> {
+    let arguments = remaining_arguments(options);
+    let (command, arguments) = arguments.split_first()?;
+
+    let result = match command.as_str() {
+        "add" => add(options, arguments),
+        "list" => list(options, arguments),
+        "done" => done(options, arguments),
+        "export" => export(options, arguments),
+        _ => return None,
+    };
+    match result {
+        Ok(message) => {
+            println!("{message}");
+            Some(glib::ExitCode::SUCCESS)
+        }
+        Err(message) => {
+            eprintln!("{message}");
+            Some(glib::ExitCode::FAILURE)
+        }
+    }
+}
+
+// Arguments that are no command are files or links to open
+pub fn files(options: &glib::VariantDict) -> Vec<gio::File> {
+    remaining_arguments(options)
+        .iter()
+        .map(gio::File::for_commandline_arg)
+        .collect()
+}
+
+fn remaining_arguments(options: &glib::VariantDict) -> Vec<String> {
+    options.lookup("").ok().flatten().unwrap_or_default()
+}
+
+fn add(options: &glib::VariantDict, arguments: &[String]) -> Result<String, String> {
+    let collection: String = options.lookup("collection").ok().flatten().unwrap_or_default();
+    let mut text = arguments.join(" ");
+    if text.trim().is_empty() {
+        return Err(USAGE.to_string());
+    }
+
+    // The date is added to the text, where it is parsed like in the window
+    if let Some(due) = options.lookup::<String>("due").ok().flatten() {
+        let due_date = parser::parse_due_date(&due).ok_or_else(|| format!("“{due}” is not a date"))?;
+        text.push(' ');
+        text.push_str(&due_date);
+    }
+
+    let reply = call("AddTask", (collection.clone(), text.clone()).to_variant(), "(s)")?;
+    let content = parser::parse_task(&text).content;
+    if wants_json(options) {
+        let (uuid,) = reply.get::<(String,)>().unwrap_or_default();
+        Ok(json!({ "uuid": uuid, "content": content }).to_string())
+    } else if collection.is_empty() {
+        Ok(format!("Added “{content}”"))
+    } else {
+        Ok(format!("Added “{content}” to {collection}"))
+    }
+}
+
+// The tasks of one collection, or of all of them, one per line:
+// the id, "[x]" for done tasks, the content and the due date
+fn list(options: &glib::VariantDict, arguments: &[String]) -> Result<String, String> {
+    let collections: Vec<(String, String)> = match arguments {
+        [] => {
+            let reply = call("ListCollections", ().to_variant(), "(a(ssu))")?;
+            let (collections,) = reply.get::<(Vec<(String, String, u32)>,)>().unwrap_or_default();
+            collections.into_iter().map(|(uuid, title, _)| (uuid, title)).collect()
+        }
+        [collection] => vec![(collection.clone(), collection.clone())],
+        _ => return Err(USAGE.to_string()),
+    };
+
+    let mut tasks = Vec::new();
+    for (uuid, title) in collections {
+        let reply = call("ListTasks", (uuid,).to_variant(), "(a(ssbs))")?;
+        let (collection_tasks,) = reply.get::<(Vec<(String, String, bool, String)>,)>().unwrap_or_default();
+        tasks.extend(collection_tasks.into_iter().map(|task| (title.clone(), task)));
+    }
+
+    if wants_json(options) {
+        let tasks: Vec<serde_json::Value> = tasks
+            .into_iter()
+            .map(|(collection, (uuid, content, completed, due_date))| {
+                json!({
+                    "uuid": uuid,
+                    "content": content,
+                    "completed": completed,
+                    "due_date": due_date,
+                    "collection": collection,
+                })
+            })
+            .collect();
+        return Ok(serde_json::Value::from(tasks).to_string());
+    }
+
+    let lines: Vec<String> = tasks
+        .into_iter()
+        .map(|(_, (uuid, content, completed, due_date))| {
+            let check = if completed { "[x]" } else { "[ ]" };
+            if due_date.is_empty() {
+                format!("{uuid} {check} {content}")
+            } else {
+                format!("{uuid} {check} {content} (due {due_date})")
+            }
+        })
+        .collect();
+    Ok(lines.join("\n"))
+}
+
+fn done(options: &glib::VariantDict, arguments: &[String]) -> Result<String, String> {
+    let [uuid] = arguments else {
+        return Err(USAGE.to_string());
+    };
+
+    call("CompleteTask", (uuid.clone(),).to_variant(), "()")?;
+    if wants_json(options) {
+        Ok(json!({ "uuid": uuid, "completed": true }).to_string())
+    } else {
+        Ok(format!("Completed {uuid}"))
+    }
+}
+
+fn export(options: &glib::VariantDict, arguments: &[String]) -> Result<String, String> {
+    let collection = match arguments {
+        [] => String::new(),
+        [collection] => collection.clone(),
+        _ => return Err(USAGE.to_string()),
+    };
+    // `--json` alone exports to JSON as well
+    let format = options
+        .lookup::<String>("format")
+        .ok()
+        .flatten()
+        .or_else(|| wants_json(options).then(|| "json".to_string()))
+        .ok_or_else(|| format!("Choose a format: {}", dbus_api::EXPORT_FORMATS.join(", ")))?;
+
+    let reply = call("Export", (collection, format).to_variant(), "(s)")?;
+    let (contents,) = reply.get::<(String,)>().unwrap_or_default();
+    Ok(contents.trim_end().to_string())
+}
+
+pub fn wants_quick_add(options: &glib::VariantDict) -> bool {
+    options.lookup::<bool>("quick-add").ok().flatten().unwrap_or(false)
+}
+
+pub fn wants_today(options: &glib::VariantDict) -> bool {
+    options.lookup::<bool>("today").ok().flatten().unwrap_or(false)
+}
+
+fn wants_json(options: &glib::VariantDict) -> bool {
+    options.lookup::<bool>("json").ok().flatten().unwrap_or(false)
+}
+
+// Calls the D-Bus API of the running instance, see `dbus_api`
+fn call(method: &str, parameters: glib::Variant, reply_type: &str) -> Result<glib::Variant, String> {
+    let connection = gio::bus_get_sync(gio::BusType::Session, gio::Cancellable::NONE)
+        .map_err(|err| format!("Could not connect to the session bus: {err}"))?;
+    connection
+        .call_sync(
+            Some(APP_ID),
+            dbus_api::OBJECT_PATH,
+            dbus_api::INTERFACE_NAME,
+            method,
+            Some(&parameters),
+            glib::VariantTy::new(reply_type).ok(),
+            gio::DBusCallFlags::NONE,
+            -1,
+            gio::Cancellable::NONE,
+        )
+        .map_err(|err| error_message(&err))
+}
+
+// Errors of the running instance come with the name of the D-Bus error in front
+fn error_message(err: &glib::Error) -> String {
+    let message = err.message();
+    message
+        .strip_prefix("GDBus.Error:")
+        .and_then(|message| message.split_once(": "))
+        .map_or(message, |(_, message)| message)
+        .to_string()
+}
@@ -1,4 +1,4 @@
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 
 use adw::prelude::*;
 use adw::subclass::prelude::*;
@@ -11,10 +11,48 @@ use std::cell::OnceCell;
 #[derive(Properties, Default)]
 #[properties(wrapper_type = super::CollectionObject)]
 pub struct CollectionObject {
+    #[property(get)]
+    pub uuid: RefCell<String>,
     #[property(get, set)]
     pub title: RefCell<String>,
     #[property(get, set)]
     pub tasks: OnceCell<gio::ListStore>,
+    // Pinned collections are listed first in the sidebar
+    #[property(get, set)]
+    pub pinned: Cell<bool>,
+    // Empty for the default icon and no color
+    #[property(get, set)]
+    pub icon_name: RefCell<String>,
+    #[property(get, set)]
+    pub color: RefCell<String>,
+    // Name of the folder the collection is listed in, empty for none
+    #[property(get, set)]
+    pub folder: RefCell<String>,
+    // Names of the sections the tasks can be grouped into, in order
+    #[property(get, set)]
+    pub sections: RefCell<Vec<String>>,
+    // The state filter (All, Open, Done, ...) last chosen for this collection
+    #[property(get, set)]
+    pub filter: RefCell<String>,
+    // Deleted tasks, kept until they are restored or purged
+    #[property(get, set)]
+    pub trash: OnceCell<gio::ListStore>,
+    // Kept up to date with the tasks, for the sidebar
+    #[property(get)]
+    pub task_count: Cell<u32>,
+    #[property(get)]
+    pub done_count: Cell<u32>,
+    // Fraction of the tasks that are done, between 0 and 1
+    #[property(get)]
+    pub percent_done: Cell<f64>,
+    // Completed tasks moved out of the way, kept for their history
+    #[property(get, set)]
+    pub archived: OnceCell<gio::ListStore>,
+    // Timestamps as returned by `utils::timestamp`
+    #[property(get)]
+    pub created_at: RefCell<String>,
+    #[property(get)]
+    pub modified_at: RefCell<String>,
 }
 
 // The central trait for subclassing a GObject
@@ -7,6 +7,32 @@ use gtk::{gio, glib};
 use serde::{Deserialize, Serialize};
 
 use crate::task_object::{TaskData, TaskObject};
+use crate::utils;
+
+pub const DEFAULT_FILTER: &str = "All";
+
+// Changes to these properties count as modifying the collection, the others are
+// derived from the tasks or only remember how the collection is shown
+const TRACKED_PROPERTIES: [&str; 6] = ["title", "pinned", "icon-name", "color", "folder", "sections"];
+
+pub const DEFAULT_ICON_NAME: &str = "folder-symbolic";
+pub const ICON_NAMES: [&str; 8] = [
+    DEFAULT_ICON_NAME,
+    "user-home-symbolic",
+    "starred-symbolic",
+    "emblem-favorite-symbolic",
+    "emblem-important-symbolic",
+    "x-office-calendar-symbolic",
+    "emblem-documents-symbolic",
+    "applications-games-symbolic",
+];
+
+// Each color has a matching "collection-<color>" style class
+pub const COLORS: [&str; 7] = ["blue", "green", "yellow", "orange", "red", "purple", "brown"];
+
+pub fn color_css_class(color: &str) -> Option<String> {
+    COLORS.contains(&color).then(|| format!("collection-{color}"))
+}
 
 glib::wrapper! {
     pub struct CollectionObject(ObjectSubclass<imp::CollectionObject>);
@@ -15,43 +41,239 @@ glib::wrapper! {
 // ANCHOR: impl
 impl CollectionObject {
     pub fn new(title: &str, tasks: gio::ListStore) -> Self {
-        Object::builder()
+        Self::with_uuid(&utils::new_uuid(), title, tasks)
+    }
+
+    fn with_uuid(uuid: &str, title: &str, tasks: gio::ListStore) -> Self {
+        let collection_object: Self = Object::builder()
             .property("title", title)
             .property("tasks", tasks)
-            .build()
+            .property("filter", DEFAULT_FILTER)
+            .property("trash", gio::ListStore::new::<TaskObject>())
+            .property("archived", gio::ListStore::new::<TaskObject>())
+            .build();
+        collection_object.imp().uuid.replace(uuid.to_string());
+        let timestamp = utils::timestamp();
+        collection_object.imp().modified_at.replace(timestamp.clone());
+        collection_object.imp().created_at.replace(timestamp);
+        collection_object.watch_tasks();
+
+        collection_object.connect_notify_local(None, |collection, pspec| {
+            if TRACKED_PROPERTIES.contains(&pspec.name()) {
+                collection.touch();
+            }
+        });
+        collection_object
+    }
+
+    fn touch(&self) {
+        self.imp().modified_at.replace(utils::timestamp());
+        self.notify_modified_at();
+    }
+
+    fn watch_tasks(&self) {
+        let tasks = self.tasks();
+        for task in tasks.iter::<TaskObject>().filter_map(Result::ok) {
+            self.watch_task(&task);
+        }
+
+        tasks.connect_items_changed(glib::clone!(@weak self as collection => move |tasks, position, _, added| {
+            for task in (position..position + added)
+                .filter_map(|position| tasks.item(position).and_downcast::<TaskObject>())
+            {
+                collection.watch_task(&task);
+            }
+            collection.update_counts();
+        }));
+
+        self.update_counts();
+    }
+
+    fn watch_task(&self, task: &TaskObject) {
+        // Tasks moved elsewhere keep the handler, recounting this collection is harmless
+        task.connect_notify_local(Some("completed"), glib::clone!(@weak self as collection => move |_, _| {
+            collection.update_counts();
+        }));
+    }
+
+    fn update_counts(&self) {
+        let tasks = self.tasks();
+        let task_count = tasks.n_items();
+        let done_count = tasks
+            .iter::<TaskObject>()
+            .filter_map(Result::ok)
+            .filter(TaskObject::is_completed)
+            .count() as u32;
+
+        if self.imp().task_count.replace(task_count) != task_count {
+            self.notify_task_count();
+        }
+        if self.imp().done_count.replace(done_count) != done_count {
+            self.notify_done_count();
+        }
+
+        let percent_done = if task_count > 0 {
+            f64::from(done_count) / f64::from(task_count)
+        } else {
+            0.0
+        };
+        if self.imp().percent_done.replace(percent_done) != percent_done {
+            self.notify_percent_done();
+        }
+    }
+
+    pub fn icon(&self) -> String {
+        let icon_name = self.icon_name();
+        if icon_name.is_empty() {
+            DEFAULT_ICON_NAME.to_string()
+        } else {
+            icon_name
+        }
+    }
+
+    // A copy with uuids of its own, handy for checklists that are used again and again
+    pub fn duplicate(&self) -> Self {
+        let mut collection_data = self.to_collection_data();
+        collection_data.title = format!("{} (copy)", collection_data.title);
+        Self::from_collection_data(Self::with_new_uuids(collection_data))
+    }
+
+    // The collection without the progress on its tasks, to start over from later
+    pub fn to_template(&self) -> CollectionData {
+        let mut template = self.to_collection_data();
+        template.filter.clear();
+        template.trash_data.clear();
+        template.archived_data.clear();
+        for task_data in &mut template.tasks_data {
+            task_data.completed = false;
+        }
+        template
+    }
+
+    pub fn from_template(template: &CollectionData, title: &str) -> Self {
+        let mut collection_data = template.clone();
+        collection_data.title = title.to_string();
+        Self::from_collection_data(Self::with_new_uuids(collection_data))
+    }
+
+    // Copies must not share uuids with the original, only the current tasks are kept
+    fn with_new_uuids(mut collection_data: CollectionData) -> CollectionData {
+        collection_data.uuid = utils::new_uuid();
+        collection_data.created_at.clear();
+        collection_data.modified_at.clear();
+        collection_data.pinned = false;
+        collection_data.trash_data.clear();
+        collection_data.archived_data.clear();
+        for task_data in &mut collection_data.tasks_data {
+            task_data.renew();
+        }
+        collection_data
     }
 
     pub fn to_collection_data(&self) -> CollectionData {
+        let uuid = self.uuid();
         let title = self.imp().title.borrow().clone();
-        let tasks_data = self
-            .tasks()
+        let tasks_data = Self::tasks_data(&self.tasks());
+        let trash_data = Self::tasks_data(&self.trash());
+        let archived_data = Self::tasks_data(&self.archived());
+        let filter = self.filter();
+        let icon_name = self.icon_name();
+        let color = self.color();
+        let pinned = self.pinned();
+        let folder = self.folder();
+        let sections = self.sections();
+        let created_at = self.created_at();
+        let modified_at = self.modified_at();
+        CollectionData {
+            uuid,
+            title,
+            icon_name,
+            color,
+            pinned,
+            folder,
+            sections,
+            filter,
+            tasks_data,
+            trash_data,
+            archived_data,
+            created_at,
+            modified_at,
+        }
+    }
+
+    fn tasks_data(tasks: &gio::ListStore) -> Vec<TaskData> {
+        tasks
             .iter::<TaskObject>()
             .filter_map(Result::ok)
             .map(|task_object| task_object.task_data())
-            .collect();
-        CollectionData { title, tasks_data }
+            .collect()
+    }
+
+    fn tasks_from_data(tasks_data: Vec<TaskData>) -> Vec<TaskObject> {
+        tasks_data.into_iter().map(TaskObject::from_task_data).collect()
     }
 
     pub fn from_collection_data(collection_data: CollectionData) -> Self {
         let title = collection_data.title;
-        let tasks_to_extend: Vec<TaskObject> = collection_data
-            .tasks_data
-            .into_iter()
-            .map(TaskObject::from_task_data)
-            .collect();
-
         let tasks = gio::ListStore::new::<TaskObject>();
-        tasks.extend_from_slice(&tasks_to_extend);
+        tasks.extend_from_slice(&Self::tasks_from_data(collection_data.tasks_data));
+
+        let collection_object = Self::with_uuid(&collection_data.uuid, &title, tasks);
+        collection_object.set_icon_name(collection_data.icon_name);
+        collection_object.set_color(collection_data.color);
+        collection_object.set_pinned(collection_data.pinned);
+        collection_object.set_folder(collection_data.folder);
+        collection_object.set_sections(collection_data.sections);
+        if !collection_data.filter.is_empty() {
+            collection_object.set_filter(collection_data.filter);
+        }
+        collection_object
+            .trash()
+            .extend_from_slice(&Self::tasks_from_data(collection_data.trash_data));
+        collection_object
+            .archived()
+            .extend_from_slice(&Self::tasks_from_data(collection_data.archived_data));
+
+        // Setting the properties above touched the collection, the stored timestamps
+        // win. Collections saved before they were tracked keep the current time.
+        if !collection_data.created_at.is_empty() {
+            collection_object.imp().created_at.replace(collection_data.created_at);
+        }
+        if !collection_data.modified_at.is_empty() {
+            collection_object.imp().modified_at.replace(collection_data.modified_at);
+        }
 
-        Self::new(&title, tasks)
+        collection_object
     }
 }
 // ANCHOR_END: impl
 
 // ANCHOR: collection_data
-#[derive(Default, Clone, Serialize, Deserialize)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct CollectionData {
+    pub uuid: String,
     pub title: String,
+    #[serde(default)]
+    pub icon_name: String,
+    #[serde(default)]
+    pub color: String,
+    #[serde(default)]
+    pub pinned: bool,
+    #[serde(default)]
+    pub folder: String,
+    #[serde(default)]
+    pub sections: Vec<String>,
+    #[serde(default)]
+    pub filter: String,
     pub tasks_data: Vec<TaskData>,
+    #[serde(default)]
+    pub trash_data: Vec<TaskData>,
+    #[serde(default)]
+    pub archived_data: Vec<TaskData>,
+    // Timestamps as returned by `utils::timestamp`
+    #[serde(default)]
+    pub created_at: String,
+    #[serde(default)]
+    pub modified_at: String,
 }
 // ANCHOR_END: collection_data
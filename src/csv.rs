@@ -0,0 +1,169 @@
+use anyhow::{bail, Context};
+
+use crate::collection_object::CollectionData;
+use crate::task_object::{normalize_tag, Priority, TaskData};
+use crate::utils;
+
+// Rows without a collection are imported into this one
+pub const DEFAULT_COLLECTION: &str = "Imported";
+
+const HEADER: [&str; 7] = ["collection", "content", "completed", "due_date", "priority", "tags", "notes"];
+
+// Columns are found by their header, so spreadsheets exported by other tools work too
+const COLLECTION_COLUMNS: [&str; 4] = ["collection", "list", "project", "category"];
+const CONTENT_COLUMNS: [&str; 5] = ["content", "title", "task", "name", "subject"];
+const COMPLETED_COLUMNS: [&str; 4] = ["completed", "done", "status", "state"];
+const DUE_DATE_COLUMNS: [&str; 4] = ["due_date", "due date", "due", "deadline"];
+const PRIORITY_COLUMNS: [&str; 2] = ["priority", "importance"];
+const TAGS_COLUMNS: [&str; 3] = ["tags", "labels", "contexts"];
+const NOTES_COLUMNS: [&str; 4] = ["notes", "description", "body", "comment"];
+
+pub fn to_string(collections: &[CollectionData]) -> String {
+    let mut contents = format_row(&HEADER.map(str::to_string));
+
+    for collection_data in collections {
+        for task_data in &collection_data.tasks_data {
+            let priority = match task_data.priority {
+                Priority::None => "",
+                Priority::Low => "low",
+                Priority::Medium => "medium",
+                Priority::High => "high",
+            };
+            contents.push_str(&format_row(&[
+                collection_data.title.clone(),
+                task_data.content.clone(),
+                task_data.completed.to_string(),
+                task_data.due_date.clone(),
+                priority.to_string(),
+                task_data.tags.join("; "),
+                task_data.notes.clone(),
+            ]));
+        }
+    }
+
+    contents
+}
+
+fn format_row(cells: &[String]) -> String {
+    let cells: Vec<String> = cells.iter().map(|cell| quote(cell)).collect();
+    format!("{}\r\n", cells.join(","))
+}
+
+fn quote(cell: &str) -> String {
+    if cell.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", cell.replace('"', "\"\""))
+    } else {
+        cell.to_string()
+    }
+}
+
+pub fn parse(contents: &str) -> anyhow::Result<Vec<CollectionData>> {
+    let mut rows = parse_rows(contents).into_iter();
+    let header: Vec<String> = rows
+        .next()
+        .context("The file is empty")?
+        .iter()
+        .map(|cell| cell.trim().to_lowercase())
+        .collect();
+    let column = |names: &[&str]| header.iter().position(|cell| names.contains(&cell.as_str()));
+
+    let Some(content_column) = column(&CONTENT_COLUMNS) else {
+        bail!("The file has no column with the tasks");
+    };
+    let collection_column = column(&COLLECTION_COLUMNS);
+    let completed_column = column(&COMPLETED_COLUMNS);
+    let due_date_column = column(&DUE_DATE_COLUMNS);
+    let priority_column = column(&PRIORITY_COLUMNS);
+    let tags_column = column(&TAGS_COLUMNS);
+    let notes_column = column(&NOTES_COLUMNS);
+
+    let mut collections: Vec<CollectionData> = Vec::new();
+    for row in rows {
+        let cell = |column: Option<usize>| {
+            column
+                .and_then(|column| row.get(column))
+                .map(|cell| cell.trim())
+                .unwrap_or_default()
+        };
+
+        let content = cell(Some(content_column));
+        if content.is_empty() {
+            continue;
+        }
+
+        let task_data = TaskData {
+            uuid: utils::new_uuid(),
+            completed: matches!(
+                cell(completed_column).to_lowercase().as_str(),
+                "true" | "yes" | "1" | "x" | "done" | "completed"
+            ),
+            content: content.to_string(),
+            notes: cell(notes_column).to_string(),
+            due_date: Some(cell(due_date_column))
+                .filter(|due_date| utils::parse_date(due_date).is_some())
+                .unwrap_or_default()
+                .to_string(),
+            priority: match cell(priority_column).to_lowercase().as_str() {
+                "high" | "1" => Priority::High,
+                "medium" | "normal" | "2" => Priority::Medium,
+                "low" | "3" => Priority::Low,
+                _ => Priority::None,
+            },
+            tags: cell(tags_column)
+                .split([';', ','])
+                .map(normalize_tag)
+                .filter(|tag| !tag.is_empty())
+                .collect(),
+            ..Default::default()
+        };
+
+        let title = Some(cell(collection_column))
+            .filter(|title| !title.is_empty())
+            .unwrap_or(DEFAULT_COLLECTION);
+        match collections.iter_mut().find(|collection| collection.title == title) {
+            Some(collection) => collection.tasks_data.push(task_data),
+            None => collections.push(CollectionData {
+                uuid: utils::new_uuid(),
+                title: title.to_string(),
+                tasks_data: vec![task_data],
+                ..Default::default()
+            }),
+        }
+    }
+
+    Ok(collections)
+}
+
+// Quoted cells may hold commas, doubled quotes and line breaks
+fn parse_rows(contents: &str) -> Vec<Vec<String>> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut cell = String::new();
+    let mut in_quotes = false;
+    let mut chars = contents.trim_start_matches('\u{feff}').chars().peekable();
+
+    while let Some(char) = chars.next() {
+        match (char, in_quotes) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                cell.push('"');
+                chars.next();
+            }
+            ('"', true) => in_quotes = false,
+            ('"', false) if cell.is_empty() => in_quotes = true,
+            (',', false) => row.push(std::mem::take(&mut cell)),
+            ('\r', false) => {}
+            ('\n', false) => {
+                row.push(std::mem::take(&mut cell));
+                rows.push(std::mem::take(&mut row));
+            }
+            (char, _) => cell.push(char),
+        }
+    }
+    if !cell.is_empty() || !row.is_empty() {
+        row.push(cell);
+        rows.push(row);
+    }
+
+    rows.retain(|row| row.iter().any(|cell| !cell.trim().is_empty()));
+    rows
+}
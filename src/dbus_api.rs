@@ -0,0 +1,174 @@
+use adw::prelude::*;
+use gio::{DBusConnection, DBusMethodInvocation, RegistrationId};
+use gtk::{gio, glib};
+
+use crate::collection_object::CollectionData;
+use crate::task_object::TaskObject;
+use crate::{csv, markdown, org, todo_txt};
+use crate::{TodoApplication, TodoWindow};
+
+pub const OBJECT_PATH: &str = "/org/gnome/ToDo/Tasks";
+pub const INTERFACE_NAME: &str = "org.gnome.ToDo.Tasks";
+
+const INTERFACE: &str = r#"
+<node>
+  <interface name="org.gnome.ToDo.Tasks">
+    <!-- Adds a task to the collection with the uuid or title, or to the first one if it is
+         empty. The text may hold #tags, a !priority and a due date like in the window. -->
+    <method name="AddTask">
+      <arg type="s" name="collection" direction="in" />
+      <arg type="s" name="text" direction="in" />
+      <arg type="s" name="uuid" direction="out" />
+    </method>
+    <method name="CompleteTask">
+      <arg type="s" name="uuid" direction="in" />
+    </method>
+    <!-- uuid, title and the number of open tasks of each collection -->
+    <method name="ListCollections">
+      <arg type="a(ssu)" name="collections" direction="out" />
+    </method>
+    <!-- uuid, content, completed and due date ("YYYY-MM-DD" or empty) of each task -->
+    <method name="ListTasks">
+      <arg type="s" name="collection" direction="in" />
+      <arg type="a(ssbs)" name="tasks" direction="out" />
+    </method>
+    <!-- The tasks of the collection, or of all if it is empty, in one of the formats
+         "todo.txt", "csv", "markdown", "org" or "json" -->
+    <method name="Export">
+      <arg type="s" name="collection" direction="in" />
+      <arg type="s" name="format" direction="in" />
+      <arg type="s" name="contents" direction="out" />
+    </method>
+  </interface>
+</node>
+"#;
+
+const ERROR_LOCKED: &str = "org.gnome.ToDo.Error.Locked";
+const ERROR_NOT_FOUND: &str = "org.gnome.ToDo.Error.NotFound";
+const ERROR_INVALID_TASK: &str = "org.gnome.ToDo.Error.InvalidTask";
+const ERROR_UNKNOWN_FORMAT: &str = "org.gnome.ToDo.Error.UnknownFormat";
+
+pub const EXPORT_FORMATS: [&str; 5] = ["todo.txt", "csv", "markdown", "org", "json"];
+
+// Lets scripts and other applications manage tasks
+pub fn register(application: &TodoApplication, connection: &DBusConnection) -> Result<RegistrationId, glib::Error> {
+    let interface_info = gio::DBusNodeInfo::for_xml(INTERFACE)?
+        .lookup_interface(INTERFACE_NAME)
+        .expect("the tasks interface should be defined");
+    let application = application.downgrade();
+
+    connection
+        .register_object(OBJECT_PATH, &interface_info)
+        .method_call(move |_, _, _, _, method_name, parameters, invocation| {
+            let Some(application) = application.upgrade() else {
+                return;
+            };
+            let window = application.main_window();
+            if window.is_locked() {
+                invocation.return_dbus_error(ERROR_LOCKED, "The tasks are locked");
+                return;
+            }
+            handle_method_call(&window, method_name, &parameters, invocation);
+        })
+        .build()
+}
+
+fn handle_method_call(window: &TodoWindow, method_name: &str, parameters: &glib::Variant, invocation: DBusMethodInvocation) {
+    match method_name {
+        "AddTask" => {
+            let Some((collection, text)) = parameters.get::<(String, String)>() else {
+                return invalid_arguments(invocation);
+            };
+            let collection = if collection.is_empty() {
+                window.collection_list().into_iter().next()
+            } else {
+                window.find_collection(&collection)
+            };
+            let Some(collection) = collection else {
+                return invocation.return_dbus_error(ERROR_NOT_FOUND, "There is no such collection");
+            };
+            match window.add_task(&collection, &text) {
+                Some(task) => invocation.return_value(Some(&(task.uuid(),).to_variant())),
+                None => invocation.return_dbus_error(ERROR_INVALID_TASK, "The task has no content"),
+            }
+        }
+        "CompleteTask" => {
+            let Some((uuid,)) = parameters.get::<(String,)>() else {
+                return invalid_arguments(invocation);
+            };
+            let Some(task) = window.task_by_uuid(&uuid) else {
+                return invocation.return_dbus_error(ERROR_NOT_FOUND, "There is no such task");
+            };
+            window.complete_task(&task);
+            invocation.return_value(None);
+        }
+        "ListCollections" => {
+            let collections: Vec<(String, String, u32)> = window
+                .collection_list()
+                .iter()
+                .map(|collection| {
+                    let open_tasks = collection
+                        .tasks()
+                        .iter::<TaskObject>()
+                        .filter_map(Result::ok)
+                        .filter(|task| !task.is_completed())
+                        .count();
+                    (collection.uuid(), collection.title(), open_tasks as u32)
+                })
+                .collect();
+            invocation.return_value(Some(&(collections,).to_variant()));
+        }
+        "ListTasks" => {
+            let Some((collection,)) = parameters.get::<(String,)>() else {
+                return invalid_arguments(invocation);
+            };
+            let Some(collection) = window.find_collection(&collection) else {
+                return invocation.return_dbus_error(ERROR_NOT_FOUND, "There is no such collection");
+            };
+            let tasks: Vec<(String, String, bool, String)> = collection
+                .tasks()
+                .iter::<TaskObject>()
+                .filter_map(Result::ok)
+                .map(|task| (task.uuid(), task.content(), task.is_completed(), task.due_date()))
+                .collect();
+            invocation.return_value(Some(&(tasks,).to_variant()));
+        }
+        "Export" => {
+            let Some((collection, format)) = parameters.get::<(String, String)>() else {
+                return invalid_arguments(invocation);
+            };
+            let collections = if collection.is_empty() {
+                window.collection_list()
+            } else {
+                match window.find_collection(&collection) {
+                    Some(collection) => vec![collection],
+                    None => return invocation.return_dbus_error(ERROR_NOT_FOUND, "There is no such collection"),
+                }
+            };
+            let collections_data: Vec<CollectionData> = collections
+                .iter()
+                .map(|collection| collection.to_collection_data())
+                .collect();
+            match export(&collections_data, &format) {
+                Some(contents) => invocation.return_value(Some(&(contents,).to_variant())),
+                None => invocation.return_dbus_error(ERROR_UNKNOWN_FORMAT, &format!("Unknown format “{format}”")),
+            }
+        }
+        _ => invocation.return_error(gio::DBusError::UnknownMethod, &format!("Unknown method {method_name}")),
+    }
+}
+
+fn export(collections_data: &[CollectionData], format: &str) -> Option<String> {
+    match format {
+        "todo.txt" => Some(todo_txt::to_string(collections_data)),
+        "csv" => Some(csv::to_string(collections_data)),
+        "markdown" => Some(markdown::to_string(collections_data)),
+        "org" => Some(org::to_string(collections_data)),
+        "json" => serde_json::to_string_pretty(collections_data).ok(),
+        _ => None,
+    }
+}
+
+fn invalid_arguments(invocation: DBusMethodInvocation) {
+    invocation.return_error(gio::DBusError::InvalidArgs, "Invalid arguments");
+}
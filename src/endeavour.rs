@@ -0,0 +1,64 @@
+use std::path::PathBuf;
+
+use gtk::glib;
+
+use crate::collection_object::CollectionData;
+use crate::ical;
+
+// GNOME To Do (Endeavour) keeps its tasks in the local task lists of evolution-data-server
+const SYSTEM_LIST_UID: &str = "system";
+const SYSTEM_LIST_NAME: &str = "Personal";
+
+// Inside the sandbox the directories of the host are passed on by Flatpak
+fn host_dir(variable: &str, fallback: PathBuf) -> PathBuf {
+    std::env::var_os(variable).map(PathBuf::from).unwrap_or(fallback)
+}
+
+fn tasks_dir() -> PathBuf {
+    host_dir("HOST_XDG_DATA_HOME", glib::user_data_dir()).join("evolution/tasks")
+}
+
+fn sources_dir() -> PathBuf {
+    host_dir("HOST_XDG_CONFIG_HOME", glib::user_config_dir()).join("evolution/sources")
+}
+
+// The name a list has in GNOME To Do, kept in the source file of its uid
+fn display_name(uid: &str) -> Option<String> {
+    let key_file = glib::KeyFile::new();
+    key_file
+        .load_from_file(sources_dir().join(format!("{uid}.source")), glib::KeyFileFlags::NONE)
+        .ok()?;
+    key_file
+        .locale_string("Data Source", "DisplayName", None)
+        .ok()
+        .map(|display_name| display_name.to_string())
+}
+
+// Every local task list that has tasks, blocking while the files are read
+pub fn find_task_lists() -> Vec<CollectionData> {
+    let Ok(entries) = std::fs::read_dir(tasks_dir()) else {
+        return Vec::new();
+    };
+
+    let mut task_lists: Vec<CollectionData> = entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let uid = entry.file_name().to_string_lossy().to_string();
+            let contents = std::fs::read_to_string(entry.path().join("tasks.ics")).ok()?;
+
+            let mut collection_data = ical::parse(&contents);
+            collection_data.title = display_name(&uid).unwrap_or_else(|| {
+                if uid == SYSTEM_LIST_UID {
+                    SYSTEM_LIST_NAME.to_string()
+                } else {
+                    uid
+                }
+            });
+            Some(collection_data)
+        })
+        .filter(|collection_data| !collection_data.tasks_data.is_empty())
+        .collect();
+
+    task_lists.sort_by(|a, b| a.title.cmp(&b.title));
+    task_lists
+}
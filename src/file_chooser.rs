@@ -0,0 +1,57 @@
+use std::path::PathBuf;
+
+use ashpd::desktop::file_chooser::{FileFilter, SelectedFiles};
+use ashpd::WindowIdentifier;
+use gtk::glib;
+use gtk::prelude::*;
+
+// Thin wrappers around the file chooser portal. Both return `None` if the user
+// cancelled the dialog or the portal is unavailable.
+
+pub async fn open_file(
+    window: &impl IsA<gtk::Native>,
+    title: &str,
+    filter: FileFilter,
+) -> Option<PathBuf> {
+    let identifier = WindowIdentifier::from_native(window).await;
+    let request = SelectedFiles::open_file()
+        .identifier(identifier)
+        .title(title)
+        .modal(true)
+        .multiple(false)
+        .filter(filter)
+        .send()
+        .await;
+
+    selected_path(request.and_then(|request| request.response()))
+}
+
+pub async fn save_file(
+    window: &impl IsA<gtk::Native>,
+    title: &str,
+    current_name: &str,
+    filter: FileFilter,
+) -> Option<PathBuf> {
+    let identifier = WindowIdentifier::from_native(window).await;
+    let request = SelectedFiles::save_file()
+        .identifier(identifier)
+        .title(title)
+        .modal(true)
+        .current_name(current_name)
+        .filter(filter)
+        .send()
+        .await;
+
+    selected_path(request.and_then(|request| request.response()))
+}
+
+fn selected_path(files: ashpd::Result<SelectedFiles>) -> Option<PathBuf> {
+    match files {
+        Ok(files) => files.uris().first()?.to_file_path().ok(),
+        Err(ashpd::Error::Response(ashpd::desktop::ResponseError::Cancelled)) => None,
+        Err(err) => {
+            glib::g_warning!("todo", "Could not use the file chooser portal: {}", err);
+            None
+        }
+    }
+}
@@ -0,0 +1,38 @@
+use adw::prelude::*;
+use gtk::{gio, glib};
+
+use crate::task_object::TaskObject;
+
+// Length of one focus session
+pub const FOCUS_MINUTES: i64 = 25;
+
+// A running focus session on a task
+#[derive(Debug)]
+pub struct Focus {
+    pub task: TaskObject,
+    // Monotonic time the session started, in microseconds
+    pub started: i64,
+    pub source_id: glib::SourceId,
+}
+
+impl Focus {
+    pub fn elapsed_seconds(&self) -> i64 {
+        (glib::monotonic_time() - self.started) / 1_000_000
+    }
+
+    pub fn remaining_seconds(&self) -> i64 {
+        (FOCUS_MINUTES * 60 - self.elapsed_seconds()).max(0)
+    }
+}
+
+// "24:59" for the header bar
+pub fn format_remaining(seconds: i64) -> String {
+    format!("{:02}:{:02}", seconds / 60, seconds % 60)
+}
+
+pub fn send_done(application: &gio::Application, task: &TaskObject) {
+    let notification = gio::Notification::new("Focus session done");
+    notification.set_body(Some(&format!("{FOCUS_MINUTES} minutes on “{}”", task.content())));
+    notification.set_default_action_and_target_value("app.show-task", Some(&task.uuid().to_variant()));
+    application.send_notification(Some("focus"), &notification);
+}
@@ -0,0 +1,291 @@
+use gtk::glib;
+
+use crate::collection_object::CollectionData;
+use crate::task_object::{normalize_tag, Priority, TaskData};
+use crate::utils;
+
+// Collections imported from a calendar without a name get this title
+pub const DEFAULT_CALENDAR_NAME: &str = "Imported";
+
+// Content lines longer than this many octets are folded
+const MAX_LINE_LENGTH: usize = 75;
+
+// A calendar of VTODO components as one collection. Events, journals and
+// unknown properties are skipped. Imported tasks get uuids of their own.
+pub fn parse(contents: &str) -> CollectionData {
+    let mut collection_data = parse_calendar(contents);
+    for task_data in &mut collection_data.tasks_data {
+        task_data.uuid = utils::new_uuid();
+    }
+    collection_data
+}
+
+// The todos of a calendar object, with their UID as uuid
+pub fn parse_todos(contents: &str) -> Vec<TaskData> {
+    parse_calendar(contents).tasks_data
+}
+
+fn parse_calendar(contents: &str) -> CollectionData {
+    let mut collection_data = CollectionData {
+        uuid: utils::new_uuid(),
+        title: DEFAULT_CALENDAR_NAME.to_string(),
+        ..Default::default()
+    };
+    let mut current_todo: Option<TaskData> = None;
+    // Components inside the VTODO, like alarms, have properties of their own
+    let mut nested_depth = 0;
+
+    for line in unfold(contents) {
+        let Some((name, value)) = split_property(&line) else {
+            continue;
+        };
+
+        match (name.as_str(), current_todo.as_mut()) {
+            ("BEGIN", None) if value == "VTODO" => {
+                current_todo = Some(TaskData {
+                    uuid: utils::new_uuid(),
+                    ..Default::default()
+                });
+            }
+            ("BEGIN", Some(_)) => nested_depth += 1,
+            ("END", Some(_)) if nested_depth > 0 => nested_depth -= 1,
+            ("END", Some(_)) if value == "VTODO" => {
+                collection_data.tasks_data.extend(current_todo.take());
+            }
+            (_, Some(_)) if nested_depth > 0 => {}
+            ("X-WR-CALNAME", None) if !value.is_empty() => collection_data.title = unescape(&value),
+            (name, Some(task_data)) => parse_todo_property(task_data, name, &value),
+            _ => {}
+        }
+    }
+
+    collection_data
+}
+
+fn parse_todo_property(task_data: &mut TaskData, name: &str, value: &str) {
+    match name {
+        "UID" if !value.is_empty() => task_data.uuid = value.to_string(),
+        "SUMMARY" => task_data.content = unescape(value),
+        "DESCRIPTION" => task_data.notes = unescape(value),
+        "DUE" => task_data.due_date = parse_date(value).unwrap_or_default(),
+        "DTSTART" => task_data.start_date = parse_date(value).unwrap_or_default(),
+        "COMPLETED" => {
+            task_data.completed = true;
+            task_data.completed_at = parse_timestamp(value).unwrap_or_default();
+        }
+        "STATUS" if value == "COMPLETED" => task_data.completed = true,
+        "PRIORITY" => task_data.priority = parse_priority(value),
+        "RRULE" => task_data.recurrence = value.to_string(),
+        "CATEGORIES" => {
+            for category in split_list(value) {
+                let tag = normalize_tag(&category);
+                if !tag.is_empty() && !task_data.tags.contains(&tag) {
+                    task_data.tags.push(tag);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+// Continuation lines start with a space or a tab
+fn unfold(contents: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+
+    for line in contents.lines() {
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(continuation), Some(last)) => last.push_str(continuation),
+            _ => lines.push(line.to_string()),
+        }
+    }
+
+    lines
+}
+
+// "DUE;VALUE=DATE:20230501" is ("DUE", "20230501"), parameters are not needed
+fn split_property(line: &str) -> Option<(String, String)> {
+    let (name, value) = line.split_once(':')?;
+    let name = name.split(';').next()?.trim().to_uppercase();
+    Some((name, value.trim().to_string()))
+}
+
+// "20230501" or "20230501T120000Z" to "2023-05-01"
+fn parse_date(value: &str) -> Option<String> {
+    let date = value.get(..8).filter(|date| date.bytes().all(|byte| byte.is_ascii_digit()))?;
+    let date = format!("{}-{}-{}", &date[..4], &date[4..6], &date[6..]);
+    utils::parse_date(&date).map(|_| date)
+}
+
+// "20230501T120000Z" is "2023-05-01T12:00:00Z", like `utils::timestamp`
+fn parse_timestamp(value: &str) -> Option<String> {
+    let date = parse_date(value)?;
+    let time = value.get(9..15).filter(|time| time.bytes().all(|byte| byte.is_ascii_digit()))?;
+    Some(format!("{date}T{}:{}:{}Z", &time[..2], &time[2..4], &time[4..]))
+}
+
+// 1 is the highest priority, 9 the lowest and 0 undefined
+fn parse_priority(value: &str) -> Priority {
+    match value.parse::<u8>() {
+        Ok(1..=4) => Priority::High,
+        Ok(5) => Priority::Medium,
+        Ok(6..=9) => Priority::Low,
+        _ => Priority::None,
+    }
+}
+
+fn format_priority(priority: Priority) -> Option<u8> {
+    match priority {
+        Priority::None => None,
+        Priority::Low => Some(9),
+        Priority::Medium => Some(5),
+        Priority::High => Some(1),
+    }
+}
+
+// Splits on commas that are not escaped
+fn split_list(value: &str) -> Vec<String> {
+    let mut items = vec![String::new()];
+    let mut chars = value.chars();
+
+    while let Some(char) = chars.next() {
+        match char {
+            '\\' => {
+                items.last_mut().unwrap().push(char);
+                items.last_mut().unwrap().extend(chars.next());
+            }
+            ',' => items.push(String::new()),
+            _ => items.last_mut().unwrap().push(char),
+        }
+    }
+
+    items.iter().map(|item| unescape(item)).collect()
+}
+
+fn unescape(value: &str) -> String {
+    let mut unescaped = String::new();
+    let mut chars = value.chars();
+
+    while let Some(char) = chars.next() {
+        if char != '\\' {
+            unescaped.push(char);
+            continue;
+        }
+        match chars.next() {
+            Some('n' | 'N') => unescaped.push('\n'),
+            Some(escaped) => unescaped.push(escaped),
+            None => {}
+        }
+    }
+
+    unescaped
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+pub fn to_string(collection_data: &CollectionData) -> String {
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//Todo//NONSGML Todo//EN".to_string(),
+        format!("X-WR-CALNAME:{}", escape(&collection_data.title)),
+    ];
+    for task_data in &collection_data.tasks_data {
+        lines.extend(todo_lines(task_data));
+    }
+    lines.push("END:VCALENDAR".to_string());
+
+    fold(&lines)
+}
+
+// A calendar with the single VTODO of `task_data`, as stored on CalDAV servers
+pub fn todo_to_string(task_data: &TaskData) -> String {
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//Todo//NONSGML Todo//EN".to_string(),
+    ];
+    lines.extend(todo_lines(task_data));
+    lines.push("END:VCALENDAR".to_string());
+
+    fold(&lines)
+}
+
+fn todo_lines(task_data: &TaskData) -> Vec<String> {
+    let timestamp = timestamp();
+    let mut lines = vec![
+        "BEGIN:VTODO".to_string(),
+        format!("UID:{}", task_data.uuid),
+        format!("DTSTAMP:{timestamp}"),
+        format!("SUMMARY:{}", escape(&task_data.content)),
+    ];
+
+    if !task_data.notes.is_empty() {
+        lines.push(format!("DESCRIPTION:{}", escape(&task_data.notes)));
+    }
+    if !task_data.due_date.is_empty() {
+        lines.push(format!("DUE;VALUE=DATE:{}", task_data.due_date.replace('-', "")));
+    }
+    if !task_data.start_date.is_empty() {
+        lines.push(format!("DTSTART;VALUE=DATE:{}", task_data.start_date.replace('-', "")));
+    }
+    if task_data.completed {
+        // Tasks completed before the time was kept use now, the closest there is
+        let completed_at = if task_data.completed_at.is_empty() {
+            timestamp.clone()
+        } else {
+            // iCalendar has no fractions of seconds
+            let seconds = task_data.completed_at.split(['.', 'Z']).next().unwrap_or_default();
+            format!("{}Z", seconds.replace(['-', ':'], ""))
+        };
+        lines.push("STATUS:COMPLETED".to_string());
+        lines.push(format!("COMPLETED:{completed_at}"));
+    } else {
+        lines.push("STATUS:NEEDS-ACTION".to_string());
+    }
+    if let Some(priority) = format_priority(task_data.priority) {
+        lines.push(format!("PRIORITY:{priority}"));
+    }
+    if !task_data.tags.is_empty() {
+        let categories: Vec<String> = task_data.tags.iter().map(|tag| escape(tag)).collect();
+        lines.push(format!("CATEGORIES:{}", categories.join(",")));
+    }
+    if !task_data.recurrence.is_empty() {
+        lines.push(format!("RRULE:{}", task_data.recurrence));
+    }
+    lines.push("END:VTODO".to_string());
+
+    lines
+}
+
+fn timestamp() -> String {
+    glib::DateTime::now_utc()
+        .and_then(|now| now.format("%Y%m%dT%H%M%SZ"))
+        .map(|now| now.to_string())
+        .unwrap_or_default()
+}
+
+// Lines end in CRLF and are folded without splitting characters
+fn fold(lines: &[String]) -> String {
+    let mut contents = String::new();
+
+    for line in lines {
+        let mut length = 0;
+        for char in line.chars() {
+            if length + char.len_utf8() > MAX_LINE_LENGTH {
+                contents.push_str("\r\n ");
+                length = 1;
+            }
+            contents.push(char);
+            length += char.len_utf8();
+        }
+        contents.push_str("\r\n");
+    }
+
+    contents
+}
@@ -0,0 +1,226 @@
+use anyhow::{bail, Context};
+use serde::Deserialize;
+
+use crate::collection_object::CollectionData;
+use crate::task_object::{Priority, TaskData};
+use crate::{csv, ical, migration, org, todo_txt, utils};
+
+// Other task apps whose exports can be moved over once
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    GoogleTasks,
+    MicrosoftToDo,
+}
+
+impl Source {
+    pub const ALL: [Source; 2] = [Source::GoogleTasks, Source::MicrosoftToDo];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Source::GoogleTasks => "Google Tasks",
+            Source::MicrosoftToDo => "Microsoft To Do",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Source::GoogleTasks => "The Tasks.json of a Google Takeout archive",
+            // Outlook only writes PST files, the lists are read as Microsoft Graph returns them
+            Source::MicrosoftToDo => "The JSON of the task lists from Microsoft Graph, with their tasks",
+        }
+    }
+
+    pub fn parse(&self, contents: &str) -> anyhow::Result<Vec<CollectionData>> {
+        match self {
+            Source::GoogleTasks => parse_google_tasks(contents),
+            Source::MicrosoftToDo => parse_microsoft_todo(contents),
+        }
+    }
+}
+
+// Reads a file opened with the application, its kind is told by the extension.
+// JSON may be an export of this application or of one of the `Source`s.
+pub fn parse_file(file_name: &str, contents: &str) -> anyhow::Result<Vec<CollectionData>> {
+    let extension = file_name.rsplit_once('.').map(|(_, extension)| extension.to_lowercase());
+    match extension.as_deref() {
+        Some("ics") => Ok(vec![ical::parse(contents)]),
+        Some("csv") => csv::parse(contents),
+        Some("org") => Ok(org::parse(contents)),
+        Some("txt") => Ok(todo_txt::parse(contents)),
+        Some("json") => parse_json(contents),
+        _ => bail!("Files like “{file_name}” cannot be imported"),
+    }
+}
+
+fn parse_json(contents: &str) -> anyhow::Result<Vec<CollectionData>> {
+    let value: serde_json::Value = serde_json::from_str(contents).context("The file holds no valid JSON")?;
+    // Exports and backups of this application, in any format version
+    if let Ok(store_data) = migration::migrate(value) {
+        let mut collections_data = store_data.collections;
+        // The tasks may still be here, the copies need their own uuids
+        for collection_data in &mut collections_data {
+            collection_data.uuid = utils::new_uuid();
+            let lists = [
+                &mut collection_data.tasks_data,
+                &mut collection_data.trash_data,
+                &mut collection_data.archived_data,
+            ];
+            for task_data in lists.into_iter().flatten() {
+                task_data.renew();
+            }
+        }
+        return Ok(collections_data);
+    }
+
+    // Other JSON may still fit a `Source` loosely, it has to bring some lists along
+    Source::ALL
+        .iter()
+        .find_map(|source| source.parse(contents).ok().filter(|collections_data| !collections_data.is_empty()))
+        .context("The file holds no tasks that can be imported")
+}
+
+fn new_collection(title: String, tasks_data: Vec<TaskData>) -> CollectionData {
+    CollectionData {
+        uuid: utils::new_uuid(),
+        title,
+        tasks_data,
+        ..Default::default()
+    }
+}
+
+// "2023-05-01T00:00:00.000Z" to "2023-05-01"
+fn date_of(timestamp: &str) -> String {
+    timestamp
+        .get(..10)
+        .filter(|date| utils::parse_date(date).is_some())
+        .unwrap_or_default()
+        .to_string()
+}
+
+#[derive(Deserialize)]
+struct GoogleTaskLists {
+    items: Vec<GoogleTaskList>,
+}
+
+#[derive(Deserialize)]
+struct GoogleTaskList {
+    title: String,
+    #[serde(default)]
+    items: Vec<GoogleTask>,
+}
+
+#[derive(Deserialize)]
+struct GoogleTask {
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    notes: String,
+    #[serde(default)]
+    status: String,
+    #[serde(default)]
+    due: String,
+    #[serde(default)]
+    deleted: bool,
+}
+
+fn parse_google_tasks(contents: &str) -> anyhow::Result<Vec<CollectionData>> {
+    let task_lists: GoogleTaskLists =
+        serde_json::from_str(contents).context("This is not a Google Tasks export")?;
+
+    let collections = task_lists
+        .items
+        .into_iter()
+        .map(|task_list| {
+            let tasks_data = task_list
+                .items
+                .into_iter()
+                .filter(|task| !task.deleted && !task.title.trim().is_empty())
+                .map(|task| TaskData {
+                    uuid: utils::new_uuid(),
+                    completed: task.status == "completed",
+                    content: task.title.trim().to_string(),
+                    notes: task.notes,
+                    due_date: date_of(&task.due),
+                    ..Default::default()
+                })
+                .collect();
+            new_collection(task_list.title, tasks_data)
+        })
+        .collect();
+
+    Ok(collections)
+}
+
+#[derive(Deserialize)]
+struct MicrosoftTaskLists {
+    value: Vec<MicrosoftTaskList>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MicrosoftTaskList {
+    display_name: String,
+    #[serde(default)]
+    tasks: Vec<MicrosoftTask>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MicrosoftTask {
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    status: String,
+    #[serde(default)]
+    importance: String,
+    due_date_time: Option<MicrosoftDateTime>,
+    body: Option<MicrosoftBody>,
+    #[serde(default)]
+    categories: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MicrosoftDateTime {
+    date_time: String,
+}
+
+#[derive(Deserialize)]
+struct MicrosoftBody {
+    #[serde(default)]
+    content: String,
+}
+
+fn parse_microsoft_todo(contents: &str) -> anyhow::Result<Vec<CollectionData>> {
+    let task_lists: MicrosoftTaskLists =
+        serde_json::from_str(contents).context("This is not a Microsoft To Do export")?;
+
+    let collections = task_lists
+        .value
+        .into_iter()
+        .map(|task_list| {
+            let tasks_data = task_list
+                .tasks
+                .into_iter()
+                .filter(|task| !task.title.trim().is_empty())
+                .map(|task| TaskData {
+                    uuid: utils::new_uuid(),
+                    completed: task.status == "completed",
+                    content: task.title.trim().to_string(),
+                    notes: task.body.map(|body| body.content).unwrap_or_default(),
+                    due_date: task.due_date_time.map(|due| date_of(&due.date_time)).unwrap_or_default(),
+                    priority: match task.importance.as_str() {
+                        "high" => Priority::High,
+                        "low" => Priority::Low,
+                        _ => Priority::None,
+                    },
+                    tags: task.categories,
+                    ..Default::default()
+                })
+                .collect();
+            new_collection(task_list.display_name, tasks_data)
+        })
+        .collect();
+
+    Ok(collections)
+}
@@ -0,0 +1,33 @@
+// Links into the application, like todo://task/<uuid>, for notifications, search results
+// and other applications
+
+pub const SCHEME: &str = "todo";
+
+#[derive(Debug, PartialEq)]
+pub enum Link {
+    Collection(String),
+    Task(String),
+}
+
+impl Link {
+    pub fn parse(uri: &str) -> Option<Self> {
+        let path = uri.strip_prefix(SCHEME)?.strip_prefix("://")?;
+        let (kind, uuid) = path.trim_end_matches('/').split_once('/')?;
+        if uuid.is_empty() || uuid.contains('/') {
+            return None;
+        }
+
+        match kind {
+            "collection" => Some(Link::Collection(uuid.to_string())),
+            "task" => Some(Link::Task(uuid.to_string())),
+            _ => None,
+        }
+    }
+
+    pub fn to_uri(&self) -> String {
+        match self {
+            Link::Collection(uuid) => format!("{SCHEME}://collection/{uuid}"),
+            Link::Task(uuid) => format!("{SCHEME}://task/{uuid}"),
+        }
+    }
+}
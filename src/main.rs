@@ -19,12 +19,46 @@
  */
 
 mod application;
+mod attachments;
+mod badge;
+mod calendar;
+mod cli;
 mod config;
+mod backup;
+mod csv;
+mod dbus_api;
 mod window;
 
 mod task_object;
 mod task_row;
 mod collection_object;
+mod endeavour;
+mod file_chooser;
+mod focus;
+mod ical;
+mod import;
+mod links;
+mod markdown;
+mod merge;
+mod migration;
+mod org;
+mod page_title;
+mod parser;
+mod passphrase;
+mod preferences_window;
+mod print;
+mod quick_add;
+mod recurrence;
+mod reminders;
+mod share;
+mod search_provider;
+mod smart_collection_object;
+mod smart_view;
+mod stats;
+mod storage;
+mod sync;
+mod todo_txt;
+mod undo;
 mod utils;
 
 use self::application::TodoApplication;
@@ -53,7 +87,7 @@ fn main() -> glib::ExitCode {
     // Create a new GtkApplication. The application manages our main loop,
     // application windows, integration with the window manager/compositor, and
     // desktop features such as file opening and single-instance applications.
-    let app = TodoApplication::new("org.gnome.ToDo", &gio::ApplicationFlags::empty());
+    let app = TodoApplication::new("org.gnome.ToDo", &gio::ApplicationFlags::HANDLES_OPEN);
 
     app.connect_startup(setup_shortcuts);
     // Run the application. This function will block until the application
@@ -67,4 +101,15 @@ fn setup_shortcuts(app: &TodoApplication) {
     app.set_accels_for_action("win.filter('All')", &["<Ctrl>a"]);
     app.set_accels_for_action("win.filter('Open')", &["<Ctrl>o"]);
     app.set_accels_for_action("win.filter('Done')", &["<Ctrl>d"]);
+    app.set_accels_for_action("win.search", &["<Ctrl>f"]);
+    app.set_accels_for_action("win.preferences", &["<Ctrl>comma"]);
+    app.set_accels_for_action("win.print", &["<Ctrl>p"]);
+    app.set_accels_for_action("win.new-detailed-task", &["<Ctrl>Return"]);
+    // Only while tasks are selected, entries keep their own shortcuts as they come first
+    app.set_accels_for_action("win.copy-selected('text')", &["<Ctrl>c"]);
+    app.set_accels_for_action("win.copy-selected('markdown')", &["<Ctrl><Shift>c"]);
+    app.set_accels_for_action("win.undo", &["<Ctrl>z"]);
+    app.set_accels_for_action("win.redo", &["<Ctrl><Shift>z"]);
+    app.set_accels_for_action("win.next-collection", &["<Ctrl>Tab", "<Ctrl>Page_Down"]);
+    app.set_accels_for_action("win.previous-collection", &["<Ctrl><Shift>Tab", "<Ctrl>Page_Up"]);
 }
@@ -0,0 +1,170 @@
+use gtk::glib;
+
+use crate::collection_object::CollectionData;
+use crate::task_object::TaskData;
+
+// A "- [ ] task" checklist. Several collections get a section each.
+pub fn to_string(collections: &[CollectionData]) -> String {
+    if let [collection_data] = collections {
+        return checklist(&collection_data.tasks_data);
+    }
+
+    let sections: Vec<String> = collections
+        .iter()
+        .map(|collection_data| format!("## {}\n\n{}", collection_data.title, checklist(&collection_data.tasks_data)))
+        .collect();
+    sections.join("\n")
+}
+
+pub fn checklist(tasks_data: &[TaskData]) -> String {
+    let mut contents = String::new();
+
+    for task_data in tasks_data {
+        let marker = if task_data.completed { "x" } else { " " };
+        contents.push_str(&format!("- [{marker}] {}\n", task_data.content));
+        // Indented lines continue the list item
+        for line in task_data.notes.lines().filter(|line| !line.trim().is_empty()) {
+            contents.push_str(&format!("  {line}\n"));
+        }
+    }
+
+    contents
+}
+
+pub struct ChecklistItem {
+    pub completed: bool,
+    pub content: String,
+    pub notes: String,
+}
+
+// One item per line of a checklist, a list or plain text. Headings are skipped and
+// indented lines below an item become its notes.
+pub fn parse_checklist(text: &str) -> Vec<ChecklistItem> {
+    let mut items: Vec<ChecklistItem> = Vec::new();
+
+    for line in text.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+
+        let is_indented = line.starts_with("  ") || line.starts_with('\t');
+        let item = parse_item(trimmed);
+        match (item, items.last_mut()) {
+            (None, Some(last)) if is_indented => {
+                if !last.notes.is_empty() {
+                    last.notes.push('\n');
+                }
+                last.notes.push_str(trimmed);
+            }
+            (item, _) => items.push(item.unwrap_or(ChecklistItem {
+                completed: false,
+                content: trimmed.to_string(),
+                notes: String::new(),
+            })),
+        }
+    }
+
+    items.retain(|item| !item.content.is_empty());
+    items
+}
+
+// "- [x] task", "* [ ] task", "1. task" or "- task"
+fn parse_item(line: &str) -> Option<ChecklistItem> {
+    let rest = line
+        .strip_prefix(['-', '*', '+'])
+        .or_else(|| {
+            let digits = line.find(|char: char| !char.is_ascii_digit()).filter(|digits| *digits > 0)?;
+            line[digits..].strip_prefix(['.', ')'])
+        })?
+        .strip_prefix(' ')?
+        .trim_start();
+
+    let (completed, content) = if let Some(content) = rest.strip_prefix("[ ]") {
+        (false, content)
+    } else if let Some(content) = rest.strip_prefix("[x]").or_else(|| rest.strip_prefix("[X]")) {
+        (true, content)
+    } else {
+        (false, rest)
+    };
+
+    Some(ChecklistItem {
+        completed,
+        content: content.trim().to_string(),
+        notes: String::new(),
+    })
+}
+
+// Pango markup for notes written in basic Markdown: headings, lists, **bold**, *italics*,
+// `code` and [links](https://…). Anything else is shown as written.
+pub fn to_pango_markup(text: &str) -> String {
+    text.lines().map(line_markup).collect::<Vec<String>>().join("\n")
+}
+
+fn line_markup(line: &str) -> String {
+    let trimmed = line.trim_start();
+    let indent = &line[..line.len() - trimmed.len()];
+
+    if let Some(heading) = trimmed.strip_prefix('#') {
+        let heading = heading.trim_start_matches('#').trim();
+        return format!("<b>{}</b>", inline_markup(heading));
+    }
+    for bullet in ["- ", "* ", "+ "] {
+        if let Some(item) = trimmed.strip_prefix(bullet) {
+            return format!("{indent}• {}", inline_markup(item));
+        }
+    }
+    inline_markup(line)
+}
+
+fn inline_markup(text: &str) -> String {
+    let mut markup = String::new();
+    let mut rest = text;
+
+    while let Some(start) = rest.find(['*', '_', '`', '[']) {
+        markup.push_str(&escape(&rest[..start]));
+        rest = &rest[start..];
+
+        let (span, length) = if let Some(inner) = delimited(rest, "**") {
+            (format!("<b>{}</b>", inline_markup(inner)), inner.len() + 4)
+        } else if let Some(inner) = delimited(rest, "*").or_else(|| delimited(rest, "_")) {
+            (format!("<i>{}</i>", inline_markup(inner)), inner.len() + 2)
+        } else if let Some(inner) = delimited(rest, "`") {
+            (format!("<tt>{}</tt>", escape(inner)), inner.len() + 2)
+        } else if let Some((label, url, length)) = link(rest) {
+            (format!("<a href=\"{}\">{}</a>", escape(url), escape(label)), length)
+        } else {
+            (escape(&rest[..1]), 1)
+        };
+        markup.push_str(&span);
+        rest = &rest[length..];
+    }
+
+    markup.push_str(&escape(rest));
+    markup
+}
+
+// The text between `delimiter` at the start of `text` and the next one, if not empty
+fn delimited<'a>(text: &'a str, delimiter: &str) -> Option<&'a str> {
+    let inner = text.strip_prefix(delimiter)?;
+    let end = inner.find(delimiter)?;
+    let inner = &inner[..end];
+    (!inner.is_empty() && !inner.starts_with(' ')).then_some(inner)
+}
+
+// "[label](url)" at the start of `text`, with the length it takes up
+fn link(text: &str) -> Option<(&str, &str, usize)> {
+    let label_end = text.find("](")?;
+    let label = &text[1..label_end];
+    let url_start = label_end + 2;
+    let url_end = url_start + text[url_start..].find(')')?;
+    let url = &text[url_start..url_end];
+    if label.contains('[') || !url.contains("://") {
+        return None;
+    }
+    Some((label, url, url_end + 1))
+}
+
+fn escape(text: &str) -> String {
+    glib::markup_escape_text(text).to_string()
+}
@@ -0,0 +1,335 @@
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+
+use serde_json::{json, Value};
+
+use crate::collection_object::CollectionData;
+use crate::migration::{StoreData, Tombstone};
+use crate::task_object::TaskData;
+
+// The three lists of a collection a task can be in
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum List {
+    Tasks,
+    Trash,
+    Archived,
+}
+
+impl List {
+    const ALL: [List; 3] = [List::Tasks, List::Trash, List::Archived];
+
+    fn of(self, collection_data: &CollectionData) -> &Vec<TaskData> {
+        match self {
+            List::Tasks => &collection_data.tasks_data,
+            List::Trash => &collection_data.trash_data,
+            List::Archived => &collection_data.archived_data,
+        }
+    }
+
+    fn of_mut(self, collection_data: &mut CollectionData) -> &mut Vec<TaskData> {
+        match self {
+            List::Tasks => &mut collection_data.tasks_data,
+            List::Trash => &mut collection_data.trash_data,
+            List::Archived => &mut collection_data.archived_data,
+        }
+    }
+}
+
+// The collection uuid and list a task is in
+type Place = (String, List);
+
+// Merges two versions of the data that were changed independently, like copies of
+// the data file on two devices. The result does not depend on which was written last:
+// - every property of a task takes the newer of both values
+// - a task moved on both sides ends up where it was changed last
+// - changes made at the same time are settled by their values, not by which side they are on
+// - tasks and collections of one side are kept, unless the other side deleted them
+//   and they were not changed since
+// - the order of `local` is kept, additions of `remote` follow their predecessor there
+pub fn merge(local: StoreData, remote: StoreData) -> StoreData {
+    let mut deleted: HashMap<String, String> = HashMap::new();
+    for tombstone in local.tombstones.iter().chain(&remote.tombstones) {
+        let deleted_at = deleted.entry(tombstone.uuid.clone()).or_default();
+        if tombstone.deleted_at > *deleted_at {
+            *deleted_at = tombstone.deleted_at.clone();
+        }
+    }
+    let is_deleted = |uuid: &str, modified_at: &str| {
+        deleted.get(uuid).is_some_and(|deleted_at| modified_at <= deleted_at.as_str())
+    };
+
+    let local_tasks = task_places(&local.collections);
+    let mut tasks: HashMap<String, (Place, TaskData)> = HashMap::new();
+    for (uuid, (remote_place, remote_data)) in task_places(&remote.collections) {
+        let merged = match local_tasks.get(&uuid) {
+            Some((local_place, local_data)) => {
+                let place = match remote_data.modified_at.cmp(&local_data.modified_at) {
+                    Ordering::Greater => remote_place,
+                    Ordering::Less => local_place.clone(),
+                    Ordering::Equal => remote_place.max(local_place.clone()),
+                };
+                (place, merge_task(local_data, &remote_data))
+            }
+            None => (remote_place, remote_data),
+        };
+        tasks.insert(uuid, merged);
+    }
+    for (uuid, local_task) in local_tasks {
+        tasks.entry(uuid).or_insert(local_task);
+    }
+    tasks.retain(|uuid, (_, task_data)| !is_deleted(uuid, &task_data.modified_at));
+
+    let local_collections: HashMap<&str, &CollectionData> = local
+        .collections
+        .iter()
+        .map(|collection_data| (collection_data.uuid.as_str(), collection_data))
+        .collect();
+    let remote_collections: HashMap<&str, &CollectionData> = remote
+        .collections
+        .iter()
+        .map(|collection_data| (collection_data.uuid.as_str(), collection_data))
+        .collect();
+
+    let collection_order = merge_order(
+        collection_uuids(&local.collections),
+        collection_uuids(&remote.collections),
+    );
+    let collections: Vec<CollectionData> = collection_order
+        .into_iter()
+        .filter_map(|uuid| {
+            let local_collection = local_collections.get(uuid.as_str()).copied();
+            let remote_collection = remote_collections.get(uuid.as_str()).copied();
+            let mut collection_data = match (local_collection, remote_collection) {
+                (Some(local_collection), Some(remote_collection)) => {
+                    merge_collection(local_collection, remote_collection)
+                }
+                (Some(collection_data), None) | (None, Some(collection_data)) => collection_data.clone(),
+                (None, None) => return None,
+            };
+            if is_deleted(&uuid, &collection_data.modified_at) {
+                return None;
+            }
+
+            for list in List::ALL {
+                let list_uuids = |collection_data: Option<&CollectionData>| -> Vec<String> {
+                    collection_data
+                        .map(|collection_data| task_uuids(list.of(collection_data)))
+                        .unwrap_or_default()
+                        .into_iter()
+                        .filter(|task_uuid| {
+                            tasks
+                                .get(task_uuid)
+                                .is_some_and(|((place_uuid, place_list), _)| {
+                                    *place_uuid == uuid && *place_list == list
+                                })
+                        })
+                        .collect()
+                };
+                let order = merge_order(list_uuids(local_collection), list_uuids(remote_collection));
+                *list.of_mut(&mut collection_data) = order
+                    .into_iter()
+                    .filter_map(|task_uuid| tasks.get(&task_uuid).map(|(_, task_data)| task_data.clone()))
+                    .collect();
+            }
+            Some(collection_data)
+        })
+        .collect();
+
+    // Smart collections and templates are rarely edited, those of `local` win
+    let mut smart_collections = local.smart_collections;
+    for smart_collection in remote.smart_collections {
+        if !smart_collections.iter().any(|known| known.uuid == smart_collection.uuid) {
+            smart_collections.push(smart_collection);
+        }
+    }
+    let mut templates = local.templates;
+    for template in remote.templates {
+        if !templates.iter().any(|known| known.title == template.title) {
+            templates.push(template);
+        }
+    }
+
+    // Items changed after they were deleted are back, their tombstones go
+    let kept_uuids: HashSet<&String> = collections
+        .iter()
+        .map(|collection_data| &collection_data.uuid)
+        .chain(tasks.keys())
+        .collect();
+    let tombstones = deleted
+        .into_iter()
+        .filter(|(uuid, _)| !kept_uuids.contains(&uuid))
+        .map(|(uuid, deleted_at)| Tombstone { uuid, deleted_at })
+        .collect();
+
+    StoreData {
+        sync: local.sync,
+        tombstones,
+        ..StoreData::new(collections, smart_collections, templates)
+    }
+}
+
+fn collection_uuids(collections: &[CollectionData]) -> Vec<String> {
+    collections.iter().map(|collection_data| collection_data.uuid.clone()).collect()
+}
+
+fn task_uuids(tasks_data: &[TaskData]) -> Vec<String> {
+    tasks_data.iter().map(|task_data| task_data.uuid.clone()).collect()
+}
+
+// Every task by uuid, with where it is
+fn task_places(collections: &[CollectionData]) -> HashMap<String, (Place, TaskData)> {
+    let mut places = HashMap::new();
+    for collection_data in collections {
+        for list in List::ALL {
+            for task_data in list.of(collection_data) {
+                let place = (collection_data.uuid.clone(), list);
+                places.insert(task_data.uuid.clone(), (place, task_data.clone()));
+            }
+        }
+    }
+    places
+}
+
+// The order of `local`, with each item only in `remote` inserted after the closest
+// item that comes before it in `remote` and is already placed
+fn merge_order(local: Vec<String>, remote: Vec<String>) -> Vec<String> {
+    let mut merged = local;
+
+    for (index, uuid) in remote.iter().enumerate() {
+        if merged.contains(uuid) {
+            continue;
+        }
+
+        let position = remote[..index]
+            .iter()
+            .rev()
+            .find_map(|predecessor| merged.iter().position(|known| known == predecessor))
+            .map(|position| position + 1)
+            .unwrap_or(0);
+        merged.insert(position, uuid.clone());
+    }
+
+    merged
+}
+
+// Property by property the newer value wins
+fn merge_task(local: &TaskData, remote: &TaskData) -> TaskData {
+    let changed_at = |task_data: &TaskData, property: &str| -> String {
+        task_data
+            .field_modified_at
+            .get(property)
+            .unwrap_or(&task_data.modified_at)
+            .clone()
+    };
+    let remote_is_newer = |property: &str, value: fn(&TaskData) -> Value| {
+        match changed_at(remote, property).cmp(&changed_at(local, property)) {
+            Ordering::Greater => true,
+            Ordering::Less => false,
+            // Changed at the same time on both sides, both pick the greater value
+            Ordering::Equal => value(remote).to_string() > value(local).to_string(),
+        }
+    };
+
+    let mut merged = local.clone();
+    if remote_is_newer("completed", |task_data| json!((task_data.completed, &task_data.completed_at))) {
+        merged.completed = remote.completed;
+        merged.completed_at = remote.completed_at.clone();
+    }
+    if remote_is_newer("content", |task_data| json!(task_data.content)) {
+        merged.content = remote.content.clone();
+    }
+    if remote_is_newer("starred", |task_data| json!(task_data.starred)) {
+        merged.starred = remote.starred;
+    }
+    if remote_is_newer("notes", |task_data| json!(task_data.notes)) {
+        merged.notes = remote.notes.clone();
+    }
+    if remote_is_newer("link", |task_data| json!(task_data.link)) {
+        merged.link = remote.link.clone();
+    }
+    if remote_is_newer("due-date", |task_data| json!(task_data.due_date)) {
+        merged.due_date = remote.due_date.clone();
+    }
+    if remote_is_newer("attachments", |task_data| json!(task_data.attachments)) {
+        merged.attachments = remote.attachments.clone();
+    }
+    if remote_is_newer("subtasks", |task_data| json!(task_data.subtasks)) {
+        merged.subtasks = remote.subtasks.clone();
+    }
+    if remote_is_newer("section", |task_data| json!(task_data.section)) {
+        merged.section = remote.section.clone();
+    }
+    if remote_is_newer("estimate-minutes", |task_data| json!(task_data.estimate_minutes)) {
+        merged.estimate_minutes = remote.estimate_minutes;
+    }
+    if remote_is_newer("focus-seconds", |task_data| json!(task_data.focus_seconds)) {
+        merged.focus_seconds = remote.focus_seconds;
+    }
+    if remote_is_newer("blocked-by", |task_data| json!(task_data.blocked_by)) {
+        merged.blocked_by = remote.blocked_by.clone();
+    }
+    if remote_is_newer("start-date", |task_data| json!(task_data.start_date)) {
+        merged.start_date = remote.start_date.clone();
+    }
+    if remote_is_newer("recurrence", |task_data| json!(task_data.recurrence)) {
+        merged.recurrence = remote.recurrence.clone();
+    }
+    if remote_is_newer("reminder-time", |task_data| json!(task_data.reminder_time)) {
+        merged.reminder_time = remote.reminder_time.clone();
+    }
+    if remote_is_newer("priority", |task_data| json!(task_data.priority)) {
+        merged.priority = remote.priority;
+    }
+    if remote_is_newer("tags", |task_data| json!(task_data.tags)) {
+        merged.tags = remote.tags.clone();
+    }
+    if remote_is_newer("deleted-at", |task_data| json!(task_data.deleted_at)) {
+        merged.deleted_at = remote.deleted_at.clone();
+    }
+
+    for (property, modified_at) in &remote.field_modified_at {
+        let known = merged.field_modified_at.entry(property.clone()).or_default();
+        if modified_at > known {
+            *known = modified_at.clone();
+        }
+    }
+    merged.modified_at = local.modified_at.clone().max(remote.modified_at.clone());
+    if !remote.created_at.is_empty() && (local.created_at.is_empty() || remote.created_at < local.created_at) {
+        merged.created_at = remote.created_at.clone();
+    }
+    merged
+}
+
+// Collections have few properties, the newer version wins as a whole. The tasks
+// are merged separately.
+fn merge_collection(local: &CollectionData, remote: &CollectionData) -> CollectionData {
+    let properties = |collection_data: &CollectionData| {
+        json!((
+            &collection_data.title,
+            &collection_data.icon_name,
+            &collection_data.color,
+            collection_data.pinned,
+            &collection_data.folder,
+            &collection_data.sections,
+        ))
+        .to_string()
+    };
+    let remote_is_newer = match remote.modified_at.cmp(&local.modified_at) {
+        Ordering::Greater => true,
+        Ordering::Less => false,
+        // Changed at the same time on both sides, both pick the greater version
+        Ordering::Equal => properties(remote) > properties(local),
+    };
+
+    let mut merged = local.clone();
+    if remote_is_newer {
+        merged.title = remote.title.clone();
+        merged.icon_name = remote.icon_name.clone();
+        merged.color = remote.color.clone();
+        merged.pinned = remote.pinned;
+        merged.folder = remote.folder.clone();
+        merged.sections = remote.sections.clone();
+        merged.modified_at = remote.modified_at.clone();
+    }
+    merged
+}
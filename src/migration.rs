@@ -0,0 +1,116 @@
+use anyhow::{bail, Context};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::collection_object::CollectionData;
+use crate::smart_collection_object::SmartCollectionData;
+use crate::sync::SyncData;
+use crate::utils;
+
+// Bump this and append a step to `MIGRATIONS` whenever the data format changes
+// in a way that `#[serde(default)]` cannot cover
+pub const CURRENT_VERSION: u32 = 2;
+
+// A migration upgrades the JSON of version `n` (its index) to version `n + 1`
+const MIGRATIONS: [fn(Value) -> anyhow::Result<Value>; CURRENT_VERSION as usize] = [
+    migrate_v0_to_v1,
+    migrate_v1_to_v2,
+];
+
+// Root of the data file
+#[derive(Default, Clone, Serialize, Deserialize)]
+pub struct StoreData {
+    pub version: u32,
+    pub collections: Vec<CollectionData>,
+    #[serde(default)]
+    pub smart_collections: Vec<SmartCollectionData>,
+    // Collections to create new ones from
+    #[serde(default)]
+    pub templates: Vec<CollectionData>,
+    #[serde(default)]
+    pub sync: SyncData,
+    #[serde(default)]
+    pub tombstones: Vec<Tombstone>,
+}
+
+// Left behind by a task or collection that was deleted for good, so that copies
+// of the data on other devices do not bring it back
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Tombstone {
+    pub uuid: String,
+    // As returned by `utils::timestamp`
+    pub deleted_at: String,
+}
+
+impl StoreData {
+    pub fn new(
+        collections: Vec<CollectionData>,
+        smart_collections: Vec<SmartCollectionData>,
+        templates: Vec<CollectionData>,
+    ) -> Self {
+        Self {
+            version: CURRENT_VERSION,
+            collections,
+            smart_collections,
+            templates,
+            sync: SyncData::default(),
+            tombstones: Vec::new(),
+        }
+    }
+}
+
+pub fn migrate(mut value: Value) -> anyhow::Result<StoreData> {
+    let mut version = version_of(&value)?;
+    if version > CURRENT_VERSION {
+        bail!("The data was written by a newer version of the application (format {version})");
+    }
+
+    while version < CURRENT_VERSION {
+        value = MIGRATIONS[version as usize](value)
+            .with_context(|| format!("Could not upgrade the data from format {version}"))?;
+        version += 1;
+    }
+
+    serde_json::from_value(value).context("Could not read the data")
+}
+
+fn version_of(value: &Value) -> anyhow::Result<u32> {
+    match value {
+        // Before versioning the root was a bare list of collections
+        Value::Array(_) => Ok(0),
+        Value::Object(object) => object
+            .get("version")
+            .and_then(Value::as_u64)
+            .map(|version| version as u32)
+            .context("The data has no format version"),
+        _ => bail!("The data has an unknown format"),
+    }
+}
+
+fn migrate_v0_to_v1(value: Value) -> anyhow::Result<Value> {
+    Ok(json!({
+        "version": 1,
+        "collections": value,
+    }))
+}
+
+// Give every collection and task a stable identity
+fn migrate_v1_to_v2(mut value: Value) -> anyhow::Result<Value> {
+    let collections = value
+        .get_mut("collections")
+        .and_then(Value::as_array_mut)
+        .context("The data has no collections")?;
+
+    for collection in collections {
+        collection["uuid"] = json!(utils::new_uuid());
+
+        if let Some(tasks) = collection.get_mut("tasks_data").and_then(Value::as_array_mut) {
+            for task in tasks {
+                task["uuid"] = json!(utils::new_uuid());
+            }
+        }
+    }
+
+    value["version"] = json!(2);
+    Ok(value)
+}
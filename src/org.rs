@@ -0,0 +1,197 @@
+use crate::collection_object::CollectionData;
+use crate::task_object::{normalize_tag, Priority, TaskData};
+use crate::utils;
+
+// TODO headings outside of any other heading are imported into this collection
+pub const DEFAULT_COLLECTION: &str = "Imported";
+
+const OPEN_KEYWORDS: [&str; 4] = ["TODO", "NEXT", "WAITING", "HOLD"];
+const DONE_KEYWORDS: [&str; 3] = ["DONE", "CANCELED", "CANCELLED"];
+
+struct Heading<'a> {
+    level: usize,
+    // `None` for headings without a TODO keyword
+    completed: Option<bool>,
+    priority: Priority,
+    title: &'a str,
+    tags: Vec<String>,
+}
+
+// "** TODO [#A] call mom :family:phone:"
+fn parse_heading(line: &str) -> Option<Heading> {
+    let level = line.find(|char: char| char != '*').filter(|level| *level > 0)?;
+    let mut rest = line[level..].strip_prefix(' ')?.trim();
+
+    let mut completed = None;
+    let (keyword, after) = rest.split_once(' ').unwrap_or((rest, ""));
+    if OPEN_KEYWORDS.contains(&keyword) {
+        completed = Some(false);
+        rest = after.trim_start();
+    } else if DONE_KEYWORDS.contains(&keyword) {
+        completed = Some(true);
+        rest = after.trim_start();
+    }
+
+    let mut priority = Priority::None;
+    if let Some(after) = rest.strip_prefix("[#") {
+        if let Some((letter, after)) = after.split_once(']') {
+            priority = match letter {
+                "A" => Priority::High,
+                "B" => Priority::Medium,
+                _ => Priority::Low,
+            };
+            rest = after.trim_start();
+        }
+    }
+
+    // Tags close the heading, like ":work:urgent:"
+    let mut tags = Vec::new();
+    if let Some((title, tag_list)) = rest.trim_end().rsplit_once(' ') {
+        if tag_list.len() > 1 && tag_list.starts_with(':') && tag_list.ends_with(':') {
+            tags = tag_list
+                .split(':')
+                .map(normalize_tag)
+                .filter(|tag| !tag.is_empty())
+                .collect();
+            rest = title.trim_end();
+        }
+    }
+
+    Some(Heading {
+        level,
+        completed,
+        priority,
+        title: rest,
+        tags,
+    })
+}
+
+// The date of the first "<2023-05-01 Mon>" after `keyword` on a planning line
+fn planning_date(line: &str, keyword: &str) -> Option<String> {
+    let after = &line[line.find(keyword)? + keyword.len()..];
+    let date = after.trim_start().strip_prefix('<')?.get(..10)?;
+    utils::parse_date(date).map(|_| date.to_string())
+}
+
+// Top level headings without a keyword become collections, the TODO headings in
+// them tasks. There are no subtasks, nested tasks are flattened and note their parent.
+pub fn parse(contents: &str) -> Vec<CollectionData> {
+    let mut collections: Vec<CollectionData> = Vec::new();
+    // Titles of the task headings enclosing the current one, with their level
+    let mut parents: Vec<(usize, String)> = Vec::new();
+    let mut in_task = false;
+    let mut in_drawer = false;
+
+    for line in contents.lines() {
+        if let Some(heading) = parse_heading(line) {
+            parents.retain(|(level, _)| *level < heading.level);
+            in_drawer = false;
+
+            let Some(completed) = heading.completed else {
+                in_task = false;
+                if heading.level == 1 || collections.is_empty() {
+                    collections.push(new_collection(heading.title));
+                }
+                continue;
+            };
+
+            if collections.is_empty() {
+                collections.push(new_collection(DEFAULT_COLLECTION));
+            }
+            let notes = parents
+                .last()
+                .map(|(_, parent)| format!("Subtask of “{parent}”"))
+                .unwrap_or_default();
+            let task_data = TaskData {
+                uuid: utils::new_uuid(),
+                completed,
+                content: heading.title.to_string(),
+                notes,
+                priority: heading.priority,
+                tags: heading.tags,
+                ..Default::default()
+            };
+
+            parents.push((heading.level, task_data.content.clone()));
+            collections.last_mut().unwrap().tasks_data.push(task_data);
+            in_task = true;
+            continue;
+        }
+
+        let Some(task_data) = collections
+            .last_mut()
+            .and_then(|collection| collection.tasks_data.last_mut())
+            .filter(|_| in_task)
+        else {
+            continue;
+        };
+
+        let trimmed = line.trim();
+        if in_drawer {
+            in_drawer = trimmed != ":END:";
+        } else if trimmed.starts_with(':') && trimmed.ends_with(':') && trimmed.len() > 1 {
+            in_drawer = true;
+        } else if ["DEADLINE:", "SCHEDULED:", "CLOSED:"].iter().any(|keyword| trimmed.starts_with(keyword)) {
+            // A deadline is the due date, a scheduled date stands in if there is none
+            if let Some(date) = planning_date(trimmed, "DEADLINE:") {
+                task_data.due_date = date;
+            } else if let Some(date) = planning_date(trimmed, "SCHEDULED:").filter(|_| task_data.due_date.is_empty()) {
+                task_data.due_date = date;
+            }
+        } else if !trimmed.is_empty() {
+            if !task_data.notes.is_empty() {
+                task_data.notes.push('\n');
+            }
+            task_data.notes.push_str(trimmed);
+        }
+    }
+
+    collections.retain(|collection| !collection.tasks_data.is_empty());
+    collections
+}
+
+fn new_collection(title: &str) -> CollectionData {
+    CollectionData {
+        uuid: utils::new_uuid(),
+        title: title.to_string(),
+        ..Default::default()
+    }
+}
+
+// A heading per collection with its tasks below
+pub fn to_string(collections: &[CollectionData]) -> String {
+    let mut contents = String::new();
+
+    for collection_data in collections {
+        contents.push_str(&format!("* {}\n", collection_data.title));
+
+        for task_data in &collection_data.tasks_data {
+            let keyword = if task_data.completed { "DONE" } else { "TODO" };
+            let mut heading = format!("** {keyword}");
+            match task_data.priority {
+                Priority::None => {}
+                Priority::Low => heading.push_str(" [#C]"),
+                Priority::Medium => heading.push_str(" [#B]"),
+                Priority::High => heading.push_str(" [#A]"),
+            }
+            heading.push(' ');
+            heading.push_str(&task_data.content);
+            if !task_data.tags.is_empty() {
+                let tags: Vec<String> = task_data.tags.iter().map(|tag| tag.replace(' ', "_")).collect();
+                heading.push_str(&format!(" :{}:", tags.join(":")));
+            }
+            contents.push_str(&heading);
+            contents.push('\n');
+
+            if let Some(date) = utils::parse_date(&task_data.due_date) {
+                let weekday = date.format("%a").map(|weekday| weekday.to_string()).unwrap_or_default();
+                contents.push_str(&format!("   DEADLINE: <{} {weekday}>\n", task_data.due_date));
+            }
+            for line in task_data.notes.lines() {
+                contents.push_str(&format!("   {line}\n"));
+            }
+        }
+    }
+
+    contents
+}
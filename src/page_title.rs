@@ -0,0 +1,55 @@
+use std::time::Duration;
+
+use anyhow::Context;
+use reqwest::{header, Client};
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+// The title is in the head of the page, there is no need to download all of it
+const MAX_BYTES: usize = 64 * 1024;
+const USER_AGENT: &str = "To-Do";
+
+// The <title> of the web page at `url`, `None` if it has none. Runs on the sync runtime.
+pub async fn fetch(url: String) -> anyhow::Result<Option<String>> {
+    let mut response = Client::builder()
+        .timeout(TIMEOUT)
+        .build()?
+        .get(&url)
+        .header(header::USER_AGENT, USER_AGENT)
+        .send()
+        .await
+        .context("Could not load the page")?
+        .error_for_status()?;
+
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        body.extend_from_slice(&chunk);
+        if body.len() >= MAX_BYTES || find_title(&String::from_utf8_lossy(&body)).is_some() {
+            break;
+        }
+    }
+
+    Ok(find_title(&String::from_utf8_lossy(&body)))
+}
+
+fn find_title(html: &str) -> Option<String> {
+    // Tags are case insensitive, ASCII lowercase keeps the byte offsets
+    let lowercase = html.to_ascii_lowercase();
+    let tag_start = lowercase.find("<title")?;
+    let start = tag_start + lowercase[tag_start..].find('>')? + 1;
+    let end = start + lowercase[start..].find("</title")?;
+
+    let title = decode_entities(&html[start..end]);
+    let title = title.split_whitespace().collect::<Vec<&str>>().join(" ");
+    (!title.is_empty()).then_some(title)
+}
+
+// The entities that are common in titles
+fn decode_entities(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&#x27;", "'")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+}
@@ -0,0 +1,181 @@
+use gtk::glib;
+
+use crate::task_object::{normalize_tag, Priority};
+use crate::{reminders, utils};
+
+// Result of parsing the text typed into the quick-add entry
+#[derive(Debug, Default, PartialEq)]
+pub struct ParsedTask {
+    pub content: String,
+    pub tags: Vec<String>,
+    pub priority: Priority,
+    // "YYYY-MM-DD", empty if no date was found
+    pub due_date: String,
+    // "HH:MM" from a time like "5pm", empty if there was none
+    pub reminder_time: String,
+}
+
+// Parses "buy milk #groceries !high tomorrow 5pm" into the content "buy milk" with
+// the tag, priority, due date and reminder set. Recognized tokens are stripped from the content.
+pub fn parse_task(input: &str) -> ParsedTask {
+    let words: Vec<&str> = input.split_whitespace().collect();
+    let mut parsed = ParsedTask::default();
+    let mut content_words: Vec<&str> = Vec::new();
+    let mut index = 0;
+
+    while index < words.len() {
+        let word = words[index];
+
+        if let Some(tag) = word.strip_prefix('#').map(normalize_tag).filter(|tag| !tag.is_empty()) {
+            if !parsed.tags.contains(&tag) {
+                parsed.tags.push(tag);
+            }
+            index += 1;
+        } else if let Some(priority) = word.strip_prefix('!').and_then(parse_priority) {
+            parsed.priority = priority;
+            index += 1;
+        } else if let Some((date, length)) = parse_date(&words[index..]) {
+            // "due friday" or "by tomorrow" reads naturally, but the preposition is noise
+            if content_words
+                .last()
+                .is_some_and(|last| matches!(last.to_lowercase().as_str(), "on" | "by" | "due"))
+            {
+                content_words.pop();
+            }
+            parsed.due_date = utils::format_date(&date);
+            index += length;
+        } else if let Some(time) = parse_time(word) {
+            if content_words.last().is_some_and(|last| last.eq_ignore_ascii_case("at")) {
+                content_words.pop();
+            }
+            parsed.reminder_time = time;
+            index += 1;
+        } else {
+            content_words.push(word);
+            index += 1;
+        }
+    }
+
+    // A time alone is for today, or tomorrow once it has passed
+    if !parsed.reminder_time.is_empty() && parsed.due_date.is_empty() {
+        let now = utils::now();
+        let current_time = now.format("%H:%M").map(|time| time.to_string()).unwrap_or_default();
+        let day = if parsed.reminder_time > current_time { Some(now) } else { now.add_days(1).ok() };
+        parsed.due_date = day.map(|day| utils::format_date(&day)).unwrap_or_default();
+    }
+
+    parsed.content = content_words.join(" ");
+    parsed
+}
+
+// Takes the first "@name" that `find` knows out of `input`, returning what was found
+// and the rest of the text. Unknown names stay in the text, they may be meant literally.
+pub fn take_mention<T>(input: &str, find: impl Fn(&str) -> Option<T>) -> (Option<T>, String) {
+    let words: Vec<&str> = input.split_whitespace().collect();
+    for (index, word) in words.iter().enumerate() {
+        let Some(found) = word.strip_prefix('@').filter(|name| !name.is_empty()).and_then(&find) else {
+            continue;
+        };
+        let mut rest = words.clone();
+        rest.remove(index);
+        return (Some(found), rest.join(" "));
+    }
+    (None, input.to_string())
+}
+
+// How a collection is written after "@", its title without the spaces
+pub fn mention_name(title: &str) -> String {
+    title.split_whitespace().collect()
+}
+
+// Parses a date on its own, like "tomorrow", "next week" or "2024-05-01", into "YYYY-MM-DD"
+pub fn parse_due_date(input: &str) -> Option<String> {
+    let words: Vec<&str> = input.split_whitespace().collect();
+    match parse_date(&words) {
+        Some((date, length)) if length == words.len() => Some(utils::format_date(&date)),
+        _ => None,
+    }
+}
+
+fn parse_priority(priority: &str) -> Option<Priority> {
+    match priority.to_lowercase().as_str() {
+        "low" | "3" => Some(Priority::Low),
+        "medium" | "med" | "2" => Some(Priority::Medium),
+        "high" | "1" | "!" => Some(Priority::High),
+        _ => None,
+    }
+}
+
+// Looks for a date at the start of `words`, returning it with the number of words it spans
+fn parse_date(words: &[&str]) -> Option<(glib::DateTime, usize)> {
+    let today = utils::now();
+    let first = words.first()?.to_lowercase();
+    let second = words.get(1).map(|word| word.to_lowercase());
+
+    match (first.as_str(), second.as_deref()) {
+        ("today", _) => Some((today, 1)),
+        ("tomorrow", _) => Some((today.add_days(1).ok()?, 1)),
+        ("next", Some("week")) => {
+            let days_to_monday = 8 - today.day_of_week();
+            Some((today.add_days(days_to_monday).ok()?, 2))
+        }
+        // "in 3 days", "in 2 weeks", "in 1 month"
+        ("in", Some(count)) => {
+            let count: i32 = count.parse().ok().filter(|count| *count > 0)?;
+            let date = match words.get(2)?.to_lowercase().trim_end_matches('s') {
+                "day" => today.add_days(count).ok()?,
+                "week" => today.add_weeks(count).ok()?,
+                "month" => today.add_months(count).ok()?,
+                _ => return None,
+            };
+            Some((date, 3))
+        }
+        (word, _) => {
+            if let Some(weekday) = parse_weekday(word) {
+                // The next such day, a week from today if it is that day already
+                let days = (weekday - today.day_of_week() + 6) % 7 + 1;
+                Some((today.add_days(days).ok()?, 1))
+            } else if word.len() == "YYYY-MM-DD".len() {
+                utils::parse_date(word).map(|date| (date, 1))
+            } else {
+                None
+            }
+        }
+    }
+}
+
+// "5pm", "5:30pm" or "17:00" into "HH:MM"
+fn parse_time(word: &str) -> Option<String> {
+    let word = word.to_lowercase();
+    let (time, offset) = if let Some(time) = word.strip_suffix("am") {
+        (time, 0)
+    } else if let Some(time) = word.strip_suffix("pm") {
+        (time, 12)
+    } else {
+        // Without "am" or "pm" only "HH:MM", a number alone is no time
+        if !word.contains(':') {
+            return None;
+        }
+        return reminders::parse_time(&word);
+    };
+
+    let (hour, minute) = time.split_once(':').unwrap_or((time, "0"));
+    let hour: u32 = hour.parse().ok().filter(|hour| (1..=12).contains(hour))?;
+    let minute: u32 = minute.parse().ok().filter(|minute| *minute < 60)?;
+    // 12am is midnight and 12pm noon
+    Some(format!("{:02}:{minute:02}", hour % 12 + offset))
+}
+
+// Monday is 1, like `glib::DateTime::day_of_week()`
+fn parse_weekday(word: &str) -> Option<i32> {
+    match word {
+        "monday" | "mon" => Some(1),
+        "tuesday" | "tue" => Some(2),
+        "wednesday" | "wed" => Some(3),
+        "thursday" | "thu" => Some(4),
+        "friday" | "fri" => Some(5),
+        "saturday" | "sat" => Some(6),
+        "sunday" | "sun" => Some(7),
+        _ => None,
+    }
+}
@@ -0,0 +1,70 @@
+use std::collections::HashMap;
+
+use gtk::glib;
+use libsecret::{Schema, SchemaAttributeType, SchemaFlags};
+
+// The secrets of the application itself, kept in the keyring
+#[derive(Debug, Clone, Copy)]
+pub enum Purpose {
+    // Of the encrypted data, only remembered until the user logs out
+    Data,
+    // Of the lock screen
+    Lock,
+    // Shared by the devices that sync on the local network
+    PairingCode,
+}
+
+impl Purpose {
+    fn attribute(self) -> &'static str {
+        match self {
+            Purpose::Data => "data",
+            Purpose::Lock => "lock",
+            Purpose::PairingCode => "pairing-code",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Purpose::Data => "To-Do data passphrase",
+            Purpose::Lock => "To-Do lock password",
+            Purpose::PairingCode => "To-Do pairing code",
+        }
+    }
+
+    fn collection(self) -> &'static str {
+        match self {
+            Purpose::Data => libsecret::COLLECTION_SESSION,
+            Purpose::Lock | Purpose::PairingCode => libsecret::COLLECTION_DEFAULT,
+        }
+    }
+}
+
+fn schema() -> Schema {
+    let attributes = HashMap::from([("purpose", SchemaAttributeType::String)]);
+    Schema::new("org.gnome.ToDo.Passphrase", SchemaFlags::NONE, attributes)
+}
+
+fn attributes(purpose: Purpose) -> HashMap<&'static str, &'static str> {
+    HashMap::from([("purpose", purpose.attribute())])
+}
+
+pub async fn remember(purpose: Purpose, passphrase: &str) -> Result<(), glib::Error> {
+    libsecret::password_store_future(
+        Some(&schema()),
+        attributes(purpose),
+        Some(purpose.collection()),
+        purpose.label(),
+        passphrase,
+    )
+    .await
+}
+
+pub async fn forget(purpose: Purpose) -> Result<bool, glib::Error> {
+    libsecret::password_clear_future(Some(&schema()), attributes(purpose)).await
+}
+
+pub async fn lookup(purpose: Purpose) -> Result<Option<String>, glib::Error> {
+    libsecret::password_lookup_future(Some(&schema()), attributes(purpose))
+        .await
+        .map(|passphrase| passphrase.map(|passphrase| passphrase.to_string()))
+}
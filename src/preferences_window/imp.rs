@@ -0,0 +1,77 @@
+use std::cell::RefCell;
+
+use adw::subclass::prelude::*;
+use gtk::{glib, Button, CompositeTemplate, Spinner};
+
+// Object holding the state
+#[derive(Default, CompositeTemplate)]
+#[template(resource = "/org/gnome/ToDo/gtk/preferences-window.ui")]
+pub struct PreferencesWindow {
+    #[template_child]
+    pub auto_clear_row: TemplateChild<adw::ComboRow>,
+    #[template_child]
+    pub auto_clear_days_row: TemplateChild<adw::SpinRow>,
+    #[template_child]
+    pub quick_add_row: TemplateChild<adw::ComboRow>,
+    #[template_child]
+    pub digest_row: TemplateChild<adw::SwitchRow>,
+    #[template_child]
+    pub digest_time_row: TemplateChild<adw::EntryRow>,
+    #[template_child]
+    pub encryption_button: TemplateChild<Button>,
+    #[template_child]
+    pub link_titles_row: TemplateChild<adw::SwitchRow>,
+    #[template_child]
+    pub lock_button: TemplateChild<Button>,
+    #[template_child]
+    pub lock_timeout_row: TemplateChild<adw::SpinRow>,
+    #[template_child]
+    pub server_row: TemplateChild<adw::EntryRow>,
+    #[template_child]
+    pub login_row: TemplateChild<adw::ActionRow>,
+    #[template_child]
+    pub login_button: TemplateChild<Button>,
+    #[template_child]
+    pub account_row: TemplateChild<adw::ActionRow>,
+    #[template_child]
+    pub logout_button: TemplateChild<Button>,
+    #[template_child]
+    pub other_server_row: TemplateChild<adw::ActionRow>,
+    #[template_child]
+    pub calendars_group: TemplateChild<adw::PreferencesGroup>,
+    #[template_child]
+    pub calendars_spinner: TemplateChild<Spinner>,
+    #[template_child]
+    pub lan_sync_row: TemplateChild<adw::SwitchRow>,
+    #[template_child]
+    pub pairing_code_row: TemplateChild<adw::PasswordEntryRow>,
+    // One switch per remote task list, to remove them when the list is reloaded
+    pub calendar_rows: RefCell<Vec<adw::SwitchRow>>,
+}
+
+// The central trait for subclassing a GObject
+#[glib::object_subclass]
+impl ObjectSubclass for PreferencesWindow {
+    // `NAME` needs to match `class` attribute of template
+    const NAME: &'static str = "TodoPreferencesWindow";
+    type Type = super::PreferencesWindow;
+    type ParentType = adw::PreferencesWindow;
+
+    fn class_init(klass: &mut Self::Class) {
+        klass.bind_template();
+    }
+
+    fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+        obj.init_template();
+    }
+}
+
+// Trait shared by all GObjects
+impl ObjectImpl for PreferencesWindow {}
+
+// Trait shared by all widgets
+impl WidgetImpl for PreferencesWindow {}
+
+impl WindowImpl for PreferencesWindow {}
+impl AdwWindowImpl for PreferencesWindow {}
+impl PreferencesWindowImpl for PreferencesWindow {}
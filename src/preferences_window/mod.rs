@@ -0,0 +1,358 @@
+mod imp;
+
+use adw::prelude::*;
+use adw::subclass::prelude::*;
+use glib::{clone, Object};
+use gtk::{gdk, gio, glib};
+
+use crate::reminders;
+use crate::sync::{self, caldav::CalDavClient, credentials, nextcloud, Account};
+use crate::{TodoWindow, APP_ID};
+
+glib::wrapper! {
+    pub struct PreferencesWindow(ObjectSubclass<imp::PreferencesWindow>)
+    @extends adw::PreferencesWindow, adw::Window, gtk::Window, gtk::Widget,
+    @implements gtk::Accessible, gtk::Buildable, gtk::ConstraintTarget, gtk::Native, gtk::Root,
+        gtk::ShortcutManager;
+}
+
+impl PreferencesWindow {
+    pub fn new(window: &TodoWindow) -> Self {
+        let preferences: Self = Object::builder()
+            .property("transient-for", window)
+            .build();
+        preferences.setup_auto_clear();
+        preferences.setup_quick_add();
+        preferences.setup_digest();
+        preferences.setup_encryption();
+        preferences.setup_link_titles();
+        preferences.setup_lock();
+        preferences.setup_account();
+        preferences.setup_lan_sync();
+        preferences
+    }
+
+    fn window(&self) -> Option<TodoWindow> {
+        self.transient_for().and_downcast::<TodoWindow>()
+    }
+
+    fn setup_account(&self) {
+        let imp = self.imp();
+
+        imp.login_button.connect_clicked(clone!(@weak self as preferences => move |_| {
+            glib::MainContext::default().spawn_local(clone!(@weak preferences => async move {
+                preferences.log_in().await;
+            }));
+        }));
+        imp.logout_button.connect_clicked(clone!(@weak self as preferences => move |_| {
+            glib::MainContext::default().spawn_local(clone!(@weak preferences => async move {
+                preferences.log_out().await;
+            }));
+        }));
+        // The generic dialog links every task list of the server
+        imp.other_server_row.connect_activated(clone!(@weak self as preferences => move |_| {
+            if let Some(window) = preferences.window() {
+                preferences.close();
+                let _ = window.activate_action("win.set-up-sync", None);
+            }
+        }));
+
+        self.update_account();
+    }
+
+    fn setup_auto_clear(&self) {
+        let imp = self.imp();
+        let settings = gio::Settings::new(APP_ID);
+
+        // In the order of the rows of the combo row
+        const CHOICES: [&str; 3] = ["Never", "Archive", "Delete"];
+        let choice = settings.string("auto-clear-completed");
+        let position = CHOICES.iter().position(|value| *value == choice.as_str()).unwrap_or_default();
+        imp.auto_clear_row.set_selected(position as u32);
+        imp.auto_clear_row.connect_selected_notify(clone!(@strong settings => move |row| {
+            let choice = CHOICES.get(row.selected() as usize).copied().unwrap_or(CHOICES[0]);
+            if let Err(err) = settings.set_string("auto-clear-completed", choice) {
+                glib::g_warning!("todo", "Could not change when tasks are cleared: {}", err);
+            }
+        }));
+
+        settings
+            .bind("auto-clear-days", &*imp.auto_clear_days_row, "value")
+            .build();
+        imp.auto_clear_row
+            .bind_property("selected", &*imp.auto_clear_days_row, "sensitive")
+            .sync_create()
+            .transform_to(|_, selected: u32| Some(selected > 0))
+            .build();
+    }
+
+    fn setup_quick_add(&self) {
+        let Some(window) = self.window() else {
+            return;
+        };
+        let row = &self.imp().quick_add_row;
+        let settings = gio::Settings::new(APP_ID);
+
+        let collections = window.collection_list();
+        let titles: Vec<String> = collections.iter().map(|collection| collection.title()).collect();
+        let titles: Vec<&str> = titles.iter().map(String::as_str).collect();
+        row.set_model(Some(&gtk::StringList::new(&titles)));
+        if let Some(collection) = window.quick_add_collection() {
+            let position = collections.iter().position(|other| *other == collection).unwrap_or_default();
+            row.set_selected(position as u32);
+        }
+
+        row.connect_selected_notify(move |row| {
+            let Some(collection) = collections.get(row.selected() as usize) else {
+                return;
+            };
+            if let Err(err) = settings.set_string("quick-add-collection", &collection.uuid()) {
+                glib::g_warning!("todo", "Could not change the quick-add collection: {}", err);
+            }
+        });
+    }
+
+    fn setup_digest(&self) {
+        let imp = self.imp();
+        let settings = gio::Settings::new(APP_ID);
+
+        settings
+            .bind("digest-enabled", &*imp.digest_row, "active")
+            .build();
+        settings
+            .bind("digest-enabled", &*imp.digest_time_row, "sensitive")
+            .get()
+            .build();
+
+        imp.digest_time_row.set_text(&settings.string("digest-time"));
+        imp.digest_time_row.connect_apply(move |row| {
+            let Some(time) = reminders::parse_time(&row.text()) else {
+                row.add_css_class("error");
+                return;
+            };
+            row.remove_css_class("error");
+            row.set_text(&time);
+            if let Err(err) = settings.set_string("digest-time", &time) {
+                glib::g_warning!("todo", "Could not store the digest time: {}", err);
+            }
+        });
+    }
+
+    fn setup_encryption(&self) {
+        self.imp().encryption_button.connect_clicked(clone!(@weak self as preferences => move |button| {
+            let Some(window) = preferences.window() else {
+                return;
+            };
+            button.set_sensitive(false);
+            glib::MainContext::default().spawn_local(clone!(@weak preferences, @weak button => async move {
+                if window.is_encrypted() {
+                    window.disable_encryption().await;
+                } else {
+                    window.enable_encryption().await;
+                }
+                button.set_sensitive(true);
+                preferences.update_encryption();
+            }));
+        }));
+
+        self.update_encryption();
+    }
+
+    fn update_encryption(&self) {
+        let encrypted = self.window().is_some_and(|window| window.is_encrypted());
+        let button = &self.imp().encryption_button;
+        if encrypted {
+            button.set_label("Turn _Off");
+            button.remove_css_class("suggested-action");
+        } else {
+            button.set_label("Turn _On…");
+            button.add_css_class("suggested-action");
+        }
+        button.set_use_underline(true);
+    }
+
+    fn setup_link_titles(&self) {
+        gio::Settings::new(APP_ID)
+            .bind("fetch-link-titles", &*self.imp().link_titles_row, "active")
+            .build();
+    }
+
+    fn setup_lock(&self) {
+        let imp = self.imp();
+        let settings = gio::Settings::new(APP_ID);
+
+        settings
+            .bind("lock-timeout-minutes", &*imp.lock_timeout_row, "value")
+            .build();
+        settings
+            .bind("lock-enabled", &*imp.lock_timeout_row, "sensitive")
+            .get()
+            .build();
+
+        imp.lock_button.connect_clicked(clone!(@weak self as preferences => move |button| {
+            let Some(window) = preferences.window() else {
+                return;
+            };
+            button.set_sensitive(false);
+            glib::MainContext::default().spawn_local(clone!(@weak preferences, @weak button => async move {
+                if window.is_lock_enabled() {
+                    window.disable_lock().await;
+                } else {
+                    window.enable_lock().await;
+                }
+                button.set_sensitive(true);
+                preferences.update_lock();
+            }));
+        }));
+
+        self.update_lock();
+    }
+
+    fn update_lock(&self) {
+        let enabled = self.window().is_some_and(|window| window.is_lock_enabled());
+        let button = &self.imp().lock_button;
+        if enabled {
+            button.set_label("Turn _Off");
+            button.remove_css_class("suggested-action");
+        } else {
+            button.set_label("Turn _On…");
+            button.add_css_class("suggested-action");
+        }
+        button.set_use_underline(true);
+    }
+
+    fn setup_lan_sync(&self) {
+        let imp = self.imp();
+        let settings = gio::Settings::new(APP_ID);
+
+        settings
+            .bind("lan-sync-enabled", &*imp.lan_sync_row, "active")
+            .build();
+
+        // Syncing restarts whenever the code changes, so only take it once it is complete
+        glib::MainContext::default().spawn_local(clone!(@weak self as preferences => async move {
+            preferences.imp().pairing_code_row.set_text(&TodoWindow::pairing_code().await);
+        }));
+        imp.pairing_code_row.connect_apply(clone!(@weak self as preferences => move |row| {
+            let Some(window) = preferences.window() else {
+                return;
+            };
+            let pairing_code = row.text().trim().to_string();
+            glib::MainContext::default().spawn_local(async move {
+                window.set_pairing_code(&pairing_code).await;
+            });
+        }));
+    }
+
+    fn update_account(&self) {
+        let imp = self.imp();
+        let account = Account::from_settings(&gio::Settings::new(APP_ID));
+
+        imp.server_row.set_visible(account.is_none());
+        imp.login_row.set_visible(account.is_none());
+        imp.account_row.set_visible(account.is_some());
+        imp.calendars_group.set_visible(account.is_some());
+
+        if let Some(account) = account {
+            imp.account_row.set_subtitle(&format!("{} on {}", account.username, account.server_url));
+            glib::MainContext::default().spawn_local(clone!(@weak self as preferences => async move {
+                preferences.load_calendars(&account).await;
+            }));
+        }
+    }
+
+    async fn log_in(&self) {
+        let imp = self.imp();
+        let server_url = imp.server_row.text().to_string();
+        if server_url.trim().is_empty() {
+            imp.server_row.add_css_class("error");
+            return;
+        }
+        imp.server_row.remove_css_class("error");
+        imp.login_button.set_sensitive(false);
+
+        let result = self.run_login(server_url).await;
+
+        imp.login_button.set_sensitive(true);
+        imp.login_row.set_subtitle("");
+        match result {
+            Ok(()) => self.update_account(),
+            Err(err) => {
+                glib::g_warning!("todo", "Could not log in: {:#}", err);
+                self.add_toast(adw::Toast::new(&format!("Could not log in: {err}")));
+            }
+        }
+    }
+
+    async fn run_login(&self, server_url: String) -> anyhow::Result<()> {
+        let flow = sync::run(nextcloud::start_login(server_url)).await?;
+
+        self.imp().login_row.set_subtitle("Waiting for access to be granted in the browser…");
+        gtk::show_uri(Some(self), &flow.login_url, gdk::CURRENT_TIME);
+
+        let login = sync::run(nextcloud::poll_login(flow)).await?;
+        let account = Account {
+            server_url: login.caldav_url(),
+            username: login.login_name.clone(),
+        };
+
+        let Some(window) = self.window() else {
+            return Ok(());
+        };
+        window.store_sync_account(&account, &login.app_password).await
+    }
+
+    async fn log_out(&self) {
+        if let Some(window) = self.window() {
+            window.forget_sync_account().await;
+        }
+        self.update_account();
+    }
+
+    async fn load_calendars(&self, account: &Account) {
+        let imp = self.imp();
+        for row in imp.calendar_rows.take() {
+            imp.calendars_group.remove(&row);
+        }
+        imp.calendars_spinner.start();
+
+        let result = async {
+            let password = credentials::lookup_password(account)
+                .await?
+                .ok_or_else(|| anyhow::anyhow!("The password of the account is missing"))?;
+            let client = CalDavClient::new(&account.username, &password);
+            sync::run(sync::discover_calendars(client, account.server_url.clone())).await
+        }
+        .await;
+
+        imp.calendars_spinner.stop();
+        let Some(window) = self.window() else {
+            return;
+        };
+        let calendars = match result {
+            Ok(calendars) => calendars,
+            Err(err) => {
+                glib::g_warning!("todo", "Could not list the task lists: {:#}", err);
+                self.add_toast(adw::Toast::new("Could not list the task lists"));
+                return;
+            }
+        };
+
+        for calendar in calendars {
+            let row = adw::SwitchRow::builder()
+                .title(&calendar.display_name)
+                .active(window.is_calendar_linked(&calendar.url))
+                .build();
+            row.connect_active_notify(clone!(@weak window => move |row| {
+                if row.is_active() {
+                    window.link_calendar(&calendar);
+                } else {
+                    window.unlink_calendar(&calendar.url);
+                }
+            }));
+
+            imp.calendars_group.add(&row);
+            imp.calendar_rows.borrow_mut().push(row);
+        }
+    }
+}
@@ -0,0 +1,143 @@
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+use gtk::{glib, pango, prelude::*, CheckButton, PrintContext, PrintOperation};
+
+use crate::collection_object::CollectionData;
+use crate::task_object::TaskData;
+
+const FONT: &str = "Sans 11";
+// Space below the title and between tasks, in points
+const SPACING: f64 = 8.0;
+
+// Besides the tasks themselves, chosen on a tab of the print dialog
+#[derive(Clone, Copy)]
+struct PrintOptions {
+    notes: bool,
+    due_dates: bool,
+}
+
+// A block of text with its position on the page
+type Page = Vec<(String, f64)>;
+
+// Prints the collection as a checklist to tick off on paper
+pub fn print_collection(
+    window: &impl IsA<gtk::Window>,
+    collection_data: CollectionData,
+) -> Result<gtk::PrintOperationResult, glib::Error> {
+    let operation = PrintOperation::new();
+    operation.set_job_name(&collection_data.title);
+    operation.set_embed_page_setup(true);
+    operation.set_custom_tab_label(Some("Tasks"));
+
+    let options = Rc::new(Cell::new(PrintOptions { notes: true, due_dates: true }));
+    let notes_button = CheckButton::with_mnemonic("Include _Notes");
+    notes_button.set_active(true);
+    let due_dates_button = CheckButton::with_mnemonic("Include _Due Dates");
+    due_dates_button.set_active(true);
+    let options_box = gtk::Box::builder()
+        .orientation(gtk::Orientation::Vertical)
+        .spacing(6)
+        .margin_top(12)
+        .margin_bottom(12)
+        .margin_start(12)
+        .margin_end(12)
+        .build();
+    options_box.append(&notes_button);
+    options_box.append(&due_dates_button);
+
+    operation.connect_create_custom_widget(move |_| Some(options_box.clone().upcast()));
+    operation.connect_custom_widget_apply(glib::clone!(@strong options => move |_, _| {
+        options.set(PrintOptions {
+            notes: notes_button.is_active(),
+            due_dates: due_dates_button.is_active(),
+        });
+    }));
+
+    let pages: Rc<RefCell<Vec<Page>>> = Rc::default();
+    operation.connect_begin_print(glib::clone!(@strong pages => move |operation, context| {
+        let blocks = blocks(&collection_data, options.get());
+        let pages_data = paginate(context, blocks);
+        operation.set_n_pages(pages_data.len().max(1) as i32);
+        pages.replace(pages_data);
+    }));
+    operation.connect_draw_page(move |_, context, page_number| {
+        let pages = pages.borrow();
+        let Some(page) = pages.get(page_number as usize) else {
+            return;
+        };
+
+        let cairo_context = context.cairo_context();
+        for (markup, y) in page {
+            let layout = create_layout(context, markup);
+            cairo_context.move_to(0.0, *y);
+            pangocairo::functions::show_layout(&cairo_context, &layout);
+        }
+    });
+
+    operation.run(gtk::PrintOperationAction::PrintDialog, Some(window))
+}
+
+// The title, then one block per task with its due date and notes below
+fn blocks(collection_data: &CollectionData, options: PrintOptions) -> Vec<String> {
+    let title = format!(
+        "<span size=\"x-large\" weight=\"bold\">{}</span>",
+        glib::markup_escape_text(&collection_data.title)
+    );
+    std::iter::once(title)
+        .chain(collection_data.tasks_data.iter().map(|task_data| task_block(task_data, options)))
+        .collect()
+}
+
+fn task_block(task_data: &TaskData, options: PrintOptions) -> String {
+    let check_box = if task_data.completed { "☑" } else { "☐" };
+    let mut block = format!("{check_box}  {}", glib::markup_escape_text(&task_data.content));
+
+    if options.due_dates && !task_data.due_date.is_empty() {
+        block.push_str(&format!(
+            "  <span size=\"small\">(due {})</span>",
+            glib::markup_escape_text(&task_data.due_date)
+        ));
+    }
+    if options.notes {
+        for line in task_data.notes.lines().filter(|line| !line.trim().is_empty()) {
+            block.push_str(&format!(
+                "\n      <span size=\"small\">{}</span>",
+                glib::markup_escape_text(line)
+            ));
+        }
+    }
+    block
+}
+
+// Fills the pages from the top, a block that does not fit starts the next page
+fn paginate(context: &PrintContext, blocks: Vec<String>) -> Vec<Page> {
+    let mut pages: Vec<Page> = Vec::new();
+    let mut page: Page = Vec::new();
+    let mut y = 0.0;
+
+    for markup in blocks {
+        let (_, height) = create_layout(context, &markup).size();
+        let height = f64::from(height) / f64::from(pango::SCALE);
+        if y + height > context.height() && !page.is_empty() {
+            pages.push(std::mem::take(&mut page));
+            y = 0.0;
+        }
+        page.push((markup, y));
+        y += height + SPACING;
+    }
+
+    if !page.is_empty() {
+        pages.push(page);
+    }
+    pages
+}
+
+fn create_layout(context: &PrintContext, markup: &str) -> pango::Layout {
+    let layout = context.create_pango_layout();
+    layout.set_font_description(Some(&pango::FontDescription::from_string(FONT)));
+    layout.set_width((context.width() * f64::from(pango::SCALE)) as i32);
+    layout.set_wrap(pango::WrapMode::WordChar);
+    layout.set_markup(markup);
+    layout
+}
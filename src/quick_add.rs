@@ -0,0 +1,68 @@
+use adw::prelude::*;
+use glib::clone;
+use gtk::{gdk, glib};
+
+use crate::TodoWindow;
+
+// A small window with only an entry, to jot down a task from anywhere with
+// `todo --quick-add` bound to a shortcut of the desktop. GTK 4 can't keep a window
+// above the others, presenting it brings it to the front which is enough here.
+pub fn show(window: &TodoWindow) {
+    // The lock screen is shown instead
+    if window.is_locked() {
+        window.present();
+        return;
+    }
+
+    let Some(collection) = window.quick_add_collection() else {
+        window.present();
+        return;
+    };
+
+    let entry = gtk::Entry::builder()
+        .placeholder_text(format!("New task in “{}”", collection.title()))
+        .margin_top(12)
+        .margin_bottom(12)
+        .margin_start(12)
+        .margin_end(12)
+        .build();
+    window.setup_entry_completion(&entry);
+
+    let quick_add = adw::Window::builder()
+        .title("Quick Add")
+        .default_width(420)
+        .resizable(false)
+        .content(&entry)
+        .build();
+    quick_add.set_application(window.application().as_ref());
+
+    // The task is added and the window gone in one go, nothing else to do there
+    entry.connect_activate(clone!(@weak window, @weak quick_add => move |entry| {
+        window.add_task(&collection, &entry.text());
+        quick_add.close();
+    }));
+
+    let key_controller = gtk::EventControllerKey::new();
+    key_controller.connect_key_pressed(
+        clone!(@weak quick_add => @default-return glib::Propagation::Proceed, move |_, key, _, _| {
+            if key == gdk::Key::Escape {
+                quick_add.close();
+                glib::Propagation::Stop
+            } else {
+                glib::Propagation::Proceed
+            }
+        })
+    );
+    quick_add.add_controller(key_controller);
+
+    // The main window was only created to hold the tasks, closing it saves them and
+    // lets the application exit or go back to the background
+    quick_add.connect_close_request(clone!(@weak window => @default-return glib::Propagation::Proceed, move |_| {
+        if !window.is_visible() {
+            window.close();
+        }
+        glib::Propagation::Proceed
+    }));
+
+    quick_add.present();
+}
@@ -0,0 +1,113 @@
+use gtk::glib;
+
+use crate::utils;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+// The subset of an iCalendar RRULE ("FREQ=WEEKLY;INTERVAL=2") that tasks support
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Recurrence {
+    pub frequency: Frequency,
+    pub interval: i32,
+}
+
+impl Recurrence {
+    pub const PRESETS: [Recurrence; 4] = [
+        Recurrence { frequency: Frequency::Daily, interval: 1 },
+        Recurrence { frequency: Frequency::Weekly, interval: 1 },
+        Recurrence { frequency: Frequency::Monthly, interval: 1 },
+        Recurrence { frequency: Frequency::Yearly, interval: 1 },
+    ];
+
+    pub fn parse(rrule: &str) -> Option<Self> {
+        let rrule = rrule.trim();
+        let rrule = rrule.strip_prefix("RRULE:").unwrap_or(rrule);
+
+        let mut frequency = None;
+        let mut interval = 1;
+
+        for part in rrule.split(';') {
+            let (key, value) = part.split_once('=')?;
+            match key.trim().to_uppercase().as_str() {
+                "FREQ" => {
+                    frequency = match value.trim().to_uppercase().as_str() {
+                        "DAILY" => Some(Frequency::Daily),
+                        "WEEKLY" => Some(Frequency::Weekly),
+                        "MONTHLY" => Some(Frequency::Monthly),
+                        "YEARLY" => Some(Frequency::Yearly),
+                        _ => return None,
+                    }
+                }
+                "INTERVAL" => interval = value.trim().parse().ok().filter(|interval| *interval > 0)?,
+                // Other rule parts are not supported, rather ignore them than lose the task
+                _ => {}
+            }
+        }
+
+        Some(Self {
+            frequency: frequency?,
+            interval,
+        })
+    }
+
+    pub fn to_rrule(&self) -> String {
+        let frequency = match self.frequency {
+            Frequency::Daily => "DAILY",
+            Frequency::Weekly => "WEEKLY",
+            Frequency::Monthly => "MONTHLY",
+            Frequency::Yearly => "YEARLY",
+        };
+
+        if self.interval == 1 {
+            format!("FREQ={frequency}")
+        } else {
+            format!("FREQ={frequency};INTERVAL={}", self.interval)
+        }
+    }
+
+    pub fn label(&self) -> String {
+        let (singular, plural) = match self.frequency {
+            Frequency::Daily => ("Daily", "days"),
+            Frequency::Weekly => ("Weekly", "weeks"),
+            Frequency::Monthly => ("Monthly", "months"),
+            Frequency::Yearly => ("Yearly", "years"),
+        };
+
+        if self.interval == 1 {
+            singular.to_string()
+        } else {
+            format!("Every {} {plural}", self.interval)
+        }
+    }
+
+    pub fn next_date(&self, from: &glib::DateTime) -> Option<glib::DateTime> {
+        match self.frequency {
+            Frequency::Daily => from.add_days(self.interval),
+            Frequency::Weekly => from.add_weeks(self.interval),
+            Frequency::Monthly => from.add_months(self.interval),
+            Frequency::Yearly => from.add_years(self.interval),
+        }
+        .ok()
+    }
+
+    // The due date of the occurrence after one due on `due_date` ("YYYY-MM-DD", may
+    // be empty). Occurrences that would already be in the past are skipped.
+    pub fn next_due_date(&self, due_date: &str) -> Option<String> {
+        let today = utils::today();
+        let mut date = utils::parse_date(due_date).unwrap_or_else(utils::now);
+
+        loop {
+            date = self.next_date(&date)?;
+            let formatted = utils::format_date(&date);
+            if formatted > today {
+                return Some(formatted);
+            }
+        }
+    }
+}
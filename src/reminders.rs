@@ -0,0 +1,100 @@
+use adw::prelude::*;
+use gtk::{gio, glib};
+
+use crate::collection_object::CollectionObject;
+use crate::task_object::TaskObject;
+use crate::utils;
+
+// How often pending reminders are looked for
+pub const CHECK_INTERVAL_SECONDS: u32 = 30;
+// How long the snooze button of a reminder puts it off
+pub const SNOOZE_MINUTES: i32 = 10;
+
+// Reminders are compared as "YYYY-MM-DD HH:MM" strings, which sort chronologically
+pub fn current_minute() -> String {
+    utils::now()
+        .format("%Y-%m-%d %H:%M")
+        .expect("Could not format date")
+        .to_string()
+}
+
+// Parses "HH:MM" (or "H:MM") into a normalized "HH:MM"
+pub fn parse_time(time: &str) -> Option<String> {
+    let (hour, minute) = time.trim().split_once(':')?;
+    let hour: u32 = hour.parse().ok().filter(|hour| *hour < 24)?;
+    let minute: u32 = minute.parse().ok().filter(|minute| *minute < 60)?;
+    Some(format!("{hour:02}:{minute:02}"))
+}
+
+// Open tasks whose reminder time falls into `(after, until]`
+pub fn due_reminders(collections: &gio::ListStore, after: &str, until: &str) -> Vec<TaskObject> {
+    let mut tasks = Vec::new();
+
+    for collection in collections.iter::<CollectionObject>().filter_map(Result::ok) {
+        for task in collection.tasks().iter::<TaskObject>().filter_map(Result::ok) {
+            let Some(reminder) = task.reminder() else {
+                continue;
+            };
+
+            if !task.is_completed() && reminder.as_str() > after && reminder.as_str() <= until {
+                tasks.push(task);
+            }
+        }
+    }
+
+    tasks
+}
+
+pub fn send_reminder(application: &gio::Application, task: &TaskObject) {
+    let notification = gio::Notification::new(&task.content());
+    notification.set_body(Some("Reminder"));
+    notification.set_default_action_and_target_value(
+        "app.show-task",
+        Some(&task.uuid().to_variant()),
+    );
+    notification.add_button_with_target_value(
+        &format!("Snooze {SNOOZE_MINUTES} Minutes"),
+        "app.snooze-task",
+        Some(&task.uuid().to_variant()),
+    );
+
+    application.send_notification(Some(&format!("reminder-{}", task.uuid())), &notification);
+}
+
+// "5 tasks due today, 2 overdue", opening the Today view when clicked
+pub fn send_digest(application: &gio::Application, due_today: usize, overdue: usize) {
+    let mut parts = Vec::new();
+    if due_today > 0 {
+        parts.push(format!("{} due today", utils::count_label(due_today, "task")));
+    }
+    if overdue > 0 {
+        parts.push(format!("{overdue} overdue"));
+    }
+    if parts.is_empty() {
+        return;
+    }
+
+    let notification = gio::Notification::new(&parts.join(", "));
+    notification.set_body(Some("Daily digest"));
+    notification.set_default_action("app.show-today");
+    application.send_notification(Some("digest"), &notification);
+}
+
+// Asks the background portal to let the application run without a window
+pub async fn request_background(window: &impl IsA<gtk::Native>) -> bool {
+    let identifier = ashpd::WindowIdentifier::from_native(window).await;
+    let request = ashpd::desktop::background::Background::request()
+        .identifier(identifier)
+        .reason("Deliver task reminders while the window is closed")
+        .auto_start(true)
+        .send()
+        .await;
+
+    match request.and_then(|request| request.response()) {
+        Ok(response) => response.run_in_background(),
+        Err(err) => {
+            glib::g_warning!("todo", "Could not request running in the background: {}", err);
+            false
+        }
+    }
+}
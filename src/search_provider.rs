@@ -0,0 +1,206 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use adw::prelude::*;
+use gio::{DBusConnection, DBusMethodInvocation, RegistrationId};
+use gtk::{gio, glib};
+
+use crate::collection_object::CollectionData;
+use crate::storage::JsonStorage;
+use crate::utils::data_path;
+use crate::{TodoApplication, TodoWindow, APP_ID};
+
+pub const OBJECT_PATH: &str = "/org/gnome/ToDo/SearchProvider";
+
+const INTERFACE: &str = r#"
+<node>
+  <interface name="org.gnome.Shell.SearchProvider2">
+    <method name="GetInitialResultSet">
+      <arg type="as" name="terms" direction="in" />
+      <arg type="as" name="results" direction="out" />
+    </method>
+    <method name="GetSubsearchResultSet">
+      <arg type="as" name="previous_results" direction="in" />
+      <arg type="as" name="terms" direction="in" />
+      <arg type="as" name="results" direction="out" />
+    </method>
+    <method name="GetResultMetas">
+      <arg type="as" name="identifiers" direction="in" />
+      <arg type="aa{sv}" name="metas" direction="out" />
+    </method>
+    <method name="ActivateResult">
+      <arg type="s" name="identifier" direction="in" />
+      <arg type="as" name="terms" direction="in" />
+      <arg type="u" name="timestamp" direction="in" />
+    </method>
+    <method name="LaunchSearch">
+      <arg type="as" name="terms" direction="in" />
+      <arg type="u" name="timestamp" direction="in" />
+    </method>
+  </interface>
+</node>
+"#;
+
+// A task found by a search, results are identified by the task uuid
+#[derive(Clone)]
+struct SearchResult {
+    content: String,
+    notes: String,
+    collection_title: String,
+    completed: bool,
+}
+
+impl SearchResult {
+    fn matches(&self, terms: &[String]) -> bool {
+        let content = self.content.to_lowercase();
+        let notes = self.notes.to_lowercase();
+        terms.iter().all(|term| content.contains(term) || notes.contains(term))
+    }
+}
+
+// Lets the search of the Shell overview find tasks
+pub fn register(application: &TodoApplication, connection: &DBusConnection) -> Result<RegistrationId, glib::Error> {
+    let interface_info = gio::DBusNodeInfo::for_xml(INTERFACE)?
+        .lookup_interface("org.gnome.Shell.SearchProvider2")
+        .expect("the search provider interface should be defined");
+
+    // The tasks of the last search, the Shell asks for their details afterwards
+    let results: Rc<RefCell<HashMap<String, SearchResult>>> = Rc::default();
+    let application = application.downgrade();
+
+    connection
+        .register_object(OBJECT_PATH, &interface_info)
+        .method_call(move |_, _, _, _, method_name, parameters, invocation| {
+            let Some(application) = application.upgrade() else {
+                return;
+            };
+            handle_method_call(&application, &results, method_name, &parameters, invocation);
+        })
+        .build()
+}
+
+fn handle_method_call(
+    application: &TodoApplication,
+    results: &Rc<RefCell<HashMap<String, SearchResult>>>,
+    method_name: &str,
+    parameters: &glib::Variant,
+    invocation: DBusMethodInvocation,
+) {
+    match method_name {
+        "GetInitialResultSet" => {
+            let Some((terms,)) = parameters.get::<(Vec<String>,)>() else {
+                return invalid_arguments(invocation);
+            };
+            results.replace(search_tasks(application));
+            let identifiers = matching(&results.borrow(), results.borrow().keys(), &normalize(&terms));
+            invocation.return_value(Some(&(identifiers,).to_variant()));
+        }
+        "GetSubsearchResultSet" => {
+            let Some((previous_results, terms)) = parameters.get::<(Vec<String>, Vec<String>)>() else {
+                return invalid_arguments(invocation);
+            };
+            let identifiers = matching(&results.borrow(), previous_results.iter(), &normalize(&terms));
+            invocation.return_value(Some(&(identifiers,).to_variant()));
+        }
+        "GetResultMetas" => {
+            let Some((identifiers,)) = parameters.get::<(Vec<String>,)>() else {
+                return invalid_arguments(invocation);
+            };
+            let results = results.borrow();
+            let metas = identifiers.iter().filter_map(|identifier| {
+                let result = results.get(identifier)?;
+                let meta = glib::VariantDict::new(None);
+                meta.insert_value("id", &identifier.to_variant());
+                meta.insert_value("name", &result.content.to_variant());
+                meta.insert_value("description", &result.collection_title.to_variant());
+                Some(meta.end())
+            });
+            let metas = glib::Variant::array_from_iter_with_type(glib::VariantTy::VARDICT, metas);
+            invocation.return_value(Some(&glib::Variant::tuple_from_iter([metas])));
+        }
+        "ActivateResult" => {
+            let Some((identifier, _, _)) = parameters.get::<(String, Vec<String>, u32)>() else {
+                return invalid_arguments(invocation);
+            };
+            application.activate_action("show-task", Some(&identifier.to_variant()));
+            invocation.return_value(None);
+        }
+        "LaunchSearch" => {
+            let Some((terms, _)) = parameters.get::<(Vec<String>, u32)>() else {
+                return invalid_arguments(invocation);
+            };
+            application.activate();
+            if let Some(window) = application.active_window().and_downcast::<TodoWindow>() {
+                window.search(&terms.join(" "));
+            }
+            invocation.return_value(None);
+        }
+        _ => invocation.return_error(gio::DBusError::UnknownMethod, &format!("Unknown method {method_name}")),
+    }
+}
+
+fn invalid_arguments(invocation: DBusMethodInvocation) {
+    invocation.return_error(gio::DBusError::InvalidArgs, "Invalid arguments");
+}
+
+fn normalize(terms: &[String]) -> Vec<String> {
+    terms.iter().map(|term| term.to_lowercase()).collect()
+}
+
+// Open tasks come first, then those that are done
+fn matching<'a>(
+    results: &HashMap<String, SearchResult>,
+    identifiers: impl Iterator<Item = &'a String>,
+    terms: &[String],
+) -> Vec<String> {
+    let mut identifiers: Vec<&String> = identifiers
+        .filter(|identifier| results.get(*identifier).is_some_and(|result| result.matches(terms)))
+        .collect();
+    identifiers.sort_by_key(|identifier| (results[*identifier].completed, results[*identifier].content.to_lowercase()));
+    identifiers.into_iter().cloned().collect()
+}
+
+// The tasks of the open window, or of the data file when the application runs only for
+// the search. Nothing is found while the tasks are locked or encrypted.
+fn search_tasks(application: &TodoApplication) -> HashMap<String, SearchResult> {
+    let collections_data = match application
+        .windows()
+        .into_iter()
+        .find_map(|window| window.downcast::<TodoWindow>().ok())
+    {
+        Some(window) => window.searchable_collections(),
+        None => read_collections(),
+    };
+
+    let mut results = HashMap::new();
+    for collection_data in collections_data {
+        for task_data in &collection_data.tasks_data {
+            results.insert(
+                task_data.uuid.clone(),
+                SearchResult {
+                    content: task_data.content.clone(),
+                    notes: task_data.notes.clone(),
+                    collection_title: collection_data.title.clone(),
+                    completed: task_data.completed,
+                },
+            );
+        }
+    }
+    results
+}
+
+fn read_collections() -> Vec<CollectionData> {
+    let settings = gio::Settings::new(APP_ID);
+    if settings.boolean("lock-enabled") || settings.boolean("encrypt-data") || !data_path().exists() {
+        return Vec::new();
+    }
+
+    match JsonStorage::read_file(&data_path()) {
+        Ok(store_data) => store_data.collections,
+        Err(err) => {
+            glib::g_warning!("todo", "Could not search the tasks: {:#}", err);
+            Vec::new()
+        }
+    }
+}
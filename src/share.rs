@@ -0,0 +1,50 @@
+use ashpd::desktop::email::EmailRequest;
+use ashpd::WindowIdentifier;
+use gtk::glib;
+use gtk::prelude::*;
+
+use crate::collection_object::CollectionData;
+use crate::markdown;
+use crate::task_object::TaskData;
+
+// There is no portal to share with any application, the email portal lets the user
+// send the text with the mail client of their choice. Returns false if it failed.
+pub async fn share(window: &impl IsA<gtk::Native>, subject: &str, body: &str) -> bool {
+    let identifier = WindowIdentifier::from_native(window).await;
+    let request = EmailRequest::default()
+        .identifier(identifier)
+        .subject(subject)
+        .body(body)
+        .send()
+        .await;
+
+    match request.and_then(|request| request.response()) {
+        Ok(()) => true,
+        Err(ashpd::Error::Response(ashpd::desktop::ResponseError::Cancelled)) => true,
+        Err(err) => {
+            glib::g_warning!("todo", "Could not share: {}", err);
+            false
+        }
+    }
+}
+
+// The task with its due date, link and notes below
+pub fn task_text(task_data: &TaskData) -> String {
+    let mut lines = vec![task_data.content.clone()];
+    if !task_data.due_date.is_empty() {
+        lines.push(format!("Due {}", task_data.due_date));
+    }
+    if !task_data.link.is_empty() {
+        lines.push(task_data.link.clone());
+    }
+    if !task_data.notes.trim().is_empty() {
+        lines.push(String::new());
+        lines.push(task_data.notes.trim().to_string());
+    }
+    lines.join("\n")
+}
+
+// The collection as a Markdown checklist, which reads well as plain text
+pub fn collection_text(collection_data: &CollectionData) -> String {
+    markdown::to_string(std::slice::from_ref(collection_data))
+}
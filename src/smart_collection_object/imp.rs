@@ -0,0 +1,28 @@
+use std::cell::RefCell;
+
+use glib::Properties;
+use gtk::glib;
+use gtk::prelude::*;
+use gtk::subclass::prelude::*;
+
+use super::SmartCollectionData;
+
+// Object holding the state
+#[derive(Properties, Default)]
+#[properties(wrapper_type = super::SmartCollectionObject)]
+pub struct SmartCollectionObject {
+    #[property(name = "uuid", get, type = String, member = uuid)]
+    #[property(name = "title", get, set, type = String, member = title)]
+    pub data: RefCell<SmartCollectionData>,
+}
+
+// The central trait for subclassing a GObject
+#[glib::object_subclass]
+impl ObjectSubclass for SmartCollectionObject {
+    const NAME: &'static str = "TodoSmartCollectionObject";
+    type Type = super::SmartCollectionObject;
+}
+
+// Trait shared by all GObjects
+#[glib::derived_properties]
+impl ObjectImpl for SmartCollectionObject {}
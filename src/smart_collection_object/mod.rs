@@ -0,0 +1,78 @@
+mod imp;
+
+use adw::subclass::prelude::*;
+use glib::Object;
+use gtk::glib;
+use serde::{Deserialize, Serialize};
+
+use crate::task_object::{Priority, TaskObject};
+use crate::utils;
+
+glib::wrapper! {
+    pub struct SmartCollectionObject(ObjectSubclass<imp::SmartCollectionObject>);
+}
+
+// A collection defined by a query, showing the matching tasks of every collection
+impl SmartCollectionObject {
+    pub fn new(mut smart_collection_data: SmartCollectionData) -> Self {
+        smart_collection_data.uuid = utils::new_uuid();
+        Self::from_smart_collection_data(smart_collection_data)
+    }
+
+    pub fn smart_collection_data(&self) -> SmartCollectionData {
+        self.imp().data.borrow().clone()
+    }
+
+    pub fn from_smart_collection_data(smart_collection_data: SmartCollectionData) -> Self {
+        let smart_collection_object: Self = Object::builder().build();
+        smart_collection_object.imp().data.replace(smart_collection_data);
+        smart_collection_object
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct SmartCollectionData {
+    pub uuid: String,
+    pub title: String,
+    // Every condition that is set has to match, empty ones match everything
+    #[serde(default)]
+    pub text: String,
+    #[serde(default)]
+    pub tag: String,
+    // Tasks with at least this priority
+    #[serde(default)]
+    pub priority: Priority,
+    // Open tasks due within this many days, including overdue ones
+    #[serde(default)]
+    pub due_within_days: Option<i32>,
+}
+
+impl SmartCollectionData {
+    // A matcher for the query, the due range is taken from today's date
+    pub fn matcher(&self) -> impl Fn(&TaskObject) -> bool {
+        let text = self.text.trim().to_lowercase();
+        let tag = self.tag.clone();
+        let priority = self.priority;
+        let last_due_date = self.due_within_days.map(|days| {
+            utils::now()
+                .add_days(days)
+                .map(|date| utils::format_date(&date))
+                .unwrap_or_default()
+        });
+
+        move |task_object| {
+            let matches_text = text.is_empty()
+                || task_object.content().to_lowercase().contains(&text)
+                || task_object.notes().to_lowercase().contains(&text);
+            let matches_tag = tag.is_empty() || task_object.has_tag(&tag);
+            let matches_priority = task_object.priority() >= priority;
+            let matches_due_date = last_due_date.as_ref().map_or(true, |last_due_date| {
+                !task_object.is_completed()
+                    && task_object.has_due_date()
+                    && task_object.due_date() <= *last_due_date
+            });
+
+            matches_text && matches_tag && matches_priority && matches_due_date
+        }
+    }
+}
@@ -0,0 +1,82 @@
+use gtk::prelude::*;
+use gtk::CustomFilter;
+
+use crate::smart_collection_object::SmartCollectionObject;
+use crate::task_object::TaskObject;
+use crate::utils;
+
+// How many days ahead the upcoming view looks
+const UPCOMING_DAYS: i32 = 7;
+
+// Views over the tasks of every collection, shown in place of a collection
+#[derive(Debug, Clone, PartialEq)]
+pub enum SmartView {
+    AllTasks,
+    Today,
+    Upcoming,
+    Starred,
+    // Done tasks, most recently completed first
+    Completed,
+    // Defined by the user
+    Saved(SmartCollectionObject),
+}
+
+impl SmartView {
+    pub fn title(&self) -> String {
+        match self {
+            SmartView::AllTasks => "All Tasks".to_string(),
+            SmartView::Today => "Today".to_string(),
+            SmartView::Upcoming => "Upcoming".to_string(),
+            SmartView::Starred => "Starred".to_string(),
+            SmartView::Completed => "Completed".to_string(),
+            SmartView::Saved(smart_collection) => smart_collection.title(),
+        }
+    }
+
+    // Selects the tasks belonging to the view, `None` if it shows every task.
+    // The dates are taken when the filter is created, it needs replacing at midnight.
+    pub fn filter(&self) -> Option<CustomFilter> {
+        match self {
+            SmartView::AllTasks => None,
+            SmartView::Today => Some(CustomFilter::new(|obj| {
+                let task_object = obj.downcast_ref::<TaskObject>().expect("Expecting TaskObject");
+                task_object.is_due_today() || task_object.is_overdue()
+            })),
+            SmartView::Upcoming => {
+                let today = utils::today();
+                let last_day = utils::now()
+                    .add_days(UPCOMING_DAYS)
+                    .map(|date| utils::format_date(&date))
+                    .unwrap_or_default();
+
+                Some(CustomFilter::new(move |obj| {
+                    let task_object = obj.downcast_ref::<TaskObject>().expect("Expecting TaskObject");
+                    let due_date = task_object.due_date();
+                    !task_object.is_completed() && due_date > today && due_date <= last_day
+                }))
+            }
+            SmartView::Starred => Some(CustomFilter::new(|obj| {
+                let task_object = obj.downcast_ref::<TaskObject>().expect("Expecting TaskObject");
+                task_object.is_starred()
+            })),
+            SmartView::Completed => Some(CustomFilter::new(|obj| {
+                let task_object = obj.downcast_ref::<TaskObject>().expect("Expecting TaskObject");
+                task_object.is_completed()
+            })),
+            SmartView::Saved(smart_collection) => {
+                let matches = smart_collection.smart_collection_data().matcher();
+                Some(CustomFilter::new(move |obj| {
+                    let task_object = obj.downcast_ref::<TaskObject>().expect("Expecting TaskObject");
+                    matches(task_object)
+                }))
+            }
+        }
+    }
+}
+
+// Seconds until the date changes, when the date based views need to be refreshed
+pub fn seconds_until_midnight() -> u32 {
+    let now = utils::now();
+    let seconds_today = now.hour() * 60 * 60 + now.minute() * 60 + now.second();
+    (24 * 60 * 60 - seconds_today).max(1) as u32
+}
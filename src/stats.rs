@@ -0,0 +1,174 @@
+use std::collections::HashMap;
+
+use adw::prelude::*;
+use gtk::{glib, Align, LevelBar};
+
+use crate::collection_object::CollectionData;
+use crate::task_object::TaskData;
+use crate::utils;
+
+// Days shown one by one, and weeks summed up, counting back from today
+const DAYS: i32 = 7;
+const WEEKS: i32 = 4;
+
+pub struct Stats {
+    // "YYYY-MM-DD", tasks completed and tasks added that day, oldest first
+    pub days: Vec<(String, usize, usize)>,
+    // Tasks completed in each of the last weeks, the current one first
+    pub weeks: Vec<usize>,
+    // Title, completed and all tasks of each collection
+    pub collections: Vec<(String, usize, usize)>,
+    // Days in a row with at least one completed task, up to today or yesterday
+    pub streak: usize,
+}
+
+impl Stats {
+    // Tasks in the trash do not count, archived ones do as they were done
+    pub fn new(collections_data: &[CollectionData]) -> Self {
+        let mut completed_on: HashMap<String, usize> = HashMap::new();
+        let mut added_on: HashMap<String, usize> = HashMap::new();
+        let mut collections = Vec::new();
+
+        for collection_data in collections_data {
+            let tasks: Vec<&TaskData> = collection_data
+                .tasks_data
+                .iter()
+                .chain(&collection_data.archived_data)
+                .collect();
+            for task_data in &tasks {
+                if let Some(date) = utils::local_date(&task_data.completed_at).filter(|_| task_data.completed) {
+                    *completed_on.entry(date).or_default() += 1;
+                }
+                if let Some(date) = utils::local_date(&task_data.created_at) {
+                    *added_on.entry(date).or_default() += 1;
+                }
+            }
+
+            let completed = tasks.iter().filter(|task_data| task_data.completed).count();
+            collections.push((collection_data.title.clone(), completed, tasks.len()));
+        }
+
+        let count = |counts: &HashMap<String, usize>, date: &str| counts.get(date).copied().unwrap_or_default();
+
+        let days = (0..DAYS)
+            .rev()
+            .map(|days_ago| {
+                let date = days_before_today(days_ago);
+                let completed = count(&completed_on, &date);
+                let added = count(&added_on, &date);
+                (date, completed, added)
+            })
+            .collect();
+
+        let weeks = (0..WEEKS)
+            .map(|week| {
+                (week * 7..(week + 1) * 7)
+                    .map(|days_ago| count(&completed_on, &days_before_today(days_ago)))
+                    .sum()
+            })
+            .collect();
+
+        // A day without completed tasks yet does not break the streak until it is over
+        let first_day = if completed_on.contains_key(&utils::today()) { 0 } else { 1 };
+        let streak = (first_day..)
+            .take_while(|days_ago| completed_on.contains_key(&days_before_today(*days_ago)))
+            .count();
+
+        Self {
+            days,
+            weeks,
+            collections,
+            streak,
+        }
+    }
+}
+
+fn days_before_today(days: i32) -> String {
+    utils::now()
+        .add_days(-days)
+        .map(|date| utils::format_date(&date))
+        .unwrap_or_default()
+}
+
+fn week_label(week: usize) -> String {
+    match week {
+        0 => "This Week".to_string(),
+        1 => "Last Week".to_string(),
+        week => format!("{week} Weeks Ago"),
+    }
+}
+
+// A row with a bar filled to `value` out of `max`
+fn bar_row(title: &str, subtitle: &str, value: usize, max: usize) -> adw::ActionRow {
+    let level_bar = LevelBar::builder()
+        .max_value(max.max(1) as f64)
+        .value(value as f64)
+        .valign(Align::Center)
+        .width_request(120)
+        .build();
+    let row = adw::ActionRow::builder()
+        .title(title)
+        .subtitle(subtitle)
+        .build();
+    row.add_suffix(&level_bar);
+    row
+}
+
+// Tasks completed over the last days and weeks, per collection and the current streak
+pub fn stats_window(parent: &impl IsA<gtk::Window>, stats: &Stats) -> adw::Window {
+    let page = adw::PreferencesPage::new();
+
+    let streak_group = adw::PreferencesGroup::new();
+    let streak_row = adw::ActionRow::builder()
+        .title("Current Streak")
+        .subtitle("Days in a row with completed tasks")
+        .build();
+    let streak_label = gtk::Label::new(Some(&utils::count_label(stats.streak, "day")));
+    streak_label.add_css_class("title-3");
+    streak_row.add_suffix(&streak_label);
+    streak_group.add(&streak_row);
+    page.add(&streak_group);
+
+    let days_group = adw::PreferencesGroup::builder()
+        .title("Completed per Day")
+        .build();
+    let max_per_day = stats.days.iter().map(|(_, completed, _)| *completed).max().unwrap_or_default();
+    for (date, completed, added) in &stats.days {
+        let subtitle = format!("{completed} completed, {added} added");
+        days_group.add(&bar_row(&utils::display_date(date), &subtitle, *completed, max_per_day));
+    }
+    page.add(&days_group);
+
+    let weeks_group = adw::PreferencesGroup::builder()
+        .title("Completed per Week")
+        .build();
+    let max_per_week = stats.weeks.iter().copied().max().unwrap_or_default();
+    for (week, completed) in stats.weeks.iter().enumerate() {
+        let subtitle = utils::count_label(*completed, "task");
+        weeks_group.add(&bar_row(&week_label(week), &subtitle, *completed, max_per_week));
+    }
+    page.add(&weeks_group);
+
+    let collections_group = adw::PreferencesGroup::builder()
+        .title("Completion Rate")
+        .build();
+    for (title, completed, total) in &stats.collections {
+        let rate = if *total == 0 { 0 } else { completed * 100 / total };
+        let subtitle = format!("{rate}% · {completed} of {}", utils::count_label(*total, "task"));
+        collections_group.add(&bar_row(&glib::markup_escape_text(title), &subtitle, *completed, *total));
+    }
+    page.add(&collections_group);
+
+    let toolbar_view = adw::ToolbarView::new();
+    toolbar_view.add_top_bar(&adw::HeaderBar::new());
+    toolbar_view.set_content(Some(&page));
+
+    adw::Window::builder()
+        .title("Statistics")
+        .transient_for(parent)
+        .modal(true)
+        .default_width(480)
+        .default_height(640)
+        .content(&toolbar_view)
+        .build()
+}
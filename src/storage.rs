@@ -0,0 +1,339 @@
+use std::any::Any;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use age::secrecy::Secret;
+use anyhow::{bail, Context};
+use gtk::{gio, glib};
+use gtk::prelude::*;
+
+use crate::migration::{self, StoreData};
+
+// Keeps a watch alive, changes are no longer reported once it is dropped
+#[derive(Debug)]
+pub struct WatchHandle(#[allow(dead_code)] Box<dyn Any>);
+
+pub trait Storage: std::fmt::Debug + Send + Sync {
+    // Returns `None` if nothing has been stored yet
+    fn load(&self) -> anyhow::Result<Option<StoreData>>;
+
+    fn save(&self, data: &StoreData) -> anyhow::Result<()>;
+
+    // Reads the data again after `watch` reported a change. Unlike `load` this
+    // should not fall back to older data when the stored data cannot be read.
+    fn reload(&self) -> anyhow::Result<Option<StoreData>> {
+        self.load()
+    }
+
+    // Encrypted backends cannot load or save anything before they get the passphrase.
+    // Whether it is the right one shows when loading.
+    fn unlock(&self, _passphrase: &str) {}
+
+    fn is_locked(&self) -> bool {
+        false
+    }
+
+    // Calls `callback` whenever the stored data is changed by someone else.
+    // Backends without change notifications return `None`.
+    fn watch(&self, _callback: Box<dyn Fn() + 'static>) -> Option<WatchHandle> {
+        None
+    }
+}
+
+// Returned while an encrypted backend waits for its passphrase
+#[derive(Debug)]
+pub struct Locked;
+
+impl std::fmt::Display for Locked {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "The tasks are encrypted")
+    }
+}
+
+impl std::error::Error for Locked {}
+
+#[derive(Debug)]
+pub struct WrongPassphrase;
+
+impl std::fmt::Display for WrongPassphrase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "The passphrase is wrong")
+    }
+}
+
+impl std::error::Error for WrongPassphrase {}
+
+// Kept out of debug output
+struct Passphrase(String);
+
+impl std::fmt::Debug for Passphrase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Passphrase(..)")
+    }
+}
+
+// Number of previous data files kept next to the current one
+const BACKUP_COUNT: usize = 5;
+// A new backup is made when saving after the newest one is this old
+const BACKUP_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+#[derive(Debug)]
+pub struct JsonStorage {
+    path: PathBuf,
+    // For data encrypted with age, holds the passphrase once it is unlocked
+    encryption: Option<Mutex<Option<Passphrase>>>,
+}
+
+impl JsonStorage {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path, encryption: None }
+    }
+
+    // The data and its backups are encrypted, see `Storage::unlock`
+    pub fn encrypted(path: PathBuf) -> Self {
+        Self {
+            path,
+            encryption: Some(Mutex::new(None)),
+        }
+    }
+
+    // Removes the data file and its backups, after the data moved elsewhere
+    pub fn remove_files(&self) -> io::Result<()> {
+        for path in self.backup_paths() {
+            fs::remove_file(path)?;
+        }
+        if self.path.exists() {
+            fs::remove_file(&self.path)?;
+        }
+        Ok(())
+    }
+
+    fn passphrase(&self) -> anyhow::Result<Option<Secret<String>>> {
+        let Some(encryption) = &self.encryption else {
+            return Ok(None);
+        };
+        match &*encryption.lock().unwrap() {
+            Some(Passphrase(passphrase)) => Ok(Some(Secret::new(passphrase.clone()))),
+            None => Err(Locked.into()),
+        }
+    }
+
+    fn read(&self, path: &Path) -> anyhow::Result<StoreData> {
+        let Some(passphrase) = self.passphrase()? else {
+            return Self::read_file(path);
+        };
+
+        let encrypted = fs::read(path).with_context(|| format!("Could not open {}", path.display()))?;
+        let decryptor = match age::Decryptor::new(&encrypted[..])
+            .with_context(|| format!("Could not parse {}", path.display()))?
+        {
+            age::Decryptor::Passphrase(decryptor) => decryptor,
+            _ => bail!("{} is not encrypted with a passphrase", path.display()),
+        };
+        let mut contents = Vec::new();
+        decryptor
+            .decrypt(&passphrase, None)
+            .map_err(|_| WrongPassphrase)?
+            .read_to_end(&mut contents)
+            .with_context(|| format!("Could not decrypt {}", path.display()))?;
+
+        let value = serde_json::from_slice(&contents)
+            .with_context(|| format!("Could not parse {}", path.display()))?;
+        migration::migrate(value)
+            .with_context(|| format!("Could not read {}", path.display()))
+    }
+
+    // The bytes to store, encrypted when a passphrase is set
+    fn encode(&self, data: &StoreData) -> anyhow::Result<Vec<u8>> {
+        let contents = serde_json::to_vec(data).context("Could not serialize data")?;
+        let Some(passphrase) = self.passphrase()? else {
+            return Ok(contents);
+        };
+
+        let mut encrypted = Vec::new();
+        let mut writer = age::Encryptor::with_user_passphrase(passphrase)
+            .wrap_output(&mut encrypted)
+            .context("Could not encrypt data")?;
+        writer.write_all(&contents).context("Could not encrypt data")?;
+        writer.finish().context("Could not encrypt data")?;
+        Ok(encrypted)
+    }
+
+    // Backups are named after the time they were made, `data.json.20240131-084500`
+    fn backup_path(&self, time: &glib::DateTime) -> PathBuf {
+        let suffix = time.format("%Y%m%d-%H%M%S").expect("Could not format the backup time");
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{suffix}"));
+        path.into()
+    }
+
+    // Newest first. Older versions numbered their backups, `data.json.1` being the
+    // newest, those come last until they are rotated out.
+    fn backup_paths(&self) -> Vec<PathBuf> {
+        let (Some(directory), Some(file_name)) = (self.path.parent(), self.path.file_name()) else {
+            return Vec::new();
+        };
+        let prefix = format!("{}.", file_name.to_string_lossy());
+
+        let mut timestamped = Vec::new();
+        let mut numbered = Vec::new();
+        for entry in fs::read_dir(directory).into_iter().flatten().flatten() {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let Some(suffix) = name.strip_prefix(&prefix) else {
+                continue;
+            };
+            if is_backup_time(suffix) {
+                timestamped.push(entry.path());
+            } else if let Ok(index) = suffix.parse::<usize>() {
+                numbered.push((index, entry.path()));
+            }
+        }
+
+        timestamped.sort_unstable_by(|a, b| b.cmp(a));
+        numbered.sort_unstable();
+        timestamped
+            .into_iter()
+            .chain(numbered.into_iter().map(|(_, path)| path))
+            .collect()
+    }
+
+    // Copies the data being replaced to a new backup, unless the newest one is recent
+    // enough. Autosaves come every few seconds, backups every day keep some history.
+    fn rotate_backups(&self) -> io::Result<()> {
+        if !self.path.exists() {
+            return Ok(());
+        }
+
+        let backup_paths = self.backup_paths();
+        let newest_age = backup_paths
+            .first()
+            .and_then(|path| fs::metadata(path).ok()?.modified().ok()?.elapsed().ok());
+        if newest_age.is_some_and(|age| age < BACKUP_INTERVAL) {
+            return Ok(());
+        }
+
+        fs::copy(&self.path, self.backup_path(&glib::DateTime::now_utc().expect("Could not get the current time")))?;
+        for path in backup_paths.iter().skip(BACKUP_COUNT - 1) {
+            fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+
+    pub fn read_file(path: &Path) -> anyhow::Result<StoreData> {
+        let file = File::open(path)
+            .with_context(|| format!("Could not open {}", path.display()))?;
+        let value = serde_json::from_reader(file)
+            .with_context(|| format!("Could not parse {}", path.display()))?;
+        migration::migrate(value)
+            .with_context(|| format!("Could not read {}", path.display()))
+    }
+
+    pub fn write_file(path: &Path, data: &StoreData) -> anyhow::Result<()> {
+        let contents = serde_json::to_vec(data).context("Could not serialize data")?;
+        Self::write_atomically(path, &contents)
+            .with_context(|| format!("Could not write {}", path.display()))
+    }
+
+    // Write to a temporary file first, so a failed write never truncates the existing data
+    fn write_atomically(path: &Path, contents: &[u8]) -> io::Result<()> {
+        let tmp_path = Self::write_temporary(path, contents)?;
+        fs::rename(&tmp_path, path)
+    }
+
+    // Returns the path of the written file, to be renamed to `path`
+    fn write_temporary(path: &Path, contents: &[u8]) -> io::Result<PathBuf> {
+        let mut tmp_path = path.as_os_str().to_owned();
+        tmp_path.push(".tmp");
+
+        let mut file = File::create(&tmp_path)?;
+        file.write_all(contents)?;
+        file.sync_all()?;
+
+        Ok(tmp_path.into())
+    }
+}
+
+impl Storage for JsonStorage {
+    fn load(&self) -> anyhow::Result<Option<StoreData>> {
+        if !self.path.exists() {
+            return Ok(None);
+        }
+
+        match self.read(&self.path) {
+            Ok(data) => Ok(Some(data)),
+            // The backups have the same passphrase
+            Err(err) if err.is::<Locked>() || err.is::<WrongPassphrase>() => Err(err),
+            Err(err) => {
+                // The primary file is damaged, fall back to the newest readable backup
+                self.backup_paths()
+                    .into_iter()
+                    .find_map(|path| self.read(&path).ok())
+                    .map(Some)
+                    .ok_or(err)
+            }
+        }
+    }
+
+    fn save(&self, data: &StoreData) -> anyhow::Result<()> {
+        let contents = self.encode(data)?;
+        let tmp_path = Self::write_temporary(&self.path, &contents)
+            .with_context(|| format!("Could not write {}", self.path.display()))?;
+
+        // The data being replaced may become the newest backup. Saving matters more than
+        // keeping history, so a failure is only reported.
+        if let Err(err) = self.rotate_backups() {
+            glib::g_warning!("todo", "Could not rotate backups: {}", err);
+        }
+
+        fs::rename(&tmp_path, &self.path).with_context(|| format!("Could not write {}", self.path.display()))
+    }
+
+    fn reload(&self) -> anyhow::Result<Option<StoreData>> {
+        if !self.path.exists() {
+            return Ok(None);
+        }
+
+        self.read(&self.path).map(Some)
+    }
+
+    fn unlock(&self, passphrase: &str) {
+        if let Some(encryption) = &self.encryption {
+            encryption.lock().unwrap().replace(Passphrase(passphrase.to_string()));
+        }
+    }
+
+    fn is_locked(&self) -> bool {
+        self.encryption
+            .as_ref()
+            .is_some_and(|encryption| encryption.lock().unwrap().is_none())
+    }
+
+    fn watch(&self, callback: Box<dyn Fn() + 'static>) -> Option<WatchHandle> {
+        let monitor = gio::File::for_path(&self.path)
+            .monitor_file(gio::FileMonitorFlags::WATCH_MOVES, gio::Cancellable::NONE)
+            .ok()?;
+
+        monitor.connect_changed(move |_, _, _, event| {
+            if matches!(
+                event,
+                gio::FileMonitorEvent::ChangesDoneHint
+                    | gio::FileMonitorEvent::Created
+                    | gio::FileMonitorEvent::MovedIn
+                    | gio::FileMonitorEvent::Renamed
+            ) {
+                callback();
+            }
+        });
+
+        Some(WatchHandle(Box::new(monitor)))
+    }
+}
+
+// Whether a suffix is a backup time, see `JsonStorage::backup_path`
+fn is_backup_time(suffix: &str) -> bool {
+    suffix.len() == 15
+        && suffix.char_indices().all(|(index, c)| if index == 8 { c == '-' } else { c.is_ascii_digit() })
+}
@@ -0,0 +1,309 @@
+use anyhow::{bail, Context};
+use reqwest::{header, Client, Method, Response, StatusCode, Url};
+
+const DAV: &str = "DAV:";
+const CALDAV: &str = "urn:ietf:params:xml:ns:caldav";
+
+#[derive(Debug, Clone)]
+pub struct RemoteCalendar {
+    pub url: String,
+    pub display_name: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct RemoteTodo {
+    pub url: String,
+    pub etag: String,
+    // The iCalendar object holding the VTODO
+    pub data: String,
+}
+
+#[derive(Debug)]
+pub enum WriteResult {
+    // With the new etag of a written todo, `None` after deleting one
+    Written(Option<String>),
+    // The resource was changed on the server since its etag was seen
+    Conflict,
+}
+
+// One <response> of a multistatus
+#[derive(Debug, Default)]
+struct PropResponse {
+    href: String,
+    // Text of the found properties by local name, hrefs for properties holding one
+    props: Vec<(String, String)>,
+    resource_types: Vec<String>,
+    components: Vec<String>,
+}
+
+impl PropResponse {
+    fn prop(&self, name: &str) -> Option<&str> {
+        self.props
+            .iter()
+            .find(|(prop_name, _)| prop_name == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CalDavClient {
+    client: Client,
+    username: String,
+    password: String,
+}
+
+impl CalDavClient {
+    pub fn new(username: &str, password: &str) -> Self {
+        Self {
+            client: Client::new(),
+            username: username.to_string(),
+            password: password.to_string(),
+        }
+    }
+
+    async fn send(
+        &self,
+        method: &str,
+        url: &Url,
+        depth: Option<&str>,
+        body: Option<String>,
+    ) -> anyhow::Result<Response> {
+        let method = Method::from_bytes(method.as_bytes()).context("Invalid method")?;
+        let mut request = self
+            .client
+            .request(method, url.clone())
+            .basic_auth(&self.username, Some(&self.password));
+        if let Some(depth) = depth {
+            request = request.header("Depth", depth);
+        }
+        if let Some(body) = body {
+            request = request
+                .header(header::CONTENT_TYPE, "application/xml; charset=utf-8")
+                .body(body);
+        }
+
+        let response = request
+            .send()
+            .await
+            .with_context(|| format!("Could not reach {url}"))?;
+        if response.status() == StatusCode::UNAUTHORIZED {
+            bail!("The server did not accept the username or password");
+        }
+        Ok(response)
+    }
+
+    async fn multistatus(
+        &self,
+        method: &str,
+        url: &Url,
+        depth: &str,
+        body: String,
+    ) -> anyhow::Result<Vec<PropResponse>> {
+        let response = self.send(method, url, Some(depth), Some(body)).await?;
+        if response.status() != StatusCode::MULTI_STATUS {
+            bail!("{url} answered {} to {method}", response.status());
+        }
+
+        let text = response.text().await.context("Could not read the response")?;
+        parse_multistatus(&text).with_context(|| format!("Could not parse the response of {url}"))
+    }
+
+    async fn find_href(&self, url: &Url, prop: &str, namespace: &str) -> anyhow::Result<Url> {
+        let body = format!(
+            r#"<d:propfind xmlns:d="DAV:" xmlns:c="{CALDAV}"><d:prop><{prefix}:{prop}/></d:prop></d:propfind>"#,
+            prefix = if namespace == DAV { "d" } else { "c" },
+        );
+        let responses = self.multistatus("PROPFIND", url, "0", body).await?;
+        let href = responses
+            .iter()
+            .find_map(|response| response.prop(prop))
+            .filter(|href| !href.is_empty())
+            .with_context(|| format!("{url} has no {prop}"))?;
+
+        url.join(href).context("Invalid href")
+    }
+
+    // Follows the principal of the user to the calendars that can hold tasks
+    pub async fn calendars(&self, server_url: &Url) -> anyhow::Result<Vec<RemoteCalendar>> {
+        let principal_url = self.find_href(server_url, "current-user-principal", DAV).await?;
+        let home_url = self.find_href(&principal_url, "calendar-home-set", CALDAV).await?;
+
+        let body = format!(
+            r#"<d:propfind xmlns:d="DAV:" xmlns:c="{CALDAV}"><d:prop><d:resourcetype/><d:displayname/><c:supported-calendar-component-set/></d:prop></d:propfind>"#
+        );
+        let responses = self.multistatus("PROPFIND", &home_url, "1", body).await?;
+
+        let calendars = responses
+            .into_iter()
+            .filter(|response| response.resource_types.iter().any(|resource_type| resource_type == "calendar"))
+            // Servers that do not tell the supported components accept all of them
+            .filter(|response| {
+                response.components.is_empty() || response.components.iter().any(|component| component == "VTODO")
+            })
+            .filter_map(|response| {
+                let url = home_url.join(&response.href).ok()?;
+                let display_name = response
+                    .prop("displayname")
+                    .filter(|display_name| !display_name.is_empty())
+                    .map(str::to_string)
+                    .unwrap_or_else(|| {
+                        url.path_segments()
+                            .and_then(|segments| segments.filter(|segment| !segment.is_empty()).last())
+                            .unwrap_or_default()
+                            .to_string()
+                    });
+                Some(RemoteCalendar {
+                    url: url.to_string(),
+                    display_name,
+                })
+            })
+            .collect();
+
+        Ok(calendars)
+    }
+
+    pub async fn todos(&self, calendar_url: &Url) -> anyhow::Result<Vec<RemoteTodo>> {
+        let body = format!(
+            r#"<c:calendar-query xmlns:d="DAV:" xmlns:c="{CALDAV}"><d:prop><d:getetag/><c:calendar-data/></d:prop><c:filter><c:comp-filter name="VCALENDAR"><c:comp-filter name="VTODO"/></c:comp-filter></c:filter></c:calendar-query>"#
+        );
+        let responses = self.multistatus("REPORT", calendar_url, "1", body).await?;
+
+        let todos = responses
+            .into_iter()
+            .filter_map(|response| {
+                Some(RemoteTodo {
+                    url: calendar_url.join(&response.href).ok()?.to_string(),
+                    etag: response.prop("getetag")?.to_string(),
+                    data: response.prop("calendar-data")?.to_string(),
+                })
+            })
+            .collect();
+
+        Ok(todos)
+    }
+
+    // Creates the todo if `etag` is `None`, otherwise only overwrites the version with that etag
+    pub async fn put_todo(&self, url: &Url, data: String, etag: Option<&str>) -> anyhow::Result<WriteResult> {
+        let request = self
+            .client
+            .put(url.clone())
+            .basic_auth(&self.username, Some(&self.password))
+            .header(header::CONTENT_TYPE, "text/calendar; charset=utf-8")
+            .body(data);
+        let request = match etag {
+            Some(etag) => request.header(header::IF_MATCH, etag),
+            None => request.header(header::IF_NONE_MATCH, "*"),
+        };
+
+        let response = request
+            .send()
+            .await
+            .with_context(|| format!("Could not reach {url}"))?;
+        match response.status() {
+            StatusCode::PRECONDITION_FAILED => Ok(WriteResult::Conflict),
+            status if status.is_success() => {
+                let etag = response
+                    .headers()
+                    .get(header::ETAG)
+                    .and_then(|etag| etag.to_str().ok())
+                    .map(str::to_string);
+                // Servers may leave the etag out when they changed the data while storing it
+                let etag = match etag {
+                    Some(etag) => etag,
+                    None => self.etag(url).await?,
+                };
+                Ok(WriteResult::Written(Some(etag)))
+            }
+            status => bail!("{url} answered {status} to PUT"),
+        }
+    }
+
+    async fn etag(&self, url: &Url) -> anyhow::Result<String> {
+        let body = r#"<d:propfind xmlns:d="DAV:"><d:prop><d:getetag/></d:prop></d:propfind>"#.to_string();
+        let responses = self.multistatus("PROPFIND", url, "0", body).await?;
+        responses
+            .iter()
+            .find_map(|response| response.prop("getetag"))
+            .filter(|etag| !etag.is_empty())
+            .map(str::to_string)
+            .with_context(|| format!("{url} has no etag"))
+    }
+
+    pub async fn delete_todo(&self, url: &Url, etag: &str) -> anyhow::Result<WriteResult> {
+        let response = self
+            .client
+            .delete(url.clone())
+            .basic_auth(&self.username, Some(&self.password))
+            .header(header::IF_MATCH, etag)
+            .send()
+            .await
+            .with_context(|| format!("Could not reach {url}"))?;
+
+        match response.status() {
+            StatusCode::PRECONDITION_FAILED => Ok(WriteResult::Conflict),
+            // Already gone is just as good
+            StatusCode::NOT_FOUND => Ok(WriteResult::Written(None)),
+            status if status.is_success() => Ok(WriteResult::Written(None)),
+            status => bail!("{url} answered {status} to DELETE"),
+        }
+    }
+}
+
+fn parse_multistatus(text: &str) -> anyhow::Result<Vec<PropResponse>> {
+    let document = roxmltree::Document::parse(text)?;
+    let mut responses = Vec::new();
+
+    for response_node in document.descendants().filter(|node| node.has_tag_name((DAV, "response"))) {
+        let mut response = PropResponse::default();
+
+        for child in response_node.children().filter(|node| node.is_element()) {
+            if child.has_tag_name((DAV, "href")) {
+                response.href = child.text().unwrap_or_default().trim().to_string();
+            }
+        }
+
+        // Properties the server does not have are listed with a status other than 200
+        let props = response_node
+            .descendants()
+            .filter(|node| node.has_tag_name((DAV, "propstat")))
+            .filter(|propstat| {
+                propstat
+                    .children()
+                    .find(|node| node.has_tag_name((DAV, "status")))
+                    .and_then(|status| status.text())
+                    .is_some_and(|status| status.contains(" 200 "))
+            })
+            .flat_map(|propstat| propstat.children().filter(|node| node.has_tag_name((DAV, "prop"))))
+            .flat_map(|prop| prop.children().filter(|node| node.is_element()));
+
+        for prop in props {
+            match prop.tag_name().name() {
+                "resourcetype" => response.resource_types.extend(
+                    prop.children()
+                        .filter(|node| node.is_element())
+                        .map(|node| node.tag_name().name().to_string()),
+                ),
+                "supported-calendar-component-set" => response.components.extend(
+                    prop.children()
+                        .filter_map(|node| node.attribute("name"))
+                        .map(str::to_string),
+                ),
+                name => {
+                    let value = prop
+                        .descendants()
+                        .find(|node| node.has_tag_name((DAV, "href")))
+                        .unwrap_or(prop)
+                        .text()
+                        .unwrap_or_default()
+                        .trim()
+                        .to_string();
+                    response.props.push((name.to_string(), value));
+                }
+            }
+        }
+
+        responses.push(response);
+    }
+
+    Ok(responses)
+}
@@ -0,0 +1,44 @@
+use std::collections::HashMap;
+
+use gtk::glib;
+use libsecret::{Schema, SchemaAttributeType, SchemaFlags};
+
+use super::Account;
+
+// Passwords are kept in the keyring, looked up by server and username
+fn schema() -> Schema {
+    let attributes = HashMap::from([
+        ("server", SchemaAttributeType::String),
+        ("username", SchemaAttributeType::String),
+    ]);
+    Schema::new("org.gnome.ToDo.SyncAccount", SchemaFlags::NONE, attributes)
+}
+
+fn attributes(account: &Account) -> HashMap<&str, &str> {
+    HashMap::from([
+        ("server", account.server_url.as_str()),
+        ("username", account.username.as_str()),
+    ])
+}
+
+pub async fn store_password(account: &Account, password: &str) -> Result<(), glib::Error> {
+    let label = format!("To-Do sync account {} on {}", account.username, account.server_url);
+    libsecret::password_store_future(
+        Some(&schema()),
+        attributes(account),
+        Some(&libsecret::COLLECTION_DEFAULT),
+        &label,
+        password,
+    )
+    .await
+}
+
+pub async fn clear_password(account: &Account) -> Result<bool, glib::Error> {
+    libsecret::password_clear_future(Some(&schema()), attributes(account)).await
+}
+
+pub async fn lookup_password(account: &Account) -> Result<Option<String>, glib::Error> {
+    libsecret::password_lookup_future(Some(&schema()), attributes(account))
+        .await
+        .map(|password| password.map(|password| password.to_string()))
+}
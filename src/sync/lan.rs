@@ -0,0 +1,318 @@
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+use anyhow::{bail, Context};
+use gtk::glib;
+use mdns_sd::{ServiceDaemon, ServiceEvent, ServiceInfo};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+use tokio_rustls::rustls::client::{ServerCertVerified, ServerCertVerifier};
+use tokio_rustls::rustls::{self, Certificate, ClientConfig, PrivateKey, ServerConfig, ServerName};
+use tokio_rustls::{TlsAcceptor, TlsConnector};
+
+use super::pake::{Confirmation, Element, Side, Spake2};
+use super::runtime;
+use crate::migration::StoreData;
+use crate::utils;
+
+const SERVICE_TYPE: &str = "_gnome-todo._tcp.local.";
+// The certificates are made up on the spot, this is the name in them
+const CERTIFICATE_NAME: &str = "todo.local";
+// Anything larger is refused, data files are nowhere near it
+const MAX_MESSAGE_SIZE: usize = 64 * 1024 * 1024;
+// Peers sync every few minutes, quicker attempts are someone guessing the pairing code
+const ATTEMPT_INTERVAL: Duration = Duration::from_secs(1);
+const FAILED_ATTEMPT_DELAY: Duration = Duration::from_secs(10);
+
+// The devices prove that they know the same pairing code with SPAKE2, see `pake`.
+// The key also covers the certificate of the server: a device in the middle shows
+// another certificate than the server, so the keys on both ends differ.
+
+// Opens a sync
+#[derive(Serialize, Deserialize)]
+struct Hello {
+    client_element: Element,
+}
+
+// The server confirms the key first, the client sends nothing else before checking it
+#[derive(Serialize, Deserialize)]
+struct Challenge {
+    server_element: Element,
+    server_confirmation: Confirmation,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SyncRequest {
+    client_confirmation: Confirmation,
+    data: StoreData,
+}
+
+#[derive(Serialize, Deserialize)]
+enum SyncResponse {
+    Merged(StoreData),
+    WrongPairingCode,
+}
+
+// The data of a peer, to be merged on the main loop. `reply` takes the merged data.
+pub struct IncomingSync {
+    pub data: StoreData,
+    pub reply: oneshot::Sender<StoreData>,
+}
+
+// Announces this device on the local network, keeps track of the other devices and
+// answers their syncs. Everything stops when it is dropped.
+pub struct LanSync {
+    daemon: ServiceDaemon,
+    fullname: String,
+    // The addresses of each peer, by its service name
+    peers: Arc<Mutex<HashMap<String, Vec<SocketAddr>>>>,
+    pairing_code: String,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl LanSync {
+    pub fn start(pairing_code: String) -> anyhow::Result<(Self, mpsc::Receiver<IncomingSync>)> {
+        let _guard = runtime().enter();
+
+        let listener = std::net::TcpListener::bind("0.0.0.0:0").context("Could not listen for peers")?;
+        listener.set_nonblocking(true)?;
+        let port = listener.local_addr()?.port();
+        let listener = TcpListener::from_std(listener)?;
+
+        let (sender, receiver) = mpsc::channel(1);
+        let (config, certificate) = server_config()?;
+        let acceptor = TlsAcceptor::from(Arc::new(config));
+        let mut tasks = vec![runtime().spawn(serve(listener, acceptor, certificate, pairing_code.clone(), sender))];
+
+        let daemon = ServiceDaemon::new().context("Could not start service discovery")?;
+        let host_name = glib::host_name();
+        // Several users of one computer may sync, the suffix keeps their names apart
+        let instance_name = format!("To Do on {host_name} {}", &utils::new_uuid()[..8]);
+        let service_info = ServiceInfo::new(
+            SERVICE_TYPE,
+            &instance_name,
+            &format!("{host_name}.local."),
+            "",
+            port,
+            None::<HashMap<String, String>>,
+        )?
+        .enable_addr_auto();
+        let fullname = service_info.get_fullname().to_string();
+        daemon.register(service_info).context("Could not announce this device")?;
+
+        let peers: Arc<Mutex<HashMap<String, Vec<SocketAddr>>>> = Arc::default();
+        let events = daemon.browse(SERVICE_TYPE).context("Could not look for peers")?;
+        let own_fullname = fullname.clone();
+        let known_peers = peers.clone();
+        tasks.push(runtime().spawn(async move {
+            while let Ok(event) = events.recv_async().await {
+                let mut peers = known_peers.lock().unwrap();
+                match event {
+                    ServiceEvent::ServiceResolved(info) if info.get_fullname() != own_fullname => {
+                        let port = info.get_port();
+                        let addresses = info
+                            .get_addresses()
+                            .iter()
+                            .map(|address| SocketAddr::new((*address).into(), port))
+                            .collect();
+                        peers.insert(info.get_fullname().to_string(), addresses);
+                    }
+                    ServiceEvent::ServiceRemoved(_, fullname) => {
+                        peers.remove(&fullname);
+                    }
+                    _ => {}
+                }
+            }
+        }));
+
+        Ok((Self { daemon, fullname, peers, pairing_code, tasks }, receiver))
+    }
+
+    pub fn pairing_code(&self) -> String {
+        self.pairing_code.clone()
+    }
+
+    // The addresses of every peer found so far
+    pub fn peers(&self) -> Vec<Vec<SocketAddr>> {
+        self.peers.lock().unwrap().values().cloned().collect()
+    }
+}
+
+impl std::fmt::Debug for LanSync {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LanSync")
+            .field("fullname", &self.fullname)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for LanSync {
+    fn drop(&mut self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+        let _ = self.daemon.unregister(&self.fullname);
+        let _ = self.daemon.shutdown();
+    }
+}
+
+// Sends our data to a peer and returns the result of merging it with theirs
+pub async fn sync_with(addresses: Vec<SocketAddr>, pairing_code: String, data: StoreData) -> anyhow::Result<StoreData> {
+    let stream = TcpStream::connect(&addresses[..]).await.context("Could not reach the peer")?;
+    let connector = TlsConnector::from(Arc::new(client_config()));
+    let server_name = ServerName::try_from(CERTIFICATE_NAME)?;
+    let mut stream = connector.connect(server_name, stream).await.context("Could not connect securely")?;
+    let certificate = match stream.get_ref().1.peer_certificates() {
+        Some([certificate, ..]) => certificate.0.clone(),
+        _ => bail!("The peer has no certificate"),
+    };
+
+    let spake2 = Spake2::start(Side::Client, &pairing_code);
+    write_message(&mut stream, &Hello { client_element: spake2.element() }).await?;
+    let challenge: Challenge = read_message(&mut stream).await?;
+    let key = spake2.finish(&challenge.server_element, &certificate)?;
+    if !key.verify(Side::Server, &challenge.server_confirmation) {
+        bail!("The peer has another pairing code");
+    }
+
+    let client_confirmation = key.confirmation(Side::Client);
+    write_message(&mut stream, &SyncRequest { client_confirmation, data }).await?;
+    match read_message(&mut stream).await? {
+        SyncResponse::Merged(data) => Ok(data),
+        SyncResponse::WrongPairingCode => bail!("The peer has another pairing code"),
+    }
+}
+
+async fn serve(
+    listener: TcpListener,
+    acceptor: TlsAcceptor,
+    certificate: Vec<u8>,
+    pairing_code: String,
+    sender: mpsc::Sender<IncomingSync>,
+) {
+    let certificate = Arc::new(certificate);
+    let next_attempt = Arc::new(Mutex::new(Instant::now()));
+    loop {
+        let Ok((stream, _)) = listener.accept().await else {
+            continue;
+        };
+
+        let acceptor = acceptor.clone();
+        let certificate = certificate.clone();
+        let next_attempt = next_attempt.clone();
+        let pairing_code = pairing_code.clone();
+        let sender = sender.clone();
+        tokio::spawn(async move {
+            if let Err(err) = answer(stream, acceptor, &certificate, &next_attempt, pairing_code, sender).await {
+                glib::g_warning!("todo", "Could not sync with a peer: {:#}", err);
+            }
+        });
+    }
+}
+
+async fn answer(
+    stream: TcpStream,
+    acceptor: TlsAcceptor,
+    certificate: &[u8],
+    next_attempt: &Mutex<Instant>,
+    pairing_code: String,
+    sender: mpsc::Sender<IncomingSync>,
+) -> anyhow::Result<()> {
+    // Each attempt is one guess of the pairing code, so there are only a few of them
+    {
+        let now = Instant::now();
+        let mut next_attempt = next_attempt.lock().unwrap();
+        if now < *next_attempt {
+            bail!("A peer tried again too soon");
+        }
+        *next_attempt = now + ATTEMPT_INTERVAL;
+    }
+
+    let mut stream = acceptor.accept(stream).await?;
+    let hello: Hello = read_message(&mut stream).await?;
+    let spake2 = Spake2::start(Side::Server, &pairing_code);
+    let server_element = spake2.element();
+    let key = spake2.finish(&hello.client_element, certificate)?;
+    let server_confirmation = key.confirmation(Side::Server);
+    write_message(&mut stream, &Challenge { server_element, server_confirmation }).await?;
+
+    let request: SyncRequest = read_message(&mut stream).await?;
+    if !key.verify(Side::Client, &request.client_confirmation) {
+        *next_attempt.lock().unwrap() = Instant::now() + FAILED_ATTEMPT_DELAY;
+        return write_message(&mut stream, &SyncResponse::WrongPairingCode).await;
+    }
+
+    let (reply, merged) = oneshot::channel();
+    sender
+        .send(IncomingSync { data: request.data, reply })
+        .await
+        .context("Syncing stopped")?;
+    let merged = merged.await.context("Syncing stopped")?;
+    write_message(&mut stream, &SyncResponse::Merged(merged)).await
+}
+
+// Messages are JSON, preceded by their length
+async fn write_message(stream: &mut (impl AsyncWrite + Unpin), message: &impl Serialize) -> anyhow::Result<()> {
+    let contents = serde_json::to_vec(message)?;
+    stream.write_u32(contents.len() as u32).await?;
+    stream.write_all(&contents).await?;
+    stream.flush().await?;
+    Ok(())
+}
+
+async fn read_message<T: DeserializeOwned>(stream: &mut (impl AsyncRead + Unpin)) -> anyhow::Result<T> {
+    let length = stream.read_u32().await? as usize;
+    if length > MAX_MESSAGE_SIZE {
+        bail!("The message is too large");
+    }
+
+    let mut contents = vec![0; length];
+    stream.read_exact(&mut contents).await?;
+    serde_json::from_slice(&contents).context("The message could not be read")
+}
+
+// There is no authority to vouch for devices on a home network. TLS keeps the tasks
+// away from anyone listening in, the pairing code keeps other devices out.
+// Returns the certificate too, the keys of the syncs cover it.
+fn server_config() -> anyhow::Result<(ServerConfig, Vec<u8>)> {
+    let certificate = rcgen::generate_simple_self_signed(vec![CERTIFICATE_NAME.to_string()])?;
+    let certificate_der = certificate.serialize_der()?;
+    let config = ServerConfig::builder()
+        .with_safe_defaults()
+        .with_no_client_auth()
+        .with_single_cert(
+            vec![Certificate(certificate_der.clone())],
+            PrivateKey(certificate.serialize_private_key_der()),
+        )?;
+    Ok((config, certificate_der))
+}
+
+fn client_config() -> ClientConfig {
+    ClientConfig::builder()
+        .with_safe_defaults()
+        .with_custom_certificate_verifier(Arc::new(AnyCertificate))
+        .with_no_client_auth()
+}
+
+// Peers make up their certificates, see `server_config`. The certificate is checked
+// by the key confirmation of the server in `sync_with` instead.
+struct AnyCertificate;
+
+impl ServerCertVerifier for AnyCertificate {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &Certificate,
+        _intermediates: &[Certificate],
+        _server_name: &ServerName,
+        _scts: &mut dyn Iterator<Item = &[u8]>,
+        _ocsp_response: &[u8],
+        _now: SystemTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+}
@@ -0,0 +1,277 @@
+pub mod caldav;
+pub mod credentials;
+pub mod lan;
+pub mod nextcloud;
+mod pake;
+
+use std::future::Future;
+use std::sync::OnceLock;
+
+use anyhow::Context;
+use gio::Settings;
+use gtk::{gio, prelude::*};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use tokio::runtime::Runtime;
+
+use crate::ical;
+use crate::task_object::TaskData;
+use caldav::{CalDavClient, RemoteTodo, WriteResult};
+
+// The server the collections are synced with, its password is in the keyring
+#[derive(Debug, Clone)]
+pub struct Account {
+    pub server_url: String,
+    pub username: String,
+}
+
+impl Account {
+    // `None` if no account has been set up
+    pub fn from_settings(settings: &Settings) -> Option<Self> {
+        let server_url = settings.string("sync-server-url").to_string();
+        let username = settings.string("sync-username").to_string();
+        if server_url.is_empty() || username.is_empty() {
+            return None;
+        }
+
+        Some(Self { server_url, username })
+    }
+}
+
+// What is known about the server since the last sync, stored with the collections
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct SyncData {
+    pub calendars: Vec<CalendarLink>,
+}
+
+// A collection synced with a calendar
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CalendarLink {
+    pub collection_uuid: String,
+    pub url: String,
+    #[serde(default)]
+    pub tasks: Vec<TaskLink>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskLink {
+    pub uuid: String,
+    pub url: String,
+    pub etag: String,
+    // Fingerprint of the task as of the last sync, to tell whether it changed locally since
+    pub synced: String,
+}
+
+pub struct SyncOutcome {
+    pub link: CalendarLink,
+    // The tasks of the collection after the sync
+    pub tasks_data: Vec<TaskData>,
+    // Tasks changed on both sides, the server won and the local version was kept as a copy
+    pub conflicts: usize,
+}
+
+// reqwest needs a tokio runtime, the main loop is a glib one
+fn runtime() -> &'static Runtime {
+    static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+    RUNTIME.get_or_init(|| Runtime::new().expect("Could not create the sync runtime"))
+}
+
+// Runs `future` on the sync runtime, the result can be awaited from the main loop
+pub async fn run<T: Send + 'static>(
+    future: impl Future<Output = anyhow::Result<T>> + Send + 'static,
+) -> anyhow::Result<T> {
+    runtime().spawn(future).await.context("The sync was interrupted")?
+}
+
+// Only what is stored on the server counts as a change
+fn fingerprint(task_data: &TaskData) -> String {
+    serde_json::to_string(&(
+        &task_data.content,
+        &task_data.notes,
+        &task_data.due_date,
+        task_data.completed,
+        task_data.priority,
+        &task_data.tags,
+        &task_data.recurrence,
+    ))
+    .unwrap_or_default()
+}
+
+// The remote version of a task, keeping what the server does not know about
+fn apply_remote(local: &TaskData, remote: &TaskData) -> TaskData {
+    TaskData {
+        uuid: local.uuid.clone(),
+        completed: remote.completed,
+        content: remote.content.clone(),
+        notes: remote.notes.clone(),
+        due_date: remote.due_date.clone(),
+        recurrence: remote.recurrence.clone(),
+        priority: remote.priority,
+        tags: remote.tags.clone(),
+        ..local.clone()
+    }
+}
+
+// Reads the VTODO of a todo, with its url resolved
+fn parse_todo(todo: RemoteTodo) -> Option<(String, String, TaskData)> {
+    let task_data = ical::parse_todos(&todo.data).into_iter().next()?;
+    Some((todo.url, todo.etag, task_data))
+}
+
+// Two-way sync of the `tasks_data` of a collection with the calendar of `link`.
+// Tasks with a uuid in `kept` (like archived ones) are left alone on both sides.
+pub async fn sync_calendar(
+    client: CalDavClient,
+    link: CalendarLink,
+    tasks_data: Vec<TaskData>,
+    kept: Vec<String>,
+) -> anyhow::Result<SyncOutcome> {
+    let calendar_url = Url::parse(&link.url).context("Invalid calendar url")?;
+    let mut remote: Vec<(String, String, TaskData)> = client
+        .todos(&calendar_url)
+        .await?
+        .into_iter()
+        .filter_map(parse_todo)
+        .collect();
+
+    let mut outcome = SyncOutcome {
+        link: CalendarLink {
+            tasks: Vec::new(),
+            ..link.clone()
+        },
+        tasks_data: Vec::new(),
+        conflicts: 0,
+    };
+
+    for task_data in tasks_data {
+        let remote_todo = remote
+            .iter()
+            .position(|(_, _, remote_data)| remote_data.uuid == task_data.uuid)
+            .map(|position| remote.remove(position));
+
+        match (find_link(&link.tasks, &task_data.uuid), remote_todo) {
+            (previous, None) => {
+                // New locally, or deleted on the server after it was changed locally.
+                // Otherwise it was deleted on the server and goes away here too.
+                if previous.map_or(true, |previous| fingerprint(&task_data) != previous.synced) {
+                    upload(&client, &calendar_url, task_data, &mut outcome).await?;
+                }
+            }
+            // Known to both sides without a link, like after setting up the account again
+            (None, Some((url, etag, remote_data))) => {
+                take_remote(url, etag, &task_data, &remote_data, &mut outcome);
+            }
+            (Some(previous), Some((url, etag, remote_data))) => {
+                let changed_locally = fingerprint(&task_data) != previous.synced;
+                let changed_remotely = etag != previous.etag;
+
+                match (changed_locally, changed_remotely) {
+                    (false, false) => {
+                        outcome.link.tasks.push(previous.clone());
+                        outcome.tasks_data.push(task_data);
+                    }
+                    (false, true) => take_remote(url, etag, &task_data, &remote_data, &mut outcome),
+                    (true, false) => {
+                        let todo_url = Url::parse(&url).context("Invalid todo url")?;
+                        let data = ical::todo_to_string(&task_data);
+                        match client.put_todo(&todo_url, data, Some(&etag)).await? {
+                            WriteResult::Written(new_etag) => {
+                                outcome.link.tasks.push(TaskLink {
+                                    uuid: task_data.uuid.clone(),
+                                    url,
+                                    etag: new_etag.unwrap_or_default(),
+                                    synced: fingerprint(&task_data),
+                                });
+                                outcome.tasks_data.push(task_data);
+                            }
+                            // Changed on the server in the meantime
+                            WriteResult::Conflict => keep_both(url, etag, task_data, &remote_data, &mut outcome),
+                        }
+                    }
+                    (true, true) => keep_both(url, etag, task_data, &remote_data, &mut outcome),
+                }
+            }
+        }
+    }
+
+    // What is left on the server is new there, or was deleted locally
+    for (url, etag, remote_data) in remote {
+        if kept.contains(&remote_data.uuid) {
+            if let Some(previous) = find_link(&link.tasks, &remote_data.uuid) {
+                outcome.link.tasks.push(previous.clone());
+            }
+            continue;
+        }
+
+        match find_link(&link.tasks, &remote_data.uuid) {
+            Some(previous) if previous.etag == etag => {
+                let todo_url = Url::parse(&url).context("Invalid todo url")?;
+                match client.delete_todo(&todo_url, &etag).await? {
+                    WriteResult::Written(_) => {}
+                    // Changed on the server after it was seen, rather keep it
+                    WriteResult::Conflict => take_remote(url, etag, &remote_data, &remote_data, &mut outcome),
+                }
+            }
+            // Changed on the server after it was deleted locally, the change wins
+            _ => take_remote(url, etag, &remote_data, &remote_data, &mut outcome),
+        }
+    }
+
+    Ok(outcome)
+}
+
+fn find_link<'a>(task_links: &'a [TaskLink], uuid: &str) -> Option<&'a TaskLink> {
+    task_links.iter().find(|task_link| task_link.uuid == uuid)
+}
+
+async fn upload(
+    client: &CalDavClient,
+    calendar_url: &Url,
+    task_data: TaskData,
+    outcome: &mut SyncOutcome,
+) -> anyhow::Result<()> {
+    let todo_url = calendar_url
+        .join(&format!("{}.ics", task_data.uuid))
+        .context("Invalid todo url")?;
+    let data = ical::todo_to_string(&task_data);
+
+    // A conflict means the uuid is taken, the next sync picks the remote one up
+    if let WriteResult::Written(etag) = client.put_todo(&todo_url, data, None).await? {
+        outcome.link.tasks.push(TaskLink {
+            uuid: task_data.uuid.clone(),
+            url: todo_url.to_string(),
+            etag: etag.unwrap_or_default(),
+            synced: fingerprint(&task_data),
+        });
+    }
+    outcome.tasks_data.push(task_data);
+
+    Ok(())
+}
+
+fn take_remote(url: String, etag: String, local: &TaskData, remote: &TaskData, outcome: &mut SyncOutcome) {
+    let task_data = apply_remote(local, remote);
+    outcome.link.tasks.push(TaskLink {
+        uuid: task_data.uuid.clone(),
+        url,
+        etag,
+        synced: fingerprint(&task_data),
+    });
+    outcome.tasks_data.push(task_data);
+}
+
+// The server wins, the local version becomes a new task that is uploaded on the next sync
+fn keep_both(url: String, etag: String, mut local: TaskData, remote: &TaskData, outcome: &mut SyncOutcome) {
+    take_remote(url, etag, &local, remote, outcome);
+    local.renew();
+    outcome.tasks_data.push(local);
+    outcome.conflicts += 1;
+}
+
+pub async fn discover_calendars(
+    client: CalDavClient,
+    server_url: String,
+) -> anyhow::Result<Vec<caldav::RemoteCalendar>> {
+    let server_url = Url::parse(&server_url).context("Invalid server url")?;
+    client.calendars(&server_url).await
+}
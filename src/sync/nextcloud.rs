@@ -0,0 +1,93 @@
+use std::time::Duration;
+
+use anyhow::{bail, Context};
+use reqwest::{header, Client, StatusCode};
+use serde::Deserialize;
+
+// How often the server is asked whether the login was granted
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+// Nextcloud forgets about a login flow after 20 minutes
+const POLL_TIMEOUT: Duration = Duration::from_secs(20 * 60);
+// Shown to the user on the login page and in the list of their devices
+const USER_AGENT: &str = "To-Do";
+
+#[derive(Debug, Deserialize)]
+struct Poll {
+    token: String,
+    endpoint: String,
+}
+
+// A started login flow v2, the user grants access on `login_url` in the browser
+#[derive(Debug, Deserialize)]
+pub struct LoginFlow {
+    poll: Poll,
+    #[serde(rename = "login")]
+    pub login_url: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LoginCredentials {
+    pub server: String,
+    pub login_name: String,
+    pub app_password: String,
+}
+
+impl LoginCredentials {
+    pub fn caldav_url(&self) -> String {
+        format!("{}/remote.php/dav", self.server.trim_end_matches('/'))
+    }
+}
+
+pub async fn start_login(server_url: String) -> anyhow::Result<LoginFlow> {
+    let server_url = server_url.trim().trim_end_matches('/');
+    let server_url = if server_url.contains("://") {
+        server_url.to_string()
+    } else {
+        format!("https://{server_url}")
+    };
+
+    let response = Client::new()
+        .post(format!("{server_url}/index.php/login/v2"))
+        .header(header::USER_AGENT, USER_AGENT)
+        .send()
+        .await
+        .with_context(|| format!("Could not reach {server_url}"))?;
+    if !response.status().is_success() {
+        bail!("{server_url} does not look like a Nextcloud server ({})", response.status());
+    }
+
+    let text = response.text().await.context("Could not read the response")?;
+    serde_json::from_str(&text).context("Could not parse the login flow")
+}
+
+// Waits until the user granted access in the browser
+pub async fn poll_login(flow: LoginFlow) -> anyhow::Result<LoginCredentials> {
+    let client = Client::new();
+    let started = tokio::time::Instant::now();
+
+    while started.elapsed() < POLL_TIMEOUT {
+        tokio::time::sleep(POLL_INTERVAL).await;
+
+        let response = client
+            .post(&flow.poll.endpoint)
+            .header(header::USER_AGENT, USER_AGENT)
+            .form(&[("token", &flow.poll.token)])
+            .send()
+            .await
+            .context("Could not reach the server")?;
+
+        // Not granted yet
+        if response.status() == StatusCode::NOT_FOUND {
+            continue;
+        }
+        if !response.status().is_success() {
+            bail!("The server answered {} to the login", response.status());
+        }
+
+        let text = response.text().await.context("Could not read the response")?;
+        return serde_json::from_str(&text).context("Could not parse the login");
+    }
+
+    bail!("The login was not completed in time")
+}
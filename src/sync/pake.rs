@@ -0,0 +1,124 @@
+// SPAKE2 (RFC 9382) over the Ristretto group, so that two devices agree on a key only
+// if they know the same pairing code. A peer without the code learns nothing it could
+// use to guess the code offline, every guess takes a connection of its own.
+
+use anyhow::bail;
+use curve25519_dalek::constants::RISTRETTO_BASEPOINT_POINT;
+use curve25519_dalek::ristretto::{CompressedRistretto, RistrettoPoint};
+use curve25519_dalek::scalar::Scalar;
+use curve25519_dalek::traits::Identity;
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use sha2::{Digest, Sha256, Sha512};
+
+pub type Element = [u8; 32];
+pub type Confirmation = Vec<u8>;
+
+#[derive(Clone, Copy)]
+pub enum Side {
+    // Sends its element first, blinded with `M`
+    Client,
+    // Answers, blinded with `N`
+    Server,
+}
+
+impl Side {
+    fn label(self) -> &'static [u8] {
+        match self {
+            Side::Client => b"client",
+            Side::Server => b"server",
+        }
+    }
+}
+
+// One side of one exchange
+pub struct Spake2 {
+    side: Side,
+    password: Scalar,
+    secret: Scalar,
+    element: RistrettoPoint,
+}
+
+// What both sides agree on after the exchange, if their pairing codes match
+pub struct SharedKey([u8; 32]);
+
+impl Spake2 {
+    pub fn start(side: Side, pairing_code: &str) -> Self {
+        let password = hash_to_scalar(&[b"todo-lan-sync password", pairing_code.as_bytes()]);
+        let mut secret_bytes = [0; 64];
+        rand::thread_rng().fill_bytes(&mut secret_bytes);
+        let secret = Scalar::from_bytes_mod_order_wide(&secret_bytes);
+
+        let element = RISTRETTO_BASEPOINT_POINT * secret + blinding(side) * password;
+        Self { side, password, secret, element }
+    }
+
+    // Sent to the other side
+    pub fn element(&self) -> Element {
+        self.element.compress().to_bytes()
+    }
+
+    // `binding` ties the key to the connection, the certificate of the server here
+    pub fn finish(self, peer_element: &Element, binding: &[u8]) -> anyhow::Result<SharedKey> {
+        let Some(peer_point) = CompressedRistretto(*peer_element).decompress() else {
+            bail!("The peer sent an invalid element");
+        };
+        let peer_side = match self.side {
+            Side::Client => Side::Server,
+            Side::Server => Side::Client,
+        };
+        let shared_point = (peer_point - blinding(peer_side) * self.password) * self.secret;
+        if shared_point == RistrettoPoint::identity() {
+            bail!("The peer sent an invalid element");
+        }
+
+        let (client_element, server_element) = match self.side {
+            Side::Client => (self.element.compress(), peer_point.compress()),
+            Side::Server => (peer_point.compress(), self.element.compress()),
+        };
+        // Every part has a fixed length, so plain concatenation is unambiguous
+        let key = Sha256::new()
+            .chain_update(b"todo-lan-sync key")
+            .chain_update(Sha256::digest(binding))
+            .chain_update(client_element.as_bytes())
+            .chain_update(server_element.as_bytes())
+            .chain_update(shared_point.compress().as_bytes())
+            .chain_update(self.password.as_bytes())
+            .finalize();
+        Ok(SharedKey(key.into()))
+    }
+}
+
+impl SharedKey {
+    // Shows the other side that this one has the same key
+    pub fn confirmation(&self, side: Side) -> Confirmation {
+        self.mac(side).finalize().into_bytes().to_vec()
+    }
+
+    pub fn verify(&self, side: Side, confirmation: &[u8]) -> bool {
+        self.mac(side).verify_slice(confirmation).is_ok()
+    }
+
+    fn mac(&self, side: Side) -> Hmac<Sha256> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.0).expect("HMAC takes keys of any length");
+        mac.update(side.label());
+        mac
+    }
+}
+
+// `M` and `N` of the RFC, hashed to the group so that nobody knows their logarithms
+fn blinding(side: Side) -> RistrettoPoint {
+    let mut bytes = [0; 64];
+    bytes.copy_from_slice(&Sha512::new().chain_update(b"todo-lan-sync blinding ").chain_update(side.label()).finalize());
+    RistrettoPoint::from_uniform_bytes(&bytes)
+}
+
+fn hash_to_scalar(parts: &[&[u8]]) -> Scalar {
+    let mut hasher = Sha512::new();
+    for part in parts {
+        hasher.update(part);
+    }
+    let mut bytes = [0; 64];
+    bytes.copy_from_slice(&hasher.finalize());
+    Scalar::from_bytes_mod_order_wide(&bytes)
+}
@@ -1,19 +1,42 @@
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 
 use glib::Properties;
 use gtk::glib;
 use gtk::prelude::*;
 use gtk::subclass::prelude::*;
 
-use super::TaskData;
+use super::{Priority, Subtasks, TaskData};
+use crate::utils;
 
 // Object holding the state
 #[derive(Properties, Default)]
 #[properties(wrapper_type = super::TaskObject)]
 pub struct TaskObject {
+    #[property(name = "uuid", get, type = String, member = uuid)]
     #[property(name = "completed", get, set, type = bool, member = completed)]
     #[property(name = "content", get, set, type = String, member = content)]
+    #[property(name = "starred", get, set, type = bool, member = starred)]
+    #[property(name = "notes", get, set, type = String, member = notes)]
+    #[property(name = "link", get, set, type = String, member = link)]
+    #[property(name = "due-date", get, set, type = String, member = due_date)]
+    #[property(name = "start-date", get, set, type = String, member = start_date)]
+    #[property(name = "recurrence", get, set, type = String, member = recurrence)]
+    #[property(name = "reminder-time", get, set, type = String, member = reminder_time)]
+    #[property(name = "deleted-at", get, set, type = String, member = deleted_at)]
+    #[property(name = "created-at", get, type = String, member = created_at)]
+    #[property(name = "modified-at", get, type = String, member = modified_at)]
+    #[property(name = "completed-at", get, set, type = String, member = completed_at)]
+    #[property(name = "tags", get, set, type = Vec<String>, member = tags)]
+    #[property(name = "focus-seconds", get, set, type = u64, member = focus_seconds)]
+    #[property(name = "estimate-minutes", get, set, type = u32, member = estimate_minutes)]
+    #[property(name = "attachments", get, set, type = Vec<String>, member = attachments)]
+    #[property(name = "subtasks", get, set, type = Subtasks, member = subtasks)]
+    #[property(name = "section", get, set, type = String, member = section)]
+    #[property(name = "blocked-by", get, set, type = Vec<String>, member = blocked_by)]
+    #[property(name = "priority", get, set, type = Priority, member = priority, builder(Priority::None))]
     pub data: RefCell<TaskData>,
+    // Set while another version of the task is taken over with its own timestamps
+    pub taking_over: Cell<bool>,
 }
 
 // The central trait for subclassing a GObject
@@ -25,4 +48,21 @@ impl ObjectSubclass for TaskObject {
 
 // Trait shared by all GObjects
 #[glib::derived_properties]
-impl ObjectImpl for TaskObject {}
+impl ObjectImpl for TaskObject {
+    fn constructed(&self) {
+        self.parent_constructed();
+
+        self.obj().connect_notify_local(None, |task, pspec| {
+            if pspec.name() != "modified-at" && !task.imp().taking_over.get() {
+                task.touch(pspec.name());
+            }
+        });
+        self.obj().connect_completed_notify(|task| {
+            if task.imp().taking_over.get() {
+                return;
+            }
+            let completed_at = if task.is_completed() { utils::timestamp() } else { String::new() };
+            task.set_completed_at(completed_at);
+        });
+    }
+}
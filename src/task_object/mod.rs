@@ -1,37 +1,322 @@
 mod imp;
 
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+
 use adw::subclass::prelude::*;
 use glib::Object;
 use gtk::glib;
+use gtk::prelude::*;
 use serde::{Deserialize, Serialize};
 
+use crate::attachments;
+use crate::recurrence::Recurrence;
+use crate::utils;
+
 glib::wrapper! {
     pub struct TaskObject(ObjectSubclass<imp::TaskObject>);
 }
 
 impl TaskObject {
     pub fn new(completed: bool, content: String) -> Self {
-        Object::builder()
+        let task_object: Self = Object::builder()
             .property("completed", completed)
             .property("content", content)
-            .build()
+            .build();
+        let mut data = task_object.imp().data.borrow_mut();
+        data.uuid = utils::new_uuid();
+        data.created_at = utils::timestamp();
+        data.modified_at = data.created_at.clone();
+        drop(data);
+        task_object
     }
 
     pub fn is_completed(&self) -> bool {
         self.imp().data.borrow().completed
     }
 
+    pub fn is_starred(&self) -> bool {
+        self.imp().data.borrow().starred
+    }
+
+    pub fn has_due_date(&self) -> bool {
+        !self.imp().data.borrow().due_date.is_empty()
+    }
+
+    // Due dates are stored as "YYYY-MM-DD", so plain string comparison orders them
+    pub fn is_due_today(&self) -> bool {
+        self.imp().data.borrow().due_date == utils::today()
+    }
+
+    // Open tasks that are put off until a later day
+    pub fn is_deferred(&self) -> bool {
+        let data = self.imp().data.borrow();
+        !data.completed && data.start_date > utils::today()
+    }
+
+    pub fn is_overdue(&self) -> bool {
+        let data = self.imp().data.borrow();
+        !data.completed && !data.due_date.is_empty() && data.due_date < utils::today()
+    }
+
+    // "YYYY-MM-DD HH:MM" when the task should be reminded of, on its due date
+    pub fn reminder(&self) -> Option<String> {
+        let data = self.imp().data.borrow();
+        if data.due_date.is_empty() || data.reminder_time.is_empty() {
+            return None;
+        }
+
+        Some(format!("{} {}", data.due_date, data.reminder_time))
+    }
+
+    pub fn recurrence_rule(&self) -> Option<Recurrence> {
+        Recurrence::parse(&self.imp().data.borrow().recurrence)
+    }
+
+    // The next occurrence of a recurring task, or `None` if it does not repeat
+    pub fn next_occurrence(&self) -> Option<TaskObject> {
+        let recurrence = self.recurrence_rule()?;
+        let mut task_data = self.task_data();
+
+        task_data.renew();
+        task_data.completed = false;
+        task_data.due_date = recurrence.next_due_date(&task_data.due_date)?;
+        // The steps are done again for every occurrence
+        for subtask in &mut task_data.subtasks.0 {
+            subtask.completed = false;
+        }
+        Self::copy_attachments(&self.uuid(), &task_data);
+
+        Some(Self::from_task_data(task_data))
+    }
+
+    // A copy of the task with its own uuid, still to be done like a new task
+    pub fn duplicate(&self) -> TaskObject {
+        let mut task_data = self.task_data();
+        task_data.renew();
+        task_data.completed = false;
+        task_data.completed_at.clear();
+        for subtask in &mut task_data.subtasks.0 {
+            subtask.completed = false;
+        }
+        Self::copy_attachments(&self.uuid(), &task_data);
+        Self::from_task_data(task_data)
+    }
+
+    fn copy_attachments(from_uuid: &str, task_data: &TaskData) {
+        if task_data.attachments.is_empty() {
+            return;
+        }
+        if let Err(err) = attachments::copy_all(from_uuid, &task_data.uuid) {
+            glib::g_warning!("todo", "Could not copy the attachments: {}", err);
+        }
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.imp().data.borrow().tags.iter().any(|task_tag| task_tag == tag)
+    }
+
     pub fn task_data(&self) -> TaskData {
         self.imp().data.borrow().clone()
     }
 
-    pub fn from_task_data(task_data: TaskData) -> Self {
-        Self::new(task_data.completed, task_data.content)
+    // Takes over another version of the task with its timestamps, notifying about the
+    // changed properties only. Touching them would make stale values look like new edits.
+    pub fn set_task_data(&self, task_data: TaskData) {
+        let old_data = self.imp().data.replace(task_data);
+        let data = self.imp().data.borrow();
+        let changed: Vec<&str> = [
+            ("completed", old_data.completed != data.completed),
+            ("completed-at", old_data.completed_at != data.completed_at),
+            ("content", old_data.content != data.content),
+            ("starred", old_data.starred != data.starred),
+            ("notes", old_data.notes != data.notes),
+            ("link", old_data.link != data.link),
+            ("due-date", old_data.due_date != data.due_date),
+            ("start-date", old_data.start_date != data.start_date),
+            ("recurrence", old_data.recurrence != data.recurrence),
+            ("reminder-time", old_data.reminder_time != data.reminder_time),
+            ("priority", old_data.priority != data.priority),
+            ("tags", old_data.tags != data.tags),
+            ("blocked-by", old_data.blocked_by != data.blocked_by),
+            ("focus-seconds", old_data.focus_seconds != data.focus_seconds),
+            ("estimate-minutes", old_data.estimate_minutes != data.estimate_minutes),
+            ("attachments", old_data.attachments != data.attachments),
+            ("subtasks", old_data.subtasks != data.subtasks),
+            ("section", old_data.section != data.section),
+            ("deleted-at", old_data.deleted_at != data.deleted_at),
+            ("modified-at", old_data.modified_at != data.modified_at),
+        ]
+        .into_iter()
+        .filter_map(|(property, changed)| changed.then_some(property))
+        .collect();
+        drop(data);
+
+        self.imp().taking_over.set(true);
+        for property in changed {
+            self.notify(property);
+        }
+        self.imp().taking_over.set(false);
+    }
+
+    // Calls `callback` on changes of `property` for as long as `widget` exists. Rows are
+    // rebuilt whenever the list changes, handlers kept for good would pile up on the task.
+    pub fn connect_notify_for(
+        &self,
+        widget: &impl IsA<gtk::Widget>,
+        property: &str,
+        callback: impl Fn(&TaskObject) + 'static,
+    ) {
+        let handler_id = self.connect_notify_local(Some(property), move |task_object, _| callback(task_object));
+        let handler_id = RefCell::new(Some(handler_id));
+        widget.connect_destroy(glib::clone!(@weak self as task_object => move |_| {
+            if let Some(handler_id) = handler_id.take() {
+                task_object.disconnect(handler_id);
+            }
+        }));
+    }
+
+    // Called on every change of the other properties
+    fn touch(&self, property: &str) {
+        let timestamp = utils::timestamp();
+        let mut data = self.imp().data.borrow_mut();
+        data.field_modified_at.insert(property.to_string(), timestamp.clone());
+        data.modified_at = timestamp;
+        drop(data);
+        self.notify_modified_at();
+    }
+
+    // Tasks without timestamps (imported or saved before they were tracked) count as new
+    pub fn from_task_data(mut task_data: TaskData) -> Self {
+        if task_data.created_at.is_empty() {
+            task_data.created_at = utils::timestamp();
+        }
+        if task_data.modified_at.is_empty() {
+            task_data.modified_at = task_data.created_at.clone();
+        }
+
+        let task_object: Self = Object::builder().build();
+        task_object.imp().data.replace(task_data);
+        task_object
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, glib::Enum, Serialize, Deserialize)]
+#[enum_type(name = "TodoPriority")]
+pub enum Priority {
+    #[default]
+    None,
+    Low,
+    Medium,
+    High,
+}
+
+impl Priority {
+    pub const ALL: [Priority; 4] = [Priority::None, Priority::Low, Priority::Medium, Priority::High];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Priority::None => "No Priority",
+            Priority::Low => "Low",
+            Priority::Medium => "Medium",
+            Priority::High => "High",
+        }
+    }
+
+    pub fn css_class(&self) -> Option<&'static str> {
+        match self {
+            Priority::None => None,
+            Priority::Low => Some("priority-low"),
+            Priority::Medium => Some("priority-medium"),
+            Priority::High => Some("priority-high"),
+        }
     }
 }
 
-#[derive(Default, Clone, Serialize, Deserialize)]
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Subtask {
+    pub content: String,
+    pub completed: bool,
+}
+
+// The steps of a task, a boxed type so they can be a property
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize, glib::Boxed)]
+#[boxed_type(name = "TodoSubtasks")]
+#[serde(transparent)]
+pub struct Subtasks(pub Vec<Subtask>);
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
 pub struct TaskData {
+    pub uuid: String,
     pub completed: bool,
     pub content: String,
+    // Starred tasks are kept at the top of their collection
+    #[serde(default)]
+    pub starred: bool,
+    #[serde(default)]
+    pub notes: String,
+    // A web page or file the task is about, empty for none
+    #[serde(default)]
+    pub link: String,
+    #[serde(default)]
+    pub due_date: String,
+    // "YYYY-MM-DD" before which the task is hidden from open tasks, empty to always show it
+    #[serde(default)]
+    pub start_date: String,
+    // iCalendar RRULE, empty for tasks that do not repeat
+    #[serde(default)]
+    pub recurrence: String,
+    // "HH:MM" on the due date, empty for no reminder
+    #[serde(default)]
+    pub reminder_time: String,
+    #[serde(default)]
+    pub priority: Priority,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    // Uuids of the tasks that have to be completed before this one
+    #[serde(default)]
+    pub blocked_by: Vec<String>,
+    // Time spent in focus sessions on the task
+    #[serde(default)]
+    pub focus_seconds: u64,
+    // Expected effort, 0 for none
+    #[serde(default)]
+    pub estimate_minutes: u32,
+    // File names in `attachments::directory`
+    #[serde(default)]
+    pub attachments: Vec<String>,
+    #[serde(default)]
+    pub subtasks: Subtasks,
+    // Name of the section of its collection, empty for none
+    #[serde(default)]
+    pub section: String,
+    // "YYYY-MM-DD" the task was moved to the trash, empty for tasks that are not deleted
+    #[serde(default)]
+    pub deleted_at: String,
+    // Timestamps as returned by `utils::timestamp`
+    #[serde(default)]
+    pub created_at: String,
+    #[serde(default)]
+    pub modified_at: String,
+    // When the task was completed, empty for open tasks and those completed before it was kept
+    #[serde(default)]
+    pub completed_at: String,
+    // When each property was last changed, by property name, to merge edits field by field
+    #[serde(default)]
+    pub field_modified_at: BTreeMap<String, String>,
+}
+
+impl TaskData {
+    // Copies get a uuid of their own and count as new tasks
+    pub fn renew(&mut self) {
+        self.uuid = utils::new_uuid();
+        self.created_at.clear();
+        self.modified_at.clear();
+        self.field_modified_at.clear();
+    }
+}
+
+// Tags are stored without the leading '#' that is used to type them
+pub fn normalize_tag(tag: &str) -> String {
+    tag.trim().trim_start_matches('#').trim().to_string()
 }
@@ -0,0 +1,1166 @@
+use std::time::Duration;
+
+use adw::subclass::prelude::*;
+use adw::{prelude::*, ExpanderRow, Toast};
+use glib::clone;
+use gtk::{
+    gdk, gio, glib, pango, Align, Button, Calendar, CheckButton, DropDown, Entry, EventControllerFocus,
+    EventControllerKey, Label, ListBoxRow, MenuButton, Popover,
+};
+
+use crate::collection_object::CollectionObject;
+use crate::links::Link;
+use crate::recurrence::Recurrence;
+use crate::reminders;
+use crate::task_object::{normalize_tag, Priority, TaskObject};
+use crate::utils;
+use crate::window::{TodoWindow, ROW_ANIMATION_MS};
+
+// Pixels per second a horizontal swipe needs to count as one
+const SWIPE_MIN_VELOCITY: f64 = 500.0;
+// Lines of the notes previewed in the tooltip of a task
+const TOOLTIP_NOTES_LINES: usize = 3;
+// Offered in the task menu, in minutes
+const ESTIMATE_PRESETS: [u32; 7] = [0, 15, 30, 60, 120, 240, 480];
+
+// The rows of the task list, built and kept up to date by the window
+impl TodoWindow {
+    pub(crate) fn create_task_row(&self, task_object: &TaskObject) -> ExpanderRow {
+        let check_button = CheckButton::builder()
+            .valign(Align::Center)
+            .can_focus(false)
+            .build();
+
+        let row = ExpanderRow::builder()
+            .subtitle_lines(1)
+            .build();
+        if self.is_selection_mode() {
+            row.add_prefix(&self.create_select_button(task_object));
+        }
+        row.add_prefix(&check_button);
+
+        task_object
+            .bind_property("completed", &check_button, "active")
+            .bidirectional()
+            .sync_create()
+            .build();
+
+        task_object
+            .bind_property("content", &row, "title")
+            .sync_create()
+            .build();
+
+        Self::update_completed_style(&row, task_object);
+        task_object.connect_notify_for(
+            &row,
+            "completed",
+            clone!(@weak row => move |task_object| {
+                Self::update_completed_style(&row, task_object);
+            })
+        );
+
+        // The title is ellipsized, the tooltip has all of it until the row is expanded
+        Self::update_task_tooltip(&row, task_object);
+        for property in ["content", "notes", "due-date", "tags"] {
+            task_object.connect_notify_for(
+                &row,
+                property,
+                clone!(@weak row => move |task_object| {
+                    Self::update_task_tooltip(&row, task_object);
+                })
+            );
+        }
+        row.connect_expanded_notify(clone!(@weak task_object => move |row| {
+            Self::update_task_tooltip(row, &task_object);
+        }));
+
+        Self::update_task_subtitle(&row, task_object);
+        for property in ["notes", "completed-at"] {
+            task_object.connect_notify_for(
+                &row,
+                property,
+                clone!(@weak row => move |task_object| {
+                    Self::update_task_subtitle(&row, task_object);
+                })
+            );
+        }
+
+        row.add_row(&self.create_content_links(task_object));
+        row.add_row(&Self::create_notes_editor(task_object));
+        row.add_row(&self.create_attachments_box(task_object));
+
+        // Opens the page the task is about
+        let link_button = Button::builder()
+            .icon_name("web-browser-symbolic")
+            .tooltip_text("Open Link")
+            .valign(Align::Center)
+            .build();
+        link_button.add_css_class("flat");
+        task_object
+            .bind_property("link", &link_button, "visible")
+            .sync_create()
+            .transform_to(|_, link: String| Some(!link.is_empty()))
+            .build();
+        link_button.connect_clicked(clone!(@weak self as window, @weak task_object => move |_| {
+            gtk::show_uri(Some(&window), &task_object.link(), gdk::CURRENT_TIME);
+        }));
+        row.add_suffix(&link_button);
+
+        let due_label = Label::builder()
+            .valign(Align::Center)
+            .build();
+        due_label.add_css_class("caption");
+        due_label.add_css_class("due-chip");
+        row.add_suffix(&due_label);
+
+        // Rows are rebuilt at midnight, when tasks due today become overdue
+        Self::update_due_label(&due_label, task_object);
+        Self::update_overdue_style(&row, task_object);
+        for property in ["due-date", "completed", "recurrence"] {
+            task_object.connect_notify_for(
+                &row,
+                property,
+                clone!(@weak row, @weak due_label => move |task_object| {
+                    Self::update_due_label(&due_label, task_object);
+                    Self::update_overdue_style(&row, task_object);
+                })
+            );
+        }
+
+        let estimate_label = Label::builder()
+            .valign(Align::Center)
+            .build();
+        estimate_label.add_css_class("caption");
+        estimate_label.add_css_class("dim-label");
+        task_object
+            .bind_property("estimate-minutes", &estimate_label, "label")
+            .sync_create()
+            .transform_to(|_, minutes: u32| Some(utils::format_minutes(minutes.into())))
+            .build();
+        task_object
+            .bind_property("estimate-minutes", &estimate_label, "visible")
+            .sync_create()
+            .transform_to(|_, minutes: u32| Some(minutes > 0))
+            .build();
+        row.add_suffix(&estimate_label);
+
+        let tags_box = gtk::Box::builder()
+            .valign(Align::Center)
+            .spacing(4)
+            .build();
+        row.add_suffix(&tags_box);
+
+        Self::update_tags_box(&tags_box, task_object);
+        task_object.connect_notify_for(
+            &tags_box,
+            "tags",
+            clone!(@weak tags_box => move |task_object| {
+                Self::update_tags_box(&tags_box, task_object);
+            })
+        );
+
+        // Smart views mix the collections, note where each task comes from
+        if self.imp().current_smart_view.borrow().is_some() {
+            if let Some((collection, _)) = self.find_task_collection(task_object) {
+                let collection_label = Label::builder()
+                    .valign(Align::Center)
+                    .ellipsize(pango::EllipsizeMode::End)
+                    .max_width_chars(12)
+                    .build();
+                collection_label.add_css_class("caption");
+                collection_label.add_css_class("dim-label");
+                collection
+                    .bind_property("title", &collection_label, "label")
+                    .sync_create()
+                    .build();
+                row.add_suffix(&collection_label);
+            }
+        }
+
+        row.add_suffix(&self.create_tags_button(task_object));
+        row.add_suffix(&self.create_due_date_button(task_object));
+        row.add_suffix(&self.create_priority_button(task_object));
+        row.add_suffix(&self.create_star_button(task_object));
+
+        self.update_blocked_style(&row, task_object);
+        task_object.connect_notify_for(
+            &row,
+            "blocked-by",
+            clone!(@weak self as window, @weak row => move |task_object| {
+                window.update_blocked_style(&row, task_object);
+            })
+        );
+        let edit_entry = Entry::builder()
+            .valign(Align::Center)
+            .hexpand(true)
+            .visible(false)
+            .build();
+        row.add_suffix(&edit_entry);
+
+        let edit_button = Button::builder()
+            .icon_name("document-edit-symbolic")
+            .valign(Align::Center)
+            .tooltip_text("Edit Task")
+            .build();
+        edit_button.add_css_class("flat");
+        row.add_suffix(&edit_button);
+
+        let details_button = Button::builder()
+            .icon_name("sidebar-show-right-symbolic")
+            .valign(Align::Center)
+            .tooltip_text("Show Details")
+            .action_name("task.show-details")
+            .build();
+        details_button.add_css_class("flat");
+        row.add_suffix(&details_button);
+
+        edit_button.connect_clicked(
+            clone!(@weak row, @weak edit_entry, @weak task_object => move |edit_button| {
+                Self::start_task_edit(&row, &edit_entry, edit_button, &task_object);
+            })
+        );
+
+        // F2 on a focused row swaps the title for the entry, like renaming a file.
+        // The single letter keys triage the tasks without the mouse, so they are
+        // caught before the row activates on Space
+        let row_key_controller = EventControllerKey::new();
+        row_key_controller.set_propagation_phase(gtk::PropagationPhase::Capture);
+        row_key_controller.connect_key_pressed(
+            clone!(@weak self as window, @weak row, @weak edit_entry, @weak edit_button, @weak task_object
+                => @default-return glib::Propagation::Proceed, move |_, key, _, modifier| {
+                if key == gdk::Key::F2 {
+                    Self::start_task_edit(&row, &edit_entry, &edit_button, &task_object);
+                    return glib::Propagation::Stop;
+                }
+
+                // Only the row itself, so typing in an entry or pressing one of its buttons still works
+                let row_focused = window.focus().is_some_and(|focus| focus.is::<ListBoxRow>());
+                let has_modifier = modifier.intersects(
+                    gdk::ModifierType::CONTROL_MASK | gdk::ModifierType::ALT_MASK | gdk::ModifierType::SUPER_MASK
+                );
+                if !row_focused || has_modifier {
+                    return glib::Propagation::Proceed;
+                }
+
+                match key {
+                    gdk::Key::j => window.focus_task_row(row.index() + 1),
+                    gdk::Key::k => window.focus_task_row(row.index() - 1),
+                    gdk::Key::x | gdk::Key::space => {
+                        window.set_task_property(&task_object, "completed", (!task_object.is_completed()).to_value());
+                    }
+                    gdk::Key::e => Self::start_task_edit(&row, &edit_entry, &edit_button, &task_object),
+                    gdk::Key::d => {
+                        let index = row.index();
+                        Self::fade_out_row(&row, clone!(@weak window, @weak task_object => move || {
+                            window.delete_task(&task_object);
+                            window.focus_task_row(index);
+                        }));
+                    }
+                    gdk::Key::m => {
+                        let index = row.index();
+                        glib::MainContext::default().spawn_local(clone!(@weak window, @weak task_object => async move {
+                            if window.move_tasks_to_chosen_collection(&[task_object]).await {
+                                window.focus_task_row(index);
+                            }
+                        }));
+                    }
+                    _ => return glib::Propagation::Proceed,
+                }
+                glib::Propagation::Stop
+            })
+        );
+        row.add_controller(row_key_controller);
+
+        edit_entry.connect_activate(
+            clone!(@weak self as window, @weak row, @weak edit_button, @weak task_object => move |edit_entry| {
+                window.finish_task_edit(&row, edit_entry, &edit_button, &task_object, true);
+            })
+        );
+
+        let entry_key_controller = EventControllerKey::new();
+        entry_key_controller.connect_key_pressed(
+            clone!(@weak self as window, @weak row, @weak edit_entry, @weak edit_button, @weak task_object
+                => @default-return glib::Propagation::Proceed, move |_, key, _, _| {
+                if key == gdk::Key::Escape {
+                    window.finish_task_edit(&row, &edit_entry, &edit_button, &task_object, false);
+                    glib::Propagation::Stop
+                } else {
+                    glib::Propagation::Proceed
+                }
+            })
+        );
+        edit_entry.add_controller(entry_key_controller);
+
+        // Clicking somewhere else keeps whatever was typed
+        let entry_focus_controller = EventControllerFocus::new();
+        entry_focus_controller.connect_leave(
+            clone!(@weak self as window, @weak row, @weak edit_entry, @weak edit_button, @weak task_object => move |_| {
+                window.finish_task_edit(&row, &edit_entry, &edit_button, &task_object, true);
+            })
+        );
+        edit_entry.add_controller(entry_focus_controller);
+
+        self.setup_task_menu(&row, task_object);
+        self.setup_task_swipe(&row, task_object);
+        self.setup_task_drag(&row, task_object);
+
+        row
+    }
+
+    fn create_select_button(&self, task_object: &TaskObject) -> CheckButton {
+        let select_button = CheckButton::builder()
+            .valign(Align::Center)
+            .tooltip_text("Select")
+            .build();
+        select_button.add_css_class("selection-mode");
+
+        if let Some(selection_model) = self.imp().current_selection_model.borrow().as_ref() {
+            let position = Self::selection_position(selection_model, task_object);
+            select_button.set_active(position.is_some_and(|position| selection_model.is_selected(position)));
+        }
+
+        select_button.connect_toggled(clone!(@weak self as window, @weak task_object => move |select_button| {
+            let Some(selection_model) = window.imp().current_selection_model.borrow().clone() else {
+                return;
+            };
+            let Some(position) = Self::selection_position(&selection_model, &task_object) else {
+                return;
+            };
+
+            if select_button.is_active() {
+                selection_model.select_item(position, false);
+            } else {
+                selection_model.unselect_item(position);
+            }
+        }));
+
+        select_button
+    }
+
+    // Dragging a task onto another moves it to the section of that one
+    fn setup_task_drag(&self, row: &ExpanderRow, task_object: &TaskObject) {
+        let drag_source = gtk::DragSource::builder()
+            .actions(gdk::DragAction::MOVE)
+            .content(&gdk::ContentProvider::for_value(&task_object.to_value()))
+            .build();
+        row.add_controller(drag_source);
+
+        let drop_target = gtk::DropTarget::new(TaskObject::static_type(), gdk::DragAction::MOVE);
+        drop_target.connect_drop(clone!(@weak self as window, @weak task_object => @default-return false, move |_, value, _, _| {
+            let Some(dropped_task) = value.get::<TaskObject>().ok().filter(|task| window.is_in_current_collection(task)) else {
+                return false;
+            };
+            window.move_task_to_section(&dropped_task, &task_object.section());
+            true
+        }));
+        row.add_controller(drop_target);
+    }
+
+    // On touch screens, swiping right toggles the task and swiping left reveals a delete button
+    fn setup_task_swipe(&self, row: &ExpanderRow, task_object: &TaskObject) {
+        let delete_button = Button::builder()
+            .label("Delete")
+            .valign(Align::Center)
+            .build();
+        delete_button.add_css_class("destructive-action");
+        delete_button.connect_clicked(clone!(@weak self as window, @weak row, @weak task_object => move |_| {
+            Self::fade_out_row(&row, clone!(@weak window, @weak task_object => move || {
+                window.delete_task(&task_object);
+            }));
+        }));
+
+        let delete_revealer = gtk::Revealer::builder()
+            .transition_type(gtk::RevealerTransitionType::SlideLeft)
+            .child(&delete_button)
+            .build();
+        row.add_suffix(&delete_revealer);
+
+        let swipe_gesture = gtk::GestureSwipe::builder()
+            .touch_only(true)
+            .build();
+        swipe_gesture.connect_swipe(
+            clone!(@weak self as window, @weak delete_revealer, @weak task_object => move |gesture, velocity_x, velocity_y| {
+                // Mostly vertical swipes scroll the list
+                if velocity_x.abs() < SWIPE_MIN_VELOCITY || velocity_x.abs() < velocity_y.abs() {
+                    return;
+                }
+                gesture.set_state(gtk::EventSequenceState::Claimed);
+
+                if velocity_x < 0.0 {
+                    delete_revealer.set_reveal_child(true);
+                } else if delete_revealer.reveals_child() {
+                    delete_revealer.set_reveal_child(false);
+                } else {
+                    window.set_task_property(&task_object, "completed", (!task_object.is_completed()).to_value());
+                }
+            })
+        );
+        row.add_controller(swipe_gesture);
+    }
+
+    // Right click, long press or the menu key open a menu with actions on the task
+    fn setup_task_menu(&self, row: &ExpanderRow, task_object: &TaskObject) {
+        let actions = gio::SimpleActionGroup::new();
+
+        let action_delete = gio::SimpleAction::new("delete", None);
+        action_delete.connect_activate(clone!(@weak self as window, @weak row, @weak task_object => move |_, _| {
+            Self::fade_out_row(&row, clone!(@weak window, @weak task_object => move || {
+                window.delete_task(&task_object);
+            }));
+        }));
+        actions.add_action(&action_delete);
+
+        let action_show_details = gio::SimpleAction::new("show-details", None);
+        action_show_details.connect_activate(clone!(@weak self as window, @weak task_object => move |_, _| {
+            window.show_task_details(&task_object);
+        }));
+        actions.add_action(&action_show_details);
+
+        let action_duplicate = gio::SimpleAction::new("duplicate", None);
+        action_duplicate.connect_activate(clone!(@weak self as window, @weak task_object => move |_, _| {
+            window.duplicate_task(&task_object);
+        }));
+        actions.add_action(&action_duplicate);
+
+        let action_copy = gio::SimpleAction::new("copy", None);
+        action_copy.connect_activate(clone!(@weak self as window, @weak task_object => move |_, _| {
+            window.copy_tasks(&window.tasks_for_menu(&task_object), false);
+        }));
+        actions.add_action(&action_copy);
+
+        let action_copy_markdown = gio::SimpleAction::new("copy-markdown", None);
+        action_copy_markdown.connect_activate(clone!(@weak self as window, @weak task_object => move |_, _| {
+            window.copy_tasks(&window.tasks_for_menu(&task_object), true);
+        }));
+        actions.add_action(&action_copy_markdown);
+
+        let action_copy_link = gio::SimpleAction::new("copy-link", None);
+        action_copy_link.connect_activate(clone!(@weak self as window, @weak task_object => move |_, _| {
+            window.clipboard().set_text(&Link::Task(task_object.uuid()).to_uri());
+            window.imp().toast_overlay.add_toast(Toast::new("Link copied to clipboard"));
+        }));
+        actions.add_action(&action_copy_link);
+
+        let action_share = gio::SimpleAction::new("share", None);
+        action_share.connect_activate(clone!(@weak self as window, @weak task_object => move |_, _| {
+            glib::MainContext::default().spawn_local(clone!(@weak window, @weak task_object => async move {
+                window.share_task(&task_object).await;
+            }));
+        }));
+        actions.add_action(&action_share);
+
+        // "today", "tomorrow" or "next-week"
+        let action_reschedule = gio::SimpleAction::new("reschedule", Some(glib::VariantTy::STRING));
+        action_reschedule.connect_activate(clone!(@weak self as window, @weak task_object => move |_, parameter| {
+            let days = match parameter.and_then(|parameter| parameter.get::<String>()).as_deref() {
+                Some("today") => 0,
+                Some("tomorrow") => 1,
+                Some("next-week") => 7,
+                _ => return,
+            };
+            let Some(date) = utils::now().add_days(days).ok() else {
+                return;
+            };
+            window.reschedule_task(&task_object, &utils::format_date(&date));
+        }));
+        actions.add_action(&action_reschedule);
+
+        let action_pick_date = gio::SimpleAction::new("pick-date", None);
+        action_pick_date.connect_activate(clone!(@weak self as window, @weak task_object => move |_, _| {
+            glib::MainContext::default().spawn_local(clone!(@weak window, @weak task_object => async move {
+                window.pick_due_date(&task_object).await;
+            }));
+        }));
+        actions.add_action(&action_pick_date);
+
+        let action_set_estimate = gio::SimpleAction::new("set-estimate", Some(glib::VariantTy::UINT32));
+        action_set_estimate.connect_activate(clone!(@weak self as window, @weak task_object => move |_, parameter| {
+            let Some(minutes) = parameter.and_then(|parameter| parameter.get::<u32>()) else {
+                return;
+            };
+            if minutes != task_object.estimate_minutes() {
+                window.set_task_property(&task_object, "estimate-minutes", minutes.to_value());
+            }
+        }));
+        actions.add_action(&action_set_estimate);
+
+        let action_start_focus = gio::SimpleAction::new("start-focus", None);
+        action_start_focus.connect_activate(clone!(@weak self as window, @weak task_object => move |_, _| {
+            window.start_focus(&task_object);
+        }));
+        actions.add_action(&action_start_focus);
+
+        let action_blocked_by = gio::SimpleAction::new("blocked-by", None);
+        action_blocked_by.connect_activate(clone!(@weak self as window, @weak task_object => move |_, _| {
+            glib::MainContext::default().spawn_local(clone!(@weak window, @weak task_object => async move {
+                window.choose_blockers(&task_object).await;
+            }));
+        }));
+        actions.add_action(&action_blocked_by);
+
+        let action_defer = gio::SimpleAction::new("defer", None);
+        action_defer.connect_activate(clone!(@weak self as window, @weak task_object => move |_, _| {
+            glib::MainContext::default().spawn_local(clone!(@weak window, @weak task_object => async move {
+                window.defer_task(&task_object).await;
+            }));
+        }));
+        actions.add_action(&action_defer);
+
+        let action_set_section = gio::SimpleAction::new("set-section", Some(glib::VariantTy::STRING));
+        action_set_section.connect_activate(clone!(@weak self as window, @weak task_object => move |_, parameter| {
+            if let Some(section) = parameter.and_then(|parameter| parameter.get::<String>()) {
+                window.move_task_to_section(&task_object, &section);
+            }
+        }));
+        actions.add_action(&action_set_section);
+
+        let action_move_to = gio::SimpleAction::new("move-to", Some(glib::VariantTy::STRING));
+        action_move_to.connect_activate(clone!(@weak self as window, @weak task_object => move |_, parameter| {
+            let Some(uuid) = parameter.and_then(|parameter| parameter.get::<String>()) else {
+                return;
+            };
+            window.move_task_to_collection(&task_object, &uuid);
+        }));
+        actions.add_action(&action_move_to);
+
+        row.insert_action_group("task", Some(&actions));
+
+        let click_gesture = gtk::GestureClick::builder()
+            .button(gdk::BUTTON_SECONDARY)
+            .build();
+        click_gesture.connect_pressed(clone!(@weak self as window, @weak row => move |gesture, _, x, y| {
+            gesture.set_state(gtk::EventSequenceState::Claimed);
+            window.show_task_menu(&row, x, y);
+        }));
+        row.add_controller(click_gesture);
+
+        let long_press_gesture = gtk::GestureLongPress::builder()
+            .touch_only(true)
+            .build();
+        long_press_gesture.connect_pressed(clone!(@weak self as window, @weak row => move |gesture, x, y| {
+            gesture.set_state(gtk::EventSequenceState::Claimed);
+            window.show_task_menu(&row, x, y);
+        }));
+        row.add_controller(long_press_gesture);
+
+        let key_controller = EventControllerKey::new();
+        key_controller.connect_key_pressed(
+            clone!(@weak self as window, @weak row => @default-return glib::Propagation::Proceed, move |_, key, _, modifier| {
+                let is_menu_key = key == gdk::Key::Menu
+                    || (key == gdk::Key::F10 && modifier.contains(gdk::ModifierType::SHIFT_MASK));
+                if is_menu_key {
+                    window.show_task_menu(&row, 0.0, 0.0);
+                    glib::Propagation::Stop
+                } else {
+                    glib::Propagation::Proceed
+                }
+            })
+        );
+        row.add_controller(key_controller);
+    }
+
+    fn show_task_menu(&self, row: &ExpanderRow, x: f64, y: f64) {
+        let menu = gio::Menu::new();
+
+        let edit_section = gio::Menu::new();
+        edit_section.append(Some("Show Details"), Some("task.show-details"));
+        edit_section.append(Some("Duplicate"), Some("task.duplicate"));
+        edit_section.append(Some("Copy Text"), Some("task.copy"));
+        edit_section.append(Some("Copy as Checklist"), Some("task.copy-markdown"));
+        edit_section.append(Some("Copy Link"), Some("task.copy-link"));
+        edit_section.append(Some("Share…"), Some("task.share"));
+        menu.append_section(None, &edit_section);
+
+        let estimate_menu = gio::Menu::new();
+        for minutes in ESTIMATE_PRESETS {
+            let label = if minutes == 0 { "None".to_string() } else { utils::format_minutes(minutes.into()) };
+            let item = gio::MenuItem::new(Some(&label), None);
+            item.set_action_and_target_value(Some("task.set-estimate"), Some(&minutes.to_variant()));
+            estimate_menu.append_item(&item);
+        }
+        menu.append_submenu(Some("Estimate"), &estimate_menu);
+
+        let focus_section = gio::Menu::new();
+        focus_section.append(Some("Start Focus"), Some("task.start-focus"));
+        menu.append_section(None, &focus_section);
+
+        let reschedule_menu = gio::Menu::new();
+        for (label, target) in [("Today", "today"), ("Tomorrow", "tomorrow"), ("Next Week", "next-week")] {
+            let item = gio::MenuItem::new(Some(label), None);
+            item.set_action_and_target_value(Some("task.reschedule"), Some(&target.to_variant()));
+            reschedule_menu.append_item(&item);
+        }
+        reschedule_menu.append(Some("Pick Date…"), Some("task.pick-date"));
+        menu.append_submenu(Some("Reschedule"), &reschedule_menu);
+        menu.append(Some("Defer Until…"), Some("task.defer"));
+        menu.append(Some("Blocked By…"), Some("task.blocked-by"));
+
+        // The collections are listed when the menu opens, so it never shows stale titles
+        let move_menu = gio::Menu::new();
+        let current_collection = self.imp().current_collection.borrow().clone();
+
+        let sections = self.current_sections();
+        if !sections.is_empty() {
+            let section_menu = gio::Menu::new();
+            for section in std::iter::once("").chain(sections.iter().map(String::as_str)) {
+                let label = if section.is_empty() { "No Section" } else { section };
+                let item = gio::MenuItem::new(Some(label), None);
+                item.set_action_and_target_value(Some("task.set-section"), Some(&section.to_variant()));
+                section_menu.append_item(&item);
+            }
+            menu.append_submenu(Some("Move to Section"), &section_menu);
+        }
+
+        for collection in self.collections().iter::<CollectionObject>().filter_map(Result::ok) {
+            if Some(&collection) == current_collection.as_ref() {
+                continue;
+            }
+            let item = gio::MenuItem::new(Some(&collection.title()), None);
+            item.set_action_and_target_value(Some("task.move-to"), Some(&collection.uuid().to_variant()));
+            move_menu.append_item(&item);
+        }
+        if move_menu.n_items() > 0 {
+            menu.append_submenu(Some("Move to Collection"), &move_menu);
+        }
+
+        let delete_section = gio::Menu::new();
+        delete_section.append(Some("Delete"), Some("task.delete"));
+        menu.append_section(None, &delete_section);
+
+        Self::popup_menu(row.upcast_ref(), &menu, x, y);
+    }
+
+    // The content again with its web addresses clickable, only shown if it has any
+    fn create_content_links(&self, task_object: &TaskObject) -> Label {
+        let links_label = Label::builder()
+            .wrap(true)
+            .wrap_mode(pango::WrapMode::WordChar)
+            .xalign(0.0)
+            .margin_top(12)
+            .margin_start(12)
+            .margin_end(12)
+            .build();
+        links_label.connect_activate_link(clone!(@weak self as window => @default-return glib::Propagation::Proceed, move |_, uri| {
+            gtk::show_uri(Some(&window), uri, gdk::CURRENT_TIME);
+            glib::Propagation::Stop
+        }));
+
+        let update_links = |links_label: &Label, task_object: &TaskObject| {
+            let markup = utils::link_markup(&task_object.content());
+            links_label.set_markup(markup.as_deref().unwrap_or_default());
+            links_label.set_visible(markup.is_some());
+        };
+        update_links(&links_label, task_object);
+        task_object.connect_notify_for(
+            &links_label,
+            "content",
+            clone!(@weak links_label => move |task_object| {
+                update_links(&links_label, task_object);
+            })
+        );
+
+        links_label
+    }
+
+    pub(crate) fn update_due_label(due_label: &Label, task_object: &TaskObject) {
+        let due_date = task_object.due_date();
+        let mut label = utils::display_date(&due_date);
+        if let Some(recurrence) = task_object.recurrence_rule() {
+            if !label.is_empty() {
+                label.push_str(" · ");
+            }
+            label.push_str(&recurrence.label());
+        }
+
+        due_label.set_label(&label);
+        due_label.set_visible(!label.is_empty());
+
+        // Red when overdue, orange when due today and neutral for later days
+        let due_today = !task_object.is_completed() && task_object.is_due_today();
+        for (css_class, active) in [("overdue", task_object.is_overdue()), ("due-today", due_today)] {
+            if active {
+                due_label.add_css_class(css_class);
+            } else {
+                due_label.remove_css_class(css_class);
+            }
+        }
+    }
+
+    fn update_overdue_style(row: &ExpanderRow, task_object: &TaskObject) {
+        if task_object.is_overdue() {
+            row.add_css_class("overdue");
+        } else {
+            row.remove_css_class("overdue");
+        }
+    }
+
+    // Rebuilds the task rows, for what depends on the day like the due dates
+    pub(crate) fn refresh_task_rows(&self) {
+        if let Some(selection_model) = self.imp().current_selection_model.borrow().clone() {
+            let n_items = selection_model.n_items();
+            selection_model.items_changed(0, n_items, n_items);
+        }
+    }
+
+    pub(crate) fn create_due_date_button(&self, task_object: &TaskObject) -> MenuButton {
+        let calendar = Calendar::new();
+        let clear_button = Button::builder()
+            .label("Clear Due Date")
+            .build();
+        clear_button.add_css_class("flat");
+
+        let repeat_labels: Vec<String> = std::iter::once("Does Not Repeat".to_string())
+            .chain(Recurrence::PRESETS.iter().map(Recurrence::label))
+            .chain(std::iter::once("Custom".to_string()))
+            .collect();
+        let repeat_labels: Vec<&str> = repeat_labels.iter().map(String::as_str).collect();
+        let repeat_dropdown = DropDown::from_strings(&repeat_labels);
+        let custom_position = repeat_labels.len() as u32 - 1;
+
+        let rule_entry = Entry::builder()
+            .placeholder_text("RRULE, e.g. FREQ=WEEKLY;INTERVAL=2")
+            .visible(false)
+            .build();
+
+        let reminder_entry = Entry::builder()
+            .placeholder_text("Remind at HH:MM")
+            .primary_icon_name("alarm-symbolic")
+            .build();
+
+        let popover_box = gtk::Box::builder()
+            .orientation(gtk::Orientation::Vertical)
+            .spacing(6)
+            .build();
+        popover_box.append(&calendar);
+        popover_box.append(&clear_button);
+        popover_box.append(&repeat_dropdown);
+        popover_box.append(&rule_entry);
+        popover_box.append(&reminder_entry);
+
+        let popover = Popover::builder()
+            .child(&popover_box)
+            .build();
+
+        let due_button = MenuButton::builder()
+            .icon_name("x-office-calendar-symbolic")
+            .valign(Align::Center)
+            .tooltip_text("Set Due Date")
+            .popover(&popover)
+            .build();
+        due_button.add_css_class("flat");
+
+        popover.connect_show(
+            clone!(@weak calendar, @weak clear_button, @weak repeat_dropdown, @weak rule_entry, @weak reminder_entry,
+                @weak task_object => move |_| {
+                if let Some(due_date) = utils::parse_date(&task_object.due_date()) {
+                    calendar.select_day(&due_date);
+                }
+                clear_button.set_sensitive(task_object.has_due_date());
+
+                let recurrence = task_object.recurrence_rule();
+                let position = match recurrence {
+                    None => 0,
+                    Some(recurrence) => Recurrence::PRESETS
+                        .iter()
+                        .position(|preset| *preset == recurrence)
+                        .map_or(custom_position, |index| index as u32 + 1),
+                };
+                rule_entry.set_text(&task_object.recurrence());
+                reminder_entry.set_text(&task_object.reminder_time());
+                reminder_entry.set_sensitive(task_object.has_due_date());
+                rule_entry.set_visible(position == custom_position);
+                repeat_dropdown.set_selected(position);
+            })
+        );
+
+        repeat_dropdown.connect_selected_notify(
+            clone!(@weak self as window, @weak rule_entry, @weak task_object => move |repeat_dropdown| {
+                let position = repeat_dropdown.selected();
+                rule_entry.set_visible(position == custom_position);
+
+                let recurrence = match position {
+                    0 => String::new(),
+                    position if position == custom_position => return,
+                    position => Recurrence::PRESETS[position as usize - 1].to_rrule(),
+                };
+
+                if recurrence != task_object.recurrence() {
+                    window.set_task_property(&task_object, "recurrence", recurrence.to_value());
+                }
+            })
+        );
+
+        reminder_entry.connect_activate(
+            clone!(@weak self as window, @weak popover, @weak task_object => move |reminder_entry| {
+                let text = reminder_entry.text();
+                let reminder_time = if text.trim().is_empty() {
+                    String::new()
+                } else if let Some(reminder_time) = reminders::parse_time(&text) {
+                    reminder_time
+                } else {
+                    reminder_entry.add_css_class("error");
+                    return;
+                };
+                reminder_entry.remove_css_class("error");
+
+                if reminder_time != task_object.reminder_time() {
+                    window.set_task_property(&task_object, "reminder-time", reminder_time.to_value());
+                }
+                popover.popdown();
+            })
+        );
+
+        rule_entry.connect_activate(
+            clone!(@weak self as window, @weak task_object => move |rule_entry| {
+                let Some(recurrence) = Recurrence::parse(&rule_entry.text()) else {
+                    rule_entry.add_css_class("error");
+                    return;
+                };
+                rule_entry.remove_css_class("error");
+
+                let recurrence = recurrence.to_rrule();
+                if recurrence != task_object.recurrence() {
+                    window.set_task_property(&task_object, "recurrence", recurrence.to_value());
+                }
+            })
+        );
+
+        calendar.connect_day_selected(
+            clone!(@weak self as window, @weak popover, @weak task_object => move |calendar| {
+                let due_date = utils::format_date(&calendar.date());
+                // Selecting the stored date when the popover opens should not close it
+                if due_date != task_object.due_date() {
+                    window.set_task_property(&task_object, "due-date", due_date.to_value());
+                    popover.popdown();
+                }
+            })
+        );
+
+        clear_button.connect_clicked(
+            clone!(@weak self as window, @weak popover, @weak task_object => move |_| {
+                window.set_task_property(&task_object, "due-date", String::new().to_value());
+                popover.popdown();
+            })
+        );
+
+        due_button
+    }
+
+    // When the task was completed, or else the first line of the notes
+    fn update_task_subtitle(row: &ExpanderRow, task_object: &TaskObject) {
+        let completed_on = utils::local_date(&task_object.completed_at())
+            .filter(|_| task_object.is_completed());
+        let subtitle = match completed_on {
+            Some(date) => format!("Completed {}", utils::display_date(&date)),
+            None => task_object.notes().lines().next().unwrap_or_default().to_string(),
+        };
+        row.set_subtitle(&subtitle);
+    }
+
+    // The content in full, the start of the notes, the due date and the tags
+    fn update_task_tooltip(row: &ExpanderRow, task_object: &TaskObject) {
+        if row.is_expanded() {
+            row.set_tooltip_markup(None);
+            return;
+        }
+
+        let mut lines = vec![format!("<b>{}</b>", glib::markup_escape_text(&task_object.content()))];
+        let notes = task_object.notes();
+        let notes_lines = notes
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .take(TOOLTIP_NOTES_LINES);
+        lines.extend(notes_lines.map(|line| glib::markup_escape_text(line).to_string()));
+
+        let mut details = Vec::new();
+        let due_date = task_object.due_date();
+        if !due_date.is_empty() {
+            details.push(format!("Due {}", utils::display_date(&due_date)));
+        }
+        details.extend(task_object.tags().iter().map(|tag| format!("#{tag}")));
+        if !details.is_empty() {
+            lines.push(format!("<small>{}</small>", glib::markup_escape_text(&details.join(" · "))));
+        }
+
+        row.set_tooltip_markup(Some(&lines.join("\n")));
+    }
+
+    // Fades `row` out before `remove` takes it from the list, instead of it vanishing at once
+    fn fade_out_row(row: &ExpanderRow, remove: impl FnOnce() + 'static) {
+        if row.has_css_class("leaving") {
+            return;
+        }
+        row.add_css_class("leaving");
+        glib::timeout_add_local_once(Duration::from_millis(ROW_ANIMATION_MS), remove);
+    }
+
+    // Done tasks are struck through and dimmed, so they stand apart from the open ones
+    fn update_completed_style(row: &ExpanderRow, task_object: &TaskObject) {
+        if task_object.is_completed() {
+            row.add_css_class("completed");
+        } else {
+            row.remove_css_class("completed");
+        }
+    }
+
+    pub(crate) fn update_blocked_style(&self, row: &ExpanderRow, task_object: &TaskObject) {
+        if self.is_blocked(task_object) {
+            row.add_css_class("blocked");
+        } else {
+            row.remove_css_class("blocked");
+        }
+    }
+
+    pub(crate) fn update_tags_box(tags_box: &gtk::Box, task_object: &TaskObject) {
+        while let Some(child) = tags_box.first_child() {
+            tags_box.remove(&child);
+        }
+
+        for tag in task_object.tags() {
+            let tag_label = Label::new(Some(&format!("#{tag}")));
+            tag_label.add_css_class("tag");
+            tag_label.add_css_class("caption");
+            tags_box.append(&tag_label);
+        }
+    }
+
+    pub(crate) fn create_tags_button(&self, task_object: &TaskObject) -> MenuButton {
+        let tag_entry = Entry::builder()
+            .placeholder_text("Add Tag")
+            .build();
+
+        let tags_list = gtk::Box::builder()
+            .orientation(gtk::Orientation::Vertical)
+            .build();
+
+        let popover_box = gtk::Box::builder()
+            .orientation(gtk::Orientation::Vertical)
+            .spacing(6)
+            .build();
+        popover_box.append(&tag_entry);
+        popover_box.append(&tags_list);
+
+        let popover = Popover::builder()
+            .child(&popover_box)
+            .build();
+
+        let tags_button = MenuButton::builder()
+            .icon_name("tag-symbolic")
+            .valign(Align::Center)
+            .tooltip_text("Edit Tags")
+            .popover(&popover)
+            .build();
+        tags_button.add_css_class("flat");
+
+        popover.connect_show(clone!(@weak self as window, @weak tags_list, @weak task_object => move |_| {
+            window.update_tags_list(&tags_list, &task_object);
+        }));
+
+        tag_entry.connect_activate(
+            clone!(@weak self as window, @weak tags_list, @weak task_object => move |tag_entry| {
+                let tag = normalize_tag(&tag_entry.text());
+                tag_entry.set_text("");
+                if tag.is_empty() || task_object.has_tag(&tag) {
+                    return;
+                }
+
+                let mut tags = task_object.tags();
+                tags.push(tag);
+                window.set_task_property(&task_object, "tags", tags.to_value());
+                window.update_tags_list(&tags_list, &task_object);
+            })
+        );
+
+        tags_button
+    }
+
+    // Lists the tags of a task in its tag popover, each with a button to remove it
+    fn update_tags_list(&self, tags_list: &gtk::Box, task_object: &TaskObject) {
+        while let Some(child) = tags_list.first_child() {
+            tags_list.remove(&child);
+        }
+
+        for tag in task_object.tags() {
+            let tag_label = Label::builder()
+                .label(format!("#{tag}"))
+                .xalign(0.0)
+                .hexpand(true)
+                .build();
+
+            let remove_button = Button::builder()
+                .icon_name("window-close-symbolic")
+                .tooltip_text("Remove Tag")
+                .build();
+            remove_button.add_css_class("flat");
+            remove_button.add_css_class("circular");
+
+            remove_button.connect_clicked(
+                clone!(@weak self as window, @weak tags_list, @weak task_object => move |_| {
+                    let tags: Vec<String> = task_object
+                        .tags()
+                        .into_iter()
+                        .filter(|task_tag| *task_tag != tag)
+                        .collect();
+                    window.set_task_property(&task_object, "tags", tags.to_value());
+                    window.update_tags_list(&tags_list, &task_object);
+                })
+            );
+
+            let tag_box = gtk::Box::builder()
+                .spacing(6)
+                .build();
+            tag_box.append(&tag_label);
+            tag_box.append(&remove_button);
+            tags_list.append(&tag_box);
+        }
+    }
+
+    fn create_star_button(&self, task_object: &TaskObject) -> Button {
+        let star_button = Button::builder()
+            .valign(Align::Center)
+            .build();
+        star_button.add_css_class("flat");
+        star_button.connect_clicked(clone!(@weak self as window, @weak task_object => move |_| {
+            window.set_task_property(&task_object, "starred", (!task_object.is_starred()).to_value());
+        }));
+
+        Self::update_star_button(&star_button, task_object.is_starred());
+        task_object.connect_notify_for(
+            &star_button,
+            "starred",
+            clone!(@weak star_button => move |task_object| {
+                Self::update_star_button(&star_button, task_object.is_starred());
+            })
+        );
+
+        star_button
+    }
+
+    fn update_star_button(star_button: &Button, starred: bool) {
+        if starred {
+            star_button.set_icon_name("starred-symbolic");
+            star_button.set_tooltip_text(Some("Unstar"));
+            star_button.add_css_class("starred");
+        } else {
+            star_button.set_icon_name("non-starred-symbolic");
+            star_button.set_tooltip_text(Some("Star"));
+            star_button.remove_css_class("starred");
+        }
+    }
+
+    pub(crate) fn create_priority_button(&self, task_object: &TaskObject) -> MenuButton {
+        let popover_box = gtk::Box::builder()
+            .orientation(gtk::Orientation::Vertical)
+            .build();
+
+        let popover = Popover::builder()
+            .child(&popover_box)
+            .build();
+        popover.add_css_class("menu");
+
+        for priority in Priority::ALL {
+            let button = Button::builder()
+                .label(priority.label())
+                .build();
+            button.add_css_class("flat");
+            button.connect_clicked(
+                clone!(@weak self as window, @weak popover, @weak task_object => move |_| {
+                    if task_object.priority() != priority {
+                        window.set_task_property(&task_object, "priority", priority.to_value());
+                    }
+                    popover.popdown();
+                })
+            );
+            popover_box.append(&button);
+        }
+
+        let priority_button = MenuButton::builder()
+            .icon_name("emblem-important-symbolic")
+            .valign(Align::Center)
+            .tooltip_text("Set Priority")
+            .popover(&popover)
+            .build();
+        priority_button.add_css_class("flat");
+
+        Self::update_priority_button(&priority_button, task_object.priority());
+        task_object.connect_notify_for(
+            &priority_button,
+            "priority",
+            clone!(@weak priority_button => move |task_object| {
+                Self::update_priority_button(&priority_button, task_object.priority());
+            })
+        );
+
+        priority_button
+    }
+
+    fn update_priority_button(priority_button: &MenuButton, priority: Priority) {
+        for other in Priority::ALL {
+            if let Some(css_class) = other.css_class() {
+                priority_button.remove_css_class(css_class);
+            }
+        }
+
+        if let Some(css_class) = priority.css_class() {
+            priority_button.add_css_class(css_class);
+        }
+    }
+
+    // Focuses the task row at `index`, or the last one when it is past the end
+    fn focus_task_row(&self, index: i32) {
+        let Some(sort_model) = self.imp().current_sort_model.borrow().clone() else {
+            return;
+        };
+        let last_index = sort_model.n_items() as i32 - 1;
+        if let Some(row) = self.imp().tasks_list.row_at_index(index.min(last_index).max(0)) {
+            row.grab_focus();
+        }
+    }
+
+    fn start_task_edit(row: &ExpanderRow, edit_entry: &Entry, edit_button: &Button, task_object: &TaskObject) {
+        if edit_entry.is_visible() {
+            return;
+        }
+
+        edit_entry.set_text(&task_object.content());
+        row.set_title("");
+        edit_button.set_visible(false);
+        edit_entry.set_visible(true);
+        edit_entry.grab_focus();
+    }
+
+    fn finish_task_edit(
+        &self,
+        row: &ExpanderRow,
+        edit_entry: &Entry,
+        edit_button: &Button,
+        task_object: &TaskObject,
+        save: bool,
+    ) {
+        if !edit_entry.is_visible() {
+            return;
+        }
+
+        edit_entry.set_visible(false);
+        edit_button.set_visible(true);
+
+        let content = edit_entry.text().trim().to_string();
+        if save && !content.is_empty() && content != task_object.content() {
+            self.set_task_property(task_object, "content", content.to_value());
+        }
+
+        // The binding only fires on changes, so restore the title ourselves
+        row.set_title(&task_object.content());
+        row.grab_focus();
+    }
+
+    // The row showing `task` in the current list, if any
+    pub(crate) fn task_row(&self, task: &TaskObject) -> Option<ExpanderRow> {
+        let selection_model = self.imp().current_selection_model.borrow().clone()?;
+        let position = selection_model
+            .iter::<glib::Object>()
+            .position(|item| item.ok().as_ref() == Some(task.upcast_ref()))?;
+        self.imp().tasks_list.row_at_index(position as i32).and_downcast::<ExpanderRow>()
+    }
+}
@@ -1,3 +1,4 @@
+mod expander_row;
 mod imp;
 
 use glib::Object;
@@ -0,0 +1,162 @@
+use crate::collection_object::CollectionData;
+use crate::task_object::{normalize_tag, Priority, TaskData};
+use crate::utils;
+
+// Tasks without a +project are imported into this collection
+pub const DEFAULT_PROJECT: &str = "Inbox";
+
+// Parses todo.txt lines ("x 2023-05-02 (A) call mom +Family @phone due:2023-05-01")
+// into one collection per project. Contexts become tags, any further projects too.
+pub fn parse(contents: &str) -> Vec<CollectionData> {
+    let mut collections: Vec<CollectionData> = Vec::new();
+
+    for line in contents.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        let (project, task_data) = parse_line(line);
+        let title = project.unwrap_or_else(|| DEFAULT_PROJECT.to_string());
+
+        match collections.iter_mut().find(|collection| collection.title == title) {
+            Some(collection) => collection.tasks_data.push(task_data),
+            None => collections.push(CollectionData {
+                uuid: utils::new_uuid(),
+                title,
+                tasks_data: vec![task_data],
+                ..Default::default()
+            }),
+        }
+    }
+
+    collections
+}
+
+fn parse_line(line: &str) -> (Option<String>, TaskData) {
+    let mut words = line.split_whitespace().peekable();
+    let mut task_data = TaskData {
+        uuid: utils::new_uuid(),
+        ..Default::default()
+    };
+
+    if words.peek() == Some(&"x") {
+        task_data.completed = true;
+        words.next();
+    }
+    if let Some(priority) = words.peek().and_then(|word| parse_priority(word)) {
+        task_data.priority = priority;
+        words.next();
+    }
+    // Completion and creation dates, the app keeps neither
+    while words.peek().is_some_and(|word| is_date(word)) {
+        words.next();
+    }
+
+    let mut project = None;
+    let mut content_words = Vec::new();
+    for word in words {
+        if let Some(name) = word.strip_prefix('+').filter(|name| !name.is_empty()) {
+            // Spaces are written as underscores, as projects are single words
+            let name = name.replace('_', " ");
+            if project.is_none() {
+                project = Some(name);
+            } else {
+                add_tag(&mut task_data, &name);
+            }
+        } else if let Some(context) = word.strip_prefix('@').filter(|context| !context.is_empty()) {
+            add_tag(&mut task_data, context);
+        } else if let Some(due_date) = word.strip_prefix("due:").filter(|date| is_date(date)) {
+            task_data.due_date = due_date.to_string();
+        } else if let Some(start_date) = word.strip_prefix("t:").filter(|date| is_date(date)) {
+            // The threshold date of the todo.txt extensions
+            task_data.start_date = start_date.to_string();
+        } else if let Some(priority) = word.strip_prefix("pri:").and_then(parse_priority_letter) {
+            // Done tasks keep their priority in a "pri:" tag
+            task_data.priority = priority;
+        } else {
+            content_words.push(word);
+        }
+    }
+
+    task_data.content = content_words.join(" ");
+    (project, task_data)
+}
+
+fn add_tag(task_data: &mut TaskData, tag: &str) {
+    let tag = normalize_tag(tag);
+    if !tag.is_empty() && !task_data.tags.contains(&tag) {
+        task_data.tags.push(tag);
+    }
+}
+
+fn is_date(word: &str) -> bool {
+    word.len() == "YYYY-MM-DD".len() && utils::parse_date(word).is_some()
+}
+
+// "(A)"
+fn parse_priority(word: &str) -> Option<Priority> {
+    word.strip_prefix('(')?
+        .strip_suffix(')')
+        .and_then(parse_priority_letter)
+}
+
+// Only three priorities are known, everything below B is low
+fn parse_priority_letter(letter: &str) -> Option<Priority> {
+    match letter.chars().collect::<Vec<char>>().as_slice() {
+        ['A'] => Some(Priority::High),
+        ['B'] => Some(Priority::Medium),
+        [letter] if letter.is_ascii_uppercase() => Some(Priority::Low),
+        _ => None,
+    }
+}
+
+fn priority_letter(priority: Priority) -> Option<char> {
+    match priority {
+        Priority::None => None,
+        Priority::Low => Some('C'),
+        Priority::Medium => Some('B'),
+        Priority::High => Some('A'),
+    }
+}
+
+// One line per task, with the collection as its project. Notes have no place in
+// the format and are left out.
+pub fn to_string(collections: &[CollectionData]) -> String {
+    let mut contents = String::new();
+
+    for collection in collections {
+        let project = collection.title.split_whitespace().collect::<Vec<&str>>().join("_");
+        for task_data in &collection.tasks_data {
+            contents.push_str(&format_line(task_data, &project));
+            contents.push('\n');
+        }
+    }
+
+    contents
+}
+
+fn format_line(task_data: &TaskData, project: &str) -> String {
+    let mut words: Vec<String> = Vec::new();
+
+    let priority = priority_letter(task_data.priority);
+    if task_data.completed {
+        words.push("x".to_string());
+    } else if let Some(priority) = priority {
+        words.push(format!("({priority})"));
+    }
+
+    words.push(task_data.content.clone());
+    if !project.is_empty() {
+        words.push(format!("+{project}"));
+    }
+    for tag in &task_data.tags {
+        words.push(format!("@{}", tag.split_whitespace().collect::<Vec<&str>>().join("_")));
+    }
+    if !task_data.due_date.is_empty() {
+        words.push(format!("due:{}", task_data.due_date));
+    }
+    if !task_data.start_date.is_empty() {
+        words.push(format!("t:{}", task_data.start_date));
+    }
+    if let Some(priority) = priority.filter(|_| task_data.completed) {
+        words.push(format!("pri:{priority}"));
+    }
+
+    words.join(" ")
+}
@@ -0,0 +1,159 @@
+use adw::prelude::*;
+use gtk::{gio, glib};
+
+use crate::collection_object::CollectionObject;
+use crate::task_object::TaskObject;
+
+// A reversible change to the model. Positions are recorded in ascending order,
+// so re-inserting them front to back restores the original layout.
+#[derive(Debug)]
+pub enum Command {
+    InsertTasks {
+        tasks: gio::ListStore,
+        items: Vec<(u32, TaskObject)>,
+    },
+    RemoveTasks {
+        tasks: gio::ListStore,
+        items: Vec<(u32, TaskObject)>,
+    },
+    EditTask {
+        task: TaskObject,
+        property: &'static str,
+        old_value: glib::Value,
+        new_value: glib::Value,
+    },
+    EditCollection {
+        collection: CollectionObject,
+        property: &'static str,
+        old_value: glib::Value,
+        new_value: glib::Value,
+    },
+    MoveTask {
+        task: TaskObject,
+        from: gio::ListStore,
+        from_position: u32,
+        to: gio::ListStore,
+        to_position: u32,
+    },
+    InsertCollection {
+        collections: gio::ListStore,
+        position: u32,
+        collection: CollectionObject,
+    },
+    RemoveCollection {
+        collections: gio::ListStore,
+        position: u32,
+        collection: CollectionObject,
+    },
+    // Several commands that are undone and redone as one step
+    Group(Vec<Command>),
+}
+
+impl Command {
+    pub fn undo(&self) {
+        match self {
+            Command::InsertTasks { tasks, items } => remove_items(tasks, items),
+            Command::RemoveTasks { tasks, items } => insert_items(tasks, items),
+            Command::EditTask { task, property, old_value, .. } => {
+                task.set_property_from_value(property, old_value);
+            }
+            Command::EditCollection { collection, property, old_value, .. } => {
+                collection.set_property_from_value(property, old_value);
+            }
+            Command::MoveTask { task, from, from_position, to, to_position } => {
+                to.remove(*to_position);
+                from.insert(*from_position, task);
+            }
+            Command::InsertCollection { collections, position, .. } => {
+                collections.remove(*position);
+            }
+            Command::RemoveCollection { collections, position, collection } => {
+                collections.insert(*position, collection);
+            }
+            Command::Group(commands) => {
+                for command in commands.iter().rev() {
+                    command.undo();
+                }
+            }
+        }
+    }
+
+    pub fn redo(&self) {
+        match self {
+            Command::InsertTasks { tasks, items } => insert_items(tasks, items),
+            Command::RemoveTasks { tasks, items } => remove_items(tasks, items),
+            Command::EditTask { task, property, new_value, .. } => {
+                task.set_property_from_value(property, new_value);
+            }
+            Command::EditCollection { collection, property, new_value, .. } => {
+                collection.set_property_from_value(property, new_value);
+            }
+            Command::MoveTask { task, from, from_position, to, to_position } => {
+                from.remove(*from_position);
+                to.insert(*to_position, task);
+            }
+            Command::InsertCollection { collections, position, collection } => {
+                collections.insert(*position, collection);
+            }
+            Command::RemoveCollection { collections, position, .. } => {
+                collections.remove(*position);
+            }
+            Command::Group(commands) => {
+                for command in commands {
+                    command.redo();
+                }
+            }
+        }
+    }
+}
+
+fn insert_items(tasks: &gio::ListStore, items: &[(u32, TaskObject)]) {
+    for (position, task) in items {
+        tasks.insert(*position, task);
+    }
+}
+
+fn remove_items(tasks: &gio::ListStore, items: &[(u32, TaskObject)]) {
+    for (position, _) in items.iter().rev() {
+        tasks.remove(*position);
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct UndoStack {
+    undo: Vec<Command>,
+    redo: Vec<Command>,
+}
+
+impl UndoStack {
+    pub fn push(&mut self, command: Command) {
+        self.undo.push(command);
+        self.redo.clear();
+    }
+
+    // Commands are handed out instead of executed here, so that signal handlers
+    // triggered by undoing them can still access the stack
+    pub fn pop_undo(&mut self) -> Option<Command> {
+        self.undo.pop()
+    }
+
+    pub fn pop_redo(&mut self) -> Option<Command> {
+        self.redo.pop()
+    }
+
+    pub fn push_undone(&mut self, command: Command) {
+        self.redo.push(command);
+    }
+
+    pub fn push_redone(&mut self, command: Command) {
+        self.undo.push(command);
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+}
@@ -11,3 +11,122 @@ pub fn data_path() -> PathBuf {
     path.push("data.json");
     path
 }
+
+// Used instead of `data_path` while the data is encrypted
+pub fn encrypted_data_path() -> PathBuf {
+    data_path().with_extension("json.age")
+}
+
+pub fn new_uuid() -> String {
+    glib::uuid_string_random().to_string()
+}
+
+pub fn now() -> glib::DateTime {
+    glib::DateTime::now_local().expect("Could not get the current time")
+}
+
+pub fn format_date(date: &glib::DateTime) -> String {
+    date.format("%Y-%m-%d")
+        .expect("Could not format date")
+        .to_string()
+}
+
+// "YYYY-MM-DDTHH:MM:SS.ffffffZ" in UTC, so timestamps from different devices compare
+// as strings. Edits within one second still get told apart when merging.
+pub fn timestamp() -> String {
+    glib::DateTime::now_utc()
+        .and_then(|now| now.format("%Y-%m-%dT%H:%M:%S.%fZ"))
+        .expect("Could not format the current time")
+        .to_string()
+}
+
+// The local "YYYY-MM-DD" of a timestamp as returned by `timestamp`
+pub fn local_date(timestamp: &str) -> Option<String> {
+    let date = glib::DateTime::from_iso8601(timestamp, None).ok()?.to_local().ok()?;
+    Some(format_date(&date))
+}
+
+pub fn today() -> String {
+    format_date(&now())
+}
+
+pub fn parse_date(date: &str) -> Option<glib::DateTime> {
+    let mut parts = date.splitn(3, '-').map(|part| part.parse::<i32>().ok());
+    let (Some(Some(year)), Some(Some(month)), Some(Some(day))) = (parts.next(), parts.next(), parts.next())
+    else {
+        return None;
+    };
+
+    glib::DateTime::from_local(year, month, day, 0, 0, 0.0).ok()
+}
+
+// Human friendly representation of a "YYYY-MM-DD" date for the task rows
+pub fn display_date(date: &str) -> String {
+    let Some(parsed) = parse_date(date) else {
+        return String::new();
+    };
+
+    let today = today();
+    let tomorrow = now().add_days(1).map(|date| format_date(&date)).unwrap_or_default();
+    let yesterday = now().add_days(-1).map(|date| format_date(&date)).unwrap_or_default();
+
+    if date == today {
+        "Today".to_string()
+    } else if date == tomorrow {
+        "Tomorrow".to_string()
+    } else if date == yesterday {
+        "Yesterday".to_string()
+    } else if parsed.year() == now().year() {
+        parsed.format("%b %-d").map(|date| date.to_string()).unwrap_or_default()
+    } else {
+        parsed.format("%b %-d, %Y").map(|date| date.to_string()).unwrap_or_default()
+    }
+}
+
+// "1 done task", "3 done tasks"
+pub fn count_label(count: usize, noun: &str) -> String {
+    if count == 1 {
+        format!("1 {noun}")
+    } else {
+        format!("{count} {noun}s")
+    }
+}
+
+// "45 min", "2 h 5 min"
+pub fn format_minutes(minutes: u64) -> String {
+    match (minutes / 60, minutes % 60) {
+        (0, minutes) => format!("{minutes} min"),
+        (hours, 0) => format!("{hours} h"),
+        (hours, minutes) => format!("{hours} h {minutes} min"),
+    }
+}
+
+// A web address on its own, like "https://gnome.org/"
+pub fn is_url(text: &str) -> bool {
+    let text = text.trim();
+    (text.starts_with("http://") || text.starts_with("https://")) && !text.contains(char::is_whitespace)
+}
+
+// Markup for a label with the web addresses in `text` as links, `None` if there are none
+pub fn link_markup(text: &str) -> Option<String> {
+    let mut markup = String::new();
+    let mut has_links = false;
+
+    for (index, word) in text.split(' ').enumerate() {
+        if index > 0 {
+            markup.push(' ');
+        }
+        // Punctuation after an address belongs to the sentence
+        let url = word.trim_end_matches(['.', ',', ';', ':', '!', '?', ')']);
+        if is_url(url) {
+            let escaped_url = glib::markup_escape_text(url);
+            markup.push_str(&format!("<a href=\"{escaped_url}\">{escaped_url}</a>"));
+            markup.push_str(&glib::markup_escape_text(&word[url.len()..]));
+            has_links = true;
+        } else {
+            markup.push_str(&glib::markup_escape_text(word));
+        }
+    }
+
+    has_links.then_some(markup)
+}
@@ -0,0 +1,222 @@
+use super::*;
+
+const LOCK_CHECK_INTERVAL_SECONDS: u32 = 30;
+
+// Hiding the tasks behind a passphrase after some idle time
+impl TodoWindow {
+    pub fn is_lock_enabled(&self) -> bool {
+        self.settings().boolean("lock-enabled")
+    }
+
+    pub async fn enable_lock(&self) -> bool {
+        let Some(password) = self
+            .choose_passphrase(
+                "Lock To-Do",
+                "The password is asked for on startup and after a while without use.",
+                "Lock",
+            )
+            .await
+        else {
+            return false;
+        };
+
+        if let Err(err) = passphrase::remember(Purpose::Lock, &password).await {
+            glib::g_warning!("todo", "Could not store the lock password: {}", err);
+            self.imp().toast_overlay.add_toast(Toast::new("Could not store the password"));
+            return false;
+        }
+        if let Err(err) = self.settings().set_boolean("lock-enabled", true) {
+            glib::g_warning!("todo", "Could not turn on the lock: {}", err);
+            return false;
+        }
+        true
+    }
+
+    pub async fn disable_lock(&self) {
+        if let Err(err) = self.settings().set_boolean("lock-enabled", false) {
+            glib::g_warning!("todo", "Could not turn off the lock: {}", err);
+            return;
+        }
+        if let Err(err) = passphrase::forget(Purpose::Lock).await {
+            glib::g_warning!("todo", "Could not remove the lock password: {}", err);
+        }
+    }
+
+    pub(super) fn setup_lock(&self) {
+        self.imp().last_activity.set(glib::monotonic_time());
+
+        // Any input counts as use, it is only observed on its way to the widgets
+        let activity_controller = gtk::EventControllerLegacy::new();
+        activity_controller.set_propagation_phase(gtk::PropagationPhase::Capture);
+        activity_controller.connect_event(clone!(@weak self as window => @default-return glib::Propagation::Proceed, move |_, _| {
+            window.imp().last_activity.set(glib::monotonic_time());
+            glib::Propagation::Proceed
+        }));
+        self.add_controller(activity_controller);
+
+        glib::timeout_add_seconds_local(
+            LOCK_CHECK_INTERVAL_SECONDS,
+            clone!(@weak self as window => @default-return glib::ControlFlow::Break, move || {
+                let timeout_minutes = i64::from(window.settings().uint("lock-timeout-minutes"));
+                let idle = glib::monotonic_time() - window.imp().last_activity.get();
+                if window.is_lock_enabled() && idle >= timeout_minutes * 60 * 1_000_000 {
+                    window.lock();
+                }
+                glib::ControlFlow::Continue
+            })
+        );
+
+        self.imp().lock_entry.connect_activate(clone!(@weak self as window => move |_| {
+            window.unlock();
+        }));
+        self.imp().unlock_button.connect_clicked(clone!(@weak self as window => move |_| {
+            window.unlock();
+        }));
+
+        if self.is_lock_enabled() {
+            self.lock();
+        }
+    }
+
+    fn lock(&self) {
+        if self.imp().locked.replace(true) {
+            return;
+        }
+
+        self.imp().lock_entry.set_text("");
+        self.imp().lock_entry.remove_css_class("error");
+        self.imp().stack.set_visible_child_name("lock");
+        self.imp().lock_entry.grab_focus();
+    }
+
+    fn unlock(&self) {
+        let password = self.imp().lock_entry.text().to_string();
+        self.imp().unlock_button.set_sensitive(false);
+
+        glib::MainContext::default().spawn_local(clone!(@weak self as window => async move {
+            let stored_password = passphrase::lookup(Purpose::Lock).await;
+            window.imp().unlock_button.set_sensitive(true);
+
+            match stored_password {
+                Ok(Some(stored_password)) if stored_password != password => {
+                    window.imp().lock_entry.add_css_class("error");
+                    window.imp().lock_entry.grab_focus();
+                }
+                // Any input would open a lock without password, it is turned off for the next start instead
+                Ok(None) => {
+                    window.disable_lock().await;
+                    window.imp().lock_entry.add_css_class("error");
+                    window
+                        .imp()
+                        .toast_overlay
+                        .add_toast(Toast::new("The lock password is missing, the lock is off from the next start"));
+                }
+                Ok(Some(_)) => {
+                    window.imp().locked.set(false);
+                    window.imp().last_activity.set(glib::monotonic_time());
+                    window.set_stack();
+                }
+                Err(err) => {
+                    glib::g_warning!("todo", "Could not look up the lock password: {}", err);
+                    window.imp().toast_overlay.add_toast(Toast::new("Could not check the password"));
+                }
+            }
+        }));
+    }
+
+    // The data stays locked until the passphrase is given, remembered ones are tried first
+    pub(super) async fn unlock_storage(&self) {
+        let remembered = passphrase::lookup(Purpose::Data).await.unwrap_or_else(|err| {
+            glib::g_warning!("todo", "Could not look up the passphrase: {}", err);
+            None
+        });
+        if let Some(passphrase) = remembered {
+            if self.load_encrypted(&passphrase).await {
+                return;
+            }
+        }
+
+        let passphrase_entry = PasswordEntry::builder()
+            .placeholder_text("Passphrase")
+            .show_peek_icon(true)
+            .activates_default(true)
+            .build();
+        let remember_button = CheckButton::builder()
+            .label("_Remember until logging out")
+            .use_underline(true)
+            .active(true)
+            .build();
+        let content = gtk::Box::builder()
+            .orientation(gtk::Orientation::Vertical)
+            .spacing(12)
+            .build();
+        content.append(&passphrase_entry);
+        content.append(&remember_button);
+
+        let quit_response = "quit";
+        let unlock_response = "unlock";
+
+        let dialog = MessageDialog::builder()
+            .heading("Unlock Tasks")
+            .body("The tasks are encrypted")
+            .transient_for(self)
+            .modal(true)
+            .destroy_with_parent(true)
+            .close_response(quit_response)
+            .default_response(unlock_response)
+            .extra_child(&content)
+            .build();
+        dialog.add_responses(&[(quit_response, "Quit"), (unlock_response, "Unlock")]);
+        dialog.set_response_appearance(unlock_response, ResponseAppearance::Suggested);
+
+        loop {
+            if dialog.choose_future().await != unlock_response {
+                if let Some(application) = self.application() {
+                    application.quit();
+                }
+                return;
+            }
+
+            let passphrase = passphrase_entry.text().to_string();
+            if self.load_encrypted(&passphrase).await {
+                if remember_button.is_active() {
+                    if let Err(err) = passphrase::remember(Purpose::Data, &passphrase).await {
+                        glib::g_warning!("todo", "Could not remember the passphrase: {}", err);
+                    }
+                }
+                return;
+            }
+
+            dialog.set_body("The passphrase is wrong");
+            passphrase_entry.set_text("");
+            passphrase_entry.grab_focus();
+        }
+    }
+
+    // Returns `false` if the passphrase is wrong
+    async fn load_encrypted(&self, passphrase: &str) -> bool {
+        let storage = self.storage();
+        storage.unlock(passphrase);
+
+        let result = gio::spawn_blocking(move || storage.load()).await;
+        match result {
+            Ok(Ok(store_data)) => {
+                self.imp().saving_blocked.set(false);
+                if let Some(store_data) = store_data {
+                    self.imp().stored_value.replace(serde_json::to_value(&store_data).ok());
+                    self.load_store_data(store_data);
+                }
+                true
+            }
+            Ok(Err(err)) if err.is::<WrongPassphrase>() => false,
+            Ok(Err(err)) => {
+                self.recover_from_load_error(err).await;
+                true
+            }
+            Err(_) => {
+                self.recover_from_load_error(anyhow::anyhow!("Could not read the tasks")).await;
+                true
+            }
+        }
+    }
+}
@@ -0,0 +1,5817 @@
+/* window/mod.rs
+ *
+ * Copyright 2023 Apostol Bakalov
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * SPDX-License-Identifier: GPLcollection_objects-3.0-or-later
+ */
+
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Context;
+
+use adw::subclass::prelude::*;
+use adw::{prelude::*, ActionRow, ExpanderRow, NavigationPage, NavigationSplitView, ResponseAppearance, MessageDialog, Toast, ToastOverlay};
+use ashpd::desktop::file_chooser::FileFilter;
+use gio::Settings;
+use gtk::{
+    gdk, gio, glib, pango, Entry, CustomFilter, CheckButton, MultiSelection, FilterListModel, Align,
+    ListBox, Stack, ListBoxRow, Label, Button, MenuButton,
+    Popover, Calendar, SortListModel, CustomSorter, TextView, SearchBar, SearchEntry, EveryFilter,
+    DropDown, MapListModel, FlattenListModel, ProgressBar, ToggleButton, PasswordEntry, TreeListModel,
+    TreeListRow
+};
+use gtk::glib::SignalHandlerId;
+use glib::clone;
+use std::cell::OnceCell;
+use crate::task_object::{normalize_tag, Priority, Subtask, Subtasks, TaskData, TaskObject};
+use crate::collection_object::{self, CollectionData, CollectionObject, DEFAULT_FILTER};
+use crate::attachments;
+use crate::backup::Backup;
+use crate::badge;
+use crate::calendar;
+use crate::csv;
+use crate::endeavour;
+use crate::file_chooser;
+use crate::focus::{self, Focus};
+use crate::ical;
+use crate::import;
+use crate::markdown;
+use crate::merge;
+use crate::migration::{StoreData, Tombstone};
+use crate::org;
+use crate::page_title;
+use crate::parser;
+use crate::passphrase::{self, Purpose};
+use crate::share;
+use crate::reminders;
+use crate::smart_collection_object::{SmartCollectionData, SmartCollectionObject};
+use crate::smart_view::{self, SmartView};
+use crate::stats;
+use crate::storage::{JsonStorage, Storage, WatchHandle, WrongPassphrase};
+use crate::preferences_window::PreferencesWindow;
+use crate::print;
+use crate::sync::{lan::LanSync, Account, SyncData};
+use crate::todo_txt;
+use crate::undo::{Command, UndoStack};
+use crate::APP_ID;
+use crate::utils::{self, data_path, encrypted_data_path};
+
+// Long enough to notice that more was removed than intended
+const UNDO_TOAST_TIMEOUT_SECONDS: u32 = 10;
+const TRASH_RETENTION_DAYS: i32 = 30;
+const TRASH_PURGE_INTERVAL_SECONDS: u32 = 60 * 60;
+// Completed tasks move down once the check mark had a moment to show
+const COMPLETED_SORT_DELAY_MS: u64 = 400;
+// Length of the fade of rows coming and going, as in style.css
+pub(crate) const ROW_ANIMATION_MS: u64 = 250;
+
+mod lock;
+mod storage;
+mod sync;
+
+mod imp {
+    use super::*;
+    #[derive(Debug, Default, gtk::CompositeTemplate)]
+    #[template(resource = "/org/gnome/ToDo/window.ui")]
+    pub struct TodoWindow {
+        pub settings: OnceCell<Settings>,
+        #[template_child]
+        pub entry: TemplateChild<Entry>,
+        #[template_child]
+        pub entry_date_chip: TemplateChild<Label>,
+        #[template_child]
+        pub tasks_list: TemplateChild<gtk::ListBox>,
+        #[template_child]
+        pub tasks_status_page: TemplateChild<adw::StatusPage>,
+        #[template_child]
+        pub tasks_status_button: TemplateChild<Button>,
+        pub tasks: RefCell<Option<gio::ListStore>>,
+        #[template_child]
+        pub collections_list: TemplateChild<ListBox>,
+        #[template_child]
+        pub split_view: TemplateChild<NavigationSplitView>,
+        #[template_child]
+        pub stack: TemplateChild<Stack>,
+        #[template_child]
+        pub lock_entry: TemplateChild<PasswordEntry>,
+        #[template_child]
+        pub unlock_button: TemplateChild<Button>,
+        pub collections:OnceCell<gio::ListStore>,
+        pub smart_collections: OnceCell<gio::ListStore>,
+        pub templates: RefCell<Vec<CollectionData>>,
+        // Pinned collections, folders and the collections outside of them
+        pub sidebar_items: OnceCell<gio::ListStore>,
+        pub sidebar_tree: OnceCell<TreeListModel>,
+        // Names of the folders whose collections are shown
+        pub expanded_folders: RefCell<HashSet<String>>,
+        pub current_collection: RefCell<Option<CollectionObject>>,
+        // Shown instead of a collection, `current_collection` is unset meanwhile
+        pub current_smart_view: RefCell<Option<SmartView>>,
+        // The tasks of every collection, for the smart views
+        pub all_tasks: OnceCell<FlattenListModel>,
+        pub current_filter_model: RefCell<Option<FilterListModel>>,
+        pub current_sort_model: RefCell<Option<SortListModel>>,
+        pub current_selection_model: RefCell<Option<MultiSelection>>,
+        pub progress_binding: RefCell<Option<glib::Binding>>,
+        // The task store being shown and the handler listening to its changes
+        pub tasks_changed_handler_id: RefCell<Option<(gio::ListModel, SignalHandlerId)>>,
+        #[template_child]
+        pub toast_overlay: TemplateChild<ToastOverlay>,
+        #[template_child]
+        pub search_bar: TemplateChild<SearchBar>,
+        #[template_child]
+        pub search_entry: TemplateChild<SearchEntry>,
+        #[template_child]
+        pub tag_filter_button: TemplateChild<MenuButton>,
+        #[template_child]
+        pub smart_list: TemplateChild<ListBox>,
+        #[template_child]
+        pub all_tasks_row: TemplateChild<ListBoxRow>,
+        #[template_child]
+        pub today_row: TemplateChild<ListBoxRow>,
+        #[template_child]
+        pub upcoming_row: TemplateChild<ListBoxRow>,
+        #[template_child]
+        pub starred_row: TemplateChild<ListBoxRow>,
+        #[template_child]
+        pub completed_row: TemplateChild<ListBoxRow>,
+        #[template_child]
+        pub smart_collections_list: TemplateChild<ListBox>,
+        #[template_child]
+        pub sidebar_footer: TemplateChild<ListBox>,
+        #[template_child]
+        pub trash_row: TemplateChild<ListBoxRow>,
+        #[template_child]
+        pub collection_progress: TemplateChild<ProgressBar>,
+        #[template_child]
+        pub collection_icon: TemplateChild<gtk::Image>,
+        #[template_child]
+        pub focus_button: TemplateChild<Button>,
+        #[template_child]
+        pub estimate_label: TemplateChild<Label>,
+        #[template_child]
+        pub selection_bar: TemplateChild<gtk::ActionBar>,
+        #[template_child]
+        pub selection_label: TemplateChild<Label>,
+        #[template_child]
+        pub tasks_page: TemplateChild<NavigationPage>,
+        #[template_child]
+        pub detail_split_view: TemplateChild<NavigationSplitView>,
+        #[template_child]
+        pub detail_stack: TemplateChild<Stack>,
+        #[template_child]
+        pub detail_box: TemplateChild<gtk::Box>,
+        #[template_child]
+        pub calendar_row: TemplateChild<ListBoxRow>,
+        #[template_child]
+        pub calendar_page: TemplateChild<NavigationPage>,
+        #[template_child]
+        pub calendar_title: TemplateChild<adw::WindowTitle>,
+        #[template_child]
+        pub calendar_grid: TemplateChild<gtk::Grid>,
+        #[template_child]
+        pub trash_page: TemplateChild<NavigationPage>,
+        #[template_child]
+        pub trash_stack: TemplateChild<Stack>,
+        #[template_child]
+        pub trash_list: TemplateChild<ListBox>,
+        pub tag_filter: RefCell<Option<String>>,
+        pub undo_stack: RefCell<UndoStack>,
+        pub save_source_id: RefCell<Option<glib::SourceId>>,
+        // Uuids of the tasks whose changes are already being listened to
+        pub watched_tasks: RefCell<HashSet<String>>,
+        // Replaced when encryption is turned on or off
+        pub storage: RefCell<Option<Arc<dyn Storage>>>,
+        pub storage_watch: RefCell<Option<WatchHandle>>,
+        pub storage_check_source_id: RefCell<Option<glib::SourceId>>,
+        // The data as last read from or written to storage, to tell our own writes apart
+        pub stored_value: RefCell<Option<serde_json::Value>>,
+        // Set while the user decides about data changed by someone else, so it is not overwritten
+        pub external_change_pending: Cell<bool>,
+        // Set while the stored data could not be loaded, so it is not overwritten
+        pub saving_blocked: Cell<bool>,
+        pub close_without_saving: Cell<bool>,
+        // Closing only hides the window while running in the background, unless quitting
+        pub quitting: Cell<bool>,
+        pub background_hold: RefCell<Option<gio::ApplicationHoldGuard>>,
+        pub last_reminder_check: RefCell<String>,
+        pub sync_data: RefCell<SyncData>,
+        pub syncing: Cell<bool>,
+        pub lan_sync: RefCell<Option<LanSync>>,
+        pub locked: Cell<bool>,
+        // Monotonic time of the last input, in microseconds
+        pub last_activity: Cell<i64>,
+        pub focus: RefCell<Option<Focus>>,
+        // The task shown in the detail pane
+        pub detail_task: RefCell<Option<TaskObject>>,
+        // Tasks that just came into the list, their rows fade in
+        pub appearing_tasks: RefCell<Vec<TaskObject>>,
+        // Tasks just completed or reopened, sorted or filtered together after a moment
+        pub completing_tasks: RefCell<Vec<TaskObject>>,
+        // First day of the month shown in the calendar
+        pub calendar_month: RefCell<Option<glib::DateTime>>,
+        pub tombstones: RefCell<Vec<Tombstone>>,
+        // Uuids of the tasks and collections as last stored, to notice deletions
+        pub stored_uuids: RefCell<HashSet<String>>,
+    }
+
+    // Behind the lock nothing may be shown or changed, shortcuts included
+    fn install_unlocked_action(
+        klass: &mut <TodoWindow as ObjectSubclass>::Class,
+        action_name: &str,
+        parameter_type: Option<&str>,
+        activate: impl Fn(&super::TodoWindow, &str, Option<&glib::Variant>) + 'static,
+    ) {
+        klass.install_action(action_name, parameter_type, move |window, action_name, parameter| {
+            if !window.is_locked() {
+                activate(window, action_name, parameter);
+            }
+        });
+    }
+
+    fn install_unlocked_action_async<Fut: std::future::Future<Output = ()>>(
+        klass: &mut <TodoWindow as ObjectSubclass>::Class,
+        action_name: &str,
+        parameter_type: Option<&str>,
+        activate: impl Fn(super::TodoWindow, String, Option<glib::Variant>) -> Fut + Clone + 'static,
+    ) {
+        klass.install_action_async(action_name, parameter_type, move |window, action_name, parameter| {
+            let activate = activate.clone();
+            async move {
+                if !window.is_locked() {
+                    activate(window, action_name, parameter).await;
+                }
+            }
+        });
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for TodoWindow {
+        const NAME: &'static str = "TodoWindow";
+        type Type = super::TodoWindow;
+        type ParentType = adw::ApplicationWindow;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.bind_template();
+
+            install_unlocked_action(klass, "win.remove-done-tasks", None, |window, _, _| {
+                window.remove_done_tasks();
+            });
+
+            install_unlocked_action(klass, "win.archive-done-tasks", None, |window, _, _| {
+                window.archive_done_tasks(&window.current_collection());
+            });
+
+            install_unlocked_action(klass, "win.search", None, |window, _, _| {
+                let search_bar = &window.imp().search_bar;
+                search_bar.set_search_mode(!search_bar.is_search_mode());
+            });
+
+            install_unlocked_action(klass, "win.clear-search", None, |window, _, _| {
+                window.imp().search_entry.set_text("");
+                window.imp().search_bar.set_search_mode(false);
+            });
+
+            install_unlocked_action(klass, "win.show-all-tasks", None, |window, _, _| {
+                window.set_tag_filter(None);
+                let _ = window.activate_action("win.filter", Some(&"All".to_variant()));
+            });
+
+            install_unlocked_action(klass, "win.undo", None, |window, _, _| {
+                window.undo();
+            });
+
+            install_unlocked_action(klass, "win.redo", None, |window, _, _| {
+                window.redo();
+            });
+
+            install_unlocked_action_async(klass, "win.new-collection", None,
+                |window, _, _| async move {
+                    window.new_collection().await;
+                }
+            );
+
+            install_unlocked_action_async(klass, "win.new-detailed-task", None,
+                |window, _, _| async move {
+                    window.new_detailed_task().await;
+                }
+            );
+
+            install_unlocked_action_async(klass, "win.add-section", None,
+                |window, _, _| async move {
+                    let current_collection = window.imp().current_collection.borrow().clone();
+                    if let Some(collection) = current_collection {
+                        window.add_section(&collection).await;
+                    }
+                }
+            );
+
+            install_unlocked_action_async(klass, "win.rename-section", Some("s"),
+                |window, _, parameter| async move {
+                    let current_collection = window.imp().current_collection.borrow().clone();
+                    let name = parameter.and_then(|parameter| parameter.get::<String>());
+                    if let (Some(collection), Some(name)) = (current_collection, name) {
+                        window.rename_section(&collection, &name).await;
+                    }
+                }
+            );
+
+            install_unlocked_action(klass, "win.delete-section", Some("s"), |window, _, parameter| {
+                let current_collection = window.imp().current_collection.borrow().clone();
+                let name = parameter.and_then(|parameter| parameter.get::<String>());
+                if let (Some(collection), Some(name)) = (current_collection, name) {
+                    window.delete_section(&collection, &name);
+                }
+            });
+
+            install_unlocked_action(klass, "win.duplicate-collection", None, |window, _, _| {
+                let current_collection = window.imp().current_collection.borrow().clone();
+                if let Some(collection) = current_collection {
+                    window.duplicate_collection(&collection);
+                }
+            });
+
+            install_unlocked_action_async(klass, "win.merge-collection", None,
+                |window, _, _| async move {
+                    let current_collection = window.imp().current_collection.borrow().clone();
+                    if let Some(collection) = current_collection {
+                        window.merge_collection(&collection).await;
+                    }
+                }
+            );
+
+            install_unlocked_action(klass, "win.next-collection", None, |window, _, _| {
+                window.cycle_collection(1);
+            });
+
+            install_unlocked_action(klass, "win.previous-collection", None, |window, _, _| {
+                window.cycle_collection(-1);
+            });
+
+            install_unlocked_action(klass, "win.complete-selected", None, |window, _, _| {
+                window.complete_selected_tasks();
+            });
+
+            install_unlocked_action(klass, "win.delete-selected", None, |window, _, _| {
+                window.delete_selected_tasks();
+            });
+
+            // "text" for one task per line, "markdown" for a checklist
+            install_unlocked_action(klass, "win.copy-selected", Some("s"), |window, _, parameter| {
+                let markdown = parameter.and_then(|parameter| parameter.get::<String>()).as_deref() == Some("markdown");
+                window.copy_tasks(&window.selected_tasks(), markdown);
+            });
+
+            install_unlocked_action_async(klass, "win.move-selected", None,
+                |window, _, _| async move {
+                    window.move_selected_tasks().await;
+                }
+            );
+
+            install_unlocked_action_async(klass, "win.tag-selected", None,
+                |window, _, _| async move {
+                    window.tag_selected_tasks().await;
+                }
+            );
+
+            install_unlocked_action_async(klass, "win.new-smart-collection", None,
+                |window, _, _| async move {
+                    window.new_smart_collection().await;
+                }
+            );
+
+            install_unlocked_action_async(klass, "win.import-todo-txt", None,
+                |window, _, _| async move {
+                    window.import_todo_txt().await;
+                }
+            );
+
+            install_unlocked_action_async(klass, "win.export-todo-txt", None,
+                |window, _, _| async move {
+                    window.export_todo_txt().await;
+                }
+            );
+
+            install_unlocked_action_async(klass, "win.import-from-app", None,
+                |window, _, _| async move {
+                    window.import_from_app().await;
+                }
+            );
+
+            install_unlocked_action_async(klass, "win.import-csv", None,
+                |window, _, _| async move {
+                    window.import_csv().await;
+                }
+            );
+
+            install_unlocked_action_async(klass, "win.export-csv", None,
+                |window, _, _| async move {
+                    window.export_csv().await;
+                }
+            );
+
+            install_unlocked_action_async(klass, "win.import-org", None,
+                |window, _, _| async move {
+                    window.import_org().await;
+                }
+            );
+
+            install_unlocked_action_async(klass, "win.export-org", None,
+                |window, _, _| async move {
+                    window.export_org().await;
+                }
+            );
+
+            install_unlocked_action_async(klass, "win.import-endeavour", None,
+                |window, _, _| async move {
+                    window.import_endeavour(false).await;
+                }
+            );
+
+            install_unlocked_action_async(klass, "win.import-ical", None,
+                |window, _, _| async move {
+                    window.import_ical().await;
+                }
+            );
+
+            install_unlocked_action_async(klass, "win.export-ical", None,
+                |window, _, _| async move {
+                    let current_collection = window.imp().current_collection.borrow().clone();
+                    if let Some(collection) = current_collection {
+                        window.export_ical(&collection).await;
+                    }
+                }
+            );
+
+            install_unlocked_action_async(klass, "win.back-up", None,
+                |window, _, _| async move {
+                    window.back_up().await;
+                }
+            );
+
+            install_unlocked_action_async(klass, "win.restore-backup", None,
+                |window, _, _| async move {
+                    window.restore_backup().await;
+                }
+            );
+
+            install_unlocked_action_async(klass, "win.sync", None,
+                |window, _, _| async move {
+                    window.sync_with_peers().await;
+                    // Only complain about a missing account if there is no other way to sync
+                    let lan_sync_enabled = window.imp().lan_sync.borrow().is_some();
+                    if !lan_sync_enabled || Account::from_settings(window.settings()).is_some() {
+                        window.sync_collections().await;
+                    }
+                }
+            );
+
+            install_unlocked_action(klass, "win.preferences", None, |window, _, _| {
+                PreferencesWindow::new(window).present();
+            });
+
+            install_unlocked_action_async(klass, "win.set-up-sync", None,
+                |window, _, _| async move {
+                    window.set_up_sync().await;
+                }
+            );
+
+            install_unlocked_action_async(klass, "win.import-markdown", None,
+                |window, _, _| async move {
+                    window.import_markdown().await;
+                }
+            );
+
+            install_unlocked_action(klass, "win.stop-focus", None, |window, _, _| {
+                window.stop_focus();
+            });
+
+            install_unlocked_action(klass, "win.statistics", None, |window, _, _| {
+                window.show_statistics();
+            });
+
+            install_unlocked_action(klass, "win.calendar-previous", None, |window, _, _| {
+                window.move_calendar(-1);
+            });
+
+            install_unlocked_action(klass, "win.calendar-next", None, |window, _, _| {
+                window.move_calendar(1);
+            });
+
+            install_unlocked_action(klass, "win.calendar-today", None, |window, _, _| {
+                window.move_calendar(0);
+            });
+
+            install_unlocked_action(klass, "win.print", None, |window, _, _| {
+                let current_collection = window.imp().current_collection.borrow().clone();
+                if let Some(collection) = current_collection {
+                    window.print_collection(&collection);
+                }
+            });
+
+            install_unlocked_action_async(klass, "win.export-markdown", None,
+                |window, _, _| async move {
+                    let current_collection = window.imp().current_collection.borrow().clone();
+                    if let Some(collection) = current_collection {
+                        window.export_markdown(&[collection]).await;
+                    }
+                }
+            );
+
+            install_unlocked_action_async(klass, "win.export-all-markdown", None,
+                |window, _, _| async move {
+                    let collections: Vec<CollectionObject> = window
+                        .collections()
+                        .iter::<CollectionObject>()
+                        .filter_map(Result::ok)
+                        .collect();
+                    window.export_markdown(&collections).await;
+                }
+            );
+
+            install_unlocked_action_async(klass, "win.empty-trash", None,
+                |window, _, _| async move {
+                    window.empty_trash().await;
+                }
+            );
+
+            install_unlocked_action_async(klass, "win.delete-collection", None,
+                |window, _, _| async move {
+                    let current_smart_view = window.imp().current_smart_view.borrow().clone();
+                    let current_collection = window.imp().current_collection.borrow().clone();
+                    if let Some(SmartView::Saved(smart_collection)) = current_smart_view {
+                        window.delete_smart_collection(&smart_collection).await;
+                    } else if let Some(collection) = current_collection {
+                        window.delete_collection(&collection).await;
+                    }
+                }
+            );
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for TodoWindow {
+        fn constructed(&self) {
+            self.parent_constructed();
+            let obj = self.obj();
+
+            obj.setup_settings();
+            obj.load_window_state();
+            obj.setup_storage();
+            obj.setup_collections();
+            obj.setup_smart_collections();
+            obj.restore_data();
+            obj.load_sidebar_state();
+            obj.setup_autosave();
+            obj.setup_storage_watch();
+            obj.setup_trash();
+            obj.auto_clear_completed();
+            obj.setup_calendar();
+            obj.setup_callbacks();
+            obj.setup_actions();
+            obj.setup_reminders();
+            obj.setup_sync();
+            obj.setup_lock();
+            obj.setup_drop();
+            obj.setup_badge();
+            obj.schedule_midnight_refresh();
+            obj.update_undo_actions();
+        }
+    }
+
+    impl WidgetImpl for TodoWindow {}
+    impl WindowImpl for TodoWindow {
+        fn close_request(&self) -> glib::Propagation {
+            if let Err(err) = self.obj().save_window_state() {
+                glib::g_warning!("todo", "Could not save the window state: {}", err);
+            }
+
+            // Flush a pending autosave right away, the main loop may not run again
+            if let Some(source_id) = self.save_source_id.take() {
+                source_id.remove();
+            }
+
+            let obj = self.obj();
+            if self.saving_blocked.get() || self.close_without_saving.get() {
+                return self.parent_close_request();
+            }
+
+            // Saving now would overwrite the changes the user is being asked about
+            if self.external_change_pending.get() {
+                return glib::Propagation::Stop;
+            }
+
+            if let Err(err) = obj.storage().save(&obj.store_data()) {
+                glib::MainContext::default().spawn_local(clone!(@weak obj => async move {
+                    obj.confirm_close_without_saving(err).await;
+                }));
+                return glib::Propagation::Stop;
+            }
+
+            let run_in_background: bool = obj.settings().get("run-in-background");
+            if run_in_background && !self.quitting.get() {
+                // Keep the application and its reminders alive without a visible window
+                if let Some(application) = obj.application() {
+                    self.background_hold.replace(Some(application.hold()));
+                }
+                obj.set_visible(false);
+                return glib::Propagation::Stop;
+            }
+
+            self.parent_close_request()
+        }
+    }
+    impl ApplicationWindowImpl for TodoWindow {}
+    impl AdwApplicationWindowImpl for TodoWindow {}
+}
+
+glib::wrapper! {
+    pub struct TodoWindow(ObjectSubclass<imp::TodoWindow>)
+        @extends gtk::Widget, gtk::Window, gtk::ApplicationWindow, adw::ApplicationWindow,
+        @implements gio::ActionGroup, gio::ActionMap, gtk::Accessible, gtk::Buildable,
+                    gtk::ConstraintTarget, gtk::Native, gtk::Root, gtk::ShortcutManager;
+}
+
+impl TodoWindow {
+    pub fn new<P: glib::IsA<gtk::Application>>(application: &P) -> Self {
+        glib::Object::builder()
+            .property("application", application)
+            .build()
+    }
+
+    // Closes the window for good, even when running in the background
+    pub fn quit(&self) {
+        self.imp().quitting.set(true);
+        self.imp().background_hold.take();
+        self.close();
+    }
+
+    // Shows the collection containing the task with `uuid` and focuses the task
+    pub fn show_task(&self, uuid: &str) {
+        let Some(collection) = self
+            .collections()
+            .iter::<CollectionObject>()
+            .filter_map(Result::ok)
+            .find(|collection| {
+                collection.tasks().iter::<TaskObject>().filter_map(Result::ok).any(|task| task.uuid() == uuid)
+            })
+        else {
+            return;
+        };
+
+        self.set_current_collection(collection);
+        self.imp().split_view.set_show_content(true);
+        self.present();
+
+        let Some(sort_model) = self.imp().current_sort_model.borrow().clone() else {
+            return;
+        };
+        let position = sort_model
+            .iter::<TaskObject>()
+            .filter_map(Result::ok)
+            .position(|task| task.uuid() == uuid);
+        if let Some(row) = position.and_then(|position| self.imp().tasks_list.row_at_index(position as i32)) {
+            row.grab_focus();
+        }
+    }
+
+    // Shows the collection with `uuid`
+    pub fn show_collection(&self, uuid: &str) {
+        let Some(collection) = self.collection_list().into_iter().find(|collection| collection.uuid() == uuid) else {
+            return;
+        };
+
+        self.set_current_collection(collection);
+        self.imp().split_view.set_show_content(true);
+        self.present();
+    }
+
+    pub fn show_today(&self) {
+        self.set_smart_view(SmartView::Today);
+        self.imp().smart_list.select_row(Some(&*self.imp().today_row));
+        self.imp().split_view.set_show_content(true);
+        self.present();
+    }
+
+    // Shows the tasks containing `text`
+    pub fn search(&self, text: &str) {
+        self.imp().search_bar.set_search_mode(true);
+        self.imp().search_entry.set_text(text);
+        self.present();
+    }
+
+    // The collections for searches from outside the window, none while the tasks are hidden
+    pub fn searchable_collections(&self) -> Vec<CollectionData> {
+        if self.is_locked() {
+            return Vec::new();
+        }
+
+        self.collection_list()
+            .iter()
+            .map(|collection| collection.to_collection_data())
+            .collect()
+    }
+
+    // The tasks cannot be read or changed from outside while the window is locked
+    pub fn is_locked(&self) -> bool {
+        self.imp().locked.get() || self.storage().is_locked()
+    }
+
+    pub fn collection_list(&self) -> Vec<CollectionObject> {
+        self.collections().iter::<CollectionObject>().filter_map(Result::ok).collect()
+    }
+
+    // The collection with the uuid or title `name`, titles are compared ignoring case
+    pub fn find_collection(&self, name: &str) -> Option<CollectionObject> {
+        let collections = self.collection_list();
+        collections
+            .iter()
+            .find(|collection| collection.uuid() == name)
+            .or_else(|| {
+                collections
+                    .iter()
+                    .find(|collection| collection.title().to_lowercase() == name.to_lowercase())
+            })
+            .cloned()
+    }
+
+    // The collection tasks from the quick-add window go to, the first one when the
+    // chosen collection no longer exists
+    pub fn quick_add_collection(&self) -> Option<CollectionObject> {
+        let uuid: String = self.settings().get("quick-add-collection");
+        let collections = self.collection_list();
+        collections
+            .iter()
+            .find(|collection| collection.uuid() == uuid)
+            .or_else(|| collections.first())
+            .cloned()
+    }
+
+    // The open or done task with `uuid`, tasks in the trash or the archive are left out
+    pub fn task_by_uuid(&self, uuid: &str) -> Option<TaskObject> {
+        self.collection_list().into_iter().find_map(|collection| {
+            collection
+                .tasks()
+                .iter::<TaskObject>()
+                .filter_map(Result::ok)
+                .find(|task| task.uuid() == uuid)
+        })
+    }
+
+    // Blocked by a task that is not completed yet, blockers that no longer exist are ignored
+    pub(crate) fn is_blocked(&self, task_object: &TaskObject) -> bool {
+        task_object
+            .blocked_by()
+            .iter()
+            .filter_map(|uuid| self.task_by_uuid(uuid))
+            .any(|blocker| !blocker.is_completed())
+    }
+
+    fn blocks_other_tasks(&self, task_object: &TaskObject) -> bool {
+        let uuid = task_object.uuid();
+        self.collection_list().iter().any(|collection| {
+            collection
+                .tasks()
+                .iter::<TaskObject>()
+                .filter_map(Result::ok)
+                .any(|task| task.blocked_by().contains(&uuid))
+        })
+    }
+
+    // The collection written as "@name" in a task, ignoring case
+    fn find_mentioned_collection(&self, name: &str) -> Option<CollectionObject> {
+        let name = name.to_lowercase();
+        self.collection_list()
+            .into_iter()
+            .find(|collection| parser::mention_name(&collection.title()).to_lowercase() == name)
+    }
+
+    // Adds a task at the end of `collection`, `text` is parsed like the entry of the window,
+    // a collection mentioned with "@name" takes the place of `collection`
+    pub fn add_task(&self, collection: &CollectionObject, text: &str) -> Option<TaskObject> {
+        let (mentioned, text) = parser::take_mention(text, |name| self.find_mentioned_collection(name));
+        let collection = mentioned.as_ref().unwrap_or(collection);
+        let parsed = parser::parse_task(&text);
+        if parsed.content.is_empty() {
+            return None;
+        }
+
+        let task = TaskObject::new(false, parsed.content);
+        task.set_tags(parsed.tags);
+        task.set_priority(parsed.priority);
+        task.set_due_date(parsed.due_date);
+        task.set_reminder_time(parsed.reminder_time);
+        let tasks = collection.tasks();
+        let position = tasks.n_items();
+        tasks.append(&task);
+        self.push_command(Command::InsertTasks {
+            tasks,
+            items: vec![(position, task.clone())],
+        });
+        Some(task)
+    }
+
+    // Adds a task with its notes, due date and collection in one go instead of editing
+    // it afterwards, starting from what was typed into the entry
+    async fn new_detailed_task(&self) {
+        let collections = self.collection_list();
+        let current_collection = self.imp().current_collection.borrow().clone();
+        // "@name" typed into the entry picks the collection below
+        let (mentioned, text) = parser::take_mention(&self.imp().entry.text(), |name| self.find_mentioned_collection(name));
+        let chosen_collection = mentioned.or_else(|| current_collection.clone());
+
+        let content_entry = Entry::builder()
+            .placeholder_text("Task")
+            .text(text)
+            .activates_default(true)
+            .build();
+
+        let notes_view = TextView::builder()
+            .wrap_mode(gtk::WrapMode::WordChar)
+            .top_margin(6)
+            .bottom_margin(6)
+            .left_margin(6)
+            .right_margin(6)
+            .build();
+        let notes_window = gtk::ScrolledWindow::builder()
+            .child(&notes_view)
+            .min_content_height(96)
+            .hscrollbar_policy(gtk::PolicyType::Never)
+            .build();
+        notes_window.add_css_class("card");
+
+        let due_entry = Entry::builder()
+            .placeholder_text("Due Date, like “tomorrow”")
+            .activates_default(true)
+            .build();
+
+        let titles: Vec<String> = collections.iter().map(CollectionObject::title).collect();
+        let titles: Vec<&str> = titles.iter().map(String::as_str).collect();
+        let collection_drop_down = DropDown::from_strings(&titles);
+        if let Some(position) = collections.iter().position(|collection| Some(collection) == chosen_collection.as_ref()) {
+            collection_drop_down.set_selected(position as u32);
+        }
+
+        let content_box = gtk::Box::builder()
+            .orientation(gtk::Orientation::Vertical)
+            .spacing(12)
+            .build();
+        content_box.append(&content_entry);
+        content_box.append(&notes_window);
+        content_box.append(&due_entry);
+        content_box.append(&collection_drop_down);
+
+        let cancel_response = "cancel";
+        let add_response = "add";
+
+        let dialog = MessageDialog::builder()
+            .heading("New Task")
+            .transient_for(self)
+            .modal(true)
+            .destroy_with_parent(true)
+            .close_response(cancel_response)
+            .default_response(add_response)
+            .extra_child(&content_box)
+            .build();
+
+        dialog.add_responses(&[(cancel_response, "Cancel"), (add_response, "Add")]);
+        dialog.set_response_appearance(add_response, ResponseAppearance::Suggested);
+
+        // A due date that can't be read would be dropped silently otherwise
+        let update_response = clone!(@weak dialog, @weak content_entry, @weak due_entry => move || {
+            let due = due_entry.text();
+            let due_valid = due.trim().is_empty() || parser::parse_due_date(&due).is_some();
+            if due_valid {
+                due_entry.remove_css_class("error");
+            } else {
+                due_entry.add_css_class("error");
+            }
+            dialog.set_response_enabled(add_response, !content_entry.text().trim().is_empty() && due_valid);
+        });
+        update_response();
+        content_entry.connect_changed(clone!(@strong update_response => move |_| update_response()));
+        due_entry.connect_changed(move |_| update_response());
+
+        if dialog.choose_future().await != add_response {
+            return;
+        }
+
+        let Some(collection) = collections.get(collection_drop_down.selected() as usize) else {
+            return;
+        };
+        let parsed = parser::parse_task(&content_entry.text());
+        if parsed.content.is_empty() {
+            return;
+        }
+        self.imp().entry.set_text("");
+
+        let task = TaskObject::new(false, parsed.content);
+        task.set_tags(parsed.tags);
+        task.set_priority(parsed.priority);
+        task.set_due_date(parser::parse_due_date(&due_entry.text()).unwrap_or(parsed.due_date));
+        task.set_reminder_time(parsed.reminder_time);
+        let notes_buffer = notes_view.buffer();
+        task.set_notes(notes_buffer.text(&notes_buffer.start_iter(), &notes_buffer.end_iter(), false).trim().to_string());
+
+        let tasks = collection.tasks();
+        let position = tasks.n_items();
+        tasks.append(&task);
+        self.push_command(Command::InsertTasks {
+            tasks,
+            items: vec![(position, task)],
+        });
+
+        if Some(collection) != current_collection.as_ref() {
+            self.imp().toast_overlay.add_toast(Toast::new(&format!("Added to “{}”", collection.title())));
+        }
+    }
+
+    pub fn complete_task(&self, task: &TaskObject) {
+        if !task.is_completed() {
+            self.set_task_property(task, "completed", true.to_value());
+        }
+    }
+
+    async fn new_collection(&self) {
+        let entry = Entry::builder()
+            .placeholder_text("Name")
+            .activates_default(true)
+            .build();
+
+        let (icon_box, icon_buttons) = Self::create_choice_buttons(&collection_object::ICON_NAMES, |icon_name| {
+            ToggleButton::builder()
+                .icon_name(icon_name)
+                .build()
+        });
+
+        // The first choice keeps the default color
+        let colors: Vec<&str> = std::iter::once("").chain(collection_object::COLORS).collect();
+        let (color_box, color_buttons) = Self::create_choice_buttons(&colors, |color| {
+            let swatch = gtk::Image::from_icon_name("media-record-symbolic");
+            if let Some(css_class) = collection_object::color_css_class(color) {
+                swatch.add_css_class(&css_class);
+            }
+            let color_button = ToggleButton::builder()
+                .child(&swatch)
+                .tooltip_text(if color.is_empty() { "Default" } else { color })
+                .build();
+            color_button.add_css_class("color-swatch");
+            color_button
+        });
+
+        // The first choice is an empty collection, the others are the saved templates
+        let templates = self.imp().templates.borrow().clone();
+        let template_titles: Vec<String> = std::iter::once("Empty Collection".to_string())
+            .chain(templates.iter().map(|template| format!("From “{}”", template.title)))
+            .collect();
+        let template_titles: Vec<&str> = template_titles.iter().map(String::as_str).collect();
+        let template_drop_down = DropDown::from_strings(&template_titles);
+        template_drop_down.set_visible(!templates.is_empty());
+        // Start out with the look of the chosen template
+        template_drop_down.connect_selected_notify(clone!(
+            @weak entry, @strong templates, @strong icon_buttons, @strong color_buttons, @strong colors => move |drop_down| {
+            let Some(template) = (drop_down.selected() as usize)
+                .checked_sub(1)
+                .and_then(|index| templates.get(index))
+            else {
+                return;
+            };
+
+            if entry.text().is_empty() {
+                entry.set_text(&template.title);
+            }
+            if let Some(position) = collection_object::ICON_NAMES.iter().position(|icon_name| *icon_name == template.icon_name) {
+                icon_buttons[position].set_active(true);
+            }
+            if let Some(position) = colors.iter().position(|color| *color == template.color) {
+                color_buttons[position].set_active(true);
+            }
+        }));
+
+        let content_box = gtk::Box::builder()
+            .orientation(gtk::Orientation::Vertical)
+            .spacing(12)
+            .build();
+        content_box.append(&template_drop_down);
+        content_box.append(&entry);
+        content_box.append(&icon_box);
+        content_box.append(&color_box);
+
+        let cancel_response = "cancel";
+        let create_response = "create";
+
+        let dialog = MessageDialog::builder()
+            .heading("New Collection")
+            .transient_for(self)
+            .modal(true)
+            .destroy_with_parent(true)
+            .close_response(cancel_response)
+            .default_response(create_response)
+            .extra_child(&content_box)
+            .build();
+
+        dialog.add_responses(&[(cancel_response, "Cancel"), (create_response, "Create")]);
+        dialog.set_response_enabled(create_response, false);
+        dialog.set_response_appearance(create_response, ResponseAppearance::Suggested);
+        entry.connect_changed(clone!(@weak dialog => move |entry| {
+            let text = entry.text();
+            let empty = text.is_empty();
+
+            dialog.set_response_enabled(create_response, !empty);
+
+            if empty {
+                entry.add_css_class("error");
+            } else {
+                entry.remove_css_class("error");
+            }
+
+        }));
+
+        let response = dialog.choose_future().await;
+
+        if response == cancel_response {
+            return;
+        }
+
+        let title = entry.text().to_string();
+        let template = (template_drop_down.selected() as usize)
+            .checked_sub(1)
+            .and_then(|index| templates.get(index));
+        let collection = match template {
+            Some(template) => CollectionObject::from_template(template, &title),
+            None => CollectionObject::new(&title, gio::ListStore::new::<TaskObject>()),
+        };
+        if let Some(position) = icon_buttons.iter().position(ToggleButton::is_active) {
+            collection.set_icon_name(collection_object::ICON_NAMES[position]);
+        }
+        if let Some(position) = color_buttons.iter().position(ToggleButton::is_active) {
+            collection.set_color(colors[position]);
+        }
+
+        let collections = self.collections();
+        let position = collections.n_items();
+        collections.append(&collection);
+        self.push_command(Command::InsertCollection {
+            collections,
+            position,
+            collection: collection.clone(),
+        });
+        self.set_current_collection(collection);
+
+        self.imp().split_view.set_show_content(true);
+    }
+
+    async fn delete_collection(&self, collection: &CollectionObject) {
+        let cancel_response = "cancel";
+        let delete_response = "delete";
+
+        let dialog = MessageDialog::builder()
+            .heading("Delete Collection?")
+            .body(format!(
+                "“{}” and all of its tasks will be deleted.",
+                collection.title()
+            ))
+            .transient_for(self)
+            .modal(true)
+            .destroy_with_parent(true)
+            .close_response(cancel_response)
+            .default_response(cancel_response)
+            .build();
+
+        dialog.add_responses(&[(cancel_response, "Cancel"), (delete_response, "Delete")]);
+        dialog.set_response_appearance(delete_response, ResponseAppearance::Destructive);
+
+        let response = dialog.choose_future().await;
+
+        if response != delete_response {
+            return;
+        }
+
+        let collections = self.collections();
+        let Some(position) = collections.find(collection) else {
+            return;
+        };
+        collections.remove(position);
+        self.push_command(Command::RemoveCollection {
+            collections: collections.clone(),
+            position,
+            collection: collection.clone(),
+        });
+        self.show_undo_toast(&format!("“{}” deleted", collection.title()));
+
+        let is_current = self.imp().current_collection.borrow().as_ref() == Some(collection);
+        if !is_current {
+            return;
+        }
+
+        // Prefer the collection that took the place of the deleted one, then the one above it
+        let next_position = position.min(collections.n_items().saturating_sub(1));
+        if let Some(next_collection) = collections
+            .item(next_position)
+            .and_downcast::<CollectionObject>()
+        {
+            self.set_current_collection(next_collection);
+        } else {
+            self.clear_current_collection();
+        }
+
+        self.imp().split_view.set_show_content(false);
+    }
+
+    pub(crate) fn current_sections(&self) -> Vec<String> {
+        self.imp()
+            .current_collection
+            .borrow()
+            .as_ref()
+            .map(CollectionObject::sections)
+            .unwrap_or_default()
+    }
+
+    // Re-sorts and regroups the tasks after sections or the section of a task changed
+    fn update_sections(&self) {
+        self.set_sorter();
+        self.imp().tasks_list.invalidate_headers();
+    }
+
+    // The name of a new or renamed section or folder, `None` if cancelled
+    async fn ask_name(&self, heading: &str, name: &str, response_label: &str, taken: &[String]) -> Option<String> {
+        let entry = Entry::builder()
+            .text(name)
+            .activates_default(true)
+            .build();
+
+        let cancel_response = "cancel";
+        let save_response = "save";
+
+        let dialog = MessageDialog::builder()
+            .heading(heading)
+            .transient_for(self)
+            .modal(true)
+            .destroy_with_parent(true)
+            .close_response(cancel_response)
+            .default_response(save_response)
+            .extra_child(&entry)
+            .build();
+
+        dialog.add_responses(&[(cancel_response, "Cancel"), (save_response, response_label)]);
+        dialog.set_response_appearance(save_response, ResponseAppearance::Suggested);
+        dialog.set_response_enabled(save_response, !name.is_empty());
+        // Sections and folders are told apart by their names
+        let taken = taken.to_vec();
+        entry.connect_changed(clone!(@weak dialog => move |entry| {
+            let name = entry.text().trim().to_string();
+            dialog.set_response_enabled(save_response, !name.is_empty() && !taken.contains(&name));
+        }));
+
+        if dialog.choose_future().await != save_response {
+            return None;
+        }
+        Some(entry.text().trim().to_string())
+    }
+
+    async fn add_section(&self, collection: &CollectionObject) {
+        let sections = collection.sections();
+        let Some(name) = self.ask_name("Add Section", "", "Add", &sections).await else {
+            return;
+        };
+
+        let mut new_sections = sections;
+        new_sections.push(name.clone());
+        let command = Self::edit_sections(collection, new_sections);
+        self.push_command(command);
+        self.imp()
+            .toast_overlay
+            .add_toast(Toast::new(&format!("Move tasks to “{name}” from their menu or by dragging them")));
+    }
+
+    async fn rename_section(&self, collection: &CollectionObject, name: &str) {
+        let taken: Vec<String> = collection.sections().into_iter().filter(|section| section != name).collect();
+        let Some(new_name) = self.ask_name("Rename Section", name, "Rename", &taken).await else {
+            return;
+        };
+        if new_name == name {
+            return;
+        }
+
+        let sections = collection
+            .sections()
+            .into_iter()
+            .map(|section| if section == name { new_name.clone() } else { section })
+            .collect();
+        let mut commands = vec![Self::edit_sections(collection, sections)];
+        commands.extend(Self::move_section_tasks(collection, name, &new_name));
+        self.push_command(Command::Group(commands));
+    }
+
+    // The tasks of the section stay in the collection, without a section
+    fn delete_section(&self, collection: &CollectionObject, name: &str) {
+        let sections = collection.sections().into_iter().filter(|section| section != name).collect();
+        let mut commands = vec![Self::edit_sections(collection, sections)];
+        commands.extend(Self::move_section_tasks(collection, name, ""));
+        self.push_command(Command::Group(commands));
+        self.show_undo_toast(&format!("Section “{name}” deleted"));
+    }
+
+    fn edit_sections(collection: &CollectionObject, sections: Vec<String>) -> Command {
+        let old_value = collection.sections().to_value();
+        let new_value = sections.to_value();
+        collection.set_sections(sections);
+        Command::EditCollection {
+            collection: collection.clone(),
+            property: "sections",
+            old_value,
+            new_value,
+        }
+    }
+
+    // Archived and trashed tasks move along, so they are in the right place when brought back
+    fn move_section_tasks(collection: &CollectionObject, from: &str, to: &str) -> Vec<Command> {
+        [collection.tasks(), collection.archived(), collection.trash()]
+            .iter()
+            .flat_map(|tasks| tasks.iter::<TaskObject>().filter_map(Result::ok).collect::<Vec<_>>())
+            .filter(|task| task.section() == from)
+            .map(|task| {
+                task.set_section(to.to_string());
+                Command::EditTask {
+                    task,
+                    property: "section",
+                    old_value: from.to_value(),
+                    new_value: to.to_value(),
+                }
+            })
+            .collect()
+    }
+
+    pub(crate) fn move_task_to_section(&self, task_object: &TaskObject, section: &str) {
+        if task_object.section() != section {
+            self.set_task_property(task_object, "section", section.to_value());
+        }
+    }
+
+    // Names of the folders in sidebar order
+    fn folders(&self) -> Vec<String> {
+        self.sidebar_top_level()
+            .into_iter()
+            .filter_map(|item| item.downcast::<gtk::StringObject>().ok())
+            .map(|folder| folder.string().to_string())
+            .collect()
+    }
+
+    fn edit_folder(collection: &CollectionObject, folder: &str) -> Command {
+        let old_value = collection.folder().to_value();
+        collection.set_folder(folder.to_string());
+        Command::EditCollection {
+            collection: collection.clone(),
+            property: "folder",
+            old_value,
+            new_value: folder.to_value(),
+        }
+    }
+
+    fn move_to_folder(&self, collection: &CollectionObject, folder: &str) {
+        if collection.folder() != folder {
+            let command = Self::edit_folder(collection, folder);
+            self.push_command(command);
+        }
+    }
+
+    async fn new_folder(&self, collection: &CollectionObject) {
+        let Some(folder) = self.ask_name("New Folder", "", "Create", &self.folders()).await else {
+            return;
+        };
+        self.imp().expanded_folders.borrow_mut().insert(folder.clone());
+        self.move_to_folder(collection, &folder);
+    }
+
+    async fn rename_folder(&self, folder: &str) {
+        let taken: Vec<String> = self.folders().into_iter().filter(|name| name != folder).collect();
+        let Some(new_folder) = self.ask_name("Rename Folder", folder, "Rename", &taken).await else {
+            return;
+        };
+        if new_folder == folder {
+            return;
+        }
+
+        if self.imp().expanded_folders.borrow_mut().remove(folder) {
+            self.imp().expanded_folders.borrow_mut().insert(new_folder.clone());
+        }
+        let commands = self
+            .collection_list()
+            .iter()
+            .filter(|collection| collection.folder() == folder)
+            .map(|collection| Self::edit_folder(collection, &new_folder))
+            .collect();
+        self.push_command(Command::Group(commands));
+    }
+
+    // The collections of the folder stay, outside of any folder
+    fn ungroup_folder(&self, folder: &str) {
+        let commands = self
+            .collection_list()
+            .iter()
+            .filter(|collection| collection.folder() == folder)
+            .map(|collection| Self::edit_folder(collection, ""))
+            .collect();
+        self.push_command(Command::Group(commands));
+        self.show_undo_toast(&format!("Folder “{folder}” ungrouped"));
+    }
+
+    async fn rename_collection(&self, collection: &CollectionObject) {
+        let entry = Entry::builder()
+            .text(collection.title())
+            .activates_default(true)
+            .build();
+
+        let cancel_response = "cancel";
+        let rename_response = "rename";
+
+        let dialog = MessageDialog::builder()
+            .heading("Rename Collection")
+            .transient_for(self)
+            .modal(true)
+            .destroy_with_parent(true)
+            .close_response(cancel_response)
+            .default_response(rename_response)
+            .extra_child(&entry)
+            .build();
+
+        dialog.add_responses(&[(cancel_response, "Cancel"), (rename_response, "Rename")]);
+        dialog.set_response_appearance(rename_response, ResponseAppearance::Suggested);
+        entry.connect_changed(clone!(@weak dialog => move |entry| {
+            dialog.set_response_enabled(rename_response, !entry.text().trim().is_empty());
+        }));
+
+        if dialog.choose_future().await != rename_response {
+            return;
+        }
+
+        let title = entry.text().trim().to_string();
+        if title == collection.title() {
+            return;
+        }
+
+        let old_value = collection.title().to_value();
+        collection.set_title(title.clone());
+        self.push_command(Command::EditCollection {
+            collection: collection.clone(),
+            property: "title",
+            old_value,
+            new_value: title.to_value(),
+        });
+        if self.imp().current_collection.borrow().as_ref() == Some(collection) {
+            self.imp().tasks_page.set_title(&title);
+        }
+    }
+
+    // Saving a template with the title of an existing one replaces it
+    fn save_as_template(&self, collection: &CollectionObject) {
+        let template = collection.to_template();
+        let mut templates = self.imp().templates.borrow_mut();
+        match templates.iter_mut().find(|existing| existing.title == template.title) {
+            Some(existing) => *existing = template,
+            None => templates.push(template),
+        }
+        drop(templates);
+
+        self.schedule_save();
+        self.imp().toast_overlay.add_toast(Toast::new(&format!("Saved “{}” as a template", collection.title())));
+    }
+
+    fn duplicate_collection(&self, collection: &CollectionObject) {
+        let collections = self.collections();
+        let Some(position) = collections.find(collection) else {
+            return;
+        };
+
+        let duplicate = collection.duplicate();
+        collections.insert(position + 1, &duplicate);
+        self.push_command(Command::InsertCollection {
+            collections,
+            position: position + 1,
+            collection: duplicate.clone(),
+        });
+        self.show_undo_toast(&format!("“{}” created", duplicate.title()));
+        self.set_current_collection(duplicate);
+    }
+
+    // Moves the tasks of `collection` into another one and deletes it, as one undoable step
+    async fn merge_collection(&self, collection: &CollectionObject) {
+        let targets: Vec<CollectionObject> = self
+            .collections()
+            .iter::<CollectionObject>()
+            .filter_map(Result::ok)
+            .filter(|target| target != collection)
+            .collect();
+        if targets.is_empty() {
+            self.imp().toast_overlay.add_toast(Toast::new("There is no other collection to merge into"));
+            return;
+        }
+
+        let titles: Vec<String> = targets.iter().map(CollectionObject::title).collect();
+        let titles: Vec<&str> = titles.iter().map(String::as_str).collect();
+        let target_drop_down = DropDown::from_strings(&titles);
+        let skip_duplicates_button = CheckButton::builder()
+            .label("Skip tasks that are already there")
+            .active(true)
+            .build();
+
+        let content_box = gtk::Box::builder()
+            .orientation(gtk::Orientation::Vertical)
+            .spacing(12)
+            .build();
+        content_box.append(&target_drop_down);
+        content_box.append(&skip_duplicates_button);
+
+        let cancel_response = "cancel";
+        let merge_response = "merge";
+
+        let dialog = MessageDialog::builder()
+            .heading("Merge Collection")
+            .body(format!(
+                "The tasks of “{}” are moved to the chosen collection, then “{}” is deleted.",
+                collection.title(),
+                collection.title()
+            ))
+            .transient_for(self)
+            .modal(true)
+            .destroy_with_parent(true)
+            .close_response(cancel_response)
+            .default_response(merge_response)
+            .extra_child(&content_box)
+            .build();
+
+        dialog.add_responses(&[(cancel_response, "Cancel"), (merge_response, "Merge")]);
+        dialog.set_response_appearance(merge_response, ResponseAppearance::Destructive);
+
+        if dialog.choose_future().await != merge_response {
+            return;
+        }
+
+        let Some(target) = targets.get(target_drop_down.selected() as usize) else {
+            return;
+        };
+        let skip_duplicates = skip_duplicates_button.is_active();
+
+        let tasks: Vec<TaskObject> = collection.tasks().iter::<TaskObject>().filter_map(Result::ok).collect();
+        let mut commands: Vec<Command> = Vec::new();
+        for task in tasks {
+            // Skipped tasks go away with the merged collection
+            let is_duplicate = target
+                .tasks()
+                .iter::<TaskObject>()
+                .filter_map(Result::ok)
+                .any(|target_task| target_task.content() == task.content());
+            if skip_duplicates && is_duplicate {
+                continue;
+            }
+
+            if let Some(command) = self.move_task_command(&task, target) {
+                commands.push(command);
+            }
+        }
+
+        let collections = self.collections();
+        if let Some(position) = collections.find(collection) {
+            collections.remove(position);
+            commands.push(Command::RemoveCollection {
+                collections,
+                position,
+                collection: collection.clone(),
+            });
+        }
+
+        self.push_command(Command::Group(commands));
+        self.show_undo_toast(&format!("Merged into “{}”", target.title()));
+        self.set_current_collection(target.clone());
+    }
+
+    async fn export_collection(&self, collection: &CollectionObject) {
+        let filter = FileFilter::new("JSON")
+            .mimetype("application/json")
+            .glob("*.json");
+        let current_name = format!("{}.json", collection.title());
+        let Some(path) = file_chooser::save_file(self, "Export Collection", &current_name, filter).await else {
+            return;
+        };
+
+        // In the format of the data file, so it can be opened like a backup
+        let data = StoreData::new(vec![collection.to_collection_data()], Vec::new(), Vec::new());
+        let result = gio::spawn_blocking(move || JsonStorage::write_file(&path, &data)).await;
+        match result {
+            Ok(Ok(())) => self.imp().toast_overlay.add_toast(Toast::new("Collection exported")),
+            Ok(Err(err)) => {
+                glib::g_warning!("todo", "Could not export collection: {:#}", err);
+                self.imp().toast_overlay.add_toast(Toast::new("Could not export the collection"));
+            }
+            Err(_) => self.imp().toast_overlay.add_toast(Toast::new("Could not export the collection")),
+        }
+    }
+
+    async fn import_todo_txt(&self) {
+        let filter = FileFilter::new("todo.txt")
+            .mimetype("text/plain")
+            .glob("*.txt");
+        if let Some(contents) = self.read_import("Import todo.txt", filter).await {
+            self.import_collections(todo_txt::parse(&contents));
+        }
+    }
+
+    async fn export_todo_txt(&self) {
+        let filter = FileFilter::new("todo.txt")
+            .mimetype("text/plain")
+            .glob("*.txt");
+        let collections_data: Vec<CollectionData> = self
+            .collections()
+            .iter::<CollectionObject>()
+            .filter_map(Result::ok)
+            .map(|collection| collection.to_collection_data())
+            .collect();
+        let contents = todo_txt::to_string(&collections_data);
+        self.write_export("Export as todo.txt", "todo.txt", filter, contents).await;
+    }
+
+    async fn import_csv(&self) {
+        let filter = FileFilter::new("CSV")
+            .mimetype("text/csv")
+            .glob("*.csv");
+        let Some(contents) = self.read_import("Import CSV", filter).await else {
+            return;
+        };
+
+        match csv::parse(&contents) {
+            Ok(collections_data) => self.import_collections(collections_data),
+            Err(err) => self.imp().toast_overlay.add_toast(Toast::new(&format!("{err}"))),
+        }
+    }
+
+    async fn export_csv(&self) {
+        let filter = FileFilter::new("CSV")
+            .mimetype("text/csv")
+            .glob("*.csv");
+        let collections_data: Vec<CollectionData> = self
+            .collections()
+            .iter::<CollectionObject>()
+            .filter_map(Result::ok)
+            .map(|collection| collection.to_collection_data())
+            .collect();
+        let contents = csv::to_string(&collections_data);
+        self.write_export("Export as CSV", "Tasks.csv", filter, contents).await;
+    }
+
+    async fn import_org(&self) {
+        let filter = FileFilter::new("Org")
+            .mimetype("text/x-org")
+            .glob("*.org");
+        if let Some(contents) = self.read_import("Import Org File", filter).await {
+            self.import_collections(org::parse(&contents));
+        }
+    }
+
+    async fn export_org(&self) {
+        let filter = FileFilter::new("Org")
+            .mimetype("text/x-org")
+            .glob("*.org");
+        let collections_data: Vec<CollectionData> = self
+            .collections()
+            .iter::<CollectionObject>()
+            .filter_map(Result::ok)
+            .map(|collection| collection.to_collection_data())
+            .collect();
+        let contents = org::to_string(&collections_data);
+        self.write_export("Export as Org", "Tasks.org", filter, contents).await;
+    }
+
+    // On the first run the import is only offered if GNOME To Do has any tasks
+    async fn import_endeavour(&self, first_run: bool) {
+        let task_lists = gio::spawn_blocking(endeavour::find_task_lists).await.unwrap_or_default();
+        if task_lists.is_empty() {
+            if !first_run {
+                self.imp().toast_overlay.add_toast(Toast::new("No tasks of GNOME To Do were found"));
+            }
+            return;
+        }
+
+        if first_run {
+            let task_count: usize = task_lists.iter().map(|task_list| task_list.tasks_data.len()).sum();
+            let not_now_response = "not-now";
+            let import_response = "import";
+
+            let dialog = MessageDialog::builder()
+                .heading("Import from GNOME To Do?")
+                .body(format!(
+                    "{} in {} were found.",
+                    utils::count_label(task_count, "task"),
+                    utils::count_label(task_lists.len(), "list"),
+                ))
+                .transient_for(self)
+                .modal(true)
+                .destroy_with_parent(true)
+                .close_response(not_now_response)
+                .default_response(import_response)
+                .build();
+            dialog.add_responses(&[(not_now_response, "Not Now"), (import_response, "Choose Lists…")]);
+            dialog.set_response_appearance(import_response, ResponseAppearance::Suggested);
+
+            if dialog.choose_future().await != import_response {
+                return;
+            }
+        }
+
+        if let Some(task_lists) = self.choose_imported_lists(task_lists).await {
+            self.import_collections(task_lists);
+        }
+    }
+
+    async fn import_ical(&self) {
+        let filter = FileFilter::new("iCalendar")
+            .mimetype("text/calendar")
+            .glob("*.ics");
+        if let Some(contents) = self.read_import("Import iCalendar", filter).await {
+            self.import_collections(vec![ical::parse(&contents)]);
+        }
+    }
+
+    async fn export_ical(&self, collection: &CollectionObject) {
+        let filter = FileFilter::new("iCalendar")
+            .mimetype("text/calendar")
+            .glob("*.ics");
+        let current_name = format!("{}.ics", collection.title());
+        let contents = ical::to_string(&collection.to_collection_data());
+        self.write_export("Export as iCalendar", &current_name, filter, contents).await;
+    }
+
+    // Leads through picking the app, its export and the lists to take over
+    async fn import_from_app(&self) {
+        let source_list = ListBox::builder()
+            .selection_mode(gtk::SelectionMode::None)
+            .css_classes(["boxed-list"])
+            .build();
+        let source_buttons: Vec<CheckButton> = import::Source::ALL
+            .iter()
+            .map(|source| {
+                let button = CheckButton::builder()
+                    .valign(Align::Center)
+                    .build();
+                let row = ActionRow::builder()
+                    .title(source.label())
+                    .subtitle(source.description())
+                    .activatable_widget(&button)
+                    .build();
+                row.add_prefix(&button);
+                source_list.append(&row);
+                button
+            })
+            .collect();
+        for button in source_buttons.iter().skip(1) {
+            button.set_group(source_buttons.first());
+        }
+        if let Some(first_button) = source_buttons.first() {
+            first_button.set_active(true);
+        }
+
+        let cancel_response = "cancel";
+        let continue_response = "continue";
+
+        let dialog = MessageDialog::builder()
+            .heading("Import from Another App")
+            .body("Task lists are added as collections, tasks of lists that already exist are added to them")
+            .transient_for(self)
+            .modal(true)
+            .destroy_with_parent(true)
+            .close_response(cancel_response)
+            .default_response(continue_response)
+            .extra_child(&source_list)
+            .build();
+        dialog.add_responses(&[(cancel_response, "Cancel"), (continue_response, "Continue")]);
+        dialog.set_response_appearance(continue_response, ResponseAppearance::Suggested);
+
+        if dialog.choose_future().await != continue_response {
+            return;
+        }
+        let Some(source) = source_buttons
+            .iter()
+            .position(CheckButton::is_active)
+            .map(|position| import::Source::ALL[position])
+        else {
+            return;
+        };
+
+        let filter = FileFilter::new("JSON")
+            .mimetype("application/json")
+            .glob("*.json");
+        let Some(contents) = self.read_import(&format!("Import from {}", source.label()), filter).await else {
+            return;
+        };
+        let collections_data = match source.parse(&contents) {
+            Ok(collections_data) => collections_data,
+            Err(err) => {
+                glib::g_warning!("todo", "Could not import: {:#}", err);
+                self.imp().toast_overlay.add_toast(Toast::new(&format!("{err}")));
+                return;
+            }
+        };
+
+        if let Some(collections_data) = self.choose_imported_lists(collections_data).await {
+            self.import_collections(collections_data);
+        }
+    }
+
+    async fn choose_imported_lists(&self, collections_data: Vec<CollectionData>) -> Option<Vec<CollectionData>> {
+        let lists = ListBox::builder()
+            .selection_mode(gtk::SelectionMode::None)
+            .css_classes(["boxed-list"])
+            .build();
+        let list_buttons: Vec<CheckButton> = collections_data
+            .iter()
+            .map(|collection_data| {
+                let button = CheckButton::builder()
+                    .active(true)
+                    .valign(Align::Center)
+                    .build();
+                let row = ActionRow::builder()
+                    .title(&collection_data.title)
+                    .subtitle(utils::count_label(collection_data.tasks_data.len(), "task"))
+                    .activatable_widget(&button)
+                    .build();
+                row.add_prefix(&button);
+                lists.append(&row);
+                button
+            })
+            .collect();
+        let scrolled_window = gtk::ScrolledWindow::builder()
+            .hscrollbar_policy(gtk::PolicyType::Never)
+            .propagate_natural_height(true)
+            .max_content_height(360)
+            .child(&lists)
+            .build();
+
+        let cancel_response = "cancel";
+        let import_response = "import";
+
+        let dialog = MessageDialog::builder()
+            .heading("Choose Lists to Import")
+            .transient_for(self)
+            .modal(true)
+            .destroy_with_parent(true)
+            .close_response(cancel_response)
+            .default_response(import_response)
+            .extra_child(&scrolled_window)
+            .build();
+        dialog.add_responses(&[(cancel_response, "Cancel"), (import_response, "Import")]);
+        dialog.set_response_appearance(import_response, ResponseAppearance::Suggested);
+
+        if dialog.choose_future().await != import_response {
+            return None;
+        }
+
+        let chosen = collections_data
+            .into_iter()
+            .zip(&list_buttons)
+            .filter(|(_, button)| button.is_active())
+            .map(|(collection_data, _)| collection_data)
+            .collect();
+        Some(chosen)
+    }
+
+    // Imports a file opened with the application or dropped onto the window,
+    // the lists to take over are picked first
+    pub async fn import_file(&self, file: gio::File) {
+        if self.is_locked() {
+            self.imp().toast_overlay.add_toast(Toast::new("Unlock the tasks to import"));
+            return;
+        }
+
+        let file_name = file
+            .basename()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let contents = match file.load_contents_future().await {
+            Ok((contents, _)) => String::from_utf8_lossy(&contents).into_owned(),
+            Err(err) => {
+                glib::g_warning!("todo", "Could not read import: {}", err);
+                self.imp().toast_overlay.add_toast(Toast::new("Could not read the file"));
+                return;
+            }
+        };
+
+        match import::parse_file(&file_name, &contents) {
+            Ok(collections_data) => {
+                if let Some(collections_data) = self.choose_imported_lists(collections_data).await {
+                    self.import_collections(collections_data);
+                }
+            }
+            Err(err) => self.imp().toast_overlay.add_toast(Toast::new(&format!("{err:#}"))),
+        }
+    }
+
+    // Files are imported, text and web addresses become tasks of the current collection
+    fn setup_drop(&self) {
+        let drop_target = gtk::DropTarget::new(glib::Type::INVALID, gdk::DragAction::COPY);
+        drop_target.set_types(&[gdk::FileList::static_type(), String::static_type()]);
+        drop_target.connect_drop(clone!(@weak self as window => @default-return false, move |_, value, _, _| {
+            if window.is_locked() {
+                return false;
+            }
+
+            if let Ok(text) = value.get::<String>() {
+                if window.imp().current_collection.borrow().is_none() {
+                    return false;
+                }
+                window.add_dropped_text(&text);
+                return true;
+            }
+
+            let Ok(file_list) = value.get::<gdk::FileList>() else {
+                return false;
+            };
+            // Browsers offer links as files too
+            let (links, files): (Vec<gio::File>, Vec<gio::File>) = file_list
+                .files()
+                .into_iter()
+                .partition(|file| utils::is_url(&file.uri()));
+            if !links.is_empty() && window.imp().current_collection.borrow().is_some() {
+                let links: Vec<String> = links.iter().map(|file| file.uri().to_string()).collect();
+                window.add_dropped_text(&links.join("\n"));
+            }
+            // One after the other, each asks which lists to take over
+            glib::MainContext::default().spawn_local(clone!(@weak window => async move {
+                for file in files {
+                    window.import_file(file).await;
+                }
+            }));
+            true
+        }));
+        self.add_controller(drop_target);
+    }
+
+    // The checklist is added to the current collection
+    async fn import_markdown(&self) {
+        let filter = FileFilter::new("Markdown and Text")
+            .mimetype("text/markdown")
+            .mimetype("text/plain")
+            .glob("*.md")
+            .glob("*.txt");
+        if let Some(contents) = self.read_import("Import Checklist", filter).await {
+            self.add_tasks_from_text(&contents);
+        }
+    }
+
+    async fn export_markdown(&self, collections: &[CollectionObject]) {
+        let filter = FileFilter::new("Markdown")
+            .mimetype("text/markdown")
+            .glob("*.md");
+        let current_name = match collections {
+            [collection] => format!("{}.md", collection.title()),
+            _ => "Tasks.md".to_string(),
+        };
+        let collections_data: Vec<CollectionData> = collections
+            .iter()
+            .map(|collection| collection.to_collection_data())
+            .collect();
+        let contents = markdown::to_string(&collections_data);
+        self.write_export("Export as Markdown", &current_name, filter, contents).await;
+    }
+
+    // Returns the contents of a file picked by the user, `None` if cancelled or unreadable
+    async fn read_import(&self, title: &str, filter: FileFilter) -> Option<String> {
+        let path = file_chooser::open_file(self, title, filter).await?;
+
+        let result = gio::spawn_blocking(move || std::fs::read_to_string(path)).await;
+        match result {
+            Ok(Ok(contents)) => Some(contents),
+            Ok(Err(err)) => {
+                glib::g_warning!("todo", "Could not read import: {}", err);
+                self.imp().toast_overlay.add_toast(Toast::new("Could not read the file"));
+                None
+            }
+            Err(_) => {
+                self.imp().toast_overlay.add_toast(Toast::new("Could not read the file"));
+                None
+            }
+        }
+    }
+
+    async fn write_export(&self, title: &str, current_name: &str, filter: FileFilter, contents: String) {
+        let Some(path) = file_chooser::save_file(self, title, current_name, filter).await else {
+            return;
+        };
+
+        let result = gio::spawn_blocking(move || std::fs::write(path, contents)).await;
+        match result {
+            Ok(Ok(())) => self.imp().toast_overlay.add_toast(Toast::new("Tasks exported")),
+            Ok(Err(err)) => {
+                glib::g_warning!("todo", "Could not export tasks: {}", err);
+                self.imp().toast_overlay.add_toast(Toast::new("Could not export the tasks"));
+            }
+            Err(_) => self.imp().toast_overlay.add_toast(Toast::new("Could not export the tasks")),
+        }
+    }
+
+    fn print_collection(&self, collection: &CollectionObject) {
+        if let Err(err) = print::print_collection(self, collection.to_collection_data()) {
+            glib::g_warning!("todo", "Could not print: {}", err);
+            self.imp().toast_overlay.add_toast(Toast::new("Could not print the collection"));
+        }
+    }
+
+    pub(crate) async fn share_task(&self, task_object: &TaskObject) {
+        let body = share::task_text(&task_object.task_data());
+        if !share::share(self, &task_object.content(), &body).await {
+            self.imp().toast_overlay.add_toast(Toast::new("Could not share the task"));
+        }
+    }
+
+    async fn share_collection(&self, collection: &CollectionObject) {
+        let body = share::collection_text(&collection.to_collection_data());
+        if !share::share(self, &collection.title(), &body).await {
+            self.imp().toast_overlay.add_toast(Toast::new("Could not share the collection"));
+        }
+    }
+
+    // Tasks go into the collection with the same title, other collections are added.
+    // The whole import is undone as one step.
+    fn import_collections(&self, imported: Vec<CollectionData>) {
+        let collections = self.collections();
+        let mut commands: Vec<Command> = Vec::new();
+        let mut task_count = 0;
+
+        for collection_data in imported {
+            task_count += collection_data.tasks_data.len();
+
+            let existing = collections
+                .iter::<CollectionObject>()
+                .filter_map(Result::ok)
+                .find(|collection| collection.title() == collection_data.title);
+            match existing {
+                Some(collection) => {
+                    let tasks = collection.tasks();
+                    let start = tasks.n_items();
+                    let task_objects: Vec<TaskObject> = collection_data
+                        .tasks_data
+                        .into_iter()
+                        .map(TaskObject::from_task_data)
+                        .collect();
+                    tasks.extend_from_slice(&task_objects);
+                    commands.push(Command::InsertTasks {
+                        tasks,
+                        items: (start..).zip(task_objects).collect(),
+                    });
+                }
+                None => {
+                    let collection = CollectionObject::from_collection_data(collection_data);
+                    let position = collections.n_items();
+                    collections.append(&collection);
+                    commands.push(Command::InsertCollection {
+                        collections: collections.clone(),
+                        position,
+                        collection,
+                    });
+                }
+            }
+        }
+
+        if task_count == 0 {
+            self.imp().toast_overlay.add_toast(Toast::new("No tasks to import"));
+            return;
+        }
+
+        self.push_command(Command::Group(commands));
+        self.show_undo_toast(&format!("Imported {}", utils::count_label(task_count, "task")));
+    }
+
+    async fn new_smart_collection(&self) {
+        let title_entry = Entry::builder()
+            .placeholder_text("Name")
+            .activates_default(true)
+            .build();
+        let text_entry = Entry::builder()
+            .placeholder_text("Containing Text")
+            .activates_default(true)
+            .build();
+        let tag_entry = Entry::builder()
+            .placeholder_text("Tag")
+            .activates_default(true)
+            .build();
+
+        let priority_labels: Vec<&str> = std::iter::once("Any Priority")
+            .chain(Priority::ALL.iter().skip(1).map(|priority| priority.label()))
+            .collect();
+        let priority_drop_down = DropDown::from_strings(&priority_labels);
+
+        let due_ranges: [(&str, Option<i32>); 4] = [
+            ("Any Due Date", None),
+            ("Due Today or Overdue", Some(0)),
+            ("Due in the Next 7 Days", Some(7)),
+            ("Due in the Next 30 Days", Some(30)),
+        ];
+        let due_labels: Vec<&str> = due_ranges.iter().map(|(label, _)| *label).collect();
+        let due_drop_down = DropDown::from_strings(&due_labels);
+
+        let query_box = gtk::Box::builder()
+            .orientation(gtk::Orientation::Vertical)
+            .spacing(6)
+            .build();
+        query_box.append(&title_entry);
+        query_box.append(&text_entry);
+        query_box.append(&tag_entry);
+        query_box.append(&priority_drop_down);
+        query_box.append(&due_drop_down);
+
+        let cancel_response = "cancel";
+        let create_response = "create";
+
+        let dialog = MessageDialog::builder()
+            .heading("New Smart Collection")
+            .body("Shows the tasks of all collections matching every condition that is set.")
+            .transient_for(self)
+            .modal(true)
+            .destroy_with_parent(true)
+            .close_response(cancel_response)
+            .default_response(create_response)
+            .extra_child(&query_box)
+            .build();
+
+        dialog.add_responses(&[(cancel_response, "Cancel"), (create_response, "Create")]);
+        dialog.set_response_enabled(create_response, false);
+        dialog.set_response_appearance(create_response, ResponseAppearance::Suggested);
+        title_entry.connect_changed(clone!(@weak dialog => move |title_entry| {
+            dialog.set_response_enabled(create_response, !title_entry.text().trim().is_empty());
+        }));
+
+        if dialog.choose_future().await != create_response {
+            return;
+        }
+
+        let smart_collection = SmartCollectionObject::new(SmartCollectionData {
+            title: title_entry.text().trim().to_string(),
+            text: text_entry.text().trim().to_string(),
+            tag: normalize_tag(&tag_entry.text()),
+            priority: Priority::ALL[priority_drop_down.selected() as usize],
+            due_within_days: due_ranges[due_drop_down.selected() as usize].1,
+            ..Default::default()
+        });
+        self.smart_collections().append(&smart_collection);
+
+        self.set_smart_view(SmartView::Saved(smart_collection));
+        self.imp().split_view.set_show_content(true);
+    }
+
+    async fn delete_smart_collection(&self, smart_collection: &SmartCollectionObject) {
+        let cancel_response = "cancel";
+        let delete_response = "delete";
+
+        let dialog = MessageDialog::builder()
+            .heading("Delete Smart Collection?")
+            .body(format!(
+                "“{}” will be deleted. Its tasks stay in their collections.",
+                smart_collection.title()
+            ))
+            .transient_for(self)
+            .modal(true)
+            .destroy_with_parent(true)
+            .close_response(cancel_response)
+            .default_response(cancel_response)
+            .build();
+
+        dialog.add_responses(&[(cancel_response, "Cancel"), (delete_response, "Delete")]);
+        dialog.set_response_appearance(delete_response, ResponseAppearance::Destructive);
+
+        if dialog.choose_future().await != delete_response {
+            return;
+        }
+
+        let smart_collections = self.smart_collections();
+        if let Some(position) = smart_collections.find(smart_collection) {
+            smart_collections.remove(position);
+        }
+        self.set_smart_view(SmartView::AllTasks);
+        self.imp().smart_list.select_row(Some(&*self.imp().all_tasks_row));
+    }
+
+    fn push_command(&self, command: Command) {
+        self.imp().undo_stack.borrow_mut().push(command);
+        self.update_undo_actions();
+    }
+
+    fn undo(&self) {
+        let command = self.imp().undo_stack.borrow_mut().pop_undo();
+        if let Some(command) = command {
+            command.undo();
+            self.imp().undo_stack.borrow_mut().push_undone(command);
+        }
+        self.after_history_change();
+    }
+
+    fn redo(&self) {
+        let command = self.imp().undo_stack.borrow_mut().pop_redo();
+        if let Some(command) = command {
+            command.redo();
+            self.imp().undo_stack.borrow_mut().push_redone(command);
+        }
+        self.after_history_change();
+    }
+
+    fn after_history_change(&self) {
+        self.update_undo_actions();
+
+        // Smart views follow the collections by themselves
+        if self.imp().current_smart_view.borrow().is_some() {
+            return;
+        }
+
+        // Undoing may have removed or restored the collection being shown
+        let current_collection = self.imp().current_collection.borrow().clone();
+        let collections = self.collections();
+        let is_current_valid = current_collection
+            .as_ref()
+            .is_some_and(|collection| collections.find(collection).is_some());
+
+        if is_current_valid {
+            return;
+        }
+
+        if let Some(first_collection) = collections.item(0).and_downcast::<CollectionObject>() {
+            self.set_current_collection(first_collection);
+        } else if current_collection.is_some() {
+            self.clear_current_collection();
+        }
+    }
+
+    fn update_undo_actions(&self) {
+        let undo_stack = self.imp().undo_stack.borrow();
+        self.action_set_enabled("win.undo", undo_stack.can_undo());
+        self.action_set_enabled("win.redo", undo_stack.can_redo());
+    }
+
+    fn show_undo_toast(&self, title: &str) {
+        let toast = Toast::builder()
+            .title(title)
+            .button_label("Undo")
+            .action_name("win.undo")
+            .timeout(UNDO_TOAST_TIMEOUT_SECONDS)
+            .build();
+        self.imp().toast_overlay.add_toast(toast);
+    }
+
+    // Sets a property of the task and records the change so it can be undone
+    pub(crate) fn set_task_property(&self, task_object: &TaskObject, property: &'static str, value: glib::Value) {
+        let old_value = task_object.property_value(property);
+        task_object.set_property_from_value(property, &value);
+        self.push_command(Command::EditTask {
+            task: task_object.clone(),
+            property,
+            old_value,
+            new_value: value,
+        });
+    }
+
+    fn setup_settings(&self) {
+        let settings = Settings::new(APP_ID);
+        self.imp()
+            .settings
+            .set(settings)
+            .expect("settings should be set before calling setup_settings");
+    }
+
+    fn save_window_state(&self) -> Result<(), glib::BoolError> {
+        let (width, height) = self.default_size();
+        let settings = self.settings();
+
+        settings.set_int("window-width", width)?;
+        settings.set_int("window-height", height)?;
+        settings.set_boolean("is-maximized", self.is_maximized())?;
+        settings.set_boolean("show-content", self.imp().split_view.shows_content())?;
+
+        Ok(())
+    }
+
+    fn load_window_state(&self) {
+        let settings = self.settings();
+
+        self.set_default_size(settings.int("window-width"), settings.int("window-height"));
+        if settings.boolean("is-maximized") {
+            self.maximize();
+        }
+    }
+
+    // Only matters while the split view is collapsed, where it picks the visible pane
+    fn load_sidebar_state(&self) {
+        if self.imp().current_collection.borrow().is_some() {
+            self.imp().split_view.set_show_content(self.settings().boolean("show-content"));
+        }
+    }
+
+    fn settings(&self) -> &Settings {
+        self.imp()
+            .settings
+            .get()
+            .expect("Settings houd be set ing setup_settings")
+    }
+
+    fn tasks(&self) -> gio::ListStore {
+        // Get state
+        self.current_collection().tasks()
+    }
+
+    fn current_collection(&self) -> CollectionObject {
+        self.imp()
+            .current_collection
+            .borrow()
+            .clone()
+            .expect("current_collection should be set in 'set_current_collection'")
+    }
+
+    pub(crate) fn collections(&self) -> gio::ListStore {
+        self.imp()
+            .collections
+            .get()
+            .expect("collections should be set in setyp_collections")
+            .clone()
+    }
+
+    fn set_filter(&self) {
+        if let Some(filter_model) = self.imp().current_filter_model.borrow().clone() {
+            filter_model.set_filter(self.filter().as_ref());
+        }
+        // The same empty list may have another reason now
+        self.update_task_list_visible();
+    }
+
+    // Sorts again after a task changed, the order itself stays the same
+    fn resort(&self) {
+        let sorter = self.imp().current_sort_model.borrow().as_ref().and_then(SortListModel::sorter);
+        if let Some(sorter) = sorter {
+            sorter.changed(gtk::SorterChange::Different);
+        }
+    }
+
+    // Takes a new order, after the settings or sections it depends on changed
+    fn set_sorter(&self) {
+        if let Some(sort_model) = self.imp().current_sort_model.borrow().clone() {
+            sort_model.set_sorter(self.sorter().as_ref());
+        }
+    }
+
+    fn smart_collections(&self) -> gio::ListStore {
+        self.imp()
+            .smart_collections
+            .get()
+            .expect("smart_collections should be set in setup_collections")
+            .clone()
+    }
+
+    fn setup_smart_collections(&self) {
+        let smart_collections = gio::ListStore::new::<SmartCollectionObject>();
+        self.imp()
+            .smart_collections
+            .set(smart_collections.clone())
+            .expect("Could not set smart collections");
+
+        self.imp().smart_collections_list.bind_model(
+            Some(&smart_collections),
+            |obj| {
+                let smart_collection = obj.downcast_ref::<SmartCollectionObject>().expect("Expecting SmartCollectionObject");
+                Self::create_smart_collection_row(smart_collection).upcast()
+            }
+        );
+
+        self.imp().smart_collections_list.connect_row_activated(
+            clone!(@weak self as window => move |_, row| {
+                let Some(smart_collection) = window
+                    .smart_collections()
+                    .item(row.index() as u32)
+                    .and_downcast::<SmartCollectionObject>()
+                else {
+                    return;
+                };
+
+                window.set_smart_view(SmartView::Saved(smart_collection));
+                window.imp().split_view.set_show_content(true);
+            })
+        );
+    }
+
+    fn create_smart_collection_row(smart_collection: &SmartCollectionObject) -> ListBoxRow {
+        let icon = gtk::Image::from_icon_name("edit-find-symbolic");
+        let label = Label::builder()
+            .ellipsize(pango::EllipsizeMode::End)
+            .xalign(0.0)
+            .build();
+        smart_collection.bind_property("title", &label, "label")
+            .sync_create()
+            .build();
+
+        let row_box = gtk::Box::builder()
+            .spacing(12)
+            .build();
+        row_box.append(&icon);
+        row_box.append(&label);
+
+        ListBoxRow::builder().child(&row_box).build()
+    }
+
+    fn setup_collections(&self) {
+        let collections = gio::ListStore::new::<CollectionObject>();
+        self.imp()
+            .collections
+            .set(collections.clone())
+            .expect("Could not set collections");
+
+        // The top level of the sidebar, folders are expanded into their collections
+        let sidebar_items = gio::ListStore::new::<glib::Object>();
+        self.imp()
+            .sidebar_items
+            .set(sidebar_items.clone())
+            .expect("Could not set sidebar items");
+
+        let sidebar_tree = TreeListModel::new(
+            sidebar_items,
+            false,
+            false,
+            clone!(@weak collections => @default-return None, move |obj| {
+                let folder = obj.downcast_ref::<gtk::StringObject>()?.string().to_string();
+                let filter = CustomFilter::new(move |obj| {
+                    let collection = obj.downcast_ref::<CollectionObject>().expect("Expecting CollectionObject");
+                    !collection.pinned() && collection.folder() == folder
+                });
+                Some(FilterListModel::new(Some(collections), Some(filter)).upcast())
+            })
+        );
+        self.imp()
+            .sidebar_tree
+            .set(sidebar_tree.clone())
+            .expect("Could not set sidebar tree");
+
+        self.imp().collections_list.bind_model(
+            Some(&sidebar_tree),
+            clone!(@weak self as window => @default-panic, move |obj| {
+                let tree_row = obj.downcast_ref::<TreeListRow>().expect("Expecting TreeListRow");
+                let Some(collection_object) = tree_row.item().and_downcast::<CollectionObject>() else {
+                    return window.create_folder_row(tree_row).upcast();
+                };
+
+                let row = window.create_collection_row(&collection_object);
+                // Collections in a folder are indented below it
+                if tree_row.depth() > 0 {
+                    if let Some(row_box) = row.child() {
+                        row_box.set_margin_start(24);
+                    }
+                }
+                row.upcast()
+            })
+        );
+
+        self.imp().collections_list.set_header_func(
+            clone!(@weak self as window => move |row, before| {
+                window.update_collection_header(row, before);
+            })
+        );
+
+        collections.connect_items_changed(clone!(@weak self as window => move |_, _, _, _| {
+            window.update_sidebar();
+        }));
+    }
+
+    fn sidebar_tree(&self) -> TreeListModel {
+        self.imp()
+            .sidebar_tree
+            .get()
+            .expect("sidebar_tree should be set in setup_collections")
+            .clone()
+    }
+
+    // Pinned collections first, then the folders and the collections outside of them in
+    // the order of the collections. A folder is listed where its first collection is.
+    fn sidebar_top_level(&self) -> Vec<glib::Object> {
+        let collections = self.collection_list();
+        let (pinned, unpinned): (Vec<CollectionObject>, Vec<CollectionObject>) =
+            collections.into_iter().partition(CollectionObject::pinned);
+
+        let mut items: Vec<glib::Object> = pinned.into_iter().map(|collection| collection.upcast()).collect();
+        let mut folders = HashSet::new();
+        for collection in unpinned {
+            let folder = collection.folder();
+            if folder.is_empty() {
+                items.push(collection.upcast());
+            } else if folders.insert(folder.clone()) {
+                items.push(gtk::StringObject::new(&folder).upcast());
+            }
+        }
+        items
+    }
+
+    // All collections in sidebar order, as if every folder was expanded
+    fn sidebar_collections(&self) -> Vec<CollectionObject> {
+        let collections = self.collection_list();
+        self.sidebar_top_level()
+            .into_iter()
+            .flat_map(|item| match item.downcast::<CollectionObject>() {
+                Ok(collection) => vec![collection],
+                Err(item) => {
+                    let folder = item.downcast::<gtk::StringObject>().map(|folder| folder.string()).unwrap_or_default();
+                    collections
+                        .iter()
+                        .filter(|collection| !collection.pinned() && collection.folder() == folder.as_str())
+                        .cloned()
+                        .collect()
+                }
+            })
+            .collect()
+    }
+
+    // Rebuilds the top level of the sidebar after collections were added, removed, pinned
+    // or moved between folders, keeping the expanded folders open
+    fn update_sidebar(&self) {
+        let Some(sidebar_items) = self.imp().sidebar_items.get() else {
+            return;
+        };
+        let items = self.sidebar_top_level();
+        sidebar_items.splice(0, sidebar_items.n_items(), &items);
+
+        let expanded_folders = self.imp().expanded_folders.borrow().clone();
+        let folder_rows: Vec<TreeListRow> = self
+            .sidebar_tree()
+            .iter::<TreeListRow>()
+            .filter_map(Result::ok)
+            .filter(|tree_row| {
+                tree_row
+                    .item()
+                    .and_downcast::<gtk::StringObject>()
+                    .is_some_and(|folder| expanded_folders.contains(folder.string().as_str()))
+            })
+            .collect();
+        for folder_row in folder_rows {
+            folder_row.set_expanded(true);
+        }
+
+        if self.imp().current_collection.borrow().is_some() {
+            self.select_collection_row();
+        }
+    }
+
+    fn create_folder_row(&self, tree_row: &TreeListRow) -> ListBoxRow {
+        let folder = tree_row
+            .item()
+            .and_downcast::<gtk::StringObject>()
+            .map(|folder| folder.string().to_string())
+            .unwrap_or_default();
+
+        let icon = gtk::Image::from_icon_name(collection_object::DEFAULT_ICON_NAME);
+        let label = Label::builder()
+            .label(&folder)
+            .ellipsize(pango::EllipsizeMode::End)
+            .xalign(0.0)
+            .hexpand(true)
+            .build();
+        label.add_css_class("heading");
+
+        let title_box = gtk::Box::builder()
+            .spacing(12)
+            .build();
+        title_box.append(&icon);
+        title_box.append(&label);
+
+        let expander = gtk::TreeExpander::builder()
+            .list_row(tree_row)
+            .child(&title_box)
+            .build();
+
+        tree_row.connect_expanded_notify(clone!(@weak self as window, @strong folder => move |tree_row| {
+            let mut expanded_folders = window.imp().expanded_folders.borrow_mut();
+            if tree_row.is_expanded() {
+                expanded_folders.insert(folder.clone());
+            } else {
+                expanded_folders.remove(&folder);
+            }
+        }));
+
+        let row = ListBoxRow::builder()
+            .child(&expander)
+            .selectable(false)
+            .build();
+        self.setup_folder_menu(&row, &folder);
+        row
+    }
+
+    fn setup_folder_menu(&self, row: &ListBoxRow, folder: &str) {
+        let actions = gio::SimpleActionGroup::new();
+        let folder = folder.to_string();
+
+        let action_rename = gio::SimpleAction::new("rename", None);
+        action_rename.connect_activate(clone!(@weak self as window, @strong folder => move |_, _| {
+            glib::MainContext::default().spawn_local(clone!(@weak window, @strong folder => async move {
+                window.rename_folder(&folder).await;
+            }));
+        }));
+        actions.add_action(&action_rename);
+
+        let action_ungroup = gio::SimpleAction::new("ungroup", None);
+        action_ungroup.connect_activate(clone!(@weak self as window, @strong folder => move |_, _| {
+            window.ungroup_folder(&folder);
+        }));
+        actions.add_action(&action_ungroup);
+
+        row.insert_action_group("folder", Some(&actions));
+
+        let menu = gio::Menu::new();
+        menu.append(Some("Rename…"), Some("folder.rename"));
+        menu.append(Some("Ungroup"), Some("folder.ungroup"));
+
+        let click_gesture = gtk::GestureClick::builder()
+            .button(gdk::BUTTON_SECONDARY)
+            .build();
+        click_gesture.connect_pressed(clone!(@weak row, @strong menu => move |gesture, _, x, y| {
+            gesture.set_state(gtk::EventSequenceState::Claimed);
+            Self::popup_menu(row.upcast_ref(), &menu, x, y);
+        }));
+        row.add_controller(click_gesture);
+
+        let long_press_gesture = gtk::GestureLongPress::builder()
+            .touch_only(true)
+            .build();
+        long_press_gesture.connect_pressed(clone!(@weak row, @strong menu => move |gesture, x, y| {
+            gesture.set_state(gtk::EventSequenceState::Claimed);
+            Self::popup_menu(row.upcast_ref(), &menu, x, y);
+        }));
+        row.add_controller(long_press_gesture);
+    }
+
+    fn collection_at_row(&self, row: &ListBoxRow) -> Option<CollectionObject> {
+        self.sidebar_tree()
+            .item(row.index() as u32)
+            .and_downcast::<TreeListRow>()?
+            .item()
+            .and_downcast::<CollectionObject>()
+    }
+
+    fn folder_tree_row(&self, row: &ListBoxRow) -> Option<TreeListRow> {
+        self.sidebar_tree()
+            .item(row.index() as u32)
+            .and_downcast::<TreeListRow>()
+            .filter(|tree_row| tree_row.item().and_downcast::<gtk::StringObject>().is_some())
+    }
+
+    fn task_at_row(&self, row: &ListBoxRow) -> Option<TaskObject> {
+        self.imp()
+            .current_sort_model
+            .borrow()
+            .as_ref()?
+            .item(row.index() as u32)
+            .and_downcast::<TaskObject>()
+    }
+
+    // The completed view is grouped by the day the tasks were completed, collections by their sections
+    fn update_task_header(&self, row: &ListBoxRow, before: Option<&ListBoxRow>) {
+        if self.imp().current_smart_view.borrow().as_ref() != Some(&SmartView::Completed) {
+            self.update_section_header(row, before);
+            return;
+        }
+
+        let completed_on = |row: &ListBoxRow| {
+            self.task_at_row(row).and_then(|task| utils::local_date(&task.completed_at()))
+        };
+        let day = completed_on(row);
+        if before.is_some() && before.and_then(completed_on) == day {
+            row.set_header(None::<&gtk::Widget>);
+            return;
+        }
+
+        let title = day.map_or("Earlier".to_string(), |day| utils::display_date(&day));
+        let header = Label::builder()
+            .label(title)
+            .xalign(0.0)
+            .margin_start(12)
+            .margin_top(12)
+            .margin_bottom(6)
+            .build();
+        header.add_css_class("heading");
+        row.set_header(Some(&header));
+    }
+
+    // Tasks without a section come first and have no header
+    fn update_section_header(&self, row: &ListBoxRow, before: Option<&ListBoxRow>) {
+        let sections = self.current_sections();
+        let section_of = |row: &ListBoxRow| self.task_at_row(row).map(|task| section_index(&sections, &task));
+        let index = section_of(row);
+
+        match index {
+            Some(index) if index > 0 && before.and_then(&section_of) != Some(index) => {
+                row.set_header(Some(&self.create_section_header(&sections[index - 1])));
+            }
+            _ => row.set_header(None::<&gtk::Widget>),
+        }
+    }
+
+    fn create_section_header(&self, name: &str) -> gtk::Box {
+        let name_label = Label::builder()
+            .label(name)
+            .xalign(0.0)
+            .hexpand(true)
+            .ellipsize(pango::EllipsizeMode::End)
+            .build();
+        name_label.add_css_class("heading");
+
+        let menu = gio::Menu::new();
+        for (label, action) in [("Rename…", "win.rename-section"), ("Delete Section", "win.delete-section")] {
+            let item = gio::MenuItem::new(Some(label), None);
+            item.set_action_and_target_value(Some(action), Some(&name.to_variant()));
+            menu.append_item(&item);
+        }
+        let menu_button = MenuButton::builder()
+            .icon_name("view-more-symbolic")
+            .tooltip_text("Section Menu")
+            .menu_model(&menu)
+            .valign(Align::Center)
+            .build();
+        menu_button.add_css_class("flat");
+
+        let header = gtk::Box::builder()
+            .spacing(6)
+            .margin_start(12)
+            .margin_top(12)
+            .margin_bottom(6)
+            .build();
+        header.append(&name_label);
+        header.append(&menu_button);
+
+        // Tasks dropped on the header join the section
+        let drop_target = gtk::DropTarget::new(TaskObject::static_type(), gdk::DragAction::MOVE);
+        let name = name.to_string();
+        drop_target.connect_drop(clone!(@weak self as window => @default-return false, move |_, value, _, _| {
+            let Some(task_object) = value.get::<TaskObject>().ok().filter(|task| window.is_in_current_collection(task)) else {
+                return false;
+            };
+            window.move_task_to_section(&task_object, &name);
+            true
+        }));
+        header.add_controller(drop_target);
+
+        header
+    }
+
+    // Sections only make sense within the collection the task is in
+    pub(crate) fn is_in_current_collection(&self, task_object: &TaskObject) -> bool {
+        self.imp()
+            .current_collection
+            .borrow()
+            .as_ref()
+            .is_some_and(|collection| collection.tasks().find(task_object).is_some())
+    }
+
+    // Pinned collections get a section of their own
+    fn update_collection_header(&self, row: &ListBoxRow, before: Option<&ListBoxRow>) {
+        let pinned = self.collection_at_row(row).is_some_and(|collection| collection.pinned());
+        let before_pinned = before
+            .and_then(|before| self.collection_at_row(before))
+            .map(|collection| collection.pinned());
+
+        let header_title = match (pinned, before_pinned) {
+            (true, None) => Some("Pinned"),
+            (false, Some(true)) => Some("Collections"),
+            _ => None,
+        };
+
+        let header = header_title.map(|header_title| {
+            let header = Label::builder()
+                .label(header_title)
+                .xalign(0.0)
+                .margin_start(6)
+                .margin_top(6)
+                .build();
+            header.add_css_class("heading");
+            header.add_css_class("dim-label");
+            header
+        });
+        row.set_header(header.as_ref());
+    }
+
+    fn toggle_pinned(&self, collection: &CollectionObject) {
+        collection.set_pinned(!collection.pinned());
+    }
+
+    // Right click or long press on a collection opens a menu with actions on it
+    fn setup_collection_menu(&self, row: &ListBoxRow, collection_object: &CollectionObject) {
+        let actions = gio::SimpleActionGroup::new();
+
+        let action_rename = gio::SimpleAction::new("rename", None);
+        action_rename.connect_activate(clone!(@weak self as window, @weak collection_object => move |_, _| {
+            glib::MainContext::default().spawn_local(clone!(@weak window, @weak collection_object => async move {
+                window.rename_collection(&collection_object).await;
+            }));
+        }));
+        actions.add_action(&action_rename);
+
+        let action_duplicate = gio::SimpleAction::new("duplicate", None);
+        action_duplicate.connect_activate(clone!(@weak self as window, @weak collection_object => move |_, _| {
+            window.duplicate_collection(&collection_object);
+        }));
+        actions.add_action(&action_duplicate);
+
+        let action_save_as_template = gio::SimpleAction::new("save-as-template", None);
+        action_save_as_template.connect_activate(clone!(@weak self as window, @weak collection_object => move |_, _| {
+            window.save_as_template(&collection_object);
+        }));
+        actions.add_action(&action_save_as_template);
+
+        let action_toggle_pinned = gio::SimpleAction::new("toggle-pinned", None);
+        action_toggle_pinned.connect_activate(clone!(@weak self as window, @weak collection_object => move |_, _| {
+            window.toggle_pinned(&collection_object);
+        }));
+        actions.add_action(&action_toggle_pinned);
+
+        let action_move_to_folder = gio::SimpleAction::new("move-to-folder", Some(glib::VariantTy::STRING));
+        action_move_to_folder.connect_activate(clone!(@weak self as window, @weak collection_object => move |_, parameter| {
+            if let Some(folder) = parameter.and_then(|parameter| parameter.get::<String>()) {
+                window.move_to_folder(&collection_object, &folder);
+            }
+        }));
+        actions.add_action(&action_move_to_folder);
+
+        let action_new_folder = gio::SimpleAction::new("new-folder", None);
+        action_new_folder.connect_activate(clone!(@weak self as window, @weak collection_object => move |_, _| {
+            glib::MainContext::default().spawn_local(clone!(@weak window, @weak collection_object => async move {
+                window.new_folder(&collection_object).await;
+            }));
+        }));
+        actions.add_action(&action_new_folder);
+
+        let action_archive = gio::SimpleAction::new("archive-done-tasks", None);
+        action_archive.connect_activate(clone!(@weak self as window, @weak collection_object => move |_, _| {
+            window.archive_done_tasks(&collection_object);
+        }));
+        actions.add_action(&action_archive);
+
+        let action_export = gio::SimpleAction::new("export", None);
+        action_export.connect_activate(clone!(@weak self as window, @weak collection_object => move |_, _| {
+            glib::MainContext::default().spawn_local(clone!(@weak window, @weak collection_object => async move {
+                window.export_collection(&collection_object).await;
+            }));
+        }));
+        actions.add_action(&action_export);
+
+        let action_export_ical = gio::SimpleAction::new("export-ical", None);
+        action_export_ical.connect_activate(clone!(@weak self as window, @weak collection_object => move |_, _| {
+            glib::MainContext::default().spawn_local(clone!(@weak window, @weak collection_object => async move {
+                window.export_ical(&collection_object).await;
+            }));
+        }));
+        actions.add_action(&action_export_ical);
+
+        let action_export_markdown = gio::SimpleAction::new("export-markdown", None);
+        action_export_markdown.connect_activate(clone!(@weak self as window, @weak collection_object => move |_, _| {
+            glib::MainContext::default().spawn_local(clone!(@weak window, @weak collection_object => async move {
+                window.export_markdown(&[collection_object]).await;
+            }));
+        }));
+        actions.add_action(&action_export_markdown);
+
+        let action_share = gio::SimpleAction::new("share", None);
+        action_share.connect_activate(clone!(@weak self as window, @weak collection_object => move |_, _| {
+            glib::MainContext::default().spawn_local(clone!(@weak window, @weak collection_object => async move {
+                window.share_collection(&collection_object).await;
+            }));
+        }));
+        actions.add_action(&action_share);
+
+        let action_delete = gio::SimpleAction::new("delete", None);
+        action_delete.connect_activate(clone!(@weak self as window, @weak collection_object => move |_, _| {
+            glib::MainContext::default().spawn_local(clone!(@weak window, @weak collection_object => async move {
+                window.delete_collection(&collection_object).await;
+            }));
+        }));
+        actions.add_action(&action_delete);
+
+        row.insert_action_group("collection", Some(&actions));
+
+        let click_gesture = gtk::GestureClick::builder()
+            .button(gdk::BUTTON_SECONDARY)
+            .build();
+        click_gesture.connect_pressed(clone!(@weak self as window, @weak row, @weak collection_object => move |gesture, _, x, y| {
+            gesture.set_state(gtk::EventSequenceState::Claimed);
+            window.show_collection_menu(&row, &collection_object, x, y);
+        }));
+        row.add_controller(click_gesture);
+
+        let long_press_gesture = gtk::GestureLongPress::builder()
+            .touch_only(true)
+            .build();
+        long_press_gesture.connect_pressed(clone!(@weak self as window, @weak row, @weak collection_object => move |gesture, x, y| {
+            gesture.set_state(gtk::EventSequenceState::Claimed);
+            window.show_collection_menu(&row, &collection_object, x, y);
+        }));
+        row.add_controller(long_press_gesture);
+    }
+
+    fn show_collection_menu(&self, row: &ListBoxRow, collection_object: &CollectionObject, x: f64, y: f64) {
+        let menu = gio::Menu::new();
+
+        let edit_section = gio::Menu::new();
+        edit_section.append(Some("Rename…"), Some("collection.rename"));
+        edit_section.append(Some("Duplicate"), Some("collection.duplicate"));
+        edit_section.append(Some("Save as Template"), Some("collection.save-as-template"));
+        let pin_label = if collection_object.pinned() { "Unpin" } else { "Pin" };
+        edit_section.append(Some(pin_label), Some("collection.toggle-pinned"));
+        menu.append_section(None, &edit_section);
+
+        let folder_menu = gio::Menu::new();
+        let current_folder = collection_object.folder();
+        for folder in self.folders().into_iter().filter(|folder| *folder != current_folder) {
+            let item = gio::MenuItem::new(Some(&folder), None);
+            item.set_action_and_target_value(Some("collection.move-to-folder"), Some(&folder.to_variant()));
+            folder_menu.append_item(&item);
+        }
+        let new_folder_section = gio::Menu::new();
+        new_folder_section.append(Some("New Folder…"), Some("collection.new-folder"));
+        if !current_folder.is_empty() {
+            let item = gio::MenuItem::new(Some("Remove from Folder"), None);
+            item.set_action_and_target_value(Some("collection.move-to-folder"), Some(&"".to_variant()));
+            new_folder_section.append_item(&item);
+        }
+        folder_menu.append_section(None, &new_folder_section);
+        menu.append_submenu(Some("Move to Folder"), &folder_menu);
+
+        let tasks_section = gio::Menu::new();
+        tasks_section.append(Some("Archive Done Tasks"), Some("collection.archive-done-tasks"));
+        tasks_section.append(Some("Export…"), Some("collection.export"));
+        tasks_section.append(Some("Export as iCalendar…"), Some("collection.export-ical"));
+        tasks_section.append(Some("Export as Markdown…"), Some("collection.export-markdown"));
+        tasks_section.append(Some("Share…"), Some("collection.share"));
+        menu.append_section(None, &tasks_section);
+
+        let delete_section = gio::Menu::new();
+        delete_section.append(Some("Delete…"), Some("collection.delete"));
+        menu.append_section(None, &delete_section);
+
+        Self::popup_menu(row.upcast_ref(), &menu, x, y);
+    }
+
+    // Shows `menu` at the given position of `widget`, like a context menu
+    pub(crate) fn popup_menu(widget: &gtk::Widget, menu: &gio::Menu, x: f64, y: f64) {
+        let popover = gtk::PopoverMenu::from_model(Some(menu));
+        popover.set_parent(widget);
+        popover.set_has_arrow(false);
+        popover.set_halign(Align::Start);
+        popover.set_pointing_to(Some(&gdk::Rectangle::new(x as i32, y as i32, 1, 1)));
+
+        // Actions are activated after the popover closes, unparent it once they ran
+        popover.connect_closed(|popover| {
+            glib::idle_add_local_once(clone!(@weak popover => move || {
+                popover.unparent();
+            }));
+        });
+        popover.popup();
+    }
+
+    // A row of grouped toggle buttons, one per choice, with the first one active
+    fn create_choice_buttons(
+        choices: &[&str],
+        create_button: impl Fn(&str) -> ToggleButton,
+    ) -> (gtk::Box, Vec<ToggleButton>) {
+        let choice_box = gtk::Box::builder()
+            .spacing(6)
+            .halign(Align::Center)
+            .build();
+
+        let buttons: Vec<ToggleButton> = choices.iter().map(|choice| create_button(choice)).collect();
+        for button in &buttons {
+            if let Some(first_button) = buttons.first().filter(|first_button| *first_button != button) {
+                button.set_group(Some(first_button));
+            }
+            choice_box.append(button);
+        }
+        if let Some(first_button) = buttons.first() {
+            first_button.set_active(true);
+        }
+
+        (choice_box, buttons)
+    }
+
+    fn update_collection_icon(icon: &gtk::Image, collection_object: &CollectionObject) {
+        icon.set_icon_name(Some(&collection_object.icon()));
+        for color in collection_object::COLORS {
+            if let Some(css_class) = collection_object::color_css_class(color) {
+                icon.remove_css_class(&css_class);
+            }
+        }
+        if let Some(css_class) = collection_object::color_css_class(&collection_object.color()) {
+            icon.add_css_class(&css_class);
+        }
+    }
+
+    fn create_collection_row(&self, collection_object: &CollectionObject) -> ListBoxRow {
+        let icon = gtk::Image::new();
+        Self::update_collection_icon(&icon, collection_object);
+        for property in ["icon-name", "color"] {
+            collection_object.connect_notify_local(
+                Some(property),
+                clone!(@weak icon => move |collection_object, _| {
+                    Self::update_collection_icon(&icon, collection_object);
+                })
+            );
+        }
+
+        let label = Label::builder()
+            .ellipsize(pango::EllipsizeMode::End)
+            .xalign(0.0)
+            .build();
+
+        collection_object.bind_property("title", &label, "label")
+            .sync_create()
+            .build();
+
+        let count_label = Label::builder()
+            .hexpand(true)
+            .xalign(1.0)
+            .build();
+        count_label.add_css_class("dim-label");
+        count_label.add_css_class("caption");
+        count_label.add_css_class("numeric");
+
+        Self::update_count_label(&count_label, collection_object);
+        for property in ["task-count", "done-count"] {
+            collection_object.connect_notify_local(
+                Some(property),
+                clone!(@weak count_label => move |collection_object, _| {
+                    Self::update_count_label(&count_label, collection_object);
+                })
+            );
+        }
+
+        let title_box = gtk::Box::builder()
+            .spacing(12)
+            .build();
+        title_box.append(&icon);
+        title_box.append(&label);
+        title_box.append(&count_label);
+
+        let progress_bar = ProgressBar::new();
+        collection_object.bind_property("percent-done", &progress_bar, "fraction")
+            .sync_create()
+            .build();
+        collection_object.bind_property("task-count", &progress_bar, "visible")
+            .sync_create()
+            .transform_to(|_, task_count: u32| Some(task_count > 0))
+            .build();
+
+        let row_box = gtk::Box::builder()
+            .orientation(gtk::Orientation::Vertical)
+            .spacing(6)
+            .build();
+        row_box.append(&title_box);
+        row_box.append(&progress_bar);
+
+        let row = ListBoxRow::builder().child(&row_box).build();
+        self.setup_collection_menu(&row, collection_object);
+        row
+    }
+
+    fn update_count_label(count_label: &Label, collection_object: &CollectionObject) {
+        let task_count = collection_object.task_count();
+        count_label.set_label(&format!("{}/{task_count}", collection_object.done_count()));
+        count_label.set_visible(task_count > 0);
+    }
+
+    fn set_current_collection(&self, collection: CollectionObject) {
+        if let Err(err) = self.settings().set_string("last-collection", &collection.uuid()) {
+            glib::g_warning!("todo", "Could not remember the current collection: {}", err);
+        }
+        // Set first, the filters depend on the current collection
+        self.imp().current_collection.replace(Some(collection.clone()));
+        self.imp().current_smart_view.replace(None);
+        self.set_collection_actions_enabled(true);
+        if let Some(action_filter) = self.lookup_action("filter").and_downcast::<gio::SimpleAction>() {
+            action_filter.set_state(&collection.filter().to_variant());
+        }
+
+        self.bind_tasks(self.shown_tasks(&collection).upcast());
+        self.select_collection_row();
+
+        if let Some(binding) = self.imp().progress_binding.take() {
+            binding.unbind();
+        }
+        let progress_binding = collection
+            .bind_property("percent-done", &*self.imp().collection_progress, "fraction")
+            .sync_create()
+            .build();
+        self.imp().progress_binding.replace(Some(progress_binding));
+
+        self.imp().tasks_page.set_title(&collection.title());
+        Self::update_collection_icon(&self.imp().collection_icon, &collection);
+        self.imp().smart_list.unselect_all();
+        self.imp().smart_collections_list.unselect_all();
+        self.imp().sidebar_footer.unselect_all();
+        self.imp().split_view.set_content(Some(&*self.imp().tasks_page));
+    }
+
+    fn set_smart_view(&self, smart_view: SmartView) {
+        self.disconnect_tasks_changed();
+        self.imp().current_collection.replace(None);
+        self.set_collection_actions_enabled(false);
+        // Saved smart collections can be deleted like regular ones
+        let is_saved = matches!(smart_view, SmartView::Saved(_));
+        self.action_set_enabled("win.delete-collection", is_saved);
+
+        if let Some(binding) = self.imp().progress_binding.take() {
+            binding.unbind();
+        }
+
+        self.imp().tasks_page.set_title(&smart_view.title());
+        let tasks = FilterListModel::new(Some(self.all_tasks()), smart_view.filter());
+        self.imp().current_smart_view.replace(Some(smart_view));
+        self.bind_tasks(tasks.upcast());
+
+        self.imp().collections_list.unselect_all();
+        self.imp().sidebar_footer.unselect_all();
+        if is_saved {
+            self.imp().smart_list.unselect_all();
+        } else {
+            self.imp().smart_collections_list.unselect_all();
+        }
+        self.imp().split_view.set_content(Some(&*self.imp().tasks_page));
+    }
+
+    // Counts down a focus session on the task in the header bar, replacing a running one
+    pub(crate) fn start_focus(&self, task_object: &TaskObject) {
+        self.stop_focus();
+
+        let source_id = glib::timeout_add_seconds_local(
+            1,
+            clone!(@weak self as window => @default-return glib::ControlFlow::Break, move || {
+                window.update_focus()
+            })
+        );
+        self.imp().focus.replace(Some(Focus {
+            task: task_object.clone(),
+            started: glib::monotonic_time(),
+            source_id,
+        }));
+
+        let focus_button = &self.imp().focus_button;
+        focus_button.set_tooltip_text(Some(&format!("Stop Focusing on “{}”", task_object.content())));
+        focus_button.set_visible(true);
+        self.update_focus();
+    }
+
+    fn update_focus(&self) -> glib::ControlFlow {
+        let remaining = match self.imp().focus.borrow().as_ref() {
+            Some(focus) => focus.remaining_seconds(),
+            None => return glib::ControlFlow::Break,
+        };
+        self.imp().focus_button.set_label(&focus::format_remaining(remaining));
+        if remaining > 0 {
+            return glib::ControlFlow::Continue;
+        }
+
+        // The source is removed by returning `Break`
+        if let Some(focus) = self.imp().focus.take() {
+            self.end_focus(&focus);
+            if let Some(application) = self.application() {
+                focus::send_done(application.upcast_ref(), &focus.task);
+            }
+        }
+        glib::ControlFlow::Break
+    }
+
+    // Stopping early still counts the time spent
+    fn stop_focus(&self) {
+        if let Some(focus) = self.imp().focus.take() {
+            self.end_focus(&focus);
+            focus.source_id.remove();
+        }
+    }
+
+    fn end_focus(&self, focus: &Focus) {
+        let seconds = focus.elapsed_seconds().clamp(0, focus::FOCUS_MINUTES * 60) as u64;
+        focus.task.set_focus_seconds(focus.task.focus_seconds() + seconds);
+        self.imp().focus_button.set_visible(false);
+    }
+
+    fn show_statistics(&self) {
+        let collections_data: Vec<CollectionData> = self
+            .collection_list()
+            .iter()
+            .map(CollectionObject::to_collection_data)
+            .collect();
+        stats::stats_window(self, &stats::Stats::new(&collections_data)).present();
+    }
+
+    fn setup_badge(&self) {
+        self.settings().connect_changed(
+            Some("badge-count"),
+            clone!(@weak self as window => move |_, _| {
+                window.update_badge();
+            })
+        );
+        self.update_badge();
+    }
+
+    fn update_badge(&self) {
+        let Some(application) = self.application() else {
+            return;
+        };
+
+        let badge_count: String = self.settings().get("badge-count");
+        let counted = |task: &TaskObject| match badge_count.as_str() {
+            "Due" => task.is_due_today() || task.is_overdue(),
+            "Open" => true,
+            _ => false,
+        };
+        let count = self
+            .collection_list()
+            .iter()
+            .map(|collection| {
+                collection
+                    .tasks()
+                    .iter::<TaskObject>()
+                    .filter_map(Result::ok)
+                    .filter(|task| !task.is_completed() && counted(task))
+                    .count()
+            })
+            .sum();
+        badge::update(&application, count);
+    }
+
+    // The date based views are rebuilt when the day changes
+    fn schedule_midnight_refresh(&self) {
+        glib::timeout_add_seconds_local_once(
+            smart_view::seconds_until_midnight(),
+            clone!(@weak self as window => move || {
+                let smart_view = window.imp().current_smart_view.borrow().clone();
+                if let Some(smart_view) = smart_view {
+                    window.set_smart_view(smart_view);
+                }
+                // Tasks due tomorrow are due today now
+                window.update_badge();
+                window.refresh_task_rows();
+                window.refresh_calendar();
+                window.auto_clear_completed();
+                window.schedule_midnight_refresh();
+            })
+        );
+    }
+
+    fn all_tasks(&self) -> FlattenListModel {
+        self.imp()
+            .all_tasks
+            .get_or_init(|| {
+                let tasks = MapListModel::new(
+                    Some(self.collections()),
+                    Some(|obj: &glib::Object| {
+                        let collection = obj.downcast_ref::<CollectionObject>().expect("Expecting CollectionObject");
+                        collection.tasks().upcast()
+                    }),
+                );
+                FlattenListModel::new(Some(tasks))
+            })
+            .clone()
+    }
+
+    // Adding tasks and the actions on a whole collection need a collection to act on
+    fn set_collection_actions_enabled(&self, enabled: bool) {
+        self.imp().entry.set_visible(enabled);
+        self.imp().collection_icon.set_visible(enabled);
+        self.imp().collection_progress.set_visible(enabled);
+        for action in [
+            "win.remove-done-tasks",
+            "win.archive-done-tasks",
+            "win.delete-collection",
+            "win.new-detailed-task",
+            "win.add-section",
+            "win.duplicate-collection",
+            "win.merge-collection",
+            "win.export-ical",
+            "win.export-markdown",
+            "win.import-markdown",
+            "win.print",
+        ] {
+            self.action_set_enabled(action, enabled);
+        }
+        if let Some(action_filter) = self.lookup_action("filter").and_downcast::<gio::SimpleAction>() {
+            action_filter.set_enabled(enabled);
+        }
+        self.update_task_list_visible();
+    }
+
+    // Shows `tasks` in the task list, through the filters, sorter and selection
+    fn bind_tasks(&self, tasks: gio::ListModel) {
+        let filter_model = FilterListModel::new(Some(tasks.clone()), self.filter());
+        let sort_model = SortListModel::new(Some(filter_model.clone()), self.sorter());
+        let selection_model = MultiSelection::new(Some(sort_model.clone()));
+        selection_model.connect_selection_changed(clone!(@weak self as window => move |_, _, _| {
+            window.update_selection_bar();
+        }));
+        // Connected before the list creates the rows. Tasks that were only sorted differently
+        // replace as many as come in, they are left alone.
+        selection_model.connect_items_changed(clone!(@weak self as window => move |model, position, removed, added| {
+            let appearing_tasks = if added > removed {
+                (position..position + added)
+                    .filter_map(|position| model.item(position).and_downcast::<TaskObject>())
+                    .collect()
+            } else {
+                Vec::new()
+            };
+            window.imp().appearing_tasks.replace(appearing_tasks);
+        }));
+        self.imp().tasks_list.bind_model(
+            Some(&selection_model),
+            clone!(@weak self as window => @default-panic, move |obj| {
+                let task_object = obj.downcast_ref().expect("Expecting TaskObject");
+                let row = window.create_task_row(task_object);
+
+                let mut appearing_tasks = window.imp().appearing_tasks.borrow_mut();
+                if let Some(position) = appearing_tasks.iter().position(|task| task == task_object) {
+                    appearing_tasks.remove(position);
+                    row.add_css_class("appearing");
+                }
+
+                row.upcast()
+            })
+        );
+
+        filter_model.connect_items_changed(clone!(@weak self as window => move |_, _, _, _| {
+            window.update_estimate_summary();
+            window.update_task_list_visible();
+        }));
+        self.imp().current_filter_model.replace(Some(filter_model));
+        self.update_estimate_summary();
+        self.imp().current_sort_model.replace(Some(sort_model));
+        self.imp().current_selection_model.replace(Some(selection_model));
+        self.update_selection_bar();
+        // The task in the pane may not be in the new list
+        self.clear_task_details();
+
+        self.disconnect_tasks_changed();
+
+        let tasks_changed_handler_id = tasks.connect_items_changed(
+            clone!(@weak self as window => move |_, _, _, _| {
+                window.update_task_list_visible();
+            })
+        );
+
+        self.imp()
+            .tasks_changed_handler_id
+            .replace(Some((tasks, tasks_changed_handler_id)));
+        self.update_task_list_visible();
+    }
+
+    // Shows the collection `offset` places away from the current one, wrapping around
+    fn cycle_collection(&self, offset: i32) {
+        // In sidebar order, including the collections of collapsed folders
+        let collections = self.sidebar_collections();
+        let count = collections.len() as i32;
+        if count == 0 {
+            return;
+        }
+
+        let current_collection = self.imp().current_collection.borrow().clone();
+        let current_position = collections
+            .iter()
+            .position(|collection| Some(collection) == current_collection.as_ref())
+            .map_or(-offset.signum(), |position| position as i32);
+        let position = (current_position + offset).rem_euclid(count);
+
+        if let Some(collection) = collections.get(position as usize).cloned() {
+            self.set_current_collection(collection);
+            self.imp().split_view.set_show_content(true);
+        }
+    }
+
+    fn clear_current_collection(&self) {
+        self.disconnect_tasks_changed();
+
+        self.imp().tasks_list.bind_model(
+            None::<&gio::ListModel>,
+            |_| Label::new(None).upcast()
+        );
+        self.imp().current_filter_model.replace(None);
+        self.imp().current_sort_model.replace(None);
+        self.imp().current_selection_model.replace(None);
+        self.imp().current_collection.replace(None);
+        // Nothing is left to add tasks to or to act on
+        self.set_collection_actions_enabled(false);
+        if let Some(binding) = self.imp().progress_binding.take() {
+            binding.unbind();
+        }
+        self.imp().collection_progress.set_fraction(0.0);
+        self.update_selection_bar();
+    }
+
+    fn disconnect_tasks_changed(&self) {
+        if let Some((tasks, handler_id)) = self.imp().tasks_changed_handler_id.take() {
+            tasks.disconnect(handler_id);
+        }
+    }
+
+    // The "Archived" filter shows the archived tasks instead of the regular ones
+    fn shown_tasks(&self, collection: &CollectionObject) -> gio::ListStore {
+        if collection.filter() == "Archived" {
+            collection.archived()
+        } else {
+            collection.tasks()
+        }
+    }
+
+    // The estimates of the open tasks that are shown, to tell if they fit into the day
+    fn update_estimate_summary(&self) {
+        let minutes: u64 = self
+            .imp()
+            .current_filter_model
+            .borrow()
+            .as_ref()
+            .map(|filter_model| {
+                filter_model
+                    .iter::<TaskObject>()
+                    .filter_map(Result::ok)
+                    .filter(|task| !task.is_completed())
+                    .map(|task| u64::from(task.estimate_minutes()))
+                    .sum()
+            })
+            .unwrap_or_default();
+
+        let estimate_label = &self.imp().estimate_label;
+        estimate_label.set_label(&format!("{} left", utils::format_minutes(minutes)));
+        estimate_label.set_visible(minutes > 0);
+    }
+
+    // Shows the tasks, or a page saying why there are none and what to do about it
+    fn update_task_list_visible(&self) {
+        let imp = self.imp();
+        let task_count = imp
+            .tasks_changed_handler_id
+            .borrow()
+            .as_ref()
+            .map(|(tasks, _)| tasks.n_items())
+            .unwrap_or_default();
+        let shown_count = imp
+            .current_sort_model
+            .borrow()
+            .as_ref()
+            .map(|sort_model| sort_model.n_items())
+            .unwrap_or_default();
+
+        imp.tasks_list.set_visible(shown_count > 0);
+        imp.tasks_status_page.set_visible(shown_count == 0);
+        if shown_count > 0 {
+            return;
+        }
+
+        let filter_state = imp
+            .current_collection
+            .borrow()
+            .as_ref()
+            .map_or(DEFAULT_FILTER.to_string(), CollectionObject::filter);
+        let searching = !imp.search_entry.text().trim().is_empty();
+        // Smart views have no entry to add tasks with
+        let can_add = imp.entry.is_visible();
+
+        let (icon_name, title, description, button) = if searching {
+            ("system-search-symbolic", "No Results", "No task matches the search", Some(("_Clear Search", "win.clear-search")))
+        } else if !can_add {
+            ("checkbox-checked-symbolic", "No Tasks", "There is nothing to do here", None)
+        } else if task_count == 0 && filter_state != "Archived" {
+            ("checkbox-checked-symbolic", "No Tasks Yet", "Type a task above to add it", Some(("New Task With _Details…", "win.new-detailed-task")))
+        } else if filter_state == "Open" && imp.tag_filter.borrow().is_none() {
+            ("emblem-ok-symbolic", "All Done", "Every task here is done", Some(("Show _Done Tasks", "win.filter('Done')")))
+        } else {
+            ("edit-find-symbolic", "No Matching Tasks", "No task matches the filter", Some(("Show _All Tasks", "win.show-all-tasks")))
+        };
+
+        imp.tasks_status_page.set_icon_name(Some(icon_name));
+        imp.tasks_status_page.set_title(title);
+        imp.tasks_status_page.set_description(Some(description));
+        imp.tasks_status_button.set_visible(button.is_some());
+        if let Some((label, action)) = button {
+            imp.tasks_status_button.set_label(label);
+            imp.tasks_status_button.set_detailed_action_name(action);
+        }
+    }
+
+    fn select_collection_row(&self) {
+        let current_collection = self.current_collection();
+
+        // Collections in a collapsed folder have no row yet
+        let folder = current_collection.folder();
+        if !folder.is_empty() && !current_collection.pinned() {
+            let folder_row = self.sidebar_tree().iter::<TreeListRow>().filter_map(Result::ok).find(|tree_row| {
+                tree_row
+                    .item()
+                    .and_downcast::<gtk::StringObject>()
+                    .is_some_and(|item| item.string() == folder.as_str())
+            });
+            if let Some(folder_row) = folder_row {
+                folder_row.set_expanded(true);
+            }
+        }
+
+        let index = self
+            .sidebar_tree()
+            .iter::<TreeListRow>()
+            .position(|tree_row| {
+                tree_row.is_ok_and(|tree_row| tree_row.item().as_ref() == Some(current_collection.upcast_ref()))
+            });
+        if let Some(index) = index {
+            let row = self.imp().collections_list.row_at_index(index as i32);
+            self.imp().collections_list.select_row(row.as_ref());
+        }
+    }
+
+    pub(crate) fn selection_position(selection_model: &MultiSelection, task_object: &TaskObject) -> Option<u32> {
+        selection_model
+            .iter::<TaskObject>()
+            .position(|task| task.is_ok_and(|task| &task == task_object))
+            .map(|position| position as u32)
+    }
+
+    pub(crate) fn is_selection_mode(&self) -> bool {
+        self.action_state("selection-mode")
+            .and_then(|state| state.get::<bool>())
+            .unwrap_or_default()
+    }
+
+    fn set_selection_mode(&self, selection_mode: bool) {
+        self.imp().selection_bar.set_revealed(selection_mode);
+
+        // Recreate the rows so they show or hide their selection check button
+        let current_collection = self.imp().current_collection.borrow().clone();
+        if let Some(collection) = current_collection {
+            self.set_current_collection(collection);
+        }
+    }
+
+    fn update_selection_bar(&self) {
+        let count = self
+            .imp()
+            .current_selection_model
+            .borrow()
+            .as_ref()
+            .map(|selection_model| selection_model.selection().size())
+            .unwrap_or_default();
+
+        self.imp().selection_label.set_label(&format!("{count} selected"));
+        for action in [
+            "win.complete-selected",
+            "win.delete-selected",
+            "win.copy-selected",
+            "win.move-selected",
+            "win.tag-selected",
+        ] {
+            self.action_set_enabled(action, count > 0);
+        }
+    }
+
+    fn selected_tasks(&self) -> Vec<TaskObject> {
+        let Some(selection_model) = self.imp().current_selection_model.borrow().clone() else {
+            return Vec::new();
+        };
+
+        let selection = selection_model.selection();
+        (0..selection.size())
+            .filter_map(|index| selection_model.item(selection.nth(index as u32)))
+            .filter_map(|item| item.downcast::<TaskObject>().ok())
+            .collect()
+    }
+
+    // The tasks an action of the menu of `task_object` applies to, the whole selection
+    // when the task is part of it
+    pub(crate) fn tasks_for_menu(&self, task_object: &TaskObject) -> Vec<TaskObject> {
+        let selected_tasks = self.selected_tasks();
+        if selected_tasks.contains(task_object) {
+            selected_tasks
+        } else {
+            vec![task_object.clone()]
+        }
+    }
+
+    // Copies the tasks as plain text, one per line, or as a Markdown checklist to paste elsewhere
+    pub(crate) fn copy_tasks(&self, task_objects: &[TaskObject], markdown: bool) {
+        if task_objects.is_empty() {
+            return;
+        }
+
+        let text = if markdown {
+            let tasks_data: Vec<TaskData> = task_objects.iter().map(TaskObject::task_data).collect();
+            markdown::checklist(&tasks_data)
+        } else {
+            let lines: Vec<String> = task_objects.iter().map(TaskObject::content).collect();
+            lines.join("\n")
+        };
+        self.clipboard().set_text(text.trim_end());
+
+        let message = if task_objects.len() == 1 {
+            "Copied to clipboard".to_string()
+        } else {
+            format!("Copied {} to clipboard", utils::count_label(task_objects.len(), "task"))
+        };
+        self.imp().toast_overlay.add_toast(Toast::new(&message));
+    }
+
+    fn clear_selection(&self) {
+        if let Some(selection_model) = self.imp().current_selection_model.borrow().clone() {
+            selection_model.unselect_all();
+        }
+    }
+
+    fn complete_selected_tasks(&self) {
+        let commands: Vec<Command> = self
+            .selected_tasks()
+            .into_iter()
+            .filter(|task_object| !task_object.is_completed())
+            .map(|task_object| {
+                task_object.set_completed(true);
+                Command::EditTask {
+                    task: task_object,
+                    property: "completed",
+                    old_value: false.to_value(),
+                    new_value: true.to_value(),
+                }
+            })
+            .collect();
+
+        if !commands.is_empty() {
+            self.push_command(Command::Group(commands));
+        }
+        self.clear_selection();
+    }
+
+    fn delete_selected_tasks(&self) {
+        let selected_tasks = self.selected_tasks();
+        let commands: Vec<Command> = selected_tasks
+            .iter()
+            .filter_map(|task_object| {
+                let (collection, tasks) = self.find_task_collection(task_object)?;
+                self.trash_tasks(&collection, &tasks, &[task_object.clone()])
+            })
+            .collect();
+
+        if commands.is_empty() {
+            return;
+        }
+
+        self.push_command(Command::Group(commands));
+        self.show_undo_toast(&format!("{} moved to the trash", utils::count_label(selected_tasks.len(), "task")));
+    }
+
+    async fn move_selected_tasks(&self) {
+        self.move_tasks_to_chosen_collection(&self.selected_tasks()).await;
+    }
+
+    // Asks for a collection to move `task_objects` to, returns whether they were moved
+    pub(crate) async fn move_tasks_to_chosen_collection(&self, task_objects: &[TaskObject]) -> bool {
+        let current_collection = self.imp().current_collection.borrow().clone();
+        let collections: Vec<CollectionObject> = self
+            .collections()
+            .iter::<CollectionObject>()
+            .filter_map(Result::ok)
+            .filter(|collection| Some(collection) != current_collection.as_ref())
+            .collect();
+        if collections.is_empty() {
+            self.imp().toast_overlay.add_toast(Toast::new("There is no other collection to move to"));
+            return false;
+        }
+
+        // Listed with their icons, in folders by their subtitle
+        let collection_list = ListBox::builder()
+            .selection_mode(gtk::SelectionMode::Browse)
+            .build();
+        collection_list.add_css_class("boxed-list");
+        for collection in &collections {
+            let icon = gtk::Image::new();
+            Self::update_collection_icon(&icon, collection);
+            let row = ActionRow::builder()
+                .title(glib::markup_escape_text(&collection.title()))
+                .subtitle(glib::markup_escape_text(&collection.folder()))
+                .activatable(true)
+                .build();
+            row.add_prefix(&icon);
+            collection_list.append(&row);
+        }
+        collection_list.select_row(collection_list.row_at_index(0).as_ref());
+
+        let collection_window = gtk::ScrolledWindow::builder()
+            .child(&collection_list)
+            .hscrollbar_policy(gtk::PolicyType::Never)
+            .propagate_natural_height(true)
+            .max_content_height(320)
+            .build();
+
+        let cancel_response = "cancel";
+        let move_response = "move";
+
+        let dialog = MessageDialog::builder()
+            .heading("Move Tasks")
+            .body(format!("Move {} to:", utils::count_label(task_objects.len(), "task")))
+            .transient_for(self)
+            .modal(true)
+            .destroy_with_parent(true)
+            .close_response(cancel_response)
+            .default_response(move_response)
+            .extra_child(&collection_window)
+            .build();
+
+        dialog.add_responses(&[(cancel_response, "Cancel"), (move_response, "Move")]);
+        dialog.set_response_appearance(move_response, ResponseAppearance::Suggested);
+
+        // Clicking a collection is enough, the button is there for the keyboard
+        collection_list.connect_row_activated(clone!(@weak dialog => move |_, _| {
+            dialog.response(move_response);
+        }));
+
+        if dialog.choose_future().await != move_response {
+            return false;
+        }
+
+        let Some(to_collection) = collection_list
+            .selected_row()
+            .and_then(|row| collections.get(row.index() as usize))
+        else {
+            return false;
+        };
+        let commands: Vec<Command> = task_objects
+            .iter()
+            .filter_map(|task_object| self.move_task_command(task_object, to_collection))
+            .collect();
+
+        if commands.is_empty() {
+            return false;
+        }
+
+        self.push_command(Command::Group(commands));
+        self.show_undo_toast(&format!("Moved to “{}”", to_collection.title()));
+        true
+    }
+
+    async fn tag_selected_tasks(&self) {
+        let entry = Entry::builder()
+            .placeholder_text("Tag")
+            .activates_default(true)
+            .build();
+
+        let cancel_response = "cancel";
+        let tag_response = "tag";
+
+        let dialog = MessageDialog::builder()
+            .heading("Add Tag")
+            .body(format!("Add a tag to {}.", utils::count_label(self.selected_tasks().len(), "task")))
+            .transient_for(self)
+            .modal(true)
+            .destroy_with_parent(true)
+            .close_response(cancel_response)
+            .default_response(tag_response)
+            .extra_child(&entry)
+            .build();
+
+        dialog.add_responses(&[(cancel_response, "Cancel"), (tag_response, "Add Tag")]);
+        dialog.set_response_enabled(tag_response, false);
+        dialog.set_response_appearance(tag_response, ResponseAppearance::Suggested);
+        entry.connect_changed(clone!(@weak dialog => move |entry| {
+            dialog.set_response_enabled(tag_response, !normalize_tag(&entry.text()).is_empty());
+        }));
+
+        if dialog.choose_future().await != tag_response {
+            return;
+        }
+
+        let tag = normalize_tag(&entry.text());
+        let commands: Vec<Command> = self
+            .selected_tasks()
+            .into_iter()
+            .filter(|task_object| !task_object.has_tag(&tag))
+            .map(|task_object| {
+                let old_tags = task_object.tags();
+                let mut tags = old_tags.clone();
+                tags.push(tag.clone());
+                task_object.set_tags(tags.clone());
+                Command::EditTask {
+                    task: task_object,
+                    property: "tags",
+                    old_value: old_tags.to_value(),
+                    new_value: tags.to_value(),
+                }
+            })
+            .collect();
+
+        if !commands.is_empty() {
+            self.push_command(Command::Group(commands));
+        }
+        self.clear_selection();
+    }
+
+    // The collection containing `task` and the store it is in, its tasks or its archive
+    pub(crate) fn find_task_collection(&self, task: &TaskObject) -> Option<(CollectionObject, gio::ListStore)> {
+        self.collections()
+            .iter::<CollectionObject>()
+            .filter_map(Result::ok)
+            .find_map(|collection| {
+                let tasks = [collection.tasks(), collection.archived()]
+                    .into_iter()
+                    .find(|tasks| tasks.find(task).is_some())?;
+                Some((collection, tasks))
+            })
+    }
+
+    pub(crate) fn delete_task(&self, task_object: &TaskObject) {
+        let Some((collection, tasks)) = self.find_task_collection(task_object) else {
+            return;
+        };
+
+        if let Some(command) = self.trash_tasks(&collection, &tasks, &[task_object.clone()]) {
+            let shown_in_details = self.imp().detail_task.borrow().as_ref() == Some(task_object);
+            if shown_in_details {
+                self.clear_task_details();
+            }
+            self.push_command(command);
+            self.show_undo_toast(&format!("“{}” moved to the trash", task_object.content()));
+        }
+    }
+
+    pub(crate) fn duplicate_task(&self, task_object: &TaskObject) {
+        let Some((_, tasks)) = self.find_task_collection(task_object) else {
+            return;
+        };
+        let Some(position) = tasks.find(task_object) else {
+            return;
+        };
+
+        let duplicate = task_object.duplicate();
+        tasks.insert(position + 1, &duplicate);
+        self.push_command(Command::InsertTasks {
+            tasks,
+            items: vec![(position + 1, duplicate)],
+        });
+    }
+
+    pub(crate) fn reschedule_task(&self, task_object: &TaskObject, due_date: &str) {
+        if task_object.due_date() == due_date {
+            return;
+        }
+        self.set_task_property(task_object, "due-date", due_date.to_value());
+        self.show_undo_toast(&format!("Rescheduled to {}", utils::display_date(due_date)));
+    }
+
+    pub(crate) async fn pick_due_date(&self, task_object: &TaskObject) {
+        let calendar = Calendar::new();
+        if let Some(due_date) = utils::parse_date(&task_object.due_date()) {
+            calendar.select_day(&due_date);
+        }
+
+        let cancel_response = "cancel";
+        let reschedule_response = "reschedule";
+
+        let dialog = MessageDialog::builder()
+            .heading("Reschedule")
+            .body(format!("Choose when “{}” is due.", task_object.content()))
+            .transient_for(self)
+            .modal(true)
+            .destroy_with_parent(true)
+            .close_response(cancel_response)
+            .default_response(reschedule_response)
+            .extra_child(&calendar)
+            .build();
+
+        dialog.add_responses(&[(cancel_response, "Cancel"), (reschedule_response, "Reschedule")]);
+        dialog.set_response_appearance(reschedule_response, ResponseAppearance::Suggested);
+
+        if dialog.choose_future().await != reschedule_response {
+            return;
+        }
+        self.reschedule_task(task_object, &utils::format_date(&calendar.date()));
+    }
+
+    // Hides the task from the open tasks until the chosen day
+    pub(crate) async fn defer_task(&self, task_object: &TaskObject) {
+        let calendar = Calendar::new();
+        let start_date = utils::parse_date(&task_object.start_date())
+            .or_else(|| utils::now().add_days(1).ok());
+        if let Some(start_date) = start_date {
+            calendar.select_day(&start_date);
+        }
+
+        let cancel_response = "cancel";
+        let clear_response = "clear";
+        let defer_response = "defer";
+
+        let dialog = MessageDialog::builder()
+            .heading("Defer Until")
+            .body(format!("“{}” is hidden from the open tasks until this day.", task_object.content()))
+            .transient_for(self)
+            .modal(true)
+            .destroy_with_parent(true)
+            .close_response(cancel_response)
+            .default_response(defer_response)
+            .extra_child(&calendar)
+            .build();
+
+        dialog.add_responses(&[(cancel_response, "Cancel"), (clear_response, "Don't Defer"), (defer_response, "Defer")]);
+        dialog.set_response_enabled(clear_response, !task_object.start_date().is_empty());
+        dialog.set_response_appearance(defer_response, ResponseAppearance::Suggested);
+
+        let start_date = match dialog.choose_future().await.as_str() {
+            response if response == defer_response => utils::format_date(&calendar.date()),
+            response if response == clear_response => String::new(),
+            _ => return,
+        };
+        if start_date != task_object.start_date() {
+            self.set_task_property(task_object, "start-date", start_date.to_value());
+            self.set_filter();
+        }
+    }
+
+    // Picks the tasks of the same collection that have to be completed first
+    pub(crate) async fn choose_blockers(&self, task_object: &TaskObject) {
+        let Some((collection, _)) = self.find_task_collection(task_object) else {
+            return;
+        };
+        let blocked_by = task_object.blocked_by();
+
+        let list_box = ListBox::builder()
+            .selection_mode(gtk::SelectionMode::None)
+            .build();
+        list_box.add_css_class("boxed-list");
+
+        let mut check_buttons = Vec::new();
+        for task in collection.tasks().iter::<TaskObject>().filter_map(Result::ok) {
+            // Tasks the task blocks itself would never be unblocked
+            if &task == task_object || task.blocked_by().contains(&task_object.uuid()) {
+                continue;
+            }
+            if task.is_completed() && !blocked_by.contains(&task.uuid()) {
+                continue;
+            }
+
+            let check_button = CheckButton::builder()
+                .active(blocked_by.contains(&task.uuid()))
+                .valign(Align::Center)
+                .build();
+            let row = ActionRow::builder()
+                .title(glib::markup_escape_text(&task.content()))
+                .activatable_widget(&check_button)
+                .build();
+            row.add_prefix(&check_button);
+            list_box.append(&row);
+            check_buttons.push((task.uuid(), check_button));
+        }
+
+        if check_buttons.is_empty() {
+            self.imp().toast_overlay.add_toast(Toast::new("There are no other open tasks in this collection"));
+            return;
+        }
+
+        let scrolled_window = gtk::ScrolledWindow::builder()
+            .hscrollbar_policy(gtk::PolicyType::Never)
+            .propagate_natural_height(true)
+            .max_content_height(360)
+            .child(&list_box)
+            .build();
+
+        let cancel_response = "cancel";
+        let save_response = "save";
+
+        let dialog = MessageDialog::builder()
+            .heading("Blocked By")
+            .body(format!("“{}” waits for the chosen tasks to be completed.", task_object.content()))
+            .transient_for(self)
+            .modal(true)
+            .destroy_with_parent(true)
+            .close_response(cancel_response)
+            .default_response(save_response)
+            .extra_child(&scrolled_window)
+            .build();
+
+        dialog.add_responses(&[(cancel_response, "Cancel"), (save_response, "Save")]);
+        dialog.set_response_appearance(save_response, ResponseAppearance::Suggested);
+
+        if dialog.choose_future().await != save_response {
+            return;
+        }
+
+        let new_blocked_by: Vec<String> = check_buttons
+            .into_iter()
+            .filter(|(_, check_button)| check_button.is_active())
+            .map(|(uuid, _)| uuid)
+            .collect();
+        if new_blocked_by != blocked_by {
+            self.set_task_property(task_object, "blocked-by", new_blocked_by.to_value());
+            self.set_filter();
+        }
+    }
+
+    // Puts the reminder of the task off by a few minutes, moving it to today if needed
+    pub fn snooze_task(&self, uuid: &str) {
+        let Some(task_object) = self.task_by_uuid(uuid) else {
+            return;
+        };
+        let Ok(snoozed) = utils::now().add_minutes(reminders::SNOOZE_MINUTES) else {
+            return;
+        };
+        let Ok(reminder_time) = snoozed.format("%H:%M") else {
+            return;
+        };
+
+        let mut commands = Vec::new();
+        for (property, value) in [
+            ("due-date", utils::format_date(&snoozed)),
+            ("reminder-time", reminder_time.to_string()),
+        ] {
+            let old_value = task_object.property_value(property);
+            task_object.set_property(property, &value);
+            commands.push(Command::EditTask {
+                task: task_object.clone(),
+                property,
+                old_value,
+                new_value: value.to_value(),
+            });
+        }
+        self.push_command(Command::Group(commands));
+    }
+
+    pub(crate) fn move_task_to_collection(&self, task_object: &TaskObject, uuid: &str) {
+        let Some(to_collection) = self
+            .collections()
+            .iter::<CollectionObject>()
+            .filter_map(Result::ok)
+            .find(|collection| collection.uuid() == uuid)
+        else {
+            return;
+        };
+
+        if let Some(command) = self.move_task_command(task_object, &to_collection) {
+            self.push_command(command);
+            self.show_undo_toast(&format!("Moved to “{}”", to_collection.title()));
+        }
+    }
+
+    // Moves the task to the end of `to_collection`, returning the command to undo it
+    fn move_task_command(&self, task_object: &TaskObject, to_collection: &CollectionObject) -> Option<Command> {
+        let (from_collection, from) = self.find_task_collection(task_object)?;
+        if &from_collection == to_collection {
+            return None;
+        }
+        let from_position = from.find(task_object)?;
+
+        let to = to_collection.tasks();
+        let to_position = to.n_items();
+        from.remove(from_position);
+        to.append(task_object);
+
+        Some(Command::MoveTask {
+            task: task_object.clone(),
+            from,
+            from_position,
+            to,
+            to_position,
+        })
+    }
+
+    // The pane next to the list edits everything about a task that does not fit into its row
+    pub(crate) fn show_task_details(&self, task_object: &TaskObject) {
+        self.imp().detail_task.replace(Some(task_object.clone()));
+
+        let detail_box = &self.imp().detail_box;
+        while let Some(child) = detail_box.first_child() {
+            detail_box.remove(&child);
+        }
+        detail_box.append(&self.create_details_group(task_object));
+        detail_box.append(&self.create_subtasks_group(task_object));
+        detail_box.append(&Self::create_notes_editor(task_object));
+
+        self.imp().detail_stack.set_visible_child_name("task");
+        self.imp().detail_split_view.set_show_content(true);
+    }
+
+    fn clear_task_details(&self) {
+        self.imp().detail_task.replace(None);
+
+        let detail_box = &self.imp().detail_box;
+        while let Some(child) = detail_box.first_child() {
+            detail_box.remove(&child);
+        }
+
+        self.imp().detail_stack.set_visible_child_name("empty");
+        self.imp().detail_split_view.set_show_content(false);
+    }
+
+    fn create_details_group(&self, task_object: &TaskObject) -> adw::PreferencesGroup {
+        let content_row = adw::EntryRow::builder()
+            .title("Task")
+            .text(task_object.content())
+            .show_apply_button(true)
+            .build();
+        content_row.connect_apply(clone!(@weak self as window, @weak task_object => move |content_row| {
+            let content = content_row.text().trim().to_string();
+            if content.is_empty() {
+                content_row.set_text(&task_object.content());
+            } else if content != task_object.content() {
+                window.set_task_property(&task_object, "content", content.to_value());
+            }
+        }));
+        // Edits in the row and undo show up here too
+        task_object.connect_notify_for(
+            &content_row,
+            "content",
+            clone!(@weak content_row => move |task_object| {
+                if content_row.text() != task_object.content() {
+                    content_row.set_text(&task_object.content());
+                }
+            })
+        );
+
+        let due_label = Label::builder()
+            .valign(Align::Center)
+            .build();
+        due_label.add_css_class("caption");
+        due_label.add_css_class("due-chip");
+        Self::update_due_label(&due_label, task_object);
+        for property in ["due-date", "completed", "recurrence"] {
+            task_object.connect_notify_for(
+                &due_label,
+                property,
+                clone!(@weak due_label => move |task_object| {
+                    Self::update_due_label(&due_label, task_object);
+                })
+            );
+        }
+
+        let due_row = ActionRow::builder()
+            .title("Due Date")
+            .build();
+        due_row.add_suffix(&due_label);
+        due_row.add_suffix(&self.create_due_date_button(task_object));
+
+        let tags_box = gtk::Box::builder()
+            .valign(Align::Center)
+            .spacing(4)
+            .build();
+        Self::update_tags_box(&tags_box, task_object);
+        task_object.connect_notify_for(
+            &tags_box,
+            "tags",
+            clone!(@weak tags_box => move |task_object| {
+                Self::update_tags_box(&tags_box, task_object);
+            })
+        );
+
+        let tags_row = ActionRow::builder()
+            .title("Tags")
+            .build();
+        tags_row.add_suffix(&tags_box);
+        tags_row.add_suffix(&self.create_tags_button(task_object));
+
+        let priority_row = ActionRow::builder()
+            .title("Priority")
+            .build();
+        priority_row.add_suffix(&self.create_priority_button(task_object));
+
+        let details_group = adw::PreferencesGroup::new();
+        details_group.add(&content_row);
+        details_group.add(&due_row);
+        details_group.add(&tags_row);
+        details_group.add(&priority_row);
+        details_group
+    }
+
+    // The steps of a task, checked off one by one
+    fn create_subtasks_group(&self, task_object: &TaskObject) -> adw::PreferencesGroup {
+        let subtasks_list = ListBox::builder()
+            .selection_mode(gtk::SelectionMode::None)
+            .build();
+        subtasks_list.add_css_class("boxed-list");
+
+        let add_row = adw::EntryRow::builder()
+            .title("Add Subtask")
+            .build();
+        add_row.connect_entry_activated(clone!(@weak self as window, @weak task_object => move |add_row| {
+            let content = add_row.text().trim().to_string();
+            if content.is_empty() {
+                return;
+            }
+            let mut subtasks = task_object.subtasks();
+            subtasks.0.push(Subtask {
+                content,
+                completed: false,
+            });
+            window.set_task_property(&task_object, "subtasks", subtasks.to_value());
+            add_row.set_text("");
+        }));
+        subtasks_list.append(&add_row);
+
+        let subtasks_group = adw::PreferencesGroup::builder()
+            .title("Subtasks")
+            .build();
+        subtasks_group.add(&subtasks_list);
+
+        self.update_subtasks_list(&subtasks_group, &subtasks_list, &add_row, task_object);
+        task_object.connect_notify_for(
+            &subtasks_group,
+            "subtasks",
+            clone!(@weak self as window, @weak subtasks_group, @weak subtasks_list, @weak add_row => move |task_object| {
+                window.update_subtasks_list(&subtasks_group, &subtasks_list, &add_row, task_object);
+            })
+        );
+
+        subtasks_group
+    }
+
+    // Rebuilds the rows above `add_row`, which keeps its focus and text
+    fn update_subtasks_list(
+        &self,
+        subtasks_group: &adw::PreferencesGroup,
+        subtasks_list: &ListBox,
+        add_row: &adw::EntryRow,
+        task_object: &TaskObject,
+    ) {
+        while let Some(child) = subtasks_list.first_child() {
+            if &child == add_row.upcast_ref::<gtk::Widget>() {
+                break;
+            }
+            subtasks_list.remove(&child);
+        }
+
+        let subtasks = task_object.subtasks().0;
+        for (index, subtask) in subtasks.iter().enumerate() {
+            let check_button = CheckButton::builder()
+                .valign(Align::Center)
+                .active(subtask.completed)
+                .build();
+            check_button.connect_toggled(clone!(@weak self as window, @weak task_object => move |check_button| {
+                let mut subtasks = task_object.subtasks();
+                if let Some(subtask) = subtasks.0.get_mut(index) {
+                    subtask.completed = check_button.is_active();
+                    window.set_task_property(&task_object, "subtasks", subtasks.to_value());
+                }
+            }));
+
+            let remove_button = Button::builder()
+                .icon_name("window-close-symbolic")
+                .tooltip_text("Remove Subtask")
+                .valign(Align::Center)
+                .build();
+            remove_button.add_css_class("flat");
+            remove_button.connect_clicked(clone!(@weak self as window, @weak task_object => move |_| {
+                let mut subtasks = task_object.subtasks();
+                if index < subtasks.0.len() {
+                    subtasks.0.remove(index);
+                    window.set_task_property(&task_object, "subtasks", subtasks.to_value());
+                }
+            }));
+
+            let row = ActionRow::builder()
+                .title(glib::markup_escape_text(&subtask.content))
+                .activatable_widget(&check_button)
+                .build();
+            if subtask.completed {
+                row.add_css_class("dim-label");
+            }
+            row.add_prefix(&check_button);
+            row.add_suffix(&remove_button);
+            subtasks_list.insert(&row, index as i32);
+        }
+
+        let completed = subtasks.iter().filter(|subtask| subtask.completed).count();
+        let description = format!("{completed} of {} done", subtasks.len());
+        subtasks_group.set_description((!subtasks.is_empty()).then_some(description.as_str()));
+    }
+
+    pub(crate) fn create_notes_editor(task_object: &TaskObject) -> gtk::Box {
+        let notes_view = TextView::builder()
+            .wrap_mode(gtk::WrapMode::WordChar)
+            .accepts_tab(false)
+            .top_margin(6)
+            .bottom_margin(6)
+            .left_margin(6)
+            .right_margin(6)
+            .build();
+        notes_view.add_css_class("card");
+
+        task_object
+            .bind_property("notes", &notes_view.buffer(), "text")
+            .bidirectional()
+            .sync_create()
+            .build();
+
+        // The notes are shown rendered as Markdown until they are edited
+        let preview_label = Label::builder()
+            .wrap(true)
+            .wrap_mode(pango::WrapMode::WordChar)
+            .xalign(0.0)
+            .selectable(true)
+            .use_markup(true)
+            .build();
+        task_object
+            .bind_property("notes", &preview_label, "label")
+            .sync_create()
+            .transform_to(|_, notes: String| Some(markdown::to_pango_markup(&notes)))
+            .build();
+
+        let notes_stack = Stack::new();
+        notes_stack.add_named(&preview_label, Some("preview"));
+        notes_stack.add_named(&notes_view, Some("editor"));
+
+        let edit_button = ToggleButton::builder()
+            .icon_name("document-edit-symbolic")
+            .tooltip_text("Edit Notes")
+            .active(task_object.notes().is_empty())
+            .build();
+        edit_button.add_css_class("flat");
+        edit_button
+            .bind_property("active", &notes_stack, "visible-child-name")
+            .sync_create()
+            .transform_to(|_, editing: bool| Some(if editing { "editor" } else { "preview" }))
+            .build();
+
+        let notes_label = Label::builder()
+            .label("Notes")
+            .xalign(0.0)
+            .hexpand(true)
+            .build();
+        notes_label.add_css_class("heading");
+
+        let header_box = gtk::Box::builder()
+            .spacing(6)
+            .build();
+        header_box.append(&notes_label);
+        header_box.append(&edit_button);
+
+        let notes_box = gtk::Box::builder()
+            .orientation(gtk::Orientation::Vertical)
+            .spacing(6)
+            .margin_top(12)
+            .margin_bottom(12)
+            .margin_start(12)
+            .margin_end(12)
+            .build();
+        notes_box.append(&header_box);
+        notes_box.append(&notes_stack);
+
+        let focus_label = Label::builder()
+            .xalign(0.0)
+            .build();
+        focus_label.add_css_class("caption");
+        focus_label.add_css_class("dim-label");
+        task_object
+            .bind_property("focus-seconds", &focus_label, "label")
+            .sync_create()
+            .transform_to(|_, seconds: u64| Some(format!("Focused for {}", utils::format_minutes(seconds / 60))))
+            .build();
+        task_object
+            .bind_property("focus-seconds", &focus_label, "visible")
+            .sync_create()
+            .transform_to(|_, seconds: u64| Some(seconds >= 60))
+            .build();
+        notes_box.append(&focus_label);
+
+        notes_box
+    }
+
+    // Thumbnails of the attached files, opened with the default application
+    pub(crate) fn create_attachments_box(&self, task_object: &TaskObject) -> gtk::Box {
+        let attachments_label = Label::builder()
+            .label("Attachments")
+            .xalign(0.0)
+            .hexpand(true)
+            .build();
+        attachments_label.add_css_class("heading");
+
+        let attach_button = Button::builder()
+            .icon_name("mail-attachment-symbolic")
+            .tooltip_text("Attach File")
+            .build();
+        attach_button.add_css_class("flat");
+        attach_button.connect_clicked(clone!(@weak self as window, @weak task_object => move |_| {
+            glib::MainContext::default().spawn_local(clone!(@weak window, @weak task_object => async move {
+                window.attach_file(&task_object).await;
+            }));
+        }));
+
+        let header_box = gtk::Box::builder()
+            .spacing(6)
+            .build();
+        header_box.append(&attachments_label);
+        header_box.append(&attach_button);
+
+        let flow_box = gtk::FlowBox::builder()
+            .selection_mode(gtk::SelectionMode::None)
+            .column_spacing(6)
+            .row_spacing(6)
+            .build();
+
+        let attachments_box = gtk::Box::builder()
+            .orientation(gtk::Orientation::Vertical)
+            .spacing(6)
+            .margin_bottom(12)
+            .margin_start(12)
+            .margin_end(12)
+            .build();
+        attachments_box.append(&header_box);
+        attachments_box.append(&flow_box);
+
+        self.update_attachments(&flow_box, task_object);
+        task_object.connect_notify_for(
+            &flow_box,
+            "attachments",
+            clone!(@weak self as window, @weak flow_box => move |task_object| {
+                window.update_attachments(&flow_box, task_object);
+            })
+        );
+
+        attachments_box
+    }
+
+    fn update_attachments(&self, flow_box: &gtk::FlowBox, task_object: &TaskObject) {
+        while let Some(child) = flow_box.first_child() {
+            flow_box.remove(&child);
+        }
+
+        let attachment_names = task_object.attachments();
+        flow_box.set_visible(!attachment_names.is_empty());
+        for name in attachment_names {
+            let path = attachments::path(&task_object.uuid(), &name);
+
+            let preview: gtk::Widget = if attachments::is_image(&path) {
+                gtk::Picture::builder()
+                    .file(&gio::File::for_path(&path))
+                    .content_fit(gtk::ContentFit::Cover)
+                    .width_request(96)
+                    .height_request(96)
+                    .build()
+                    .upcast()
+            } else {
+                gtk::Image::builder()
+                    .gicon(&attachments::icon(&path))
+                    .pixel_size(48)
+                    .width_request(96)
+                    .height_request(96)
+                    .build()
+                    .upcast()
+            };
+            let name_label = Label::builder()
+                .label(&name)
+                .ellipsize(pango::EllipsizeMode::Middle)
+                .max_width_chars(12)
+                .build();
+            name_label.add_css_class("caption");
+
+            let content_box = gtk::Box::builder()
+                .orientation(gtk::Orientation::Vertical)
+                .spacing(6)
+                .build();
+            content_box.append(&preview);
+            content_box.append(&name_label);
+
+            let open_button = Button::builder()
+                .child(&content_box)
+                .tooltip_text(&name)
+                .build();
+            open_button.add_css_class("flat");
+            open_button.connect_clicked(clone!(@weak self as window => move |_| {
+                let uri = gio::File::for_path(&path).uri();
+                gtk::show_uri(Some(&window), &uri, gdk::CURRENT_TIME);
+            }));
+
+            let remove_button = Button::builder()
+                .icon_name("window-close-symbolic")
+                .tooltip_text("Remove Attachment")
+                .halign(Align::End)
+                .valign(Align::Start)
+                .build();
+            remove_button.add_css_class("circular");
+            remove_button.add_css_class("osd");
+            remove_button.connect_clicked(clone!(@weak self as window, @weak task_object => move |_| {
+                let attachment_names: Vec<String> = task_object
+                    .attachments()
+                    .into_iter()
+                    .filter(|attachment_name| *attachment_name != name)
+                    .collect();
+                window.set_task_property(&task_object, "attachments", attachment_names.to_value());
+            }));
+
+            let overlay = gtk::Overlay::builder()
+                .child(&open_button)
+                .build();
+            overlay.add_overlay(&remove_button);
+            flow_box.append(&overlay);
+        }
+    }
+
+    async fn attach_file(&self, task_object: &TaskObject) {
+        let filter = FileFilter::new("All Files").glob("*");
+        let Some(source) = file_chooser::open_file(self, "Attach File", filter).await else {
+            return;
+        };
+
+        let uuid = task_object.uuid();
+        let result = gio::spawn_blocking(move || attachments::attach(&uuid, &source)).await;
+        match result {
+            Ok(Ok(name)) => {
+                let mut attachment_names = task_object.attachments();
+                attachment_names.push(name);
+                self.set_task_property(task_object, "attachments", attachment_names.to_value());
+            }
+            Ok(Err(err)) => {
+                glib::g_warning!("todo", "Could not attach the file: {}", err);
+                self.imp().toast_overlay.add_toast(Toast::new("Could not attach the file"));
+            }
+            Err(_) => self.imp().toast_overlay.add_toast(Toast::new("Could not attach the file")),
+        }
+    }
+
+    fn setup_tag_filter(&self) {
+        let tags_list = gtk::Box::builder()
+            .orientation(gtk::Orientation::Vertical)
+            .build();
+
+        let popover = Popover::builder()
+            .child(&tags_list)
+            .build();
+        popover.add_css_class("menu");
+        self.imp().tag_filter_button.set_popover(Some(&popover));
+
+        popover.connect_show(clone!(@weak self as window, @weak tags_list => move |popover| {
+            window.update_tag_filter_list(&tags_list, popover);
+        }));
+    }
+
+    // Offers every tag used in the current collection as a filter
+    fn update_tag_filter_list(&self, tags_list: &gtk::Box, popover: &Popover) {
+        while let Some(child) = tags_list.first_child() {
+            tags_list.remove(&child);
+        }
+
+        let mut tags: Vec<String> = Vec::new();
+        if let Some(collection) = self.imp().current_collection.borrow().clone() {
+            for task_object in collection.tasks().iter::<TaskObject>().filter_map(Result::ok) {
+                tags.extend(task_object.tags());
+            }
+        }
+        tags.sort();
+        tags.dedup();
+
+        let choices = std::iter::once(None).chain(tags.into_iter().map(Some));
+        for tag in choices {
+            let label = tag.as_ref().map_or("All Tags".to_string(), |tag| format!("#{tag}"));
+            let button = Button::builder()
+                .label(label)
+                .build();
+            button.add_css_class("flat");
+
+            button.connect_clicked(clone!(@weak self as window, @weak popover => move |_| {
+                window.set_tag_filter(tag.clone());
+                popover.popdown();
+            }));
+            tags_list.append(&button);
+        }
+    }
+
+    fn set_tag_filter(&self, tag: Option<String>) {
+        let tag_filter_button = &self.imp().tag_filter_button;
+        if tag.is_some() {
+            tag_filter_button.add_css_class("accent");
+        } else {
+            tag_filter_button.remove_css_class("accent");
+        }
+
+        self.imp().tag_filter.replace(tag);
+        self.set_filter();
+    }
+
+    fn setup_callbacks(&self) {
+        // Setup callback for activation of the entry
+        self.imp()
+            .entry
+            .connect_activate(clone!(@weak self as window => move |_| {
+                window.new_task();
+            }));
+
+        self.imp().entry.connect_icon_release(
+            clone!(@weak self as window => move |_, _| {
+                window.new_task();
+            })
+        );
+
+        self.setup_entry_completion(&self.imp().entry);
+
+        self.imp().entry.connect_changed(clone!(@weak self as window => move |_| {
+            window.update_entry_date_chip();
+        }));
+
+        // Pasting a list adds one task per line instead of one long task
+        if let Some(text) = self.imp().entry.delegate().and_downcast::<gtk::Text>() {
+            text.connect_paste_clipboard(clone!(@weak self as window => move |text| {
+                text.stop_signal_emission_by_name("paste-clipboard");
+                glib::MainContext::default().spawn_local(clone!(@weak window, @weak text => async move {
+                    window.paste_into_entry(&text).await;
+                }));
+            }));
+        }
+
+        self.imp().search_entry.connect_search_changed(
+            clone!(@weak self as window => move |_| {
+                window.set_filter();
+            })
+        );
+
+        self.imp().search_bar.set_key_capture_widget(Some(self));
+
+        self.setup_tag_filter();
+
+        self.settings().connect_changed(
+            Some("sort-by-priority"),
+            clone!(@weak self as window => move |_, _| {
+                window.set_sorter();
+            })
+        );
+
+        self.settings().connect_changed(
+            Some("hide-blocked-tasks"),
+            clone!(@weak self as window => move |_, _| {
+                window.set_filter();
+            })
+        );
+
+        self.set_stack();
+        self.collections().connect_items_changed(
+            clone!(@weak self as window => move |_, _, _, _| {
+                window.set_stack();
+            })
+        );
+
+        self.imp().tasks_list.set_header_func(
+            clone!(@weak self as window => move |row, before| {
+                window.update_task_header(row, before);
+            })
+        );
+
+        self.imp().smart_list.connect_row_activated(
+            clone!(@weak self as window => move |_, row| {
+                let imp = window.imp();
+                let smart_view = if row == &*imp.all_tasks_row {
+                    SmartView::AllTasks
+                } else if row == &*imp.today_row {
+                    SmartView::Today
+                } else if row == &*imp.upcoming_row {
+                    SmartView::Upcoming
+                } else if row == &*imp.starred_row {
+                    SmartView::Starred
+                } else if row == &*imp.completed_row {
+                    SmartView::Completed
+                } else {
+                    return;
+                };
+                window.set_smart_view(smart_view);
+            })
+        );
+
+        self.imp().collections_list.connect_row_activated(
+            clone!(@weak self as window => move |_, row| {
+                // Folders open and close instead
+                if let Some(folder_row) = window.folder_tree_row(row) {
+                    folder_row.set_expanded(!folder_row.is_expanded());
+                    return;
+                }
+
+                let selected_collection = window
+                    .collection_at_row(row)
+                    .expect("Collection position needs to exist");
+
+                window.set_current_collection(selected_collection);
+                window.imp().split_view.set_show_content(true);
+            })
+        );
+
+        // Setup callback for clicking (and the releasing) the icon of the entry
+        self.imp().entry.connect_icon_release(
+            clone!(@weak self as window => move |_,_| {
+                window.new_task();
+            }),
+        );
+    }
+
+    fn set_stack(&self) {
+        if self.imp().locked.get() {
+            return;
+        }
+
+        if self.collections().n_items() > 0 {
+            self.imp().stack.set_visible_child_name("main");
+        } else {
+            self.imp().stack.set_visible_child_name("placeholder");
+        }
+    }
+
+    fn new_task(&self) {
+        // Get content from entry and clear it
+        let buffer = self.imp().entry.buffer();
+        let content = buffer.text().to_string();
+        if content.is_empty() {
+            return;
+        }
+        buffer.set_text("");
+
+        // "@Work fix the printer" goes to another collection than the one shown
+        let (mentioned, content) = if utils::is_url(&content) {
+            (None, content)
+        } else {
+            parser::take_mention(&content, |name| self.find_mentioned_collection(name))
+        };
+
+        // A web address on its own is kept as it is, "#" would start a tag otherwise
+        let task = if utils::is_url(&content) {
+            let link = content.trim();
+            let task = TaskObject::new(false, link.to_string());
+            task.set_link(link);
+            self.fetch_link_title(&task);
+            task
+        } else {
+            // Tags, priority and due date can be typed along with the content
+            let parsed = parser::parse_task(&content);
+            if parsed.content.is_empty() {
+                return;
+            }
+
+            let task = TaskObject::new(false, parsed.content);
+            task.set_tags(parsed.tags);
+            task.set_priority(parsed.priority);
+            task.set_due_date(parsed.due_date);
+            task.set_reminder_time(parsed.reminder_time);
+            task
+        };
+
+        // Add new task to model
+        let collection = mentioned.unwrap_or_else(|| self.current_collection());
+        let tasks = collection.tasks();
+        let position = tasks.n_items();
+        tasks.append(&task);
+        self.push_command(Command::InsertTasks {
+            tasks,
+            items: vec![(position, task)],
+        });
+
+        // The task is out of sight otherwise
+        if collection != self.current_collection() {
+            self.imp().toast_overlay.add_toast(Toast::new(&format!("Added to “{}”", collection.title())));
+        }
+    }
+
+    // Previews the due date found in the entry, before the task is added
+    fn update_entry_date_chip(&self) {
+        let imp = self.imp();
+        let text = imp.entry.text();
+        let parsed = if utils::is_url(&text) {
+            parser::ParsedTask::default()
+        } else {
+            parser::parse_task(&text)
+        };
+
+        imp.entry_date_chip.set_visible(!parsed.due_date.is_empty());
+        let due = utils::display_date(&parsed.due_date);
+        if parsed.reminder_time.is_empty() {
+            imp.entry_date_chip.set_label(&format!("Due {due}"));
+        } else {
+            imp.entry_date_chip.set_label(&format!("Due {due} at {}", parsed.reminder_time));
+        }
+    }
+
+    // Suggests the tags in use after "#" and the collections after "@" while a task is typed
+    pub fn setup_entry_completion(&self, entry: &Entry) {
+        let model = gtk::ListStore::new(&[String::static_type()]);
+
+        // The suggestions are gathered when a "#" or "@" is typed, so they are never stale.
+        // Connected before the completion, to fill the model before it looks for matches.
+        entry.connect_changed(clone!(@weak self as window, @strong model => move |entry| {
+            let text = entry.text();
+            let word = text.rsplit(' ').next().unwrap_or_default();
+            if word == "#" || word == "@" {
+                model.clear();
+                for suggestion in window.entry_suggestions() {
+                    model.set(&model.append(), &[(0, &suggestion)]);
+                }
+            }
+        }));
+
+        let completion = gtk::EntryCompletion::builder()
+            .model(&model)
+            .text_column(0)
+            .popup_single_match(true)
+            .build();
+
+        // Only the word being typed is completed, `key` is the whole text in lowercase
+        completion.set_match_func(|completion, key, iter| {
+            let word = key.rsplit(' ').next().unwrap_or_default();
+            let Some(model) = completion.model() else {
+                return false;
+            };
+            let suggestion = model.get::<String>(iter, 0).to_lowercase();
+            (word.starts_with('#') || word.starts_with('@')) && suggestion.starts_with(word) && suggestion != word
+        });
+
+        completion.connect_match_selected(
+            clone!(@weak entry => @default-return glib::Propagation::Proceed, move |_, model, iter| {
+                let suggestion = model.get::<String>(iter, 0);
+                let text = entry.text();
+                let word_start = text.rfind(' ').map_or(0, |index| index + 1);
+                entry.set_text(&format!("{}{suggestion} ", &text[..word_start]));
+                entry.set_position(-1);
+                glib::Propagation::Stop
+            })
+        );
+        entry.set_completion(Some(&completion));
+    }
+
+    // "#tag" for every tag in use and "@Name" for every collection
+    fn entry_suggestions(&self) -> Vec<String> {
+        let collections = self.collection_list();
+        let mut tags: Vec<String> = collections
+            .iter()
+            .flat_map(|collection| collection.tasks().iter::<TaskObject>().filter_map(Result::ok))
+            .flat_map(|task| task.tags())
+            .collect();
+        tags.sort();
+        tags.dedup();
+
+        tags.into_iter()
+            .map(|tag| format!("#{tag}"))
+            .chain(collections.iter().map(|collection| format!("@{}", parser::mention_name(&collection.title()))))
+            .collect()
+    }
+
+    // Turns a task that is just a web address into "Title — address", if enabled
+    fn fetch_link_title(&self, task_object: &TaskObject) {
+        if !self.settings().boolean("fetch-link-titles") {
+            return;
+        }
+
+        let link = task_object.link();
+        glib::MainContext::default().spawn_local(clone!(@weak task_object => async move {
+            match crate::sync::run(page_title::fetch(link.clone())).await {
+                // Unless it was edited in the meantime
+                Ok(Some(title)) if task_object.content() == link => {
+                    task_object.set_content(format!("{title} — {link}"));
+                }
+                Ok(_) => {}
+                Err(err) => glib::g_warning!("todo", "Could not fetch the title of {}: {:#}", link, err),
+            }
+        }));
+    }
+
+    async fn paste_into_entry(&self, text: &gtk::Text) {
+        let clipboard_text = match text.clipboard().read_text_future().await {
+            Ok(Some(clipboard_text)) => clipboard_text,
+            _ => return,
+        };
+
+        if clipboard_text.trim().lines().count() > 1 {
+            self.add_tasks_from_text(&clipboard_text);
+            return;
+        }
+
+        text.delete_selection();
+        let mut position = text.position();
+        text.insert_text(clipboard_text.trim_end(), &mut position);
+        text.set_position(position);
+    }
+
+    // One task per line, "- [x]" items are added as done
+    fn add_tasks_from_text(&self, text: &str) {
+        let task_objects: Vec<TaskObject> = markdown::parse_checklist(text)
+            .into_iter()
+            .filter_map(|item| {
+                let parsed = parser::parse_task(&item.content);
+                if parsed.content.is_empty() {
+                    return None;
+                }
+
+                let task = TaskObject::new(item.completed, parsed.content);
+                task.set_notes(item.notes);
+                task.set_tags(parsed.tags);
+                task.set_priority(parsed.priority);
+                task.set_due_date(parsed.due_date);
+                task.set_reminder_time(parsed.reminder_time);
+                Some(task)
+            })
+            .collect();
+        self.append_tasks(task_objects);
+    }
+
+    // One task per line, web addresses are kept as the link of their task
+    fn add_dropped_text(&self, text: &str) {
+        let task_objects: Vec<TaskObject> = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .filter_map(|line| {
+                if utils::is_url(line) {
+                    let task = TaskObject::new(false, line.to_string());
+                    task.set_link(line);
+                    self.fetch_link_title(&task);
+                    return Some(task);
+                }
+
+                let parsed = parser::parse_task(line);
+                if parsed.content.is_empty() {
+                    return None;
+                }
+                let task = TaskObject::new(false, parsed.content);
+                task.set_tags(parsed.tags);
+                task.set_priority(parsed.priority);
+                task.set_due_date(parsed.due_date);
+                task.set_reminder_time(parsed.reminder_time);
+                Some(task)
+            })
+            .collect();
+        self.append_tasks(task_objects);
+    }
+
+    // Adds the tasks at the end of the current collection, as one step to undo
+    fn append_tasks(&self, task_objects: Vec<TaskObject>) {
+        if task_objects.is_empty() {
+            return;
+        }
+
+        let task_count = task_objects.len();
+        let tasks = self.tasks();
+        let start = tasks.n_items();
+        tasks.extend_from_slice(&task_objects);
+        self.push_command(Command::InsertTasks {
+            tasks,
+            items: (start..).zip(task_objects).collect(),
+        });
+        self.show_undo_toast(&format!("Added {}", utils::count_label(task_count, "task")));
+    }
+
+    fn setup_actions(&self) {
+        // Every collection keeps its own filter, the action shows the current one
+        let filter_state = self
+            .imp()
+            .current_collection
+            .borrow()
+            .as_ref()
+            .map_or(DEFAULT_FILTER.to_string(), CollectionObject::filter);
+        let action_filter = gio::SimpleAction::new_stateful(
+            "filter",
+            Some(glib::VariantTy::STRING),
+            &filter_state.to_variant(),
+        );
+        action_filter.connect_change_state(clone!(@weak self as window => move |action, state| {
+            if window.is_locked() {
+                return;
+            }
+            let Some(filter_state) = state.and_then(|state| state.get::<String>()) else {
+                return;
+            };
+            action.set_state(&filter_state.to_variant());
+
+            let current_collection = window.imp().current_collection.borrow().clone();
+            if let Some(collection) = current_collection {
+                collection.set_filter(filter_state);
+                // Switching to or from the archive changes the list being shown
+                window.set_current_collection(collection);
+            }
+        }));
+        self.add_action(&action_filter);
+
+        let action_sort_by_priority = self.settings().create_action("sort-by-priority");
+        self.add_action(&action_sort_by_priority);
+
+        let action_hide_blocked_tasks = self.settings().create_action("hide-blocked-tasks");
+        self.add_action(&action_hide_blocked_tasks);
+
+        let action_run_in_background = self.settings().create_action("run-in-background");
+        self.add_action(&action_run_in_background);
+
+        let action_badge_count = self.settings().create_action("badge-count");
+        self.add_action(&action_badge_count);
+
+        let action_selection_mode = gio::SimpleAction::new_stateful("selection-mode", None, &false.to_variant());
+        action_selection_mode.connect_change_state(clone!(@weak self as window => move |action, state| {
+            if window.is_locked() {
+                return;
+            }
+            let Some(state) = state else {
+                return;
+            };
+            action.set_state(state);
+            window.set_selection_mode(state.get::<bool>().unwrap_or_default());
+        }));
+        self.add_action(&action_selection_mode);
+    }
+
+    fn remove_done_tasks(&self) {
+        let done_tasks: Vec<TaskObject> = self
+            .tasks()
+            .iter::<TaskObject>()
+            .filter_map(Result::ok)
+            .filter(TaskObject::is_completed)
+            .collect();
+
+        let collection = self.current_collection();
+        let Some(command) = self.trash_tasks(&collection, &collection.tasks(), &done_tasks) else {
+            self.imp().toast_overlay.add_toast(Toast::new("There are no done tasks to remove"));
+            return;
+        };
+
+        // The removed tasks stay in the undo history, so the toast can bring them back
+        self.push_command(command);
+        self.show_undo_toast(&format!("{} moved to the trash", utils::count_label(done_tasks.len(), "done task")));
+    }
+
+    fn archive_done_tasks(&self, collection: &CollectionObject) {
+        let done_tasks: Vec<TaskObject> = collection
+            .tasks()
+            .iter::<TaskObject>()
+            .filter_map(Result::ok)
+            .filter(TaskObject::is_completed)
+            .collect();
+
+        let Some(command) = Self::move_tasks(&collection.tasks(), &collection.archived(), &done_tasks) else {
+            self.imp().toast_overlay.add_toast(Toast::new("There are no done tasks to archive"));
+            return;
+        };
+
+        self.push_command(command);
+        self.show_undo_toast(&format!("{} archived", utils::count_label(done_tasks.len(), "done task")));
+    }
+
+    // Moves tasks from `tasks` to the trash of `collection`, returning the command to undo it
+    fn trash_tasks(&self, collection: &CollectionObject, tasks: &gio::ListStore, task_objects: &[TaskObject]) -> Option<Command> {
+        let move_command = Self::move_tasks(tasks, &collection.trash(), task_objects)?;
+
+        // Undone with the move, a restored task must not keep the time it was trashed
+        let deleted_at = utils::today();
+        let mut commands = vec![move_command];
+        for task_object in task_objects {
+            commands.push(Command::EditTask {
+                task: task_object.clone(),
+                property: "deleted-at",
+                old_value: task_object.property_value("deleted-at"),
+                new_value: deleted_at.to_value(),
+            });
+            task_object.set_deleted_at(deleted_at.clone());
+        }
+        Some(Command::Group(commands))
+    }
+
+    // Moves tasks from one store to the end of another, returning the command to undo it
+    fn move_tasks(tasks: &gio::ListStore, destination: &gio::ListStore, task_objects: &[TaskObject]) -> Option<Command> {
+        let mut removed: Vec<(u32, TaskObject)> = task_objects
+            .iter()
+            .filter_map(|task_object| tasks.find(task_object).map(|position| (position, task_object.clone())))
+            .collect();
+        if removed.is_empty() {
+            return None;
+        }
+
+        removed.sort_by_key(|(position, _)| *position);
+        for (position, _) in removed.iter().rev() {
+            tasks.remove(*position);
+        }
+
+        let inserted = removed
+            .iter()
+            .map(|(_, task_object)| {
+                destination.append(task_object);
+                (destination.n_items() - 1, task_object.clone())
+            })
+            .collect();
+
+        Some(Command::Group(vec![
+            Command::RemoveTasks { tasks: tasks.clone(), items: removed },
+            Command::InsertTasks { tasks: destination.clone(), items: inserted },
+        ]))
+    }
+
+    fn setup_calendar(&self) {
+        self.imp().sidebar_footer.connect_row_activated(
+            clone!(@weak self as window => move |_, row| {
+                if row == &*window.imp().calendar_row {
+                    window.show_calendar();
+                }
+            })
+        );
+
+        self.all_tasks().connect_items_changed(clone!(@weak self as window => move |_, _, _, _| {
+            window.refresh_calendar();
+        }));
+    }
+
+    fn show_calendar(&self) {
+        self.imp().collections_list.unselect_all();
+        self.imp().smart_list.unselect_all();
+        self.imp().smart_collections_list.unselect_all();
+
+        if self.imp().calendar_month.borrow().is_none() {
+            self.imp().calendar_month.replace(calendar::month_start(&utils::now()));
+        }
+        self.update_calendar();
+
+        self.imp().split_view.set_content(Some(&*self.imp().calendar_page));
+        self.imp().split_view.set_show_content(true);
+    }
+
+    // Shows the month `months` away from the one shown, or the current month for 0
+    fn move_calendar(&self, months: i32) {
+        let month = if months == 0 {
+            calendar::month_start(&utils::now())
+        } else {
+            self.imp()
+                .calendar_month
+                .borrow()
+                .as_ref()
+                .and_then(|month| month.add_months(months).ok())
+        };
+
+        if month.is_some() {
+            self.imp().calendar_month.replace(month);
+            self.update_calendar();
+        }
+    }
+
+    // The grid is built from the tasks rather than bound to them, rebuild it on changes
+    fn refresh_calendar(&self) {
+        if self.imp().split_view.content().as_ref() == Some(&*self.imp().calendar_page) {
+            self.update_calendar();
+        }
+    }
+
+    fn update_calendar(&self) {
+        let Some(month_start) = self.imp().calendar_month.borrow().clone() else {
+            return;
+        };
+        self.imp().calendar_title.set_title(&calendar::month_title(&month_start));
+
+        let calendar_grid = &self.imp().calendar_grid;
+        while let Some(child) = calendar_grid.first_child() {
+            calendar_grid.remove(&child);
+        }
+
+        for (column, weekday) in calendar::WEEKDAYS.into_iter().enumerate() {
+            let weekday_label = Label::new(Some(weekday));
+            weekday_label.add_css_class("caption-heading");
+            weekday_label.add_css_class("dim-label");
+            calendar_grid.attach(&weekday_label, column as i32, 0, 1, 1);
+        }
+
+        // Open tasks of all collections by their due date
+        let mut tasks_by_date: HashMap<String, Vec<TaskObject>> = HashMap::new();
+        for task in self.all_tasks().iter::<TaskObject>().filter_map(Result::ok) {
+            if !task.is_completed() && !task.due_date().is_empty() {
+                tasks_by_date.entry(task.due_date()).or_default().push(task);
+            }
+        }
+
+        let today = utils::today();
+        for (index, day) in calendar::grid_days(&month_start).into_iter().enumerate() {
+            let date = utils::format_date(&day);
+            let tasks = tasks_by_date.remove(&date).unwrap_or_default();
+            let day_box = self.create_calendar_day(&date, &day, &tasks);
+            if day.month() != month_start.month() {
+                day_box.add_css_class("other-month");
+            }
+            if date == today {
+                day_box.add_css_class("today");
+            }
+            calendar_grid.attach(&day_box, index as i32 % 7, index as i32 / 7 + 1, 1, 1);
+        }
+    }
+
+    fn create_calendar_day(&self, date: &str, day: &glib::DateTime, tasks: &[TaskObject]) -> gtk::Box {
+        let day_label = Label::builder()
+            .label(day.day_of_month().to_string())
+            .xalign(0.0)
+            .build();
+        day_label.add_css_class("caption-heading");
+
+        let day_box = gtk::Box::builder()
+            .orientation(gtk::Orientation::Vertical)
+            .spacing(2)
+            .build();
+        day_box.add_css_class("calendar-day");
+        day_box.append(&day_label);
+
+        for task_object in tasks.iter().take(calendar::TASKS_PER_DAY) {
+            day_box.append(&self.create_calendar_task(task_object));
+        }
+        if tasks.len() > calendar::TASKS_PER_DAY {
+            let more_label = Label::builder()
+                .label(format!("{} more", tasks.len() - calendar::TASKS_PER_DAY))
+                .xalign(0.0)
+                .tooltip_text(
+                    tasks[calendar::TASKS_PER_DAY..]
+                        .iter()
+                        .map(|task| task.content())
+                        .collect::<Vec<String>>()
+                        .join("\n"),
+                )
+                .build();
+            more_label.add_css_class("caption");
+            more_label.add_css_class("dim-label");
+            day_box.append(&more_label);
+        }
+
+        // Tasks dragged from other days become due on this one
+        let drop_target = gtk::DropTarget::new(TaskObject::static_type(), gdk::DragAction::MOVE);
+        let date = date.to_string();
+        drop_target.connect_drop(clone!(@weak self as window => @default-return false, move |_, value, _, _| {
+            let Ok(task_object) = value.get::<TaskObject>() else {
+                return false;
+            };
+            window.reschedule_task(&task_object, &date);
+            true
+        }));
+        day_box.add_controller(drop_target);
+
+        day_box
+    }
+
+    fn create_calendar_task(&self, task_object: &TaskObject) -> Button {
+        let content_label = Label::builder()
+            .label(task_object.content())
+            .xalign(0.0)
+            .ellipsize(pango::EllipsizeMode::End)
+            .build();
+        content_label.add_css_class("caption");
+
+        let task_button = Button::builder()
+            .child(&content_label)
+            .tooltip_text(task_object.content())
+            .build();
+        task_button.add_css_class("flat");
+        task_button.add_css_class("calendar-task");
+        task_button.connect_clicked(clone!(@weak self as window, @weak task_object => move |_| {
+            window.show_task(&task_object.uuid());
+            window.show_task_details(&task_object);
+        }));
+
+        let drag_source = gtk::DragSource::builder()
+            .actions(gdk::DragAction::MOVE)
+            .content(&gdk::ContentProvider::for_value(&task_object.to_value()))
+            .build();
+        task_button.add_controller(drag_source);
+
+        task_button
+    }
+
+    fn setup_trash(&self) {
+        // Every collection has its own trash, the trash page shows all of them
+        let trashes = MapListModel::new(
+            Some(self.collections()),
+            Some(|obj: &glib::Object| {
+                let collection = obj.downcast_ref::<CollectionObject>().expect("Expecting CollectionObject");
+                collection.trash().upcast()
+            }),
+        );
+        let trash_model = FlattenListModel::new(Some(trashes));
+
+        self.imp().trash_list.bind_model(
+            Some(&trash_model),
+            clone!(@weak self as window => @default-panic, move |obj| {
+                let task_object = obj.downcast_ref().expect("Expecting TaskObject");
+                window.create_trash_row(task_object).upcast()
+            })
+        );
+
+        self.set_trash_stack(&trash_model);
+        trash_model.connect_items_changed(clone!(@weak self as window => move |trash_model, _, _, _| {
+            window.set_trash_stack(trash_model);
+        }));
+
+        self.imp().sidebar_footer.connect_row_activated(
+            clone!(@weak self as window => move |_, row| {
+                if row == &*window.imp().trash_row {
+                    window.show_trash();
+                }
+            })
+        );
+
+        self.purge_trash();
+        glib::timeout_add_seconds_local(
+            TRASH_PURGE_INTERVAL_SECONDS,
+            clone!(@weak self as window => @default-return glib::ControlFlow::Break, move || {
+                window.purge_trash();
+                glib::ControlFlow::Continue
+            })
+        );
+    }
+
+    fn set_trash_stack(&self, trash_model: &FlattenListModel) {
+        let page = if trash_model.n_items() > 0 { "list" } else { "empty" };
+        self.imp().trash_stack.set_visible_child_name(page);
+    }
+
+    fn show_trash(&self) {
+        self.imp().collections_list.unselect_all();
+        self.imp().smart_list.unselect_all();
+        self.imp().smart_collections_list.unselect_all();
+        self.imp().split_view.set_content(Some(&*self.imp().trash_page));
+        self.imp().split_view.set_show_content(true);
+    }
+
+    fn create_trash_row(&self, task_object: &TaskObject) -> ActionRow {
+        let collection_title = self
+            .find_trashed_task(task_object)
+            .map(|(collection, _)| collection.title())
+            .unwrap_or_default();
+
+        let row = ActionRow::builder()
+            .title(task_object.content())
+            .subtitle(format!(
+                "{collection_title} · Deleted {}",
+                utils::display_date(&task_object.deleted_at()).to_lowercase()
+            ))
+            .build();
+
+        let restore_button = Button::builder()
+            .icon_name("edit-undo-symbolic")
+            .valign(Align::Center)
+            .tooltip_text("Restore")
+            .build();
+        restore_button.add_css_class("flat");
+        restore_button.connect_clicked(clone!(@weak self as window, @weak task_object => move |_| {
+            window.restore_task(&task_object);
+        }));
+        row.add_suffix(&restore_button);
+
+        let delete_button = Button::builder()
+            .icon_name("edit-delete-symbolic")
+            .valign(Align::Center)
+            .tooltip_text("Delete Forever")
+            .build();
+        delete_button.add_css_class("flat");
+        delete_button.connect_clicked(clone!(@weak self as window, @weak task_object => move |_| {
+            window.delete_task_forever(&task_object);
+        }));
+        row.add_suffix(&delete_button);
+
+        row
+    }
+
+    // The collection whose trash contains `task` and the position of the task in it
+    fn find_trashed_task(&self, task: &TaskObject) -> Option<(CollectionObject, u32)> {
+        self.collections()
+            .iter::<CollectionObject>()
+            .filter_map(Result::ok)
+            .find_map(|collection| {
+                let position = collection.trash().find(task)?;
+                Some((collection, position))
+            })
+    }
+
+    fn restore_task(&self, task_object: &TaskObject) {
+        let Some((collection, trash_position)) = self.find_trashed_task(task_object) else {
+            return;
+        };
+
+        let trash = collection.trash();
+        let tasks = collection.tasks();
+        trash.remove(trash_position);
+        let deleted_at = task_object.property_value("deleted-at");
+        task_object.set_deleted_at(String::new());
+        tasks.append(task_object);
+
+        self.push_command(Command::Group(vec![
+            Command::RemoveTasks { tasks: trash, items: vec![(trash_position, task_object.clone())] },
+            Command::InsertTasks { tasks: tasks.clone(), items: vec![(tasks.n_items() - 1, task_object.clone())] },
+            Command::EditTask {
+                task: task_object.clone(),
+                property: "deleted-at",
+                old_value: deleted_at,
+                new_value: String::new().to_value(),
+            },
+        ]));
+        self.show_undo_toast(&format!("Restored to “{}”", collection.title()));
+    }
+
+    fn delete_task_forever(&self, task_object: &TaskObject) {
+        let Some((collection, position)) = self.find_trashed_task(task_object) else {
+            return;
+        };
+
+        let trash = collection.trash();
+        trash.remove(position);
+        self.push_command(Command::RemoveTasks {
+            tasks: trash,
+            items: vec![(position, task_object.clone())],
+        });
+    }
+
+    async fn empty_trash(&self) {
+        let cancel_response = "cancel";
+        let empty_response = "empty";
+
+        let dialog = MessageDialog::builder()
+            .heading("Empty Trash?")
+            .body("All tasks in the trash will be permanently deleted.")
+            .transient_for(self)
+            .modal(true)
+            .destroy_with_parent(true)
+            .close_response(cancel_response)
+            .default_response(cancel_response)
+            .build();
+
+        dialog.add_responses(&[(cancel_response, "Cancel"), (empty_response, "Empty Trash")]);
+        dialog.set_response_appearance(empty_response, ResponseAppearance::Destructive);
+
+        if dialog.choose_future().await != empty_response {
+            return;
+        }
+
+        for collection in self.collections().iter::<CollectionObject>().filter_map(Result::ok) {
+            for task_object in collection.trash().iter::<TaskObject>().filter_map(Result::ok) {
+                attachments::remove_all(&task_object.uuid());
+            }
+            collection.trash().remove_all();
+        }
+    }
+
+    // Archives or trashes the tasks completed more than "auto-clear-days" ago, if chosen.
+    // Tasks completed before the time of completion was kept stay.
+    fn auto_clear_completed(&self) {
+        let action = self.settings().string("auto-clear-completed");
+        if action == "Never" {
+            return;
+        }
+        let days = self.settings().uint("auto-clear-days") as i32;
+        let Ok(cutoff) = utils::now().add_days(-days) else {
+            return;
+        };
+        let cutoff = utils::format_date(&cutoff);
+
+        let mut cleared = 0;
+        let mut commands = Vec::new();
+        for collection in self.collection_list() {
+            let tasks = collection.tasks();
+            let old_tasks: Vec<TaskObject> = tasks
+                .iter::<TaskObject>()
+                .filter_map(Result::ok)
+                .filter(|task| {
+                    task.is_completed() && utils::local_date(&task.completed_at()).is_some_and(|date| date < cutoff)
+                })
+                .collect();
+            if old_tasks.is_empty() {
+                continue;
+            }
+
+            cleared += old_tasks.len();
+            let command = if action == "Archive" {
+                Self::move_tasks(&tasks, &collection.archived(), &old_tasks)
+            } else {
+                self.trash_tasks(&collection, &tasks, &old_tasks)
+            };
+            commands.extend(command);
+        }
+
+        // Recorded like any other change, the positions in the undo history stay right
+        if !commands.is_empty() {
+            self.push_command(Command::Group(commands));
+            let done = if action == "Archive" { "archived" } else { "moved to the trash" };
+            self.show_undo_toast(&format!("{} {done}", utils::count_label(cleared, "completed task")));
+        }
+    }
+
+    // Tasks stay in the trash for `TRASH_RETENTION_DAYS`, then they are gone for good
+    fn purge_trash(&self) {
+        let Ok(cutoff) = utils::now().add_days(-TRASH_RETENTION_DAYS) else {
+            return;
+        };
+        let cutoff = utils::format_date(&cutoff);
+
+        for collection in self.collections().iter::<CollectionObject>().filter_map(Result::ok) {
+            let trash = collection.trash();
+            let mut position = 0;
+            while let Some(task_object) = trash.item(position).and_downcast::<TaskObject>() {
+                if task_object.deleted_at() < cutoff {
+                    attachments::remove_all(&task_object.uuid());
+                    trash.remove(position);
+                } else {
+                    position += 1;
+                }
+            }
+        }
+    }
+
+    // Combines the All/Open/Done state filter with the other active filters
+    fn filter(&self) -> Option<gtk::Filter> {
+        let filters: Vec<gtk::Filter> = [
+            self.state_filter(),
+            self.search_filter(),
+            self.tag_filter(),
+            self.blocked_filter(),
+        ]
+        .into_iter()
+        .flatten()
+        .map(|filter| filter.upcast())
+        .collect();
+
+        match filters.len() {
+            0 => None,
+            1 => filters.into_iter().next(),
+            _ => {
+                let every_filter = EveryFilter::new();
+                for filter in filters {
+                    every_filter.append(filter);
+                }
+                Some(every_filter.upcast())
+            }
+        }
+    }
+
+    fn search_filter(&self) -> Option<CustomFilter> {
+        let search_text = self.imp().search_entry.text().trim().to_lowercase();
+        if search_text.is_empty() {
+            return None;
+        }
+
+        Some(CustomFilter::new(move |obj| {
+            let task_object = obj.downcast_ref::<TaskObject>().expect("Expecting TaskObject");
+            task_object.content().to_lowercase().contains(&search_text)
+        }))
+    }
+
+    fn tag_filter(&self) -> Option<CustomFilter> {
+        let tag = self.imp().tag_filter.borrow().clone()?;
+
+        Some(CustomFilter::new(move |obj| {
+            let task_object = obj.downcast_ref::<TaskObject>().expect("Expecting TaskObject");
+            task_object.has_tag(&tag)
+        }))
+    }
+
+    fn blocked_filter(&self) -> Option<CustomFilter> {
+        if !self.settings().boolean("hide-blocked-tasks") {
+            return None;
+        }
+
+        Some(CustomFilter::new(clone!(@weak self as window => @default-return true, move |obj| {
+            let task_object = obj.downcast_ref::<TaskObject>().expect("Expecting TaskObject");
+            !window.is_blocked(task_object)
+        })))
+    }
+
+    fn state_filter(&self) -> Option<CustomFilter> {
+        let filter_state = self
+            .imp()
+            .current_collection
+            .borrow()
+            .as_ref()
+            .map_or(DEFAULT_FILTER.to_string(), CollectionObject::filter);
+
+        // Tasks that do not start yet are left out until their start date
+        let filter_open = CustomFilter::new(|obj| {
+            let task_object = obj.downcast_ref::<TaskObject>().expect("Expecting TaskObject");
+            !task_object.is_completed() && !task_object.is_deferred()
+        });
+
+        let filter_deferred = CustomFilter::new(|obj| {
+            let task_object = obj.downcast_ref::<TaskObject>().expect("Expecting TaskObject");
+            task_object.is_deferred()
+        });
+
+        let filter_done = CustomFilter::new(|obj| {
+            let task_object = obj.downcast_ref::<TaskObject>().expect("Expecting TaskObject");
+            task_object.is_completed()
+        });
+
+        let filter_due_today = CustomFilter::new(|obj| {
+            let task_object = obj.downcast_ref::<TaskObject>().expect("Expecting TaskObject");
+            task_object.is_due_today()
+        });
+
+        let filter_overdue = CustomFilter::new(|obj| {
+            let task_object = obj.downcast_ref::<TaskObject>().expect("Expecting TaskObject");
+            task_object.is_overdue()
+        });
+
+        // The filter comes from the data file, anything unknown shows all tasks
+        match filter_state.as_str() {
+            "Open" => Some(filter_open),
+            "Done" => Some(filter_done),
+            "DueToday" => Some(filter_due_today),
+            "Overdue" => Some(filter_overdue),
+            "Deferred" => Some(filter_deferred),
+            _ => None,
+        }
+    }
+
+    fn sorter(&self) -> Option<CustomSorter> {
+        let sort_by_priority: bool = self.settings().get("sort-by-priority");
+
+        // Timestamps sort chronologically as strings
+        if self.imp().current_smart_view.borrow().as_ref() == Some(&SmartView::Completed) {
+            return Some(CustomSorter::new(|obj1, obj2| {
+                let task_object1 = obj1.downcast_ref::<TaskObject>().expect("Expecting TaskObject");
+                let task_object2 = obj2.downcast_ref::<TaskObject>().expect("Expecting TaskObject");
+
+                task_object2.completed_at().cmp(&task_object1.completed_at()).into()
+            }));
+        }
+
+        // By section, open tasks above completed ones, then starred tasks first, then
+        // higher priorities if chosen. The sort is stable, so the manual order is kept otherwise.
+        let sections = self.current_sections();
+        Some(CustomSorter::new(move |obj1, obj2| {
+            let task_object1 = obj1.downcast_ref::<TaskObject>().expect("Expecting TaskObject");
+            let task_object2 = obj2.downcast_ref::<TaskObject>().expect("Expecting TaskObject");
+
+            let order = section_index(&sections, task_object1)
+                .cmp(&section_index(&sections, task_object2))
+                .then(task_object1.is_completed().cmp(&task_object2.is_completed()))
+                .then(task_object2.is_starred().cmp(&task_object1.is_starred()));
+            if sort_by_priority {
+                order.then(task_object2.priority().cmp(&task_object1.priority())).into()
+            } else {
+                order.into()
+            }
+        }))
+    }
+
+    fn setup_reminders(&self) {
+        self.imp().last_reminder_check.replace(reminders::current_minute());
+
+        glib::timeout_add_seconds_local(
+            reminders::CHECK_INTERVAL_SECONDS,
+            clone!(@weak self as window => @default-return glib::ControlFlow::Break, move || {
+                window.check_reminders();
+                glib::ControlFlow::Continue
+            })
+        );
+
+        // The digest is only sent while the application runs
+        self.settings().connect_changed(
+            Some("digest-enabled"),
+            |settings, key| {
+                if settings.boolean(key) {
+                    let _ = settings.set_boolean("run-in-background", true);
+                }
+            }
+        );
+
+        self.settings().connect_changed(
+            Some("run-in-background"),
+            clone!(@weak self as window => move |settings, key| {
+                if !settings.boolean(key) {
+                    return;
+                }
+
+                glib::MainContext::default().spawn_local(clone!(@weak window, @weak settings => async move {
+                    if !reminders::request_background(&window).await {
+                        let _ = settings.set_boolean("run-in-background", false);
+                        window.imp().toast_overlay.add_toast(Toast::new("Running in the background is not allowed"));
+                    }
+                }));
+            })
+        );
+    }
+
+    fn check_reminders(&self) {
+        let Some(application) = self.application() else {
+            return;
+        };
+
+        let now = reminders::current_minute();
+        let last_check = self.imp().last_reminder_check.replace(now.clone());
+
+        for task in reminders::due_reminders(&self.collections(), &last_check, &now) {
+            reminders::send_reminder(application.upcast_ref(), &task);
+        }
+
+        // Compared like the reminders, so it is sent once even if a check was late
+        if self.settings().boolean("digest-enabled") {
+            let digest_at = format!("{} {}", utils::today(), self.settings().string("digest-time"));
+            if digest_at > last_check && digest_at <= now {
+                self.send_digest(application.upcast_ref());
+            }
+        }
+    }
+
+    fn send_digest(&self, application: &gio::Application) {
+        let (mut due_today, mut overdue) = (0, 0);
+        for collection in self.collection_list() {
+            for task in collection.tasks().iter::<TaskObject>().filter_map(Result::ok) {
+                if task.is_overdue() {
+                    overdue += 1;
+                } else if task.is_due_today() && !task.is_completed() {
+                    due_today += 1;
+                }
+            }
+        }
+        reminders::send_digest(application, due_today, overdue);
+    }
+
+    fn watch_collection(&self, collection: &CollectionObject) {
+        collection.connect_notify_local(None, clone!(@weak self as window => move |_, _| {
+            window.schedule_save();
+        }));
+
+        for property in ["pinned", "folder"] {
+            collection.connect_notify_local(Some(property), clone!(@weak self as window => move |_, _| {
+                window.update_sidebar();
+            }));
+        }
+
+        collection.connect_notify_local(Some("sections"), clone!(@weak self as window => move |collection, _| {
+            if window.imp().current_collection.borrow().as_ref() == Some(collection) {
+                window.update_sections();
+            }
+        }));
+
+        for store in [collection.trash(), collection.archived()] {
+            store.connect_items_changed(clone!(@weak self as window => move |_, _, _, _| {
+                window.schedule_save();
+            }));
+        }
+
+        let tasks = collection.tasks();
+        for task in tasks.iter::<TaskObject>().filter_map(Result::ok) {
+            self.watch_task(&task);
+        }
+
+        tasks.connect_items_changed(
+            clone!(@weak self as window => move |tasks, position, _, added| {
+                for task in (position..position + added)
+                    .filter_map(|position| tasks.item(position).and_downcast::<TaskObject>())
+                {
+                    window.watch_task(&task);
+                }
+                window.schedule_save();
+            })
+        );
+    }
+
+    fn watch_task(&self, task: &TaskObject) {
+        // Undo and moves between collections add the same task again
+        if !self.imp().watched_tasks.borrow_mut().insert(task.uuid()) {
+            return;
+        }
+
+        task.connect_notify_local(None, clone!(@weak self as window => move |_, _| {
+            window.schedule_save();
+        }));
+
+        task.connect_notify_local(Some("completed"), clone!(@weak self as window => move |task, _| {
+            if task.is_completed() {
+                window.add_next_occurrence(task);
+            }
+            window.schedule_completed_sort(task);
+        }));
+
+        for property in ["due-date", "content", "completed"] {
+            task.connect_notify_local(Some(property), clone!(@weak self as window => move |_, _| {
+                window.refresh_calendar();
+            }));
+        }
+
+        task.connect_notify_local(Some("completed"), clone!(@weak self as window => move |task, _| {
+            if !window.blocks_other_tasks(task) {
+                return;
+            }
+            window.update_blocked_rows(task);
+            // Hidden blocked tasks show up once their blocker is completed
+            if window.settings().boolean("hide-blocked-tasks") {
+                window.set_filter();
+            }
+        }));
+
+        // The summary counts the open tasks shown, the list itself is watched in `bind_tasks`
+        for property in ["estimate-minutes", "completed"] {
+            task.connect_notify_local(Some(property), clone!(@weak self as window => move |_, _| {
+                window.update_estimate_summary();
+            }));
+        }
+
+        // The task moves to its new place in the list
+        for property in ["priority", "starred"] {
+            task.connect_notify_local(Some(property), clone!(@weak self as window => move |_, _| {
+                window.resort();
+            }));
+        }
+        task.connect_notify_local(Some("section"), clone!(@weak self as window => move |_, _| {
+            window.resort();
+            window.imp().tasks_list.invalidate_headers();
+        }));
+    }
+
+    // Completed tasks sink below the open ones, or fade out if the filter hides them now.
+    // The check mark shows for a moment first, tasks completed meanwhile move along.
+    fn schedule_completed_sort(&self, task: &TaskObject) {
+        let mut completing_tasks = self.imp().completing_tasks.borrow_mut();
+        completing_tasks.push(task.clone());
+        if completing_tasks.len() > 1 {
+            return;
+        }
+
+        glib::timeout_add_local_once(
+            Duration::from_millis(COMPLETED_SORT_DELAY_MS),
+            clone!(@weak self as window => move || {
+                let completing_tasks = window.imp().completing_tasks.take();
+                if window.imp().current_sort_model.borrow().is_none() {
+                    return;
+                }
+
+                let hidden_rows: Vec<ExpanderRow> = match window.filter() {
+                    Some(filter) => completing_tasks
+                        .iter()
+                        .filter(|task| !filter.match_(task))
+                        .filter_map(|task| window.task_row(task))
+                        .collect(),
+                    None => Vec::new(),
+                };
+                if hidden_rows.is_empty() {
+                    window.resort();
+                    return;
+                }
+                for row in &hidden_rows {
+                    row.add_css_class("leaving");
+                }
+                glib::timeout_add_local_once(
+                    Duration::from_millis(ROW_ANIMATION_MS),
+                    clone!(@weak window => move || window.set_filter()),
+                );
+            })
+        );
+    }
+
+    // Restyles the rows of the tasks waiting for `blocker`
+    fn update_blocked_rows(&self, blocker: &TaskObject) {
+        let Some(selection_model) = self.imp().current_selection_model.borrow().clone() else {
+            return;
+        };
+        let uuid = blocker.uuid();
+        for (position, task) in selection_model.iter::<TaskObject>().enumerate() {
+            let Ok(task) = task else {
+                break;
+            };
+            if !task.blocked_by().contains(&uuid) {
+                continue;
+            }
+            if let Some(row) = self.imp().tasks_list.row_at_index(position as i32).and_downcast::<ExpanderRow>() {
+                self.update_blocked_style(&row, &task);
+            }
+        }
+    }
+
+    // A completed recurring task stays done, its next occurrence is added right below it
+    fn add_next_occurrence(&self, task: &TaskObject) {
+        let Some(next_task) = task.next_occurrence() else {
+            return;
+        };
+        let Some((tasks, position)) = self.find_task(task) else {
+            return;
+        };
+
+        let old_recurrence = task.recurrence();
+        task.set_recurrence(String::new());
+        tasks.insert(position + 1, &next_task);
+
+        self.push_command(Command::Group(vec![
+            Command::EditTask {
+                task: task.clone(),
+                property: "recurrence",
+                old_value: old_recurrence.to_value(),
+                new_value: String::new().to_value(),
+            },
+            Command::InsertTasks {
+                tasks,
+                items: vec![(position + 1, next_task)],
+            },
+        ]));
+    }
+
+    // The task store containing `task` and the position of the task in it
+    fn find_task(&self, task: &TaskObject) -> Option<(gio::ListStore, u32)> {
+        self.collections()
+            .iter::<CollectionObject>()
+            .filter_map(Result::ok)
+            .find_map(|collection| {
+                let tasks = collection.tasks();
+                tasks.find(task).map(|position| (tasks, position))
+            })
+    }
+
+}
+
+// Position of the section of the task in `sections`, counting from 1. Tasks without a
+// section, or with one that no longer exists, are at 0.
+fn section_index(sections: &[String], task_object: &TaskObject) -> usize {
+    let section = task_object.section();
+    sections
+        .iter()
+        .position(|name| *name == section)
+        .map_or(0, |position| position + 1)
+}
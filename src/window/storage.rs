@@ -0,0 +1,642 @@
+use super::*;
+
+const SAVE_DELAY_MS: u64 = 500;
+// Wait for an external writer to finish before reading the changed data file
+const STORAGE_CHECK_DELAY_MS: u64 = 1000;
+
+// Loading, saving and encrypting the data, and watching it for outside changes
+impl TodoWindow {
+    pub(super) fn setup_storage(&self) {
+        let storage = if self.settings().boolean("encrypt-data") {
+            JsonStorage::encrypted(encrypted_data_path())
+        } else {
+            JsonStorage::new(data_path())
+        };
+        self.imp().storage.replace(Some(Arc::new(storage)));
+    }
+
+    pub(super) fn storage(&self) -> Arc<dyn Storage> {
+        self.imp()
+            .storage
+            .borrow()
+            .clone()
+            .expect("storage should be set in setup_storage")
+    }
+
+    pub fn is_encrypted(&self) -> bool {
+        self.settings().boolean("encrypt-data")
+    }
+
+    // Asks for a new passphrase, then moves the data into an encrypted file
+    pub async fn enable_encryption(&self) -> bool {
+        let Some(passphrase) = self
+            .choose_passphrase(
+                "Encrypt Tasks",
+                "The passphrase is needed to open the tasks after logging in. They cannot be recovered without it.",
+                "Encrypt",
+            )
+            .await
+        else {
+            return false;
+        };
+
+        let storage = JsonStorage::encrypted(encrypted_data_path());
+        storage.unlock(&passphrase);
+        let switched = self.switch_storage(storage, true).await;
+        if switched {
+            if let Err(err) = passphrase::remember(Purpose::Data, &passphrase).await {
+                glib::g_warning!("todo", "Could not remember the passphrase: {}", err);
+            }
+        }
+        switched
+    }
+
+    pub async fn disable_encryption(&self) -> bool {
+        let cancel_response = "cancel";
+        let decrypt_response = "decrypt";
+
+        let dialog = MessageDialog::builder()
+            .heading("Stop Encrypting Tasks?")
+            .body("Anyone with access to your files will be able to read the tasks")
+            .transient_for(self)
+            .modal(true)
+            .destroy_with_parent(true)
+            .close_response(cancel_response)
+            .default_response(cancel_response)
+            .build();
+        dialog.add_responses(&[(cancel_response, "Cancel"), (decrypt_response, "Stop Encrypting")]);
+        dialog.set_response_appearance(decrypt_response, ResponseAppearance::Destructive);
+        if dialog.choose_future().await != decrypt_response {
+            return false;
+        }
+
+        let switched = self.switch_storage(JsonStorage::new(data_path()), false).await;
+        if switched {
+            if let Err(err) = passphrase::forget(Purpose::Data).await {
+                glib::g_warning!("todo", "Could not forget the passphrase: {}", err);
+            }
+        }
+        switched
+    }
+
+    // Writes everything to `storage` and removes the files of the previous one
+    async fn switch_storage(&self, storage: JsonStorage, encrypted: bool) -> bool {
+        if self.imp().saving_blocked.get() {
+            return false;
+        }
+
+        let storage = Arc::new(storage);
+        let data = self.store_data();
+        let new_storage = storage.clone();
+        let result = gio::spawn_blocking(move || new_storage.save(&data)).await;
+        if !matches!(result, Ok(Ok(()))) {
+            if let Ok(Err(err)) = result {
+                glib::g_warning!("todo", "Could not write the tasks: {:#}", err);
+            }
+            self.imp().toast_overlay.add_toast(Toast::new("Could not write the tasks"));
+            return false;
+        }
+
+        let previous_path = if encrypted { data_path() } else { encrypted_data_path() };
+        let previous_storage = JsonStorage::new(previous_path);
+        if let Ok(Err(err)) = gio::spawn_blocking(move || previous_storage.remove_files()).await {
+            glib::g_warning!("todo", "Could not remove the previous data files: {}", err);
+        }
+
+        self.imp().storage.replace(Some(storage));
+        if let Err(err) = self.settings().set_boolean("encrypt-data", encrypted) {
+            glib::g_warning!("todo", "Could not store whether the tasks are encrypted: {}", err);
+        }
+        self.setup_storage_watch();
+        true
+    }
+
+    pub(super) async fn choose_passphrase(&self, heading: &str, body: &str, accept_label: &str) -> Option<String> {
+        let passphrase_entry = PasswordEntry::builder()
+            .placeholder_text("Passphrase")
+            .show_peek_icon(true)
+            .activates_default(true)
+            .build();
+        let confirm_entry = PasswordEntry::builder()
+            .placeholder_text("Confirm Passphrase")
+            .show_peek_icon(true)
+            .activates_default(true)
+            .build();
+        let entries = gtk::Box::builder()
+            .orientation(gtk::Orientation::Vertical)
+            .spacing(12)
+            .build();
+        entries.append(&passphrase_entry);
+        entries.append(&confirm_entry);
+
+        let cancel_response = "cancel";
+        let accept_response = "accept";
+
+        let dialog = MessageDialog::builder()
+            .heading(heading)
+            .body(body)
+            .transient_for(self)
+            .modal(true)
+            .destroy_with_parent(true)
+            .close_response(cancel_response)
+            .default_response(accept_response)
+            .extra_child(&entries)
+            .build();
+        dialog.add_responses(&[(cancel_response, "Cancel"), (accept_response, accept_label)]);
+        dialog.set_response_appearance(accept_response, ResponseAppearance::Suggested);
+        dialog.set_response_enabled(accept_response, false);
+
+        let update_response = clone!(@weak dialog, @weak passphrase_entry, @weak confirm_entry => move || {
+            let passphrase = passphrase_entry.text();
+            dialog.set_response_enabled(accept_response, !passphrase.is_empty() && passphrase == confirm_entry.text());
+        });
+        passphrase_entry.connect_changed(clone!(@strong update_response => move |_| update_response()));
+        confirm_entry.connect_changed(move |_| update_response());
+
+        if dialog.choose_future().await != accept_response {
+            return None;
+        }
+        Some(passphrase_entry.text().to_string())
+    }
+
+    pub(super) fn setup_autosave(&self) {
+        self.smart_collections().connect_items_changed(
+            clone!(@weak self as window => move |_, _, _, _| {
+                window.schedule_save();
+            })
+        );
+
+        for collection in self.collections().iter::<CollectionObject>().filter_map(Result::ok) {
+            self.watch_collection(&collection);
+        }
+
+        self.collections().connect_items_changed(
+            clone!(@weak self as window => move |collections, position, _, added| {
+                for collection in (position..position + added)
+                    .filter_map(|position| collections.item(position).and_downcast::<CollectionObject>())
+                {
+                    window.watch_collection(&collection);
+                }
+                window.schedule_save();
+            })
+        );
+    }
+
+    // Changes usually come in bursts (typing notes, removing done tasks), so wait
+    // for a quiet moment before writing everything to disk
+    pub(super) fn schedule_save(&self) {
+        if let Some(source_id) = self.imp().save_source_id.take() {
+            source_id.remove();
+        }
+
+        let source_id = glib::timeout_add_local_once(
+            Duration::from_millis(SAVE_DELAY_MS),
+            clone!(@weak self as window => move || {
+                window.imp().save_source_id.take();
+                window.save_data();
+                window.update_badge();
+            })
+        );
+        self.imp().save_source_id.replace(Some(source_id));
+    }
+
+    fn save_data(&self) {
+        if self.imp().saving_blocked.get() {
+            return;
+        }
+
+        if self.imp().external_change_pending.get() {
+            return;
+        }
+
+        let storage = self.storage();
+        let data = self.store_data();
+        self.imp().stored_value.replace(serde_json::to_value(&data).ok());
+
+        glib::MainContext::default().spawn_local(clone!(@weak self as window => async move {
+            let result = gio::spawn_blocking(move || storage.save(&data)).await;
+            if let Ok(Err(err)) = result {
+                glib::g_warning!("todo", "Could not save data: {:#}", err);
+                window.imp().toast_overlay.add_toast(Toast::new("Could not save tasks"));
+            }
+        }));
+    }
+
+    pub(super) async fn confirm_close_without_saving(&self, err: anyhow::Error) {
+        let cancel_response = "cancel";
+        let close_response = "close";
+
+        let dialog = MessageDialog::builder()
+            .heading("Could Not Save Tasks")
+            .body(format!("{err:#}"))
+            .transient_for(self)
+            .modal(true)
+            .destroy_with_parent(true)
+            .close_response(cancel_response)
+            .default_response(cancel_response)
+            .build();
+
+        dialog.add_responses(&[(cancel_response, "Cancel"), (close_response, "Quit Without Saving")]);
+        dialog.set_response_appearance(close_response, ResponseAppearance::Destructive);
+
+        if dialog.choose_future().await == close_response {
+            self.imp().close_without_saving.set(true);
+            self.close();
+        }
+    }
+
+    pub(super) async fn back_up(&self) {
+        let filter = FileFilter::new("Backup")
+            .mimetype("application/json")
+            .glob("*.json");
+        let date = glib::DateTime::now_local()
+            .and_then(|now| now.format("%Y-%m-%d"))
+            .map(|date| date.to_string())
+            .unwrap_or_default();
+        let current_name = format!("Tasks Backup {date}.json");
+        let Some(path) = file_chooser::save_file(self, "Back Up", &current_name, filter).await else {
+            return;
+        };
+
+        // Attachments are not part of the data yet, so everything fits in one file
+        let backup = Backup::new(self.store_data(), self.settings());
+        let result = gio::spawn_blocking(move || backup.write(&path)).await;
+        match result {
+            Ok(Ok(())) => self.imp().toast_overlay.add_toast(Toast::new("Backup saved")),
+            Ok(Err(err)) => {
+                glib::g_warning!("todo", "Could not back up: {:#}", err);
+                self.imp().toast_overlay.add_toast(Toast::new("Could not save the backup"));
+            }
+            Err(_) => self.imp().toast_overlay.add_toast(Toast::new("Could not save the backup")),
+        }
+    }
+
+    pub(super) async fn restore_backup(&self) {
+        let filter = FileFilter::new("Backup")
+            .mimetype("application/json")
+            .glob("*.json");
+        let Some(path) = file_chooser::open_file(self, "Restore", filter).await else {
+            return;
+        };
+
+        let result = gio::spawn_blocking(move || Backup::read(&path)).await;
+        let backup = match result {
+            Ok(Ok(backup)) => backup,
+            Ok(Err(err)) => {
+                glib::g_warning!("todo", "Could not read backup: {:#}", err);
+                self.imp().toast_overlay.add_toast(Toast::new(&format!("Could not restore: {err}")));
+                return;
+            }
+            Err(_) => {
+                self.imp().toast_overlay.add_toast(Toast::new("Could not read the backup"));
+                return;
+            }
+        };
+
+        if !self.confirm_restore(&backup).await {
+            return;
+        }
+
+        backup.restore_settings(self.settings());
+        self.replace_store_data(backup.data);
+        self.schedule_save();
+        self.imp().toast_overlay.add_toast(Toast::new("Backup restored"));
+    }
+
+    // Shows what the backup holds before it replaces everything
+    async fn confirm_restore(&self, backup: &Backup) -> bool {
+        let collections = ListBox::builder()
+            .selection_mode(gtk::SelectionMode::None)
+            .css_classes(["boxed-list"])
+            .build();
+        for collection_data in &backup.data.collections {
+            let row = ActionRow::builder()
+                .title(&collection_data.title)
+                .subtitle(utils::count_label(collection_data.tasks_data.len(), "task"))
+                .build();
+            collections.append(&row);
+        }
+        let scrolled_window = gtk::ScrolledWindow::builder()
+            .hscrollbar_policy(gtk::PolicyType::Never)
+            .propagate_natural_height(true)
+            .max_content_height(360)
+            .child(&collections)
+            .visible(!backup.data.collections.is_empty())
+            .build();
+
+        let mut contents = vec![utils::count_label(backup.data.collections.len(), "collection")];
+        if !backup.data.smart_collections.is_empty() {
+            contents.push(utils::count_label(backup.data.smart_collections.len(), "smart collection"));
+        }
+        if !backup.data.templates.is_empty() {
+            contents.push(utils::count_label(backup.data.templates.len(), "template"));
+        }
+        if !backup.settings.is_empty() {
+            contents.push("the preferences".to_string());
+        }
+
+        let cancel_response = "cancel";
+        let restore_response = "restore";
+
+        let dialog = MessageDialog::builder()
+            .heading("Restore Backup?")
+            .body(format!(
+                "The backup holds {}. It replaces all current collections and preferences.",
+                contents.join(", ")
+            ))
+            .transient_for(self)
+            .modal(true)
+            .destroy_with_parent(true)
+            .close_response(cancel_response)
+            .default_response(cancel_response)
+            .extra_child(&scrolled_window)
+            .build();
+        dialog.add_responses(&[(cancel_response, "Cancel"), (restore_response, "Restore")]);
+        dialog.set_response_appearance(restore_response, ResponseAppearance::Destructive);
+
+        dialog.choose_future().await == restore_response
+    }
+
+    pub(super) fn setup_storage_watch(&self) {
+        let watch = self.storage().watch(Box::new(clone!(@weak self as window => move || {
+            window.schedule_storage_check();
+        })));
+        self.imp().storage_watch.replace(watch);
+    }
+
+    // Sync services write the file in several steps, so wait until it settled
+    fn schedule_storage_check(&self) {
+        if let Some(source_id) = self.imp().storage_check_source_id.take() {
+            source_id.remove();
+        }
+
+        let source_id = glib::timeout_add_local_once(
+            Duration::from_millis(STORAGE_CHECK_DELAY_MS),
+            clone!(@weak self as window => move || {
+                window.imp().storage_check_source_id.take();
+                glib::MainContext::default().spawn_local(clone!(@weak window => async move {
+                    window.check_storage().await;
+                }));
+            })
+        );
+        self.imp().storage_check_source_id.replace(Some(source_id));
+    }
+
+    // Someone else changed the stored data, e.g. a file sync service. Without
+    // changes of our own it is reloaded, otherwise the user decides.
+    async fn check_storage(&self) {
+        if self.imp().external_change_pending.get() || self.imp().saving_blocked.get() {
+            return;
+        }
+
+        let storage = self.storage();
+        let stored_data = match gio::spawn_blocking(move || storage.reload()).await {
+            Ok(Ok(Some(stored_data))) => stored_data,
+            Ok(Ok(None)) | Err(_) => return,
+            Ok(Err(err)) => {
+                // Likely still being written, the next change is reported again
+                glib::g_warning!("todo", "Could not read the changed data: {:#}", err);
+                return;
+            }
+        };
+
+        let stored_value = serde_json::to_value(&stored_data).ok();
+        if stored_value.is_none() || *self.imp().stored_value.borrow() == stored_value {
+            // Our own write
+            return;
+        }
+
+        let has_own_changes = self.imp().save_source_id.borrow().is_some()
+            || serde_json::to_value(self.store_data()).ok() != *self.imp().stored_value.borrow();
+        if !has_own_changes {
+            self.imp().stored_value.replace(stored_value);
+            self.replace_store_data(stored_data);
+            self.imp().toast_overlay.add_toast(Toast::new("Tasks reloaded"));
+            return;
+        }
+
+        let keep_response = "keep";
+        let merge_response = "merge";
+        let reload_response = "reload";
+
+        let dialog = MessageDialog::builder()
+            .heading("Tasks Changed Elsewhere")
+            .body("The saved tasks were changed by another program, for example a file sync service. Merging keeps the latest change to every task from both sides, reloading discards the changes made here.")
+            .transient_for(self)
+            .modal(true)
+            .destroy_with_parent(true)
+            .close_response(keep_response)
+            .default_response(merge_response)
+            .build();
+        dialog.add_responses(&[
+            (keep_response, "Keep Mine"),
+            (reload_response, "Reload"),
+            (merge_response, "Merge"),
+        ]);
+        dialog.set_response_appearance(reload_response, ResponseAppearance::Destructive);
+        dialog.set_response_appearance(merge_response, ResponseAppearance::Suggested);
+
+        self.imp().external_change_pending.set(true);
+        let response = dialog.choose_future().await;
+        self.imp().external_change_pending.set(false);
+
+        if response == reload_response {
+            if let Some(source_id) = self.imp().save_source_id.take() {
+                source_id.remove();
+            }
+            self.imp().stored_value.replace(stored_value);
+            self.replace_store_data(stored_data);
+        } else {
+            if response == merge_response {
+                let merged_data = merge::merge(self.store_data(), stored_data);
+                self.replace_store_data(merged_data);
+            }
+            // Overwrite the changed file with what is shown now
+            self.schedule_save();
+        }
+    }
+
+    // Replaces everything shown, the previous collections can not be brought back by undoing
+    pub(super) fn replace_store_data(&self, store_data: StoreData) {
+        self.clear_current_collection();
+        self.collections().remove_all();
+        self.smart_collections().remove_all();
+        self.imp().watched_tasks.borrow_mut().clear();
+        // The commands refer to the tasks and collections that were just replaced
+        self.imp().undo_stack.replace(UndoStack::default());
+        self.update_undo_actions();
+
+        self.load_store_data(store_data);
+    }
+
+    pub(super) fn store_data(&self) -> StoreData {
+        let collections_data: Vec<CollectionData> = self
+            .collections()
+            .iter::<CollectionObject>()
+            .filter_map(|collection_object| collection_object.ok())
+            .map(|collection_object| collection_object.to_collection_data())
+            .collect();
+        self.update_tombstones(collection_uuids(&collections_data));
+
+        let smart_collections_data = self
+            .smart_collections()
+            .iter::<SmartCollectionObject>()
+            .filter_map(Result::ok)
+            .map(|smart_collection| smart_collection.smart_collection_data())
+            .collect();
+
+        StoreData {
+            sync: self.imp().sync_data.borrow().clone(),
+            tombstones: self.imp().tombstones.borrow().clone(),
+            ..StoreData::new(collections_data, smart_collections_data, self.imp().templates.borrow().clone())
+        }
+    }
+
+    // Uuids that were stored before and are gone now were deleted for good.
+    // Undoing can bring them back, which drops their tombstones again.
+    fn update_tombstones(&self, current_uuids: HashSet<String>) {
+        let stored_uuids = self.imp().stored_uuids.replace(HashSet::new());
+        let deleted_at = utils::timestamp();
+
+        let mut tombstones = self.imp().tombstones.borrow_mut();
+        tombstones.retain(|tombstone| !current_uuids.contains(&tombstone.uuid));
+        tombstones.extend(stored_uuids.difference(&current_uuids).map(|uuid| Tombstone {
+            uuid: uuid.clone(),
+            deleted_at: deleted_at.clone(),
+        }));
+
+        self.imp().stored_uuids.replace(current_uuids);
+    }
+
+    pub(super) fn restore_data(&self) {
+        if self.storage().is_locked() {
+            self.imp().saving_blocked.set(true);
+            glib::MainContext::default().spawn_local(clone!(@weak self as window => async move {
+                window.unlock_storage().await;
+            }));
+            return;
+        }
+
+        match self.storage().load() {
+            Ok(Some(store_data)) => {
+                self.imp().stored_value.replace(serde_json::to_value(&store_data).ok());
+                self.load_store_data(store_data);
+            }
+            // Nothing was stored yet, this is the first run
+            Ok(None) => {
+                glib::MainContext::default().spawn_local(clone!(@weak self as window => async move {
+                    window.import_endeavour(true).await;
+                }));
+            }
+            Err(err) => {
+                self.imp().saving_blocked.set(true);
+                glib::MainContext::default().spawn_local(clone!(@weak self as window => async move {
+                    window.recover_from_load_error(err).await;
+                }));
+            }
+        }
+    }
+
+    pub(super) fn load_store_data(&self, store_data: StoreData) {
+        self.imp().stored_uuids.replace(collection_uuids(&store_data.collections));
+        let collections: Vec<CollectionObject> = store_data
+            .collections
+            .into_iter()
+            .map(CollectionObject::from_collection_data)
+            .collect();
+
+        self.collections().extend_from_slice(&collections);
+
+        let smart_collections: Vec<SmartCollectionObject> = store_data
+            .smart_collections
+            .into_iter()
+            .map(SmartCollectionObject::from_smart_collection_data)
+            .collect();
+        self.smart_collections().extend_from_slice(&smart_collections);
+        self.imp().templates.replace(store_data.templates);
+        self.imp().sync_data.replace(store_data.sync);
+        self.imp().tombstones.replace(store_data.tombstones);
+
+        // Reopen the collection that was shown last time, if it still exists
+        let last_collection: String = self.settings().get("last-collection");
+        let current_collection = collections
+            .iter()
+            .find(|collection| collection.uuid() == last_collection)
+            .or_else(|| collections.first());
+        if let Some(current_collection) = current_collection {
+            self.set_current_collection(current_collection.clone());
+        }
+    }
+
+    pub(super) async fn recover_from_load_error(&self, mut err: anyhow::Error) {
+        let quit_response = "quit";
+        let backup_response = "backup";
+        let fresh_response = "fresh";
+
+        loop {
+            let dialog = MessageDialog::builder()
+                .heading("Could Not Load Tasks")
+                .body(format!(
+                    "The saved tasks and their backups could not be read.\n\n{err:#}"
+                ))
+                .transient_for(self)
+                .modal(true)
+                .destroy_with_parent(true)
+                .close_response(quit_response)
+                .default_response(backup_response)
+                .build();
+
+            dialog.add_responses(&[
+                (quit_response, "Quit"),
+                (backup_response, "Open Backup…"),
+                (fresh_response, "Start Fresh"),
+            ]);
+            dialog.set_response_appearance(backup_response, ResponseAppearance::Suggested);
+            dialog.set_response_appearance(fresh_response, ResponseAppearance::Destructive);
+
+            let response = dialog.choose_future().await;
+
+            if response == fresh_response {
+                self.imp().saving_blocked.set(false);
+                return;
+            } else if response == backup_response {
+                let filter = FileFilter::new("Backups")
+                    .mimetype("application/json")
+                    .glob("data.json*");
+                let Some(path) = file_chooser::open_file(self, "Open Backup", filter).await else {
+                    continue;
+                };
+
+                match JsonStorage::read_file(&path) {
+                    Ok(store_data) => {
+                        self.imp().saving_blocked.set(false);
+                        self.load_store_data(store_data);
+                        return;
+                    }
+                    Err(backup_err) => err = backup_err,
+                }
+            } else {
+                if let Some(application) = self.application() {
+                    application.quit();
+                }
+                return;
+            }
+        }
+    }
+}
+
+// The uuids of the collections and of all their tasks, including deleted and archived ones
+fn collection_uuids(collections_data: &[CollectionData]) -> HashSet<String> {
+    collections_data
+        .iter()
+        .flat_map(|collection_data| {
+            let tasks_data = collection_data
+                .tasks_data
+                .iter()
+                .chain(&collection_data.trash_data)
+                .chain(&collection_data.archived_data);
+            std::iter::once(&collection_data.uuid).chain(tasks_data.map(|task_data| &task_data.uuid))
+        })
+        .cloned()
+        .collect()
+}
@@ -0,0 +1,381 @@
+use super::*;
+use crate::sync::{self, caldav::{CalDavClient, RemoteCalendar}, credentials, lan::{self, IncomingSync}, CalendarLink};
+
+const SYNC_INTERVAL_SECONDS: u32 = 15 * 60;
+
+// Syncing with devices on the local network and with CalDAV calendars
+impl TodoWindow {
+    pub(super) fn setup_sync(&self) {
+        self.setup_lan_sync();
+        self.settings().connect_changed(Some("lan-sync-enabled"), clone!(@weak self as window => move |_, _| {
+            window.setup_lan_sync();
+        }));
+
+        self.sync_in_background();
+        glib::timeout_add_seconds_local(
+            SYNC_INTERVAL_SECONDS,
+            clone!(@weak self as window => @default-return glib::ControlFlow::Break, move || {
+                window.sync_in_background();
+                glib::ControlFlow::Continue
+            })
+        );
+    }
+
+    // Without an account there is nothing to sync, and nothing to complain about
+    fn sync_in_background(&self) {
+        glib::MainContext::default().spawn_local(clone!(@weak self as window => async move {
+            window.sync_with_peers().await;
+        }));
+
+        if Account::from_settings(self.settings()).is_none() {
+            return;
+        }
+
+        glib::MainContext::default().spawn_local(clone!(@weak self as window => async move {
+            window.sync_collections().await;
+        }));
+    }
+
+    // (Re)starts syncing on the local network as configured
+    fn setup_lan_sync(&self) {
+        self.imp().lan_sync.replace(None);
+        glib::MainContext::default().spawn_local(clone!(@weak self as window => async move {
+            window.start_lan_sync().await;
+        }));
+    }
+
+    // Stores the pairing code in the keyring and syncs with it, an empty code stops syncing
+    pub async fn set_pairing_code(&self, pairing_code: &str) {
+        let result = if pairing_code.is_empty() {
+            passphrase::forget(Purpose::PairingCode).await.map(|_| ())
+        } else {
+            passphrase::remember(Purpose::PairingCode, pairing_code).await
+        };
+        if let Err(err) = result {
+            glib::g_warning!("todo", "Could not store the pairing code: {}", err);
+            self.imp().toast_overlay.add_toast(Toast::new("Could not store the pairing code"));
+            return;
+        }
+
+        self.setup_lan_sync();
+    }
+
+    pub async fn pairing_code() -> String {
+        passphrase::lookup(Purpose::PairingCode)
+            .await
+            .unwrap_or_else(|err| {
+                glib::g_warning!("todo", "Could not look up the pairing code: {}", err);
+                None
+            })
+            .unwrap_or_default()
+    }
+
+    async fn start_lan_sync(&self) {
+        // Older versions kept the code in the settings, in plain text
+        let stored_code = self.settings().string("lan-sync-code");
+        if !stored_code.is_empty() {
+            match passphrase::remember(Purpose::PairingCode, &stored_code).await {
+                Ok(()) => self.settings().reset("lan-sync-code"),
+                Err(err) => glib::g_warning!("todo", "Could not move the pairing code to the keyring: {}", err),
+            }
+        }
+
+        let pairing_code = Self::pairing_code().await;
+        // The setting may have changed while looking up the code
+        let enabled = self.settings().boolean("lan-sync-enabled");
+        if pairing_code.is_empty() || !enabled || self.imp().lan_sync.borrow().is_some() {
+            return;
+        }
+
+        let (lan_sync, mut incoming) = match LanSync::start(pairing_code) {
+            Ok(started) => started,
+            Err(err) => {
+                glib::g_warning!("todo", "Could not start syncing on the local network: {:#}", err);
+                self.imp().toast_overlay.add_toast(Toast::new("Could not sync on the local network"));
+                return;
+            }
+        };
+        self.imp().lan_sync.replace(Some(lan_sync));
+
+        // Ends once the `LanSync` is dropped
+        let window = self.downgrade();
+        glib::MainContext::default().spawn_local(async move {
+            while let Some(incoming_sync) = incoming.recv().await {
+                let Some(window) = window.upgrade() else {
+                    break;
+                };
+                window.answer_peer(incoming_sync);
+            }
+        });
+    }
+
+    fn answer_peer(&self, incoming_sync: IncomingSync) {
+        self.apply_merged_data(merge::merge(self.store_data(), incoming_sync.data));
+        let _ = incoming_sync.reply.send(self.store_data());
+    }
+
+    // Exchanges the data with every device found on the local network
+    pub(super) async fn sync_with_peers(&self) {
+        let peers = match self.imp().lan_sync.borrow().as_ref() {
+            Some(lan_sync) => lan_sync.peers(),
+            None => return,
+        };
+        let Some(pairing_code) = self.imp().lan_sync.borrow().as_ref().map(LanSync::pairing_code) else {
+            return;
+        };
+
+        for addresses in peers {
+            let result = sync::run(lan::sync_with(addresses, pairing_code.clone(), self.store_data())).await;
+            match result {
+                // Tasks may have changed while waiting for the peer
+                Ok(merged_data) => self.apply_merged_data(merge::merge(self.store_data(), merged_data)),
+                Err(err) => glib::g_warning!("todo", "Could not sync with a peer: {:#}", err),
+            }
+        }
+    }
+
+    fn apply_merged_data(&self, merged_data: StoreData) {
+        if serde_json::to_value(&merged_data).ok() == serde_json::to_value(self.store_data()).ok() {
+            return;
+        }
+
+        self.replace_store_data(merged_data);
+        self.schedule_save();
+    }
+
+    pub(super) async fn set_up_sync(&self) {
+        let server_entry = Entry::builder()
+            .placeholder_text("Server Address")
+            .text(self.settings().string("sync-server-url"))
+            .activates_default(true)
+            .build();
+        let username_entry = Entry::builder()
+            .placeholder_text("Username")
+            .text(self.settings().string("sync-username"))
+            .activates_default(true)
+            .build();
+        let password_entry = PasswordEntry::builder()
+            .placeholder_text("Password")
+            .show_peek_icon(true)
+            .activates_default(true)
+            .build();
+
+        let content_box = gtk::Box::builder()
+            .orientation(gtk::Orientation::Vertical)
+            .spacing(12)
+            .build();
+        content_box.append(&server_entry);
+        content_box.append(&username_entry);
+        content_box.append(&password_entry);
+
+        let cancel_response = "cancel";
+        let connect_response = "connect";
+
+        let dialog = MessageDialog::builder()
+            .heading("Set Up Sync")
+            .body("Collections are synced with the task lists of a CalDAV server, like Nextcloud or Radicale")
+            .transient_for(self)
+            .modal(true)
+            .destroy_with_parent(true)
+            .close_response(cancel_response)
+            .default_response(connect_response)
+            .extra_child(&content_box)
+            .build();
+        dialog.add_responses(&[(cancel_response, "Cancel"), (connect_response, "Connect")]);
+        dialog.set_response_appearance(connect_response, ResponseAppearance::Suggested);
+
+        if dialog.choose_future().await != connect_response {
+            return;
+        }
+
+        let account = Account {
+            server_url: server_entry.text().trim().to_string(),
+            username: username_entry.text().trim().to_string(),
+        };
+        let password = password_entry.text().to_string();
+
+        match self.connect_sync_account(&account, &password).await {
+            Ok(()) => self.sync_collections().await,
+            Err(err) => {
+                glib::g_warning!("todo", "Could not set up sync: {:#}", err);
+                self.imp().toast_overlay.add_toast(Toast::new(&format!("Could not set up sync: {err}")));
+            }
+        }
+    }
+
+    // Links every task list of the account
+    async fn connect_sync_account(&self, account: &Account, password: &str) -> anyhow::Result<()> {
+        let client = CalDavClient::new(&account.username, password);
+        let calendars = sync::run(sync::discover_calendars(client, account.server_url.clone())).await?;
+
+        self.store_sync_account(account, password).await?;
+        for calendar in &calendars {
+            self.link_calendar(calendar);
+        }
+        Ok(())
+    }
+
+    pub async fn store_sync_account(&self, account: &Account, password: &str) -> anyhow::Result<()> {
+        credentials::store_password(account, password)
+            .await
+            .context("Could not store the password")?;
+        self.settings().set_string("sync-server-url", &account.server_url)?;
+        self.settings().set_string("sync-username", &account.username)?;
+        Ok(())
+    }
+
+    // The collections stay, they are just no longer synced
+    pub async fn forget_sync_account(&self) {
+        if let Some(account) = Account::from_settings(self.settings()) {
+            if let Err(err) = credentials::clear_password(&account).await {
+                glib::g_warning!("todo", "Could not remove the password: {}", err);
+            }
+        }
+        let _ = self.settings().set_string("sync-server-url", "");
+        let _ = self.settings().set_string("sync-username", "");
+
+        self.imp().sync_data.replace(SyncData::default());
+        self.schedule_save();
+    }
+
+    pub fn is_calendar_linked(&self, url: &str) -> bool {
+        self.imp().sync_data.borrow().calendars.iter().any(|link| link.url == url)
+    }
+
+    // Syncs the task list into the unlinked collection with its name, or a new one
+    pub fn link_calendar(&self, calendar: &RemoteCalendar) {
+        if self.is_calendar_linked(&calendar.url) {
+            return;
+        }
+
+        let links = self.imp().sync_data.borrow().calendars.clone();
+        let collections = self.collections();
+        let existing = collections
+            .iter::<CollectionObject>()
+            .filter_map(Result::ok)
+            .filter(|collection| !links.iter().any(|link| link.collection_uuid == collection.uuid()))
+            .find(|collection| collection.title() == calendar.display_name);
+        let collection = existing.unwrap_or_else(|| {
+            let collection = CollectionObject::new(&calendar.display_name, gio::ListStore::new::<TaskObject>());
+            collections.append(&collection);
+            collection
+        });
+
+        self.imp().sync_data.borrow_mut().calendars.push(CalendarLink {
+            collection_uuid: collection.uuid(),
+            url: calendar.url.clone(),
+            tasks: Vec::new(),
+        });
+        self.schedule_save();
+        self.sync_in_background();
+    }
+
+    // The collection is kept with its tasks
+    pub fn unlink_calendar(&self, url: &str) {
+        self.imp().sync_data.borrow_mut().calendars.retain(|link| link.url != url);
+        self.schedule_save();
+    }
+
+    pub async fn sync_collections(&self) {
+        if self.imp().syncing.replace(true) {
+            return;
+        }
+        let result = self.sync_linked_collections().await;
+        self.imp().syncing.set(false);
+        self.schedule_save();
+
+        match result {
+            Ok(0) => {}
+            Ok(conflicts) => self.imp().toast_overlay.add_toast(Toast::new(&format!(
+                "Kept {} changed on both sides as copies",
+                utils::count_label(conflicts, "task")
+            ))),
+            Err(err) => {
+                glib::g_warning!("todo", "Could not sync: {:#}", err);
+                self.imp().toast_overlay.add_toast(Toast::new("Could not sync the collections"));
+            }
+        }
+    }
+
+    // Returns the number of conflicts
+    async fn sync_linked_collections(&self) -> anyhow::Result<usize> {
+        let account = Account::from_settings(self.settings()).context("No sync account is set up")?;
+        let password = credentials::lookup_password(&account)
+            .await?
+            .context("The password of the sync account is missing")?;
+        let client = CalDavClient::new(&account.username, &password);
+
+        // Collections deleted since are no longer synced
+        let collections: Vec<CollectionObject> = self.collections().iter::<CollectionObject>().filter_map(Result::ok).collect();
+        self.imp()
+            .sync_data
+            .borrow_mut()
+            .calendars
+            .retain(|link| collections.iter().any(|collection| collection.uuid() == link.collection_uuid));
+
+        let links = self.imp().sync_data.borrow().calendars.clone();
+        let mut conflicts = 0;
+        for link in links {
+            let Some(collection) = collections.iter().find(|collection| collection.uuid() == link.collection_uuid) else {
+                continue;
+            };
+            let sent: Vec<TaskData> = collection
+                .tasks()
+                .iter::<TaskObject>()
+                .filter_map(Result::ok)
+                .map(|task| task.task_data())
+                .collect();
+            let kept: Vec<String> = collection
+                .archived()
+                .iter::<TaskObject>()
+                .filter_map(Result::ok)
+                .map(|task| task.uuid())
+                .collect();
+
+            let outcome = sync::run(sync::sync_calendar(client.clone(), link, sent.clone(), kept)).await?;
+            self.apply_sync_outcome(collection, &sent, outcome.tasks_data);
+            conflicts += outcome.conflicts;
+
+            // Stored right away, so a later failure does not lose what was synced
+            let mut sync_data = self.imp().sync_data.borrow_mut();
+            if let Some(link) = sync_data.calendars.iter_mut().find(|link| link.url == outcome.link.url) {
+                *link = outcome.link;
+            }
+        }
+
+        Ok(conflicts)
+    }
+
+    // Brings the tasks of `collection` in line with a sync that started from `sent`.
+    // Tasks added while the sync was running are left as they are.
+    fn apply_sync_outcome(&self, collection: &CollectionObject, sent: &[TaskData], tasks_data: Vec<TaskData>) {
+        let tasks = collection.tasks();
+
+        for position in (0..tasks.n_items()).rev() {
+            let Some(task) = tasks.item(position).and_downcast::<TaskObject>() else {
+                continue;
+            };
+            let uuid = task.uuid();
+            let was_sent = sent.iter().any(|task_data| task_data.uuid == uuid);
+            if was_sent && !tasks_data.iter().any(|task_data| task_data.uuid == uuid) {
+                tasks.remove(position);
+            }
+        }
+
+        for task_data in tasks_data {
+            let existing = tasks
+                .iter::<TaskObject>()
+                .filter_map(Result::ok)
+                .find(|task| task.uuid() == task_data.uuid);
+            match existing {
+                Some(task) => {
+                    let unchanged = sent.iter().any(|sent_data| *sent_data == task_data);
+                    if !unchanged {
+                        task.set_task_data(task_data);
+                    }
+                }
+                None => tasks.append(&TaskObject::from_task_data(task_data)),
+            }
+        }
+    }
+}